@@ -0,0 +1,252 @@
+//! # Security audit
+//!
+//! `TlsAudit` collects the parameters negotiated during a handshake (protocol version, cipher
+//! suite, Diffie-Hellman group or temporary RSA key), and reports the weak configurations as
+//! findings, with a severity.
+//!
+//! Parameters are usually extracted from the handshake messages using `observe_message`, but
+//! can also be set directly (for ex. from a session tracked by the caller).
+//!
+//! SSLv2 support is a property of the server, not of the session: a server is exposed to
+//! DROWN if its key is also used by a server accepting SSLv2 connections. The SSLv2 support
+//! is updated from the SSLv2 messages sent by a server using `observe_sslv2_message`, and can
+//! be kept by host and copied to the `TlsAudit` of its TLS sessions.
+
+use std::cmp::Reverse;
+
+use crate::ssl2::{SSLv2Message, SSLv2ServerHello};
+use crate::tls::*;
+use crate::tls_ciphers::{TlsCipherAu, TlsCipherEnc, TlsCipherKx, TlsCipherSuite};
+use crate::tls_dh::{DhGroupAnalysis, DhGroupRisk};
+use crate::tls_kx::{parse_server_key_exchange_params, ServerKeyExchangeParams};
+
+/// Severity of an audit finding
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TlsAuditSeverity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+/// Weak configuration reported by the audit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TlsAuditIssue {
+    /// Deprecated or prohibited protocol version (SSL 3.0, TLS 1.0, TLS 1.1)
+    ObsoleteVersion(TlsVersion),
+    /// Export-grade cipher suite
+    ExportCipher(TlsCipherSuiteID),
+    /// Cipher suite without encryption
+    NullEncryption(TlsCipherSuiteID),
+    /// Cipher suite without authentication of the server
+    AnonymousCipher(TlsCipherSuiteID),
+    /// Cipher suite with a weak encryption algorithm (RC4, DES, 3DES, RC2)
+    WeakEncryption(TlsCipherSuiteID, TlsCipherEnc),
+    /// Key exchange without forward secrecy (static RSA, DH or ECDH, PSK)
+    NoForwardSecrecy(TlsCipherSuiteID),
+    /// Diffie-Hellman group smaller than 2048 bits (see `DhGroupRisk`)
+    WeakDhGroup(DhGroupAnalysis),
+    /// Client offering export-grade RSA cipher suites (FREAK)
+    ExportRsaOffered,
+    /// Temporary RSA key sent by the server, with its size in bits (FREAK)
+    ExportRsaKey { bits: usize },
+    /// Server accepting SSLv2 connections (DROWN)
+    SSLv2Enabled(SSLv2Support),
+}
+
+impl TlsAuditIssue {
+    /// Get the severity of this issue
+    pub fn severity(&self) -> TlsAuditSeverity {
+        match *self {
+            TlsAuditIssue::ObsoleteVersion(TlsVersion::Ssl30) => TlsAuditSeverity::High,
+            TlsAuditIssue::ObsoleteVersion(_) => TlsAuditSeverity::Medium,
+            TlsAuditIssue::ExportCipher(_)
+            | TlsAuditIssue::NullEncryption(_)
+            | TlsAuditIssue::ExportRsaKey { .. } => TlsAuditSeverity::Critical,
+            TlsAuditIssue::AnonymousCipher(_) => TlsAuditSeverity::High,
+            TlsAuditIssue::WeakEncryption(_, TlsCipherEnc::TripleDes) => TlsAuditSeverity::Medium,
+            TlsAuditIssue::WeakEncryption(_, _) => TlsAuditSeverity::High,
+            TlsAuditIssue::NoForwardSecrecy(_) => TlsAuditSeverity::Low,
+            TlsAuditIssue::ExportRsaOffered => TlsAuditSeverity::Medium,
+            TlsAuditIssue::SSLv2Enabled(SSLv2Support::ExportCiphers) => TlsAuditSeverity::Critical,
+            TlsAuditIssue::SSLv2Enabled(_) => TlsAuditSeverity::High,
+            TlsAuditIssue::WeakDhGroup(dh) => match dh.risk {
+                DhGroupRisk::Export => TlsAuditSeverity::Critical,
+                DhGroupRisk::Weak | DhGroupRisk::CommonPrime => TlsAuditSeverity::High,
+                DhGroupRisk::Moderate | DhGroupRisk::Low => TlsAuditSeverity::Medium,
+            },
+        }
+    }
+}
+
+/// Audit finding
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TlsAuditFinding {
+    pub severity: TlsAuditSeverity,
+    pub issue: TlsAuditIssue,
+}
+
+impl From<TlsAuditIssue> for TlsAuditFinding {
+    fn from(issue: TlsAuditIssue) -> Self {
+        TlsAuditFinding {
+            severity: issue.severity(),
+            issue,
+        }
+    }
+}
+
+/// SSLv2 support of a server, from the lowest to the highest exposure to DROWN
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SSLv2Support {
+    /// The server answered a SSLv2 CLIENT-HELLO
+    Enabled,
+    /// The server answered a SSLv2 CLIENT-HELLO, and accepts export cipher kinds
+    ExportCiphers,
+}
+
+impl SSLv2Support {
+    /// Classify the SSLv2 support of a server from its SERVER-HELLO
+    ///
+    /// A server sending a SERVER-HELLO is SSLv2-capable, even if the list of cipher kinds is
+    /// empty (some servers accept cipher kinds that they do not announce).
+    pub fn from_server_hello(hello: &SSLv2ServerHello) -> Self {
+        if hello.cipher_specs.iter().any(|c| c.is_export()) {
+            SSLv2Support::ExportCiphers
+        } else {
+            SSLv2Support::Enabled
+        }
+    }
+}
+
+/// Parameters negotiated during a handshake, to be audited
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TlsAudit {
+    /// True if the client offers export-grade RSA cipher suites (from the ClientHello)
+    pub client_export_rsa: bool,
+    /// Negotiated version (from the ServerHello)
+    pub version: Option<TlsVersion>,
+    /// Selected cipher suite (from the ServerHello)
+    pub cipher: Option<TlsCipherSuiteID>,
+    /// Diffie-Hellman group (from the ServerKeyExchange)
+    pub dh_group: Option<DhGroupAnalysis>,
+    /// Size of the temporary RSA key (from the ServerKeyExchange), in bits
+    pub export_rsa_key_bits: Option<usize>,
+    /// SSLv2 support of the server, if a SSLv2 SERVER-HELLO was observed
+    pub server_sslv2: Option<SSLv2Support>,
+}
+
+fn has_forward_secrecy(cipher: &TlsCipherSuite) -> bool {
+    match cipher.kx {
+        TlsCipherKx::Dhe
+        | TlsCipherKx::Ecdhe
+        | TlsCipherKx::Aecdh
+        | TlsCipherKx::Eccpwd
+        | TlsCipherKx::Tls13 => true,
+        // anonymous DH and ECDH use ephemeral keys
+        TlsCipherKx::Dh | TlsCipherKx::Ecdh => cipher.au == TlsCipherAu::Null,
+        _ => false,
+    }
+}
+
+impl TlsAudit {
+    pub fn new() -> Self {
+        TlsAudit::default()
+    }
+
+    /// Extract the negotiated parameters from a handshake message
+    ///
+    /// Messages must be given in order: the ServerKeyExchange message can only be decoded
+    /// after the ServerHello. Other messages are ignored.
+    pub fn observe_message(&mut self, msg: &TlsMessage) {
+        match msg {
+            TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)) => {
+                self.client_export_rsa = !ch.get_export_rsa_ciphers().is_empty();
+            }
+            TlsMessage::Handshake(TlsMessageHandshake::ServerHello(sh)) => {
+                if sh.is_hello_retry_request() {
+                    return;
+                }
+                self.version = Some(sh.get_supported_version().unwrap_or(sh.version));
+                self.cipher = Some(sh.cipher);
+            }
+            TlsMessage::Handshake(TlsMessageHandshake::ServerKeyExchange(ske)) => {
+                let cipher = match self.cipher.and_then(|c| c.get_ciphersuite()) {
+                    Some(cipher) => cipher,
+                    None => return,
+                };
+                let has_algorithm = matches!(self.version, Some(v) if v.0 >= TlsVersion::Tls12.0);
+                match parse_server_key_exchange_params(ske.parameters, cipher, has_algorithm) {
+                    Ok((_, ServerKeyExchangeParams::Dhe(dh, _)))
+                    | Ok((_, ServerKeyExchangeParams::DhAnon(dh)))
+                    | Ok((_, ServerKeyExchangeParams::DhePsk { params: dh, .. })) => {
+                        self.dh_group = Some(dh.analyze_group());
+                    }
+                    Ok((_, ServerKeyExchangeParams::RsaExport(rsa, _))) => {
+                        self.export_rsa_key_bits = Some(rsa.modulus_bits());
+                    }
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Update the SSLv2 support of the server with a SSLv2 message sent by the server
+    ///
+    /// The highest exposure observed is kept.
+    pub fn observe_sslv2_message(&mut self, msg: &SSLv2Message) {
+        if let SSLv2Message::ServerHello(hello) = msg {
+            let support = SSLv2Support::from_server_hello(hello);
+            self.server_sslv2 = self.server_sslv2.max(Some(support));
+        }
+    }
+
+    /// Get the findings for the negotiated parameters, the most severe first
+    ///
+    /// Unknown parameters (for ex. an unknown cipher suite) are not reported.
+    pub fn findings(&self) -> Vec<TlsAuditFinding> {
+        let mut issues = Vec::new();
+        match self.version {
+            Some(v @ TlsVersion::Ssl30)
+            | Some(v @ TlsVersion::Tls10)
+            | Some(v @ TlsVersion::Tls11) => issues.push(TlsAuditIssue::ObsoleteVersion(v)),
+            _ => (),
+        }
+        let cipher = self.cipher.and_then(|id| Some((id, id.get_ciphersuite()?)));
+        if let Some((id, cipher)) = cipher {
+            if cipher.is_export() {
+                issues.push(TlsAuditIssue::ExportCipher(id));
+            }
+            match cipher.enc {
+                TlsCipherEnc::Null => issues.push(TlsAuditIssue::NullEncryption(id)),
+                TlsCipherEnc::Rc4
+                | TlsCipherEnc::Des
+                | TlsCipherEnc::TripleDes
+                | TlsCipherEnc::Rc2 => issues.push(TlsAuditIssue::WeakEncryption(id, cipher.enc)),
+                _ => (),
+            }
+            if cipher.au == TlsCipherAu::Null {
+                issues.push(TlsAuditIssue::AnonymousCipher(id));
+            }
+            if !has_forward_secrecy(cipher) {
+                issues.push(TlsAuditIssue::NoForwardSecrecy(id));
+            }
+        }
+        if self.client_export_rsa {
+            issues.push(TlsAuditIssue::ExportRsaOffered);
+        }
+        if let Some(bits) = self.export_rsa_key_bits {
+            issues.push(TlsAuditIssue::ExportRsaKey { bits });
+        }
+        if let Some(sslv2) = self.server_sslv2 {
+            issues.push(TlsAuditIssue::SSLv2Enabled(sslv2));
+        }
+        match self.dh_group {
+            Some(dh) if dh.risk != DhGroupRisk::Low => issues.push(TlsAuditIssue::WeakDhGroup(dh)),
+            _ => (),
+        }
+        let mut findings: Vec<TlsAuditFinding> = issues.into_iter().map(Into::into).collect();
+        findings.sort_by_key(|f| Reverse(f.severity));
+        findings
+    }
+}
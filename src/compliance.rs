@@ -0,0 +1,285 @@
+//! # Compliance profiles
+//!
+//! A `TlsComplianceProfile` lists the parameters allowed by a configuration policy: protocol
+//! versions, cipher suites, groups and signature schemes. The parameters negotiated for a
+//! session are checked against a profile using `TlsComplianceProfile::check`, which returns
+//! the list of violations.
+//!
+//! Built-in profiles are provided for the [Mozilla server side TLS
+//! recommendations](https://wiki.mozilla.org/Security/Server_Side_TLS) (modern, intermediate
+//! and old configurations, version 5.7) and for
+//! [NIST SP 800-52 Rev. 2](https://doi.org/10.6028/NIST.SP.800-52r2). Custom profiles can be
+//! created by filling the fields of `TlsComplianceProfile`.
+
+use std::fmt;
+
+use crate::tls::*;
+use crate::tls_ec::NamedGroup;
+use crate::tls_sign_hash::SignatureScheme;
+
+static MOZILLA_MODERN_CIPHERS: &[u16] = &[
+    0x1301, // TLS_AES_128_GCM_SHA256
+    0x1302, // TLS_AES_256_GCM_SHA384
+    0x1303, // TLS_CHACHA20_POLY1305_SHA256
+];
+
+static MOZILLA_INTERMEDIATE_CIPHERS: &[u16] = &[
+    0xc02b, // TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256
+    0xc02f, // TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256
+    0xc02c, // TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384
+    0xc030, // TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384
+    0xcca9, // TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256
+    0xcca8, // TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256
+    0x009e, // TLS_DHE_RSA_WITH_AES_128_GCM_SHA256
+    0x009f, // TLS_DHE_RSA_WITH_AES_256_GCM_SHA384
+    0xccaa, // TLS_DHE_RSA_WITH_CHACHA20_POLY1305_SHA256
+];
+
+static MOZILLA_OLD_CIPHERS: &[u16] = &[
+    0xc023, // TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA256
+    0xc027, // TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA256
+    0xc009, // TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA
+    0xc013, // TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA
+    0xc024, // TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA384
+    0xc028, // TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA384
+    0xc00a, // TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA
+    0xc014, // TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA
+    0x0067, // TLS_DHE_RSA_WITH_AES_128_CBC_SHA256
+    0x006b, // TLS_DHE_RSA_WITH_AES_256_CBC_SHA256
+    0x009c, // TLS_RSA_WITH_AES_128_GCM_SHA256
+    0x009d, // TLS_RSA_WITH_AES_256_GCM_SHA384
+    0x003c, // TLS_RSA_WITH_AES_128_CBC_SHA256
+    0x003d, // TLS_RSA_WITH_AES_256_CBC_SHA256
+    0x002f, // TLS_RSA_WITH_AES_128_CBC_SHA
+    0x0035, // TLS_RSA_WITH_AES_256_CBC_SHA
+    0x000a, // TLS_RSA_WITH_3DES_EDE_CBC_SHA
+];
+
+static NIST_CIPHERS: &[u16] = &[
+    0x1301, // TLS_AES_128_GCM_SHA256
+    0x1302, // TLS_AES_256_GCM_SHA384
+    0x1304, // TLS_AES_128_CCM_SHA256
+    0x1305, // TLS_AES_128_CCM_8_SHA256
+    0xc02b, // TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256
+    0xc02c, // TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384
+    0xc0ac, // TLS_ECDHE_ECDSA_WITH_AES_128_CCM
+    0xc0ad, // TLS_ECDHE_ECDSA_WITH_AES_256_CCM
+    0xc0ae, // TLS_ECDHE_ECDSA_WITH_AES_128_CCM_8
+    0xc0af, // TLS_ECDHE_ECDSA_WITH_AES_256_CCM_8
+    0xc023, // TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA256
+    0xc024, // TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA384
+    0xc009, // TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA
+    0xc00a, // TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA
+    0xc02f, // TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256
+    0xc030, // TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384
+    0xc027, // TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA256
+    0xc028, // TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA384
+    0xc013, // TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA
+    0xc014, // TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA
+    0x009e, // TLS_DHE_RSA_WITH_AES_128_GCM_SHA256
+    0x009f, // TLS_DHE_RSA_WITH_AES_256_GCM_SHA384
+    0xc09e, // TLS_DHE_RSA_WITH_AES_128_CCM
+    0xc09f, // TLS_DHE_RSA_WITH_AES_256_CCM
+    0xc0a2, // TLS_DHE_RSA_WITH_AES_128_CCM_8
+    0xc0a3, // TLS_DHE_RSA_WITH_AES_256_CCM_8
+    0x0067, // TLS_DHE_RSA_WITH_AES_128_CBC_SHA256
+    0x006b, // TLS_DHE_RSA_WITH_AES_256_CBC_SHA256
+    0x0033, // TLS_DHE_RSA_WITH_AES_128_CBC_SHA
+    0x0039, // TLS_DHE_RSA_WITH_AES_256_CBC_SHA
+];
+
+static MOZILLA_GROUPS: &[NamedGroup] = &[
+    NamedGroup::EcdhX25519,
+    NamedGroup::Secp256r1,
+    NamedGroup::Secp384r1,
+    NamedGroup::Ffdhe2048,
+];
+
+static NIST_GROUPS: &[NamedGroup] = &[
+    NamedGroup::Secp256r1,
+    NamedGroup::Secp384r1,
+    NamedGroup::Secp521r1,
+    NamedGroup::Ffdhe2048,
+    NamedGroup::Ffdhe3072,
+    NamedGroup::Ffdhe4096,
+    NamedGroup::Ffdhe6144,
+    NamedGroup::Ffdhe8192,
+];
+
+/// Signature schemes using SHA-2 (excluding SHA-1 and MD5) with RSA or ECDSA
+static SHA2_SIGNATURE_SCHEMES: &[SignatureScheme] = &[
+    SignatureScheme::ecdsa_secp256r1_sha256,
+    SignatureScheme::ecdsa_secp384r1_sha384,
+    SignatureScheme::ecdsa_secp521r1_sha512,
+    SignatureScheme::rsa_pss_rsae_sha256,
+    SignatureScheme::rsa_pss_rsae_sha384,
+    SignatureScheme::rsa_pss_rsae_sha512,
+    SignatureScheme::rsa_pss_pss_sha256,
+    SignatureScheme::rsa_pss_pss_sha384,
+    SignatureScheme::rsa_pss_pss_sha512,
+    SignatureScheme::rsa_pkcs1_sha256,
+    SignatureScheme::rsa_pkcs1_sha384,
+    SignatureScheme::rsa_pkcs1_sha512,
+];
+
+/// Parameters negotiated for a session, to be checked against a profile
+///
+/// Parameters set to `None` (unknown, or not used by the key exchange) are not checked.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TlsSessionParameters {
+    pub version: Option<TlsVersion>,
+    pub cipher: Option<TlsCipherSuiteID>,
+    /// Group used for the key exchange
+    pub group: Option<NamedGroup>,
+    /// Signature scheme used by the server to sign the key exchange (or the CertificateVerify)
+    pub signature_scheme: Option<SignatureScheme>,
+}
+
+/// Parameter not allowed by a profile
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsComplianceViolation {
+    Version(TlsVersion),
+    Cipher(TlsCipherSuiteID),
+    Group(NamedGroup),
+    SignatureScheme(SignatureScheme),
+}
+
+impl fmt::Display for TlsComplianceViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TlsComplianceViolation::Version(v) => write!(f, "version {:?} not allowed", v),
+            TlsComplianceViolation::Cipher(c) => match c.get_ciphersuite() {
+                Some(cs) => write!(f, "cipher suite {} not allowed", cs.name),
+                None => write!(f, "cipher suite 0x{:04x} not allowed", c.0),
+            },
+            TlsComplianceViolation::Group(g) => write!(f, "group {:?} not allowed", g),
+            TlsComplianceViolation::SignatureScheme(s) => {
+                write!(f, "signature scheme {} not allowed", s)
+            }
+        }
+    }
+}
+
+/// Result of the check of a session against a profile
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlsComplianceResult<'a> {
+    /// Name of the profile
+    pub profile: &'a str,
+    pub violations: Vec<TlsComplianceViolation>,
+}
+
+impl<'a> TlsComplianceResult<'a> {
+    /// Test if the session complies with the profile
+    pub fn is_compliant(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Compliance profile: parameters allowed by a configuration policy
+///
+/// An empty list allows all values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlsComplianceProfile {
+    pub name: String,
+    pub versions: Vec<TlsVersion>,
+    pub ciphers: Vec<TlsCipherSuiteID>,
+    pub groups: Vec<NamedGroup>,
+    pub signature_schemes: Vec<SignatureScheme>,
+}
+
+fn cipher_ids(ciphers: &[&[u16]]) -> Vec<TlsCipherSuiteID> {
+    ciphers.concat().into_iter().map(TlsCipherSuiteID).collect()
+}
+
+impl TlsComplianceProfile {
+    /// Mozilla "modern" configuration: TLS 1.3 only
+    pub fn mozilla_modern() -> Self {
+        TlsComplianceProfile {
+            name: "Mozilla modern".to_string(),
+            versions: vec![TlsVersion::Tls13],
+            ciphers: cipher_ids(&[MOZILLA_MODERN_CIPHERS]),
+            groups: MOZILLA_GROUPS[..3].to_vec(),
+            signature_schemes: SHA2_SIGNATURE_SCHEMES.to_vec(),
+        }
+    }
+
+    /// Mozilla "intermediate" configuration: TLS 1.2 and 1.3, AEAD cipher suites with forward
+    /// secrecy
+    pub fn mozilla_intermediate() -> Self {
+        TlsComplianceProfile {
+            name: "Mozilla intermediate".to_string(),
+            versions: vec![TlsVersion::Tls12, TlsVersion::Tls13],
+            ciphers: cipher_ids(&[MOZILLA_MODERN_CIPHERS, MOZILLA_INTERMEDIATE_CIPHERS]),
+            groups: MOZILLA_GROUPS.to_vec(),
+            signature_schemes: SHA2_SIGNATURE_SCHEMES.to_vec(),
+        }
+    }
+
+    /// Mozilla "old" configuration: TLS 1.0 to 1.3, for compatibility with legacy clients
+    ///
+    /// Signature schemes are not restricted (legacy clients only support SHA-1).
+    pub fn mozilla_old() -> Self {
+        TlsComplianceProfile {
+            name: "Mozilla old".to_string(),
+            versions: vec![
+                TlsVersion::Tls10,
+                TlsVersion::Tls11,
+                TlsVersion::Tls12,
+                TlsVersion::Tls13,
+            ],
+            ciphers: cipher_ids(&[
+                MOZILLA_MODERN_CIPHERS,
+                MOZILLA_INTERMEDIATE_CIPHERS,
+                MOZILLA_OLD_CIPHERS,
+            ]),
+            groups: MOZILLA_GROUPS.to_vec(),
+            signature_schemes: Vec::new(),
+        }
+    }
+
+    /// NIST SP 800-52 Rev. 2, for TLS servers of government systems
+    pub fn nist_sp800_52r2() -> Self {
+        TlsComplianceProfile {
+            name: "NIST SP 800-52r2".to_string(),
+            versions: vec![TlsVersion::Tls12, TlsVersion::Tls13],
+            ciphers: cipher_ids(&[NIST_CIPHERS]),
+            groups: NIST_GROUPS.to_vec(),
+            signature_schemes: SHA2_SIGNATURE_SCHEMES.to_vec(),
+        }
+    }
+
+    /// Check the parameters of a session against this profile
+    pub fn check(&self, session: &TlsSessionParameters) -> TlsComplianceResult {
+        let mut violations = Vec::new();
+        match session.version {
+            Some(v) if !self.versions.is_empty() && !self.versions.contains(&v) => {
+                violations.push(TlsComplianceViolation::Version(v))
+            }
+            _ => (),
+        }
+        match session.cipher {
+            Some(c) if !self.ciphers.is_empty() && !self.ciphers.contains(&c) => {
+                violations.push(TlsComplianceViolation::Cipher(c))
+            }
+            _ => (),
+        }
+        match session.group {
+            Some(g) if !self.groups.is_empty() && !self.groups.contains(&g) => {
+                violations.push(TlsComplianceViolation::Group(g))
+            }
+            _ => (),
+        }
+        match session.signature_scheme {
+            Some(s)
+                if !self.signature_schemes.is_empty() && !self.signature_schemes.contains(&s) =>
+            {
+                violations.push(TlsComplianceViolation::SignatureScheme(s))
+            }
+            _ => (),
+        }
+        TlsComplianceResult {
+            profile: &self.name,
+            violations,
+        }
+    }
+}
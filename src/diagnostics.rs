@@ -0,0 +1,177 @@
+//! # Parser diagnostics
+//!
+//! The parsers are lenient: data left after a message or an extension is ignored, and unknown
+//! or illegal values are accepted. `parse_tls_plaintext_with_diagnostics` parses a record like
+//! `parse_tls_plaintext`, and also returns the anomalies found while parsing, so monitoring
+//! tools can report traffic that is parseable but not conformant.
+//!
+//! Diagnostics never cause the parsing to fail: the returned record is the same as the one
+//! returned by `parse_tls_plaintext`.
+
+use nom::IResult;
+
+use crate::tls::*;
+use crate::tls_diff::raw_extensions;
+use crate::tls_extensions::*;
+
+/// Anomaly found while parsing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsDiagnostic {
+    /// Anomaly of the record layer version (see `check_record_version`)
+    RecordVersion(TlsRecordVersionWarning),
+    /// Data left in the record after the last message, with its length
+    RecordTrailingData(usize),
+    /// Data left in a handshake message after its content, with its length
+    HandshakeTrailingData(TlsHandshakeType, usize),
+    /// Data left in the extension block of a handshake message after the last complete
+    /// extension, with its length
+    ExtensionsTrailingData(TlsHandshakeType, usize),
+    /// Data left in an extension after its content, with its length
+    ExtensionTrailingData(TlsExtensionType, usize),
+    /// The extension content could not be parsed
+    InvalidExtension(TlsExtensionType),
+    /// The extension content uses a value not allowed by the specifications (see
+    /// `parse_tls_extension_strict`)
+    IllegalExtensionValue(TlsExtensionType),
+    /// The extension appears more than once in the same message
+    DuplicateExtension(TlsExtensionType),
+    /// Extension using a code point from a draft, replaced in the final specification
+    DeprecatedExtension(TlsExtensionType),
+    /// TLS compression (other than `null`) offered or selected (CRIME)
+    DeprecatedCompression,
+}
+
+fn is_deprecated_extension(ext_type: TlsExtensionType) -> bool {
+    matches!(
+        ext_type,
+        TlsExtensionType::KeyShareOld
+            | TlsExtensionType::TicketEarlyDataInfo
+            | TlsExtensionType::ConnectionIdDeprecated
+            | TlsExtensionType::ChannelIdOld
+            | TlsExtensionType::EncryptedServerName
+    )
+}
+
+fn check_extensions(
+    ext: &[u8],
+    ctx: ExtensionContext,
+    msg_type: TlsHandshakeType,
+    diagnostics: &mut Vec<TlsDiagnostic>,
+) {
+    let exts = raw_extensions(ext);
+    let used: usize = exts.iter().map(|(_, data)| 4 + data.len()).sum();
+    if used < ext.len() {
+        diagnostics.push(TlsDiagnostic::ExtensionsTrailingData(
+            msg_type,
+            ext.len() - used,
+        ));
+    }
+    for (idx, &(ext_type, data)) in exts.iter().enumerate() {
+        let previous = exts[..idx].iter().filter(|&&(t, _)| t == ext_type).count();
+        if previous == 1 {
+            diagnostics.push(TlsDiagnostic::DuplicateExtension(ext_type));
+        }
+        if is_deprecated_extension(ext_type) {
+            diagnostics.push(TlsDiagnostic::DeprecatedExtension(ext_type));
+        }
+        match parse_tls_extension_with_type(data, ext_type.0, data.len() as u16, Some(ctx)) {
+            Ok((rem, ext)) => {
+                if !rem.is_empty() {
+                    diagnostics.push(TlsDiagnostic::ExtensionTrailingData(ext_type, rem.len()));
+                }
+                if !is_valid_strict(&ext, Some(ctx)) {
+                    diagnostics.push(TlsDiagnostic::IllegalExtensionValue(ext_type));
+                }
+            }
+            Err(_) => diagnostics.push(TlsDiagnostic::InvalidExtension(ext_type)),
+        }
+    }
+}
+
+fn check_handshake_message(
+    msg: &TlsMessageHandshake,
+    msg_type: TlsHandshakeType,
+    diagnostics: &mut Vec<TlsDiagnostic>,
+) {
+    match msg {
+        TlsMessageHandshake::ClientHello(ch) => {
+            if ch.comp.iter().any(|&c| c != TlsCompressionID::Null) {
+                diagnostics.push(TlsDiagnostic::DeprecatedCompression);
+            }
+            let ext = ch.ext.unwrap_or(&[]);
+            check_extensions(ext, ExtensionContext::ClientHello, msg_type, diagnostics);
+        }
+        TlsMessageHandshake::ServerHello(sh) => {
+            if sh.compression != TlsCompressionID::Null {
+                diagnostics.push(TlsDiagnostic::DeprecatedCompression);
+            }
+            let ctx = if sh.is_hello_retry_request() {
+                ExtensionContext::HelloRetryRequest
+            } else {
+                ExtensionContext::ServerHello
+            };
+            check_extensions(sh.ext.unwrap_or(&[]), ctx, msg_type, diagnostics);
+        }
+        TlsMessageHandshake::EncryptedExtensions(ee) => {
+            let ctx = ExtensionContext::EncryptedExtensions;
+            check_extensions(ee.ext, ctx, msg_type, diagnostics);
+        }
+        _ => (),
+    }
+}
+
+/// Check the handshake messages of a record, and return the data left after the last message
+fn check_handshake_record<'a>(i: &'a [u8], diagnostics: &mut Vec<TlsDiagnostic>) -> &'a [u8] {
+    let mut i = i;
+    while i.len() >= 4 {
+        let msg_type = TlsHandshakeType(i[0]);
+        let hl = (i[1] as usize) << 16 | (i[2] as usize) << 8 | i[3] as usize;
+        if i.len() < 4 + hl {
+            break;
+        }
+        let (raw_msg, rem) = i[4..].split_at(hl);
+        match parse_tls_handshake_msg_body(i, msg_type, raw_msg) {
+            Ok((msg_rem, msg)) => {
+                if !msg_rem.is_empty() {
+                    diagnostics.push(TlsDiagnostic::HandshakeTrailingData(
+                        msg_type,
+                        msg_rem.len(),
+                    ));
+                }
+                check_handshake_message(&msg, msg_type, diagnostics);
+            }
+            Err(_) => break,
+        }
+        i = rem;
+    }
+    i
+}
+
+/// Parse one record as plaintext, and return the anomalies found while parsing
+///
+/// The record is parsed using `parse_tls_plaintext`, and parsing errors are returned the
+/// same way. The extensions of the ClientHello, ServerHello and EncryptedExtensions messages
+/// are checked.
+pub fn parse_tls_plaintext_with_diagnostics(
+    i: &[u8],
+) -> IResult<&[u8], (TlsPlaintext, Vec<TlsDiagnostic>)> {
+    let (rem, plaintext) = parse_tls_plaintext(i)?;
+    let mut diagnostics: Vec<_> = plaintext
+        .record_version_warnings()
+        .into_iter()
+        .map(TlsDiagnostic::RecordVersion)
+        .collect();
+    // the record was parsed, so the header (5 bytes) and the content are complete
+    let content = &i[5..5 + plaintext.hdr.len as usize];
+    let trailing = if plaintext.hdr.record_type == TlsRecordType::Handshake {
+        check_handshake_record(content, &mut diagnostics).len()
+    } else {
+        parse_tls_record_with_header(content, &plaintext.hdr)
+            .map(|(rem, _)| rem.len())
+            .unwrap_or(0)
+    };
+    if trailing > 0 {
+        diagnostics.push(TlsDiagnostic::RecordTrailingData(trailing));
+    }
+    Ok((rem, (plaintext, diagnostics)))
+}
@@ -0,0 +1,554 @@
+//! # DTLS parser
+//! Parsing functions for the DTLS protocol, versions 1.0 and 1.2
+//!
+//! DTLS is defined in [RFC6347](https://tools.ietf.org/html/rfc6347). Connection IDs
+//! are defined in [RFC9146](https://tools.ietf.org/html/rfc9146).
+//!
+//! DTLS 1.3 ([RFC9147](https://tools.ietf.org/html/rfc9147)) uses the same plaintext records,
+//! but encrypted records use a compact "unified header" (see `parse_dtls13_record`).
+
+use nom::bytes::streaming::take;
+use nom::combinator::{complete, cond, map, map_parser, opt, verify};
+use nom::error::{make_error, ErrorKind};
+use nom::multi::{length_data, many1};
+use nom::number::streaming::{be_u16, be_u24, be_u32, be_u8};
+use nom::{Err, IResult};
+
+use crate::tls::*;
+use crate::tls_alert::*;
+
+/// DTLS Plaintext record header
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DTLSRecordHeader {
+    pub content_type: TlsRecordType,
+    pub version: TlsVersion,
+    /// A counter value that is incremented on every cipher state change
+    pub epoch: u16,
+    /// The sequence number for this record (48 bits)
+    pub sequence_number: u64,
+    pub length: u16,
+}
+
+/// DTLS plaintext record
+///
+/// A DTLS record can contain multiple messages (sharing the same record type).
+#[derive(Clone, Debug, PartialEq)]
+pub struct DTLSPlaintext<'a> {
+    pub header: DTLSRecordHeader,
+    pub messages: Vec<DTLSMessage<'a>>,
+}
+
+/// DTLS record with raw (unparsed) data
+///
+/// If the record uses the `tls12_cid` content type, `connection_id` contains the
+/// connection ID. Data is usually encrypted in this case.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DTLSRawRecord<'a> {
+    pub header: DTLSRecordHeader,
+    pub connection_id: Option<&'a [u8]>,
+    pub fragment: &'a [u8],
+}
+
+/// DTLS 1.3 unified header, as defined in [RFC9147](https://tools.ietf.org/html/rfc9147)
+/// section 4
+///
+/// ```text
+///  0 1 2 3 4 5 6 7
+/// +-+-+-+-+-+-+-+-+
+/// |0|0|1|C|S|L|E E|
+/// +-+-+-+-+-+-+-+-+
+/// | Connection ID |   Legend:
+/// | (if any,      |
+/// /  length as    /   C   - Connection ID (CID) present
+/// |  negotiated)  |   S   - Sequence number length
+/// +-+-+-+-+-+-+-+-+   L   - Length present
+/// |  8 or 16 bit  |   E   - Epoch
+/// |Sequence Number|
+/// +-+-+-+-+-+-+-+-+
+/// | 16 bit Length |
+/// | (if present)  |
+/// +-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct DTLSUnifiedHeader<'a> {
+    /// Low order 2 bits of the epoch
+    pub epoch_bits: u8,
+    pub connection_id: Option<&'a [u8]>,
+    /// Low order 8 or 16 bits of the (encrypted) record sequence number
+    pub sequence_number: u16,
+    /// True if the sequence number is encoded on 16 bits
+    pub sequence_number_16: bool,
+    /// Length of the encrypted record, if present. If absent, the record extends to the
+    /// end of the datagram.
+    pub length: Option<u16>,
+}
+
+/// DTLS 1.3 encrypted record
+#[derive(Clone, Debug, PartialEq)]
+pub struct DTLSCiphertext<'a> {
+    pub header: DTLSUnifiedHeader<'a>,
+    pub encrypted_record: &'a [u8],
+}
+
+/// DTLS 1.3 record, as plaintext (with a full header) or encrypted (with a unified header)
+#[derive(Clone, Debug, PartialEq)]
+pub enum DTLS13Record<'a> {
+    Plaintext(DTLSRawRecord<'a>),
+    Ciphertext(DTLSCiphertext<'a>),
+}
+
+/// DTLS Client Hello
+#[derive(Clone, PartialEq)]
+pub struct DTLSClientHello<'a> {
+    pub version: TlsVersion,
+    pub random: &'a [u8],
+    pub session_id: Option<&'a [u8]>,
+    pub cookie: &'a [u8],
+    /// A list of ciphers supported by client
+    pub ciphers: Vec<TlsCipherSuiteID>,
+    /// A list of compression methods supported by client
+    pub comp: Vec<TlsCompressionID>,
+    pub ext: Option<&'a [u8]>,
+}
+
+/// DTLS Hello Verify Request, as defined in [RFC6347](https://tools.ietf.org/html/rfc6347)
+/// section 4.2.1
+#[derive(Clone, PartialEq)]
+pub struct DTLSHelloVerifyRequest<'a> {
+    pub server_version: TlsVersion,
+    pub cookie: &'a [u8],
+}
+
+/// DTLS Generic handshake message
+#[derive(Clone, Debug, PartialEq)]
+pub struct DTLSMessageHandshake<'a> {
+    pub msg_type: TlsHandshakeType,
+    pub length: u32,
+    pub message_seq: u16,
+    pub fragment_offset: u32,
+    pub fragment_length: u32,
+    pub body: DTLSMessageHandshakeBody<'a>,
+}
+
+impl<'a> DTLSMessageHandshake<'a> {
+    /// Test if the message is fragmented (does not contain the full message body)
+    pub fn is_fragment(&self) -> bool {
+        self.fragment_offset != 0 || self.fragment_length != self.length
+    }
+}
+
+/// DTLS Generic handshake message body
+///
+/// If the handshake message is fragmented, the body is stored as `Fragment`
+/// and must be reassembled before being parsed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DTLSMessageHandshakeBody<'a> {
+    HelloRequest,
+    ClientHello(DTLSClientHello<'a>),
+    HelloVerifyRequest(DTLSHelloVerifyRequest<'a>),
+    ServerHello(TlsServerHelloContents<'a>),
+    NewSessionTicket(TlsNewSessionTicketContent<'a>),
+    Certificate(TlsCertificateContents<'a>),
+    ServerKeyExchange(TlsServerKeyExchangeContents<'a>),
+    CertificateRequest(TlsCertificateRequestContents<'a>),
+    ServerDone(&'a [u8]),
+    CertificateVerify(&'a [u8]),
+    ClientKeyExchange(TlsClientKeyExchangeContents<'a>),
+    Finished(&'a [u8]),
+    CertificateStatus(TlsCertificateStatusContents<'a>),
+    Fragment(&'a [u8]),
+}
+
+/// DTLS plaintext message
+///
+/// Plaintext records can only be found during the handshake.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DTLSMessage<'a> {
+    Handshake(DTLSMessageHandshake<'a>),
+    ChangeCipherSpec,
+    Alert(TlsMessageAlert),
+    ApplicationData(TlsMessageApplicationData<'a>),
+    Heartbeat(TlsMessageHeartbeat<'a>),
+}
+
+impl<'a> DTLSMessage<'a> {
+    /// Tell if this DTLSMessage is a (handshake) fragment that needs combining with other
+    /// fragments to be complete.
+    pub fn is_fragment(&self) -> bool {
+        match self {
+            DTLSMessage::Handshake(h) => h.is_fragment(),
+            _ => false,
+        }
+    }
+}
+
+fn be_u48(i: &[u8]) -> IResult<&[u8], u64> {
+    let (i, hi) = be_u16(i)?;
+    let (i, lo) = be_u32(i)?;
+    Ok((i, (u64::from(hi) << 32) | u64::from(lo)))
+}
+
+/// Read DTLS record header
+///
+/// For records with the `tls12_cid` content type, use `parse_dtls_raw_record_with_cid`.
+pub fn parse_dtls_record_header(i: &[u8]) -> IResult<&[u8], DTLSRecordHeader> {
+    let (i, content_type) = TlsRecordType::parse(i)?;
+    let (i, version) = TlsVersion::parse(i)?;
+    let (i, epoch) = be_u16(i)?;
+    let (i, sequence_number) = be_u48(i)?;
+    let (i, length) = be_u16(i)?;
+    let header = DTLSRecordHeader {
+        content_type,
+        version,
+        epoch,
+        sequence_number,
+        length,
+    };
+    Ok((i, header))
+}
+
+fn parse_dtls_client_hello(i: &[u8]) -> IResult<&[u8], DTLSMessageHandshakeBody> {
+    let (i, version) = TlsVersion::parse(i)?;
+    let (i, random) = take(32usize)(i)?;
+    let (i, sidlen) = verify(be_u8, |&n| n <= 32)(i)?;
+    let (i, session_id) = cond(sidlen > 0, take(sidlen as usize))(i)?;
+    let (i, cookie) = length_data(be_u8)(i)?;
+    let (i, ciphers_len) = be_u16(i)?;
+    let (i, ciphers) = parse_cipher_suites(i, ciphers_len as usize)?;
+    let (i, comp_len) = be_u8(i)?;
+    let (i, comp) = parse_compressions_algs(i, comp_len as usize)?;
+    let (i, ext) = opt(complete(length_data(be_u16)))(i)?;
+    let content = DTLSClientHello {
+        version,
+        random,
+        session_id,
+        cookie,
+        ciphers,
+        comp,
+        ext,
+    };
+    Ok((i, DTLSMessageHandshakeBody::ClientHello(content)))
+}
+
+fn parse_dtls_hello_verify_request(i: &[u8]) -> IResult<&[u8], DTLSMessageHandshakeBody> {
+    let (i, server_version) = TlsVersion::parse(i)?;
+    let (i, cookie) = length_data(be_u8)(i)?;
+    let content = DTLSHelloVerifyRequest {
+        server_version,
+        cookie,
+    };
+    Ok((i, DTLSMessageHandshakeBody::HelloVerifyRequest(content)))
+}
+
+// Messages with the same format as TLS are parsed with the TLS parser, and converted
+fn parse_dtls_handshake_msg_as_tls<'a, F>(
+    i: &'a [u8],
+    f: F,
+) -> IResult<&'a [u8], DTLSMessageHandshakeBody<'a>>
+where
+    F: Fn(&'a [u8]) -> IResult<&'a [u8], TlsMessageHandshake<'a>>,
+{
+    let (rem, msg) = f(i)?;
+    let body = match msg {
+        TlsMessageHandshake::HelloRequest => DTLSMessageHandshakeBody::HelloRequest,
+        TlsMessageHandshake::ServerHello(c) => DTLSMessageHandshakeBody::ServerHello(c),
+        TlsMessageHandshake::NewSessionTicket(c) => DTLSMessageHandshakeBody::NewSessionTicket(c),
+        TlsMessageHandshake::Certificate(c) => DTLSMessageHandshakeBody::Certificate(c),
+        TlsMessageHandshake::ServerKeyExchange(c) => DTLSMessageHandshakeBody::ServerKeyExchange(c),
+        TlsMessageHandshake::CertificateRequest(c) => {
+            DTLSMessageHandshakeBody::CertificateRequest(c)
+        }
+        TlsMessageHandshake::ServerDone(c) => DTLSMessageHandshakeBody::ServerDone(c),
+        TlsMessageHandshake::CertificateVerify(c) => DTLSMessageHandshakeBody::CertificateVerify(c),
+        TlsMessageHandshake::ClientKeyExchange(c) => DTLSMessageHandshakeBody::ClientKeyExchange(c),
+        TlsMessageHandshake::Finished(c) => DTLSMessageHandshakeBody::Finished(c),
+        TlsMessageHandshake::CertificateStatus(c) => DTLSMessageHandshakeBody::CertificateStatus(c),
+        _ => return Err(Err::Error(make_error(i, ErrorKind::Switch))),
+    };
+    Ok((rem, body))
+}
+
+pub(crate) fn parse_dtls_handshake_msg_body(
+    i: &[u8],
+    msg_type: TlsHandshakeType,
+    len: usize,
+) -> IResult<&[u8], DTLSMessageHandshakeBody> {
+    match msg_type {
+        TlsHandshakeType::HelloRequest => {
+            parse_dtls_handshake_msg_as_tls(i, parse_tls_handshake_msg_hello_request)
+        }
+        TlsHandshakeType::ClientHello => parse_dtls_client_hello(i),
+        TlsHandshakeType::HelloVerifyRequest => parse_dtls_hello_verify_request(i),
+        TlsHandshakeType::ServerHello => {
+            parse_dtls_handshake_msg_as_tls(i, parse_tls_handshake_msg_server_hello_tlsv12)
+        }
+        TlsHandshakeType::NewSessionTicket => {
+            parse_dtls_handshake_msg_as_tls(i, |d| parse_tls_handshake_msg_newsessionticket(d, len))
+        }
+        TlsHandshakeType::Certificate => {
+            parse_dtls_handshake_msg_as_tls(i, parse_tls_handshake_msg_certificate)
+        }
+        TlsHandshakeType::ServerKeyExchange => parse_dtls_handshake_msg_as_tls(i, |d| {
+            parse_tls_handshake_msg_serverkeyexchange(d, len)
+        }),
+        TlsHandshakeType::CertificateRequest => {
+            parse_dtls_handshake_msg_as_tls(i, parse_tls_handshake_msg_certificaterequest)
+        }
+        TlsHandshakeType::ServerDone => {
+            parse_dtls_handshake_msg_as_tls(i, |d| parse_tls_handshake_msg_serverdone(d, len))
+        }
+        TlsHandshakeType::CertificateVerify => parse_dtls_handshake_msg_as_tls(i, |d| {
+            parse_tls_handshake_msg_certificateverify(d, len)
+        }),
+        TlsHandshakeType::ClientKeyExchange => parse_dtls_handshake_msg_as_tls(i, |d| {
+            parse_tls_handshake_msg_clientkeyexchange(d, len)
+        }),
+        TlsHandshakeType::Finished => {
+            parse_dtls_handshake_msg_as_tls(i, |d| parse_tls_handshake_msg_finished(d, len))
+        }
+        TlsHandshakeType::CertificateStatus => {
+            parse_dtls_handshake_msg_as_tls(i, parse_tls_handshake_msg_certificatestatus)
+        }
+        _ => Err(Err::Error(make_error(i, ErrorKind::Switch))),
+    }
+}
+
+/// Parse a DTLS handshake message
+pub fn parse_dtls_message_handshake(i: &[u8]) -> IResult<&[u8], DTLSMessage> {
+    let (i, msg_type) = TlsHandshakeType::parse(i)?;
+    let (i, length) = be_u24(i)?;
+    let (i, message_seq) = be_u16(i)?;
+    let (i, fragment_offset) = be_u24(i)?;
+    let (i, fragment_length) = be_u24(i)?;
+    let (i, raw_msg) = take(fragment_length)(i)?;
+    let body = if fragment_offset != 0 || fragment_length != length {
+        DTLSMessageHandshakeBody::Fragment(raw_msg)
+    } else {
+        let (_, body) = parse_dtls_handshake_msg_body(raw_msg, msg_type, length as usize)?;
+        body
+    };
+    let msg = DTLSMessageHandshake {
+        msg_type,
+        length,
+        message_seq,
+        fragment_offset,
+        fragment_length,
+        body,
+    };
+    Ok((i, DTLSMessage::Handshake(msg)))
+}
+
+fn parse_dtls_message_changecipherspec(i: &[u8]) -> IResult<&[u8], DTLSMessage> {
+    map(parse_tls_message_changecipherspec_strict, |_| {
+        DTLSMessage::ChangeCipherSpec
+    })(i)
+}
+
+fn parse_dtls_message_alert(i: &[u8]) -> IResult<&[u8], DTLSMessage> {
+    let (i, severity) = be_u8(i)?;
+    let (i, code) = be_u8(i)?;
+    let alert = TlsMessageAlert {
+        severity: TlsAlertSeverity(severity),
+        code: TlsAlertDescription(code),
+    };
+    Ok((i, DTLSMessage::Alert(alert)))
+}
+
+fn parse_dtls_message_applicationdata(i: &[u8]) -> IResult<&[u8], DTLSMessage> {
+    let msg = DTLSMessage::ApplicationData(TlsMessageApplicationData { blob: i });
+    Ok((&[], msg))
+}
+
+fn parse_dtls_message_heartbeat(i: &[u8], len: u16) -> IResult<&[u8], Vec<DTLSMessage>> {
+    let (i, v) = parse_tls_message_heartbeat(i, len)?;
+    let v = v
+        .into_iter()
+        .filter_map(|m| match m {
+            TlsMessage::Heartbeat(h) => Some(DTLSMessage::Heartbeat(h)),
+            _ => None,
+        })
+        .collect();
+    Ok((i, v))
+}
+
+/// Given data and a DTLS record header, parse content.
+///
+/// A record can contain multiple messages (with the same type).
+pub fn parse_dtls_record_with_header<'i>(
+    i: &'i [u8],
+    hdr: &DTLSRecordHeader,
+) -> IResult<&'i [u8], Vec<DTLSMessage<'i>>> {
+    match hdr.content_type {
+        TlsRecordType::ChangeCipherSpec => map(parse_dtls_message_changecipherspec, |m| vec![m])(i),
+        TlsRecordType::Alert => many1(complete(parse_dtls_message_alert))(i),
+        TlsRecordType::Handshake => many1(complete(parse_dtls_message_handshake))(i),
+        TlsRecordType::ApplicationData => many1(complete(parse_dtls_message_applicationdata))(i),
+        TlsRecordType::Heartbeat => parse_dtls_message_heartbeat(i, hdr.length),
+        _ => Err(Err::Error(make_error(i, ErrorKind::Switch))),
+    }
+}
+
+/// Parse one DTLS record, as plaintext
+///
+/// A single record can contain multiple messages, they must share the same record type.
+pub fn parse_dtls_plaintext_record(i: &[u8]) -> IResult<&[u8], DTLSPlaintext> {
+    parse_dtls_plaintext_record_with_limits(i, &TlsRecordLimits::default())
+}
+
+/// Parse one DTLS record, as plaintext, with the specified record length limits
+///
+/// DTLS uses the same limits as TLS (see `TlsRecordLimits`).
+pub fn parse_dtls_plaintext_record_with_limits<'a>(
+    i: &'a [u8],
+    limits: &TlsRecordLimits,
+) -> IResult<&'a [u8], DTLSPlaintext<'a>> {
+    let (i, header) = parse_dtls_record_header(i)?;
+    if header.length > limits.max_plaintext_len {
+        return Err(Err::Error(make_error(i, ErrorKind::TooLarge)));
+    }
+    let (i, messages) = map_parser(take(header.length as usize), |i| {
+        parse_dtls_record_with_header(i, &header)
+    })(i)?;
+    Ok((i, DTLSPlaintext { header, messages }))
+}
+
+/// Parse multiple DTLS records, as plaintext
+///
+/// DTLS records can be packed in the same datagram.
+pub fn parse_dtls_plaintext_records(i: &[u8]) -> IResult<&[u8], Vec<DTLSPlaintext>> {
+    many1(complete(parse_dtls_plaintext_record))(i)
+}
+
+/// Read DTLS record envelope, but do not decode data
+///
+/// Records using the `tls12_cid` content type cannot be parsed with this function,
+/// since the connection ID length is not encoded in the record.
+pub fn parse_dtls_raw_record(i: &[u8]) -> IResult<&[u8], DTLSRawRecord> {
+    parse_dtls_raw_record_with_cid(i, 0)
+}
+
+/// Read DTLS record envelope, with support for connection IDs ([RFC9146])
+///
+/// `cid_len` is the length of the connection ID negotiated for the records received by
+/// this peer. It is only used if the record has the `tls12_cid` content type.
+///
+/// ```text
+/// struct {
+///     ContentType outer_type = tls12_cid;
+///     ProtocolVersion version;
+///     uint16 epoch;
+///     uint48 sequence_number;
+///     opaque cid[cid_length];
+///     uint16 length;
+///     opaque enc_content[DTLSCiphertext.length];
+/// } DTLSCiphertext;
+/// ```
+pub fn parse_dtls_raw_record_with_cid(i: &[u8], cid_len: u8) -> IResult<&[u8], DTLSRawRecord> {
+    parse_dtls_raw_record_with_limits(i, cid_len, &TlsRecordLimits::default())
+}
+
+/// Read DTLS record envelope, with support for connection IDs, and the specified record
+/// length limits
+///
+/// Since the record can be encrypted, its length is checked against the ciphertext limit.
+pub fn parse_dtls_raw_record_with_limits<'a>(
+    i: &'a [u8],
+    cid_len: u8,
+    limits: &TlsRecordLimits,
+) -> IResult<&'a [u8], DTLSRawRecord<'a>> {
+    let (i, content_type) = TlsRecordType::parse(i)?;
+    let (i, version) = TlsVersion::parse(i)?;
+    let (i, epoch) = be_u16(i)?;
+    let (i, sequence_number) = be_u48(i)?;
+    let (i, connection_id) = cond(
+        content_type == TlsRecordType::Tls12Cid,
+        take(cid_len as usize),
+    )(i)?;
+    let (i, length) = be_u16(i)?;
+    if length > limits.max_ciphertext_len {
+        return Err(Err::Error(make_error(i, ErrorKind::TooLarge)));
+    }
+    let (i, fragment) = take(length as usize)(i)?;
+    let header = DTLSRecordHeader {
+        content_type,
+        version,
+        epoch,
+        sequence_number,
+        length,
+    };
+    let record = DTLSRawRecord {
+        header,
+        connection_id,
+        fragment,
+    };
+    Ok((i, record))
+}
+
+/// Test if the first byte of a DTLS record is a DTLS 1.3 unified header
+///
+/// The 3 high bits are `001`, which does not collide with content types of records with a
+/// full header.
+pub fn is_dtls13_unified_header(first_byte: u8) -> bool {
+    first_byte & 0xe0 == 0x20
+}
+
+/// Read a DTLS 1.3 unified header
+///
+/// `cid_len` is the length of the connection ID negotiated for the records received by
+/// this peer. It is only used if the C bit is set.
+pub fn parse_dtls13_unified_header(i: &[u8], cid_len: u8) -> IResult<&[u8], DTLSUnifiedHeader> {
+    let (i, flags) = verify(be_u8, |&b| is_dtls13_unified_header(b))(i)?;
+    let (i, connection_id) = cond(flags & 0x10 != 0, take(cid_len as usize))(i)?;
+    let sequence_number_16 = flags & 0x08 != 0;
+    let (i, sequence_number) = if sequence_number_16 {
+        be_u16(i)?
+    } else {
+        map(be_u8, u16::from)(i)?
+    };
+    let (i, length) = cond(flags & 0x04 != 0, be_u16)(i)?;
+    let header = DTLSUnifiedHeader {
+        epoch_bits: flags & 0x03,
+        connection_id,
+        sequence_number,
+        sequence_number_16,
+        length,
+    };
+    Ok((i, header))
+}
+
+/// Read a DTLS 1.3 encrypted record (with a unified header), but do not decrypt data
+///
+/// If the header has no length, the record extends to the end of the input, which must be
+/// the end of the datagram.
+///
+/// Records larger than `MAX_CIPHERTEXT_RECORD_LEN` are rejected.
+pub fn parse_dtls13_ciphertext(i: &[u8], cid_len: u8) -> IResult<&[u8], DTLSCiphertext> {
+    let (i, header) = parse_dtls13_unified_header(i, cid_len)?;
+    let length = header.length.map_or(i.len(), usize::from);
+    if length > MAX_CIPHERTEXT_RECORD_LEN as usize {
+        return Err(Err::Error(make_error(i, ErrorKind::TooLarge)));
+    }
+    let (i, encrypted_record) = take(length)(i)?;
+    let record = DTLSCiphertext {
+        header,
+        encrypted_record,
+    };
+    Ok((i, record))
+}
+
+/// Read a DTLS 1.3 record envelope, but do not decode data
+///
+/// The record type is given by the first byte: records with a unified header are encrypted,
+/// other records use the full DTLS header (see `parse_dtls_raw_record_with_cid`).
+pub fn parse_dtls13_record(i: &[u8], cid_len: u8) -> IResult<&[u8], DTLS13Record> {
+    match i.first() {
+        Some(&b) if is_dtls13_unified_header(b) => map(
+            |d| parse_dtls13_ciphertext(d, cid_len),
+            DTLS13Record::Ciphertext,
+        )(i),
+        _ => map(
+            |d| parse_dtls_raw_record_with_cid(d, cid_len),
+            DTLS13Record::Plaintext,
+        )(i),
+    }
+}
@@ -0,0 +1,361 @@
+//! # TLS fingerprints
+//!
+//! Fingerprints identify TLS implementations (client libraries, malware, servers) from the
+//! content of their hello messages, which does not depend on the connection.
+//!
+//! [JA3](https://github.com/salesforce/ja3) is computed from the ClientHello: the fields are
+//! the decimal values of the version, cipher suites, extension types, supported groups and
+//! EC point formats, in the order of the message. Values of a field are separated by `-`, and
+//! fields by `,`. GREASE values ([RFC8701](https://tools.ietf.org/html/rfc8701)) are removed.
+//! The fingerprint is the MD5 hash of this string.
+//!
+//! Some clients (for ex. Chrome, since version 110) shuffle the extensions of each
+//! ClientHello, so their JA3 changes for each connection. JA3N is the same fingerprint, with
+//! the extension types sorted. `ExtensionPermutationDetector` detects this behavior.
+//!
+//! [JA4](https://github.com/FoxIO-LLC/ja4) is computed from the ClientHello, and is formatted
+//! as `a_b_c`: `a` contains the protocol, the highest version, the presence of a server
+//! name, the numbers of cipher suites and extensions and the first ALPN value; `b` is the hash
+//! of the sorted cipher suites, and `c` the hash of the sorted extension types and of the
+//! signature algorithms. It does not depend on the extension order.
+//!
+//! JA3S is computed from the ServerHello, using the version, the selected cipher suite and
+//! the extension types. Combined with the JA3 of the client, it identifies the server
+//! implementation (the response depends on the client offer).
+//!
+//! This module requires the `fingerprint` feature.
+
+use std::collections::HashMap;
+
+use sha2::{Digest, Sha256};
+
+use crate::tls::*;
+use crate::tls_extensions::*;
+
+/// A JA3 or JA3S fingerprint string, and its hash
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JA3Fingerprint {
+    /// Fingerprint string, for ex. `769,47-53-5-10,0-10-11,23-24,0`
+    pub string: String,
+    /// MD5 hash of the string, as lowercase hexadecimal
+    pub hash: String,
+}
+
+impl JA3Fingerprint {
+    fn new(string: String) -> Self {
+        let hash = format!("{:x}", md5::compute(string.as_bytes()));
+        JA3Fingerprint { string, hash }
+    }
+}
+
+/// Get the extension types of a raw list of extensions, in order
+///
+/// The extension contents are not parsed, so unknown or invalid extensions are also listed.
+/// Parsing stops at the first truncated extension.
+fn raw_extension_types(mut i: &[u8]) -> Vec<u16> {
+    let mut types = Vec::new();
+    while i.len() >= 4 {
+        let ext_type = u16::from(i[0]) << 8 | u16::from(i[1]);
+        let ext_len = (usize::from(i[2]) << 8 | usize::from(i[3])) + 4;
+        if i.len() < ext_len {
+            break;
+        }
+        types.push(ext_type);
+        i = &i[ext_len..];
+    }
+    types
+}
+
+fn join<T: ToString>(values: impl Iterator<Item = T>) -> String {
+    values.map(|v| v.to_string()).collect::<Vec<_>>().join("-")
+}
+
+/// Extension types of a ClientHello, in the order of the message and in canonical order
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TlsExtensionOrder {
+    /// Extension types, in the order of the message (including GREASE values)
+    pub observed: Vec<TlsExtensionType>,
+    /// Extension types sorted by value, without GREASE values
+    pub canonical: Vec<TlsExtensionType>,
+}
+
+/// Get the extension types of a ClientHello, in observed and canonical order
+pub fn extension_order(client_hello: &TlsClientHelloContents) -> TlsExtensionOrder {
+    let observed: Vec<_> = raw_extension_types(client_hello.ext.unwrap_or(&[]))
+        .into_iter()
+        .map(TlsExtensionType)
+        .collect();
+    let mut canonical: Vec<_> = observed
+        .iter()
+        .filter(|t| !t.is_grease())
+        .cloned()
+        .collect();
+    canonical.sort_by_key(|t| t.0);
+    TlsExtensionOrder {
+        observed,
+        canonical,
+    }
+}
+
+/// Build the JA3 string of a ClientHello
+///
+/// The version is the `legacy_version` of the message (not the versions of the
+/// `supported_versions` extension). GREASE cipher suites, extensions and groups are removed.
+pub fn ja3_string(client_hello: &TlsClientHelloContents) -> String {
+    let ext_types = raw_extension_types(client_hello.ext.unwrap_or(&[]))
+        .into_iter()
+        .filter(|&t| !is_grease_u16(t))
+        .collect();
+    ja3_string_with_extensions(client_hello, ext_types)
+}
+
+/// Build the JA3N string of a ClientHello
+///
+/// This is the JA3 string (see `ja3_string`), with the extension types sorted. It does not
+/// depend on the extension order.
+pub fn ja3n_string(client_hello: &TlsClientHelloContents) -> String {
+    let ext_types = extension_order(client_hello)
+        .canonical
+        .iter()
+        .map(|t| t.0)
+        .collect();
+    ja3_string_with_extensions(client_hello, ext_types)
+}
+
+fn ja3_string_with_extensions(
+    client_hello: &TlsClientHelloContents,
+    ext_types: Vec<u16>,
+) -> String {
+    let ext = client_hello.ext.unwrap_or(&[]);
+    let exts = parse_tls_extensions_in(ext, ExtensionContext::ClientHello)
+        .map(|(_, exts)| exts)
+        .unwrap_or_default();
+    let groups = exts
+        .iter()
+        .find_map(|ext| match ext {
+            TlsExtension::EllipticCurves(groups) => Some(groups.as_slice()),
+            _ => None,
+        })
+        .unwrap_or(&[]);
+    let point_formats = exts
+        .iter()
+        .find_map(|ext| match ext {
+            TlsExtension::EcPointFormats(formats) => Some(formats.as_slice()),
+            _ => None,
+        })
+        .unwrap_or(&[]);
+    format!(
+        "{},{},{},{},{}",
+        client_hello.version.0,
+        join(
+            client_hello
+                .ciphers
+                .iter()
+                .filter(|c| !c.is_grease())
+                .map(|c| c.0)
+        ),
+        join(ext_types.into_iter()),
+        join(groups.iter().filter(|g| !g.is_grease()).map(|g| g.0)),
+        join(point_formats.iter().map(|f| f.0)),
+    )
+}
+
+/// Compute the JA3 fingerprint of a ClientHello
+///
+/// See `ja3_string` for the content of the fingerprint string.
+pub fn ja3_fingerprint(client_hello: &TlsClientHelloContents) -> JA3Fingerprint {
+    JA3Fingerprint::new(ja3_string(client_hello))
+}
+
+/// Compute the JA3N fingerprint of a ClientHello
+///
+/// See `ja3n_string` for the content of the fingerprint string.
+pub fn ja3n_fingerprint(client_hello: &TlsClientHelloContents) -> JA3Fingerprint {
+    JA3Fingerprint::new(ja3n_string(client_hello))
+}
+
+/// Get the JA4 version code of a TLS or DTLS version
+fn ja4_version(version: TlsVersion) -> &'static str {
+    match version {
+        TlsVersion::Tls13 => "13",
+        TlsVersion::Tls12 => "12",
+        TlsVersion::Tls11 => "11",
+        TlsVersion::Tls10 => "10",
+        TlsVersion::Ssl30 => "s3",
+        TlsVersion::Ssl20 => "s2",
+        TlsVersion::DTls10 => "d1",
+        TlsVersion::DTls12 => "d2",
+        TlsVersion::DTls13 => "d3",
+        _ => "00",
+    }
+}
+
+/// Get the JA4 code of the first ALPN value: its first and last characters if alphanumeric,
+/// or the first and last hexadecimal digits otherwise
+fn ja4_alpn(alpn: Option<&[u8]>) -> String {
+    match alpn {
+        Some(v) if !v.is_empty() => {
+            let (first, last) = (v[0], v[v.len() - 1]);
+            if first.is_ascii_alphanumeric() && last.is_ascii_alphanumeric() {
+                format!("{}{}", first as char, last as char)
+            } else {
+                let (first, last) = (format!("{:02x}", first), format!("{:02x}", last));
+                format!("{}{}", &first[..1], &last[1..])
+            }
+        }
+        _ => "00".to_string(),
+    }
+}
+
+/// Hash a list of values for JA4: first 12 characters of the SHA-256 hash, or `000000000000`
+/// if the list is empty
+fn ja4_hash(s: &str) -> String {
+    if s.is_empty() {
+        return "000000000000".to_string();
+    }
+    let digest = Sha256::digest(s.as_bytes());
+    digest[..6].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn join_hex<I: Iterator<Item = u16>>(values: I) -> String {
+    values
+        .map(|v| format!("{:04x}", v))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Get the highest version offered by the client: the newest version of the
+/// `supported_versions` extension, or the `legacy_version` if the extension is absent
+pub(crate) fn offered_version(
+    client_hello: &TlsClientHelloContents,
+    exts: &[TlsExtension],
+) -> TlsVersion {
+    exts.iter()
+        .find_map(|ext| match ext {
+            TlsExtension::SupportedVersions(v) => {
+                v.iter()
+                    .filter(|v| !v.is_grease())
+                    .fold(None, |max, &v| match max {
+                        Some(max) if !is_version_newer(v, max) => Some(max),
+                        _ => Some(v),
+                    })
+            }
+            _ => None,
+        })
+        .unwrap_or(client_hello.version)
+}
+
+/// Compute the JA4 fingerprint of a ClientHello
+///
+/// `protocol` is `t` for TLS over TCP, `q` for QUIC and `d` for DTLS. The version is the
+/// highest version of the `supported_versions` extension, or the `legacy_version` if the
+/// extension is absent. GREASE values are ignored.
+pub fn ja4_fingerprint(client_hello: &TlsClientHelloContents, protocol: char) -> String {
+    let ext = client_hello.ext.unwrap_or(&[]);
+    let exts = parse_tls_extensions_in(ext, ExtensionContext::ClientHello)
+        .map(|(_, exts)| exts)
+        .unwrap_or_default();
+    let ext_types = extension_order(client_hello).canonical;
+    let mut ciphers: Vec<u16> = client_hello
+        .ciphers
+        .iter()
+        .filter(|c| !c.is_grease())
+        .map(|c| c.0)
+        .collect();
+    ciphers.sort_unstable();
+    let version = offered_version(client_hello, &exts);
+    let alpn = exts.iter().find_map(|ext| match ext {
+        TlsExtension::ALPN(v) => v.first().cloned(),
+        _ => None,
+    });
+    let sig_algs = exts
+        .iter()
+        .find_map(|ext| match ext {
+            TlsExtension::SignatureAlgorithms(v) => Some(join_hex(
+                v.iter().map(|s| s.0).filter(|&s| !is_grease_u16(s)),
+            )),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let a = format!(
+        "{}{}{}{:02}{:02}{}",
+        protocol,
+        ja4_version(version),
+        if ext_types.contains(&TlsExtensionType::ServerName) {
+            'd'
+        } else {
+            'i'
+        },
+        ciphers.len().min(99),
+        ext_types.len().min(99),
+        ja4_alpn(alpn),
+    );
+    let b = ja4_hash(&join_hex(ciphers.into_iter()));
+    let mut c = join_hex(ext_types.iter().map(|t| t.0).filter(|&t| {
+        TlsExtensionType(t) != TlsExtensionType::ServerName
+            && TlsExtensionType(t) != TlsExtensionType::ApplicationLayerProtocolNegotiation
+    }));
+    if !sig_algs.is_empty() {
+        c = format!("{}_{}", c, sig_algs);
+    }
+    format!("{}_{}_{}", a, b, ja4_hash(&c))
+}
+
+/// Detector of ClientHello extension order permutation
+///
+/// For each distinct JA3N fingerprint, the detector remembers the extension order of the first
+/// ClientHello (GREASE values excluded, since their position is fixed but their values are
+/// random). A client sending the same ClientHello content with a different extension order
+/// shuffles its extensions.
+///
+/// The detector keeps one entry per JA3N fingerprint: it should be used for one client (for
+/// ex. one source address), and cleared periodically.
+#[derive(Clone, Debug, Default)]
+pub struct ExtensionPermutationDetector {
+    orders: HashMap<String, Vec<u16>>,
+}
+
+impl ExtensionPermutationDetector {
+    pub fn new() -> Self {
+        ExtensionPermutationDetector::default()
+    }
+
+    /// Add a ClientHello, and test if its extension order differs from a previous ClientHello
+    /// with the same JA3N fingerprint
+    pub fn observe(&mut self, client_hello: &TlsClientHelloContents) -> bool {
+        let order: Vec<u16> = raw_extension_types(client_hello.ext.unwrap_or(&[]))
+            .into_iter()
+            .filter(|&t| !is_grease_u16(t))
+            .collect();
+        let first = self
+            .orders
+            .entry(ja3n_string(client_hello))
+            .or_insert_with(|| order.clone());
+        *first != order
+    }
+
+    /// Forget all ClientHello messages
+    pub fn clear(&mut self) {
+        self.orders.clear();
+    }
+}
+
+/// Build the JA3S string of a ServerHello
+///
+/// The version is the `legacy_version` of the message. Servers do not send GREASE values, so
+/// no value is removed.
+pub fn ja3s_string(server_hello: &TlsServerHelloContents) -> String {
+    format!(
+        "{},{},{}",
+        server_hello.version.0,
+        server_hello.cipher.0,
+        join(raw_extension_types(server_hello.ext.unwrap_or(&[])).into_iter()),
+    )
+}
+
+/// Compute the JA3S fingerprint of a ServerHello
+///
+/// See `ja3s_string` for the content of the fingerprint string.
+pub fn ja3s_fingerprint(server_hello: &TlsServerHelloContents) -> JA3Fingerprint {
+    JA3Fingerprint::new(ja3s_string(server_hello))
+}
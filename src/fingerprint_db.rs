@@ -0,0 +1,260 @@
+//! # Fingerprint database
+//!
+//! `FingerprintDb` matches ClientHello messages against a list of known fingerprints, and
+//! returns the labels of the matching entries.
+//!
+//! The database is a text file, with one entry per line. Empty lines and lines starting with
+//! `#` are ignored. Each entry has a type, a value, and a label (the rest of the line):
+//!
+//! ```text
+//! # JA3 hash
+//! ja3 e7d705a3286e19ea42f587b344ee6865 Tor Browser
+//! # JA4 fingerprint
+//! ja4 t13d1516h2_8daaf6152771_02713d6af862 Chrome
+//! # rule: conditions separated by ','
+//! rule version=0x0303,cipher=0x0005,!ext=0x0023 Legacy RC4 client
+//! ```
+//!
+//! Rule conditions are `version=V` (highest version offered, see `ja4_fingerprint`),
+//! `cipher=C` (cipher suite offered) and `ext=E` (extension present). A condition starting with
+//! `!` is negated. Values are decimal, or hexadecimal if prefixed with `0x`. A rule matches if
+//! all its conditions are true. GREASE values are ignored, as in JA3 and JA4.
+//!
+//! This module requires the `fingerprint` feature.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::fingerprint::*;
+use crate::tls::*;
+use crate::tls_extensions::*;
+
+/// Type of a fingerprint database entry
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FingerprintKind {
+    JA3,
+    JA4,
+    Rule,
+}
+
+/// A database entry matching a ClientHello
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FingerprintMatch<'a> {
+    pub kind: FingerprintKind,
+    pub label: &'a str,
+}
+
+/// Error returned when loading a fingerprint database
+#[derive(Debug)]
+pub enum FingerprintDbError {
+    Io(io::Error),
+    /// The line (starting at 1) is not a valid entry
+    InvalidLine(usize),
+}
+
+impl From<io::Error> for FingerprintDbError {
+    fn from(e: io::Error) -> Self {
+        FingerprintDbError::Io(e)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RuleCondition {
+    Version(u16),
+    Cipher(u16),
+    Extension(u16),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Rule {
+    /// Conditions, and `true` if the condition is negated
+    conditions: Vec<(RuleCondition, bool)>,
+    label: String,
+}
+
+/// Values of a ClientHello used by the rules
+struct RuleInput {
+    version: u16,
+    ciphers: Vec<u16>,
+    ext_types: Vec<u16>,
+}
+
+impl RuleCondition {
+    fn parse(s: &str) -> Option<(RuleCondition, bool)> {
+        let negated = s.starts_with('!');
+        let s = if negated { &s[1..] } else { s };
+        let pos = s.find('=')?;
+        let (name, value) = (&s[..pos], &s[pos + 1..]);
+        let value = match value.find("0x") {
+            Some(0) => u16::from_str_radix(&value[2..], 16).ok()?,
+            _ => value.parse().ok()?,
+        };
+        let cond = match name {
+            "version" => RuleCondition::Version(value),
+            "cipher" => RuleCondition::Cipher(value),
+            "ext" => RuleCondition::Extension(value),
+            _ => return None,
+        };
+        Some((cond, negated))
+    }
+
+    fn is_match(self, input: &RuleInput) -> bool {
+        match self {
+            RuleCondition::Version(v) => input.version == v,
+            RuleCondition::Cipher(c) => input.ciphers.contains(&c),
+            RuleCondition::Extension(e) => input.ext_types.contains(&e),
+        }
+    }
+}
+
+/// Database of known ClientHello fingerprints
+///
+/// JA4 fingerprints are computed for TLS over TCP (see `ja4_fingerprint`).
+#[derive(Clone, Debug, Default)]
+pub struct FingerprintDb {
+    ja3: HashMap<String, Vec<String>>,
+    ja4: HashMap<String, Vec<String>>,
+    rules: Vec<Rule>,
+}
+
+impl FingerprintDb {
+    /// Create an empty database
+    pub fn new() -> Self {
+        FingerprintDb::default()
+    }
+
+    /// Load a database from a file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, FingerprintDbError> {
+        let data = fs::read_to_string(path)?;
+        FingerprintDb::parse(&data)
+    }
+
+    /// Load a database from the content of a file
+    pub fn parse(data: &str) -> Result<Self, FingerprintDbError> {
+        let mut db = FingerprintDb::new();
+        for (idx, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            db.add_line(line)
+                .ok_or(FingerprintDbError::InvalidLine(idx + 1))?;
+        }
+        Ok(db)
+    }
+
+    fn add_line(&mut self, line: &str) -> Option<()> {
+        let mut parts = line.splitn(3, char::is_whitespace);
+        let kind = parts.next()?;
+        let value = parts.next()?;
+        let label = parts.next()?.trim();
+        if value.is_empty() || label.is_empty() {
+            return None;
+        }
+        match kind {
+            "ja3" => self.add_ja3(value, label),
+            "ja4" => self.add_ja4(value, label),
+            "rule" => {
+                let conditions = value
+                    .split(',')
+                    .map(RuleCondition::parse)
+                    .collect::<Option<Vec<_>>>()?;
+                self.rules.push(Rule {
+                    conditions,
+                    label: label.to_string(),
+                });
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+
+    /// Add a JA3 hash (the MD5 hash, not the fingerprint string)
+    pub fn add_ja3(&mut self, hash: &str, label: &str) {
+        self.ja3
+            .entry(hash.to_ascii_lowercase())
+            .or_default()
+            .push(label.to_string());
+    }
+
+    /// Add a JA4 fingerprint
+    pub fn add_ja4(&mut self, fingerprint: &str, label: &str) {
+        self.ja4
+            .entry(fingerprint.to_string())
+            .or_default()
+            .push(label.to_string());
+    }
+
+    /// Get the number of entries
+    pub fn len(&self) -> usize {
+        self.ja3.values().map(Vec::len).sum::<usize>()
+            + self.ja4.values().map(Vec::len).sum::<usize>()
+            + self.rules.len()
+    }
+
+    /// Test if the database has no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get all entries matching a ClientHello
+    ///
+    /// JA3 matches are returned first, then JA4 matches, then rules (in the order of the
+    /// database).
+    pub fn lookup(&self, client_hello: &TlsClientHelloContents) -> Vec<FingerprintMatch> {
+        let mut matches = Vec::new();
+        if !self.ja3.is_empty() {
+            let ja3 = ja3_fingerprint(client_hello);
+            if let Some(labels) = self.ja3.get(&ja3.hash) {
+                matches.extend(labels.iter().map(|label| FingerprintMatch {
+                    kind: FingerprintKind::JA3,
+                    label,
+                }));
+            }
+        }
+        if !self.ja4.is_empty() {
+            let ja4 = ja4_fingerprint(client_hello, 't');
+            if let Some(labels) = self.ja4.get(&ja4) {
+                matches.extend(labels.iter().map(|label| FingerprintMatch {
+                    kind: FingerprintKind::JA4,
+                    label,
+                }));
+            }
+        }
+        if !self.rules.is_empty() {
+            let exts = parse_tls_extensions_in(
+                client_hello.ext.unwrap_or(&[]),
+                ExtensionContext::ClientHello,
+            )
+            .map(|(_, exts)| exts)
+            .unwrap_or_default();
+            let input = RuleInput {
+                version: offered_version(client_hello, &exts).0,
+                ciphers: client_hello
+                    .ciphers
+                    .iter()
+                    .filter(|c| !c.is_grease())
+                    .map(|c| c.0)
+                    .collect(),
+                ext_types: extension_order(client_hello)
+                    .observed
+                    .iter()
+                    .filter(|t| !t.is_grease())
+                    .map(|t| t.0)
+                    .collect(),
+            };
+            let rules = self.rules.iter().filter(|rule| {
+                rule.conditions
+                    .iter()
+                    .all(|&(cond, negated)| cond.is_match(&input) != negated)
+            });
+            matches.extend(rules.map(|rule| FingerprintMatch {
+                kind: FingerprintKind::Rule,
+                label: &rule.label,
+            }));
+        }
+        matches
+    }
+}
@@ -0,0 +1,439 @@
+//! # JARM fingerprints
+//!
+//! [JARM](https://github.com/salesforce/jarm) is an active fingerprint of TLS servers: ten
+//! ClientHello messages (the probes), using different versions, cipher suites, cipher and
+//! extension orders, are sent to the server, and the JARM hash is computed from the ten
+//! ServerHello messages received.
+//!
+//! For each probe, the response is summarized as `cipher|version|alpn|extensions`: the
+//! selected cipher suite and the `legacy_version` of the ServerHello (in hexadecimal), the
+//! selected ALPN protocol, and the extension types (in hexadecimal, separated by `-`). If the
+//! server did not answer with a ServerHello, the response is `|||`.
+//!
+//! The hash is 62 characters long: for each probe, the index of the selected cipher suite (2
+//! characters) and the version (1 character), followed by the first half of the SHA-256 hash
+//! of the ALPN protocols and extension types.
+//!
+//! This module requires the `fingerprint` and `serialize` features.
+
+use cookie_factory::gen_simple;
+use sha2::{Digest, Sha256};
+
+use crate::tls::*;
+use crate::tls_ec::NamedGroup;
+use crate::tls_extensions::*;
+use crate::tls_serialize::*;
+
+/// Cipher suites offered by the probes, before reordering
+#[rustfmt::skip]
+const JARM_CIPHERS: &[u16] = &[
+    0x0016, 0x0033, 0x0067, 0xc09e, 0xc0a2, 0x009e, 0x0039, 0x006b,
+    0xc09f, 0xc0a3, 0x009f, 0x0045, 0x00be, 0x0088, 0x00c4, 0x009a,
+    0xc008, 0xc009, 0xc023, 0xc0ac, 0xc0ae, 0xc02b, 0xc00a, 0xc024,
+    0xc0ad, 0xc0af, 0xc02c, 0xc072, 0xc073, 0xcca9, 0x1302, 0x1301,
+    0xcc14, 0xc007, 0xc012, 0xc013, 0xc027, 0xc02f, 0xc014, 0xc028,
+    0xc030, 0xc060, 0xc061, 0xc076, 0xc077, 0xcca8, 0x1305, 0x1304,
+    0x1303, 0xcc13, 0xc011, 0x000a, 0x002f, 0x003c, 0xc09c, 0xc0a0,
+    0x009c, 0x0035, 0x003d, 0xc09d, 0xc0a1, 0x009d, 0x0041, 0x00ba,
+    0x0084, 0x00c0, 0x0007, 0x0004, 0x0005,
+];
+
+/// Cipher suites, sorted by value, used to compute the hash
+#[rustfmt::skip]
+const JARM_HASH_CIPHERS: &[u16] = &[
+    0x0004, 0x0005, 0x0007, 0x000a, 0x0016, 0x002f, 0x0033, 0x0035,
+    0x0039, 0x003c, 0x003d, 0x0041, 0x0045, 0x0067, 0x006b, 0x0084,
+    0x0088, 0x009a, 0x009c, 0x009d, 0x009e, 0x009f, 0x00ba, 0x00be,
+    0x00c0, 0x00c4, 0xc007, 0xc008, 0xc009, 0xc00a, 0xc011, 0xc012,
+    0xc013, 0xc014, 0xc023, 0xc024, 0xc027, 0xc028, 0xc02b, 0xc02c,
+    0xc02f, 0xc030, 0xc060, 0xc061, 0xc072, 0xc073, 0xc076, 0xc077,
+    0xc09c, 0xc09d, 0xc09e, 0xc09f, 0xc0a0, 0xc0a1, 0xc0a2, 0xc0a3,
+    0xc0ac, 0xc0ad, 0xc0ae, 0xc0af, 0xcc13, 0xcc14, 0xcca8, 0xcca9,
+    0x1301, 0x1302, 0x1303, 0x1304, 0x1305,
+];
+
+/// ALPN protocols offered by the probes, from the weakest to the strongest
+const JARM_ALPN: &[&[u8]] = &[
+    b"http/0.9",
+    b"http/1.0",
+    b"http/1.1",
+    b"spdy/1",
+    b"spdy/2",
+    b"spdy/3",
+    b"h2",
+    b"h2c",
+    b"hq",
+];
+
+/// ALPN protocols offered by the probes using rare protocols (without `http/1.1` and `h2`)
+const JARM_RARE_ALPN: &[&[u8]] = &[
+    b"http/0.9",
+    b"http/1.0",
+    b"spdy/1",
+    b"spdy/2",
+    b"spdy/3",
+    b"h2c",
+    b"hq",
+];
+
+/// Order of a list of values (cipher suites, ALPN protocols or versions) in a probe
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JarmOrder {
+    Forward,
+    Reverse,
+    /// The second half of the reversed list (and the middle value, if the length is odd)
+    TopHalf,
+    /// The second half of the list
+    BottomHalf,
+    /// The middle value, then alternately the values after and before it
+    MiddleOut,
+}
+
+impl JarmOrder {
+    fn apply<T: Copy>(self, values: &[T]) -> Vec<T> {
+        let len = values.len();
+        let middle = len / 2;
+        match self {
+            JarmOrder::Forward => values.to_vec(),
+            JarmOrder::Reverse => values.iter().rev().copied().collect(),
+            JarmOrder::BottomHalf => values[len - middle..].to_vec(),
+            JarmOrder::TopHalf => {
+                let mut v = Vec::new();
+                if len % 2 == 1 {
+                    v.push(values[middle]);
+                }
+                v.extend(JarmOrder::BottomHalf.apply(&JarmOrder::Reverse.apply(values)));
+                v
+            }
+            JarmOrder::MiddleOut => {
+                let mut v = Vec::with_capacity(len);
+                if len % 2 == 1 {
+                    v.push(values[middle]);
+                    for i in 1..=middle {
+                        v.push(values[middle + i]);
+                        v.push(values[middle - i]);
+                    }
+                } else {
+                    for i in 1..=middle {
+                        v.push(values[middle - 1 + i]);
+                        v.push(values[middle - i]);
+                    }
+                }
+                v
+            }
+        }
+    }
+}
+
+/// Versions offered by a probe in the `supported_versions` extension
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JarmSupportedVersions {
+    /// No `supported_versions` extension
+    None,
+    /// TLS 1.0 to TLS 1.2
+    Tls12,
+    /// TLS 1.0 to TLS 1.3
+    Tls13,
+}
+
+/// Parameters of a JARM probe
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JarmProbe {
+    /// Version of the ClientHello (TLS 1.3 probes use the TLS 1.2 version)
+    pub version: TlsVersion,
+    /// Offer the TLS 1.3 cipher suites
+    pub tls13_ciphers: bool,
+    pub cipher_order: JarmOrder,
+    /// Add GREASE values to the cipher suites and extensions
+    pub grease: bool,
+    /// Offer the rare ALPN protocols only (without `http/1.1` and `h2`)
+    pub rare_alpn: bool,
+    pub supported_versions: JarmSupportedVersions,
+    /// Order of the ALPN protocols and supported versions
+    pub extension_order: JarmOrder,
+}
+
+macro_rules! jarm_probe {
+    ($version:ident, $tls13:expr, $order:ident, $grease:expr, $rare:expr, $sv:ident, $ext:ident) => {
+        JarmProbe {
+            version: TlsVersion::$version,
+            tls13_ciphers: $tls13,
+            cipher_order: JarmOrder::$order,
+            grease: $grease,
+            rare_alpn: $rare,
+            supported_versions: JarmSupportedVersions::$sv,
+            extension_order: JarmOrder::$ext,
+        }
+    };
+}
+
+/// The ten JARM probes, in the order used to compute the hash
+#[rustfmt::skip]
+pub const JARM_PROBES: [JarmProbe; 10] = [
+    jarm_probe!(Tls12, true,  Forward,    false, false, Tls12, Reverse),
+    jarm_probe!(Tls12, true,  Reverse,    false, false, Tls12, Forward),
+    jarm_probe!(Tls12, true,  TopHalf,    false, false, None,  Forward),
+    jarm_probe!(Tls12, true,  BottomHalf, false, true,  None,  Forward),
+    jarm_probe!(Tls12, true,  MiddleOut,  true,  true,  None,  Reverse),
+    jarm_probe!(Tls11, true,  Forward,    false, false, None,  Forward),
+    jarm_probe!(Tls13, true,  Forward,    false, false, Tls13, Reverse),
+    jarm_probe!(Tls13, true,  Reverse,    false, false, Tls13, Forward),
+    jarm_probe!(Tls13, false, Forward,    false, false, Tls13, Forward),
+    jarm_probe!(Tls13, true,  MiddleOut,  true,  false, Tls13, Reverse),
+];
+
+fn push_extension(v: &mut Vec<u8>, ext_type: TlsExtensionType, data: &[u8]) {
+    v.extend_from_slice(&ext_type.0.to_be_bytes());
+    v.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    v.extend_from_slice(data);
+}
+
+fn gen_extension(v: &mut Vec<u8>, ext: &TlsExtension) -> Result<(), GenError> {
+    let buf = gen_simple(gen_tls_extension(ext), Vec::new())?;
+    v.extend_from_slice(&buf);
+    Ok(())
+}
+
+impl JarmProbe {
+    /// Build the extensions of the ClientHello
+    fn extensions(&self, host: &str, grease: u16, key_share: &[u8]) -> Result<Vec<u8>, GenError> {
+        let mut v = Vec::new();
+        if self.grease {
+            push_extension(&mut v, TlsExtensionType(grease), &[]);
+        }
+        let sni = TlsExtension::SNI(vec![(SNIType::HostName, host.as_bytes())]);
+        gen_extension(&mut v, &sni)?;
+        push_extension(&mut v, TlsExtensionType::ExtendedMasterSecret, &[]);
+        let mfl = TlsExtension::MaxFragmentLength(MaxFragmentLength::Length512);
+        gen_extension(&mut v, &mfl)?;
+        push_extension(&mut v, TlsExtensionType::RenegotiationInfo, &[0]);
+        let groups = TlsExtension::EllipticCurves(vec![
+            NamedGroup::EcdhX25519,
+            NamedGroup::Secp256r1,
+            NamedGroup::Secp384r1,
+            NamedGroup::Secp521r1,
+        ]);
+        gen_extension(&mut v, &groups)?;
+        push_extension(&mut v, TlsExtensionType::EcPointFormats, &[0x01, 0x00]);
+        push_extension(&mut v, TlsExtensionType::SessionTicketTLS, &[]);
+        let alpn = if self.rare_alpn {
+            JARM_RARE_ALPN
+        } else {
+            JARM_ALPN
+        };
+        let mut alpn_list = Vec::new();
+        for proto in self.extension_order.apply(alpn) {
+            alpn_list.push(proto.len() as u8);
+            alpn_list.extend_from_slice(proto);
+        }
+        let mut data = (alpn_list.len() as u16).to_be_bytes().to_vec();
+        data.extend_from_slice(&alpn_list);
+        push_extension(
+            &mut v,
+            TlsExtensionType::ApplicationLayerProtocolNegotiation,
+            &data,
+        );
+        let sig_algs = &[
+            0x00, 0x12, 0x04, 0x03, 0x08, 0x04, 0x04, 0x01, 0x05, 0x03, 0x08, 0x05, 0x05, 0x01,
+            0x08, 0x06, 0x06, 0x01, 0x02, 0x01,
+        ];
+        push_extension(&mut v, TlsExtensionType::SignatureAlgorithms, sig_algs);
+        let mut shares = Vec::new();
+        if self.grease {
+            shares.extend_from_slice(&grease.to_be_bytes());
+            shares.extend_from_slice(&[0x00, 0x01, 0x00]);
+        }
+        shares.extend_from_slice(&NamedGroup::EcdhX25519.0.to_be_bytes());
+        shares.extend_from_slice(&(key_share.len() as u16).to_be_bytes());
+        shares.extend_from_slice(key_share);
+        let mut data = (shares.len() as u16).to_be_bytes().to_vec();
+        data.extend_from_slice(&shares);
+        push_extension(&mut v, TlsExtensionType::KeyShare, &data);
+        push_extension(&mut v, TlsExtensionType::PskExchangeModes, &[0x01, 0x01]);
+        let versions: &[TlsVersion] = match self.supported_versions {
+            JarmSupportedVersions::None => &[],
+            JarmSupportedVersions::Tls12 => {
+                &[TlsVersion::Tls10, TlsVersion::Tls11, TlsVersion::Tls12]
+            }
+            JarmSupportedVersions::Tls13 => &[
+                TlsVersion::Tls10,
+                TlsVersion::Tls11,
+                TlsVersion::Tls12,
+                TlsVersion::Tls13,
+            ],
+        };
+        if !versions.is_empty() {
+            let mut list = Vec::new();
+            if self.grease {
+                list.extend_from_slice(&grease.to_be_bytes());
+            }
+            for version in self.extension_order.apply(versions) {
+                list.extend_from_slice(&version.0.to_be_bytes());
+            }
+            let mut data = vec![list.len() as u8];
+            data.extend_from_slice(&list);
+            push_extension(&mut v, TlsExtensionType::SupportedVersions, &data);
+        }
+        Ok(v)
+    }
+
+    /// Build the record containing the ClientHello of the probe
+    ///
+    /// The random values of the message (random, session ID, key share and GREASE values) are
+    /// derived from `seed`, which should be different for each probe.
+    pub fn client_hello_record(&self, host: &str, seed: &[u8; 32]) -> Result<Vec<u8>, GenError> {
+        let session_id = Sha256::new().chain(seed).chain(b"session_id").finalize();
+        let key_share = Sha256::new().chain(seed).chain(b"key_share").finalize();
+        let grease = 0x0a0a + 0x1010 * u16::from(seed[0] % 16);
+        let (record_version, version) = if self.version == TlsVersion::Tls13 {
+            (TlsVersion::Tls10, TlsVersion::Tls12)
+        } else {
+            (self.version, self.version)
+        };
+        let ciphers: Vec<_> = JARM_CIPHERS
+            .iter()
+            .filter(|&&c| self.tls13_ciphers || c >> 8 != 0x13)
+            .copied()
+            .collect();
+        let mut ciphers: Vec<_> = self
+            .cipher_order
+            .apply(&ciphers)
+            .into_iter()
+            .map(TlsCipherSuiteID)
+            .collect();
+        if self.grease {
+            ciphers.insert(0, TlsCipherSuiteID(grease));
+        }
+        let ext = self.extensions(host, grease, &key_share[..])?;
+        let ch = TlsClientHelloContents {
+            version,
+            rand_time: u32::from_be_bytes([seed[0], seed[1], seed[2], seed[3]]),
+            rand_data: &seed[4..],
+            session_id: Some(&session_id[..]),
+            ciphers,
+            comp: vec![TlsCompressionID::Null],
+            ext: Some(&ext),
+        };
+        let record = TlsPlaintext {
+            hdr: TlsRecordHeader {
+                record_type: TlsRecordType::Handshake,
+                version: record_version,
+                len: 0,
+            },
+            msg: vec![TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch))],
+        };
+        gen_simple(gen_tls_plaintext(&record), Vec::new())
+    }
+}
+
+/// Summarize a ServerHello, as `cipher|version|alpn|extensions`
+pub fn jarm_server_hello_string(server_hello: &TlsServerHelloContents) -> String {
+    let ext = server_hello.ext.unwrap_or(&[]);
+    let mut alpn = String::new();
+    let mut ext_types = Vec::new();
+    let mut i = ext;
+    while i.len() >= 4 {
+        let ext_type = u16::from(i[0]) << 8 | u16::from(i[1]);
+        let ext_len = (usize::from(i[2]) << 8 | usize::from(i[3])) + 4;
+        if i.len() < ext_len {
+            break;
+        }
+        // the first protocol of the list
+        if ext_type == TlsExtensionType::ApplicationLayerProtocolNegotiation.0 && ext_len > 7 {
+            alpn = String::from_utf8_lossy(&i[7..ext_len]).into_owned();
+        }
+        ext_types.push(format!("{:04x}", ext_type));
+        i = &i[ext_len..];
+    }
+    format!(
+        "{:04x}|{:04x}|{}|{}",
+        server_hello.cipher.0,
+        server_hello.version.0,
+        alpn,
+        ext_types.join("-")
+    )
+}
+
+/// Summarize the response of the server to a probe (the data received, starting with the
+/// first record)
+///
+/// If the response does not start with a ServerHello (for ex., an alert), `|||` is returned.
+/// The record does not need to be complete: only the ServerHello message is parsed.
+pub fn jarm_response_string(data: &[u8]) -> String {
+    let sh = match parse_tls_record_header(data) {
+        Ok((rem, hdr)) if hdr.record_type == TlsRecordType::Handshake => {
+            parse_tls_message_handshake(rem)
+        }
+        _ => return "|||".to_string(),
+    };
+    match sh {
+        Ok((_, TlsMessage::Handshake(TlsMessageHandshake::ServerHello(sh)))) => {
+            jarm_server_hello_string(&sh)
+        }
+        _ => "|||".to_string(),
+    }
+}
+
+/// A JARM fingerprint: the responses of the server, and the hash
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JarmFingerprint {
+    /// Responses to the probes, separated by `,`
+    pub raw: String,
+    /// JARM hash (62 characters)
+    pub hash: String,
+}
+
+fn jarm_cipher_index(cipher: &str) -> String {
+    if cipher.is_empty() {
+        return "00".to_string();
+    }
+    // unknown cipher suites get the index following the last one
+    let idx = JARM_HASH_CIPHERS
+        .iter()
+        .position(|c| format!("{:04x}", c) == cipher)
+        .unwrap_or(JARM_HASH_CIPHERS.len());
+    format!("{:02x}", idx + 1)
+}
+
+fn jarm_version_char(version: &str) -> char {
+    match version.chars().nth(3).and_then(|c| c.to_digit(10)) {
+        Some(d) if d < 6 => (b'a' + d as u8) as char,
+        _ => '0',
+    }
+}
+
+/// Compute the JARM fingerprint from the responses to the ten probes of `JARM_PROBES`
+///
+/// Responses are the strings returned by `jarm_response_string` (or `|||` if the server did
+/// not answer).
+pub fn jarm_fingerprint<S: AsRef<str>>(responses: &[S]) -> JarmFingerprint {
+    let raw = responses
+        .iter()
+        .map(|r| r.as_ref())
+        .collect::<Vec<_>>()
+        .join(",");
+    if responses.iter().all(|r| r.as_ref() == "|||") {
+        let hash = "0".repeat(62);
+        return JarmFingerprint { raw, hash };
+    }
+    let mut hash = String::with_capacity(62);
+    let mut alpn_ext = String::new();
+    for response in responses {
+        let mut fields = response.as_ref().split('|');
+        let cipher = fields.next().unwrap_or("");
+        let version = fields.next().unwrap_or("");
+        hash.push_str(&jarm_cipher_index(cipher));
+        if version.is_empty() {
+            hash.push('0');
+        } else {
+            hash.push(jarm_version_char(version));
+        }
+        alpn_ext.push_str(fields.next().unwrap_or(""));
+        alpn_ext.push_str(fields.next().unwrap_or(""));
+    }
+    let digest = Sha256::digest(alpn_ext.as_bytes());
+    for b in &digest[..16] {
+        hash.push_str(&format!("{:02x}", b));
+    }
+    JarmFingerprint { raw, hash }
+}
@@ -19,8 +19,8 @@
 //!
 //! The main parsing functions are located in the [tls.rs](src/tls.rs) file. The entry functions are:
 //! - `parse_tls_plaintext`: parses a record as plaintext
-//! - `parse_tls_encrypted`: read an encrypted record. The parser has no crypto or decryption features, so the content
-//!   will be left as opaque data.
+//! - `parse_tls_encrypted`: read an encrypted record. The content is left as opaque data (see
+//!   `TlsSessionDecryptor`, with the `decrypt` feature, to decrypt it).
 //!
 //! # Examples
 //!
@@ -48,6 +48,8 @@
 //!
 //! Note that knowing if a record is plaintext or not is the responsibility of the caller.
 //!
+//! To walk the records of a buffer without parsing them, use `TlsRecordIterator`.
+//!
 //! As reading TLS records may imply defragmenting records, some functions are
 //! provided to only read the record as opaque data (which ensures the record is
 //! complete and gives the record header) and then reading messages from data.
@@ -79,13 +81,83 @@
 //! }
 //! ```
 //!
+//! The parsers accept data that is not strictly conformant (for ex. trailing bytes after an
+//! extension). `parse_tls_plaintext_with_diagnostics`, from
+//! [diagnostics.rs](src/diagnostics.rs), also returns the anomalies found in the record.
+//!
 //! Some additional work is required if reading packets from the network, to support
 //! reassembly of TCP segments and reassembly of TLS records.
+//! Records split over several chunks of a stream can be reassembled using
+//! `TlsStreamDefragmenter` (or `TcpStreamReassembler`, with the `tcp` feature, to reorder
+//! TCP segments first), and handshake messages fragmented over several records can be reassembled using
+//! `TlsHandshakeReassembler`.
 //!
 //! For a complete example of a TLS parser supporting defragmentation and states, see the
 //! [rusticata/src/tls.rs](https://github.com/rusticata/rusticata/blob/master/src/tls.rs) file of
 //! the [rusticata](https://github.com/rusticata/rusticata) crate.
 //!
+//! ## DTLS
+//!
+//! DTLS records and handshake messages (including fragments, cookies, and connection IDs)
+//! can be parsed using the functions from [dtls.rs](src/dtls.rs), for ex.
+//! `parse_dtls_plaintext_record`.
+//! Fragmented handshake messages can be reassembled using `DTLSHandshakeReassembler`.
+//!
+//! ## SSLv2
+//!
+//! SSL 2.0 records and cleartext messages, including SSLv2-compatible CLIENT-HELLO messages
+//! sent by old clients, can be parsed using the functions from [ssl2.rs](src/ssl2.rs), for ex.
+//! `parse_ssl2_plaintext_record`.
+//!
+//! ## QUIC
+//!
+//! QUIC carries TLS 1.3 handshake messages in CRYPTO frames, without the record layer. The
+//! messages of a CRYPTO stream can be parsed using `parse_quic_crypto_messages`, from
+//! [quic.rs](src/quic.rs).
+//!
+//! ## Fingerprints
+//!
+//! With the `fingerprint` feature, the JA3 fingerprint of a ClientHello and the JA3S
+//! fingerprint of a ServerHello can be computed using `ja3_fingerprint` and
+//! `ja3s_fingerprint`, from [fingerprint.rs](src/fingerprint.rs). With the `x509` feature,
+//! the JA4X fingerprint of a certificate can be computed using `ja4x_fingerprint`.
+//!
+//! Known fingerprints (JA3, JA4, or rules on the ClientHello content) can be loaded in a
+//! `FingerprintDb`, from [fingerprint_db.rs](src/fingerprint_db.rs), to label clients.
+//!
+//! With the `fingerprint` and `serialize` features, the JARM probes of a server can be built
+//! using `JARM_PROBES`, and the JARM fingerprint computed from the responses using
+//! `jarm_fingerprint`, from [jarm.rs](src/jarm.rs).
+//!
+//! With the `serialize` feature, ClientHello records reproducing the layout of recent browsers
+//! (and their fingerprints) can be built using `TlsClientHelloBuilder::preset`, from
+//! [tls_builder.rs](src/tls_builder.rs).
+//!
+//! ## Sessions
+//!
+//! `TlsSession`, from [session.rs](src/session.rs), consumes the records of both directions
+//! of a connection and summarizes the parameters offered and negotiated during the handshake
+//! (versions, SNI, ALPN, cipher suite, group, resumption, certificates, alerts). It also
+//! collects the randoms and public key exchange values, in a `TlsKeyExchangeMaterial`.
+//!
+//! With the `crypto` feature, the handshake messages can be collected in a `TlsTranscript`,
+//! from [tls_transcript.rs](src/tls_transcript.rs), to compute the transcript hash. The
+//! TLS 1.3 secrets and traffic keys can then be derived using a `TlsKeySchedule`, from
+//! [tls_key_schedule.rs](src/tls_key_schedule.rs).
+//!
+//! With the `decrypt` feature, the records of a session can be decrypted by a
+//! `TlsSessionDecryptor`, from [tls_decrypt.rs](src/tls_decrypt.rs), using the secrets logged
+//! by one of the peers. The secrets of a key log file (`SSLKEYLOGFILE`) are loaded by a
+//! `TlsKeyLog`, from [tls_keylog.rs](src/tls_keylog.rs).
+//!
+//! ## Security audit
+//!
+//! The parameters negotiated during a handshake can be checked for weak configurations
+//! (obsolete versions, export or RC4 cipher suites, small Diffie-Hellman groups, etc.) using
+//! `TlsAudit`, from [audit.rs](src/audit.rs).
+//! They can also be checked against compliance profiles (Mozilla, NIST SP 800-52r2, or custom
+//! profiles) using `TlsComplianceProfile`, from [compliance.rs](src/compliance.rs).
+//!
 //! ## State machine
 //!
 //! A TLS state machine is provided in [tls_states.rs](src/tls_states.rs). The state machine is separated from the
@@ -95,6 +167,10 @@
 //! After reading a TLS message using the previous functions, the TLS state can be
 //! updated using the `tls_state_transition` function. If the transition succeeds,
 //! it returns `Ok(new_state)`, otherwise it returns `Err(error_state)`.
+//! DTLS messages (including the HelloVerifyRequest cookie exchange) use the
+//! `dtls_state_transition` function. Since DTLS flights can be retransmitted or reordered,
+//! `DTLSStateMachine` orders the messages using their epoch and `message_seq`, and ignores
+//! retransmissions.
 //!
 //! ```rust
 //! # extern crate nom;
@@ -117,6 +193,15 @@
 //! # }
 //! ```
 //!
+//! `TlsStateMachine` keeps the current state, and reports the main steps of the handshake
+//! (start, negotiated parameters, end, renegotiation, alerts and protocol violations) as
+//! `TlsStateEvent` values. If the TLS 1.3 handshake messages are decrypted, it also follows
+//! HelloRetryRequest, 0-RTT data and post-handshake messages (tickets, key updates and client
+//! authentication).
+//!
+//! Records encrypted after the handshake can be labeled (encrypted handshake messages,
+//! application data, alerts) using `TlsRecordClassifier`.
+//!
 //! # Implementation notes
 //!
 //! When parsing messages, if a field is an integer corresponding to an enum of known values,
@@ -131,26 +216,110 @@
 
 pub extern crate rusticata_macros;
 
+mod audit;
+mod compliance;
+mod diagnostics;
+mod dtls;
+mod quic;
+mod session;
+mod ssl2;
 mod tls;
 mod tls_alert;
 mod tls_ciphers;
+mod tls_classifier;
 mod tls_debug;
 mod tls_dh;
+mod tls_dh_groups;
+mod tls_diff;
 mod tls_ec;
+mod tls_ech;
 mod tls_extensions;
+mod tls_keylog;
+mod tls_kx;
+mod tls_reassembly;
 mod tls_sign_hash;
 mod tls_states;
+mod validate;
 
+pub use audit::*;
+pub use compliance::*;
+pub use diagnostics::*;
+pub use dtls::*;
+pub use quic::*;
+pub use session::*;
+pub use ssl2::*;
 pub use tls::*;
 pub use tls_alert::*;
 pub use tls_ciphers::*;
+pub use tls_classifier::*;
 pub use tls_dh::*;
+pub use tls_diff::*;
 pub use tls_ec::*;
+pub use tls_ech::*;
 pub use tls_extensions::*;
+pub use tls_keylog::*;
+pub use tls_kx::*;
+pub use tls_reassembly::*;
 pub use tls_sign_hash::*;
 pub use tls_states::*;
+pub use validate::*;
+
+#[cfg(feature = "compression")]
+mod tls_cert_compression;
+#[cfg(feature = "compression")]
+pub use tls_cert_compression::*;
+
+#[cfg(feature = "crypto")]
+mod tls_key_schedule;
+#[cfg(feature = "crypto")]
+pub use tls_key_schedule::*;
+#[cfg(feature = "crypto")]
+mod tls_transcript;
+#[cfg(feature = "crypto")]
+pub use tls_transcript::*;
 
+#[cfg(feature = "decrypt")]
+mod tls_decrypt;
+#[cfg(feature = "decrypt")]
+pub use tls_decrypt::*;
+
+#[cfg(feature = "fingerprint")]
+mod fingerprint;
+#[cfg(feature = "fingerprint")]
+pub use fingerprint::*;
+#[cfg(feature = "fingerprint")]
+mod fingerprint_db;
+#[cfg(feature = "fingerprint")]
+pub use fingerprint_db::*;
+
+#[cfg(all(feature = "fingerprint", feature = "serialize"))]
+mod jarm;
+#[cfg(all(feature = "fingerprint", feature = "serialize"))]
+pub use jarm::*;
+
+#[cfg(feature = "idna")]
+mod tls_idna;
+#[cfg(feature = "idna")]
+pub use tls_idna::*;
+#[cfg(feature = "serialize")]
+mod tls_builder;
+#[cfg(feature = "serialize")]
+pub use tls_builder::*;
 #[cfg(feature = "serialize")]
 mod tls_serialize;
 #[cfg(feature = "serialize")]
 pub use tls_serialize::*;
+
+#[cfg(feature = "tcp")]
+mod tcp_reassembly;
+#[cfg(feature = "tcp")]
+pub use tcp_reassembly::*;
+
+#[cfg(feature = "x509")]
+mod tls_ocsp;
+#[cfg(feature = "x509")]
+pub use tls_ocsp::*;
+#[cfg(feature = "x509")]
+mod tls_x509;
+#[cfg(feature = "x509")]
+pub use tls_x509::*;
@@ -0,0 +1,66 @@
+//! # QUIC CRYPTO streams
+//! Parsing functions for the TLS handshake messages carried by QUIC
+//!
+//! QUIC ([RFC9001](https://tools.ietf.org/html/rfc9001)) does not use the TLS record layer:
+//! handshake messages are sent in CRYPTO frames, as a stream of bytes for each encryption
+//! level. Messages can be split across frames, so the data of successive frames must be
+//! concatenated (in offset order) before parsing.
+//!
+//! Only TLS 1.3 can be used with QUIC, so messages are parsed using the TLS 1.3 format.
+
+use nom::{Err, IResult};
+
+use crate::tls::*;
+
+/// Parse one TLS handshake message from a QUIC CRYPTO stream
+///
+/// If the message is incomplete, `Err::Incomplete` is returned, with the number of missing
+/// bytes when the message header is complete.
+///
+/// Extensions are not parsed: use the methods of the message (for ex.
+/// `TlsServerHelloContents::parse_extensions`), or `parse_tls_extensions_in` with the
+/// context of the message. The `quic_transport_parameters` extension is parsed in the
+/// ClientHello and EncryptedExtensions contexts.
+pub fn parse_quic_crypto_message(i: &[u8]) -> IResult<&[u8], TlsMessage> {
+    parse_tls_message_handshake_with_version(i, TlsVersion::Tls13)
+}
+
+/// Parse all complete TLS handshake messages from a QUIC CRYPTO stream buffer
+///
+/// The remaining data is the start of an incomplete message, and must be kept until more
+/// data is received. If the buffer does not contain any complete message, the result of
+/// `parse_quic_crypto_message` is returned (`Err::Incomplete`, or an error).
+///
+/// ```rust
+/// # use tls_parser::{parse_quic_crypto_messages, Err};
+/// # fn f(crypto_stream: &mut Vec<u8>) {
+/// match parse_quic_crypto_messages(crypto_stream) {
+///     Ok((rem, messages)) => {
+///         for msg in &messages {
+///             // use msg
+///         }
+///         let consumed = crypto_stream.len() - rem.len();
+///         // remove `consumed` bytes from the stream
+///     }
+///     Err(Err::Incomplete(_)) => {
+///         // wait for more data
+///     }
+///     Err(e) => eprintln!("invalid handshake message: {:?}", e),
+/// }
+/// # }
+/// ```
+pub fn parse_quic_crypto_messages(i: &[u8]) -> IResult<&[u8], Vec<TlsMessage>> {
+    let mut messages = Vec::new();
+    let mut i = i;
+    while !i.is_empty() {
+        match parse_quic_crypto_message(i) {
+            Ok((rem, msg)) => {
+                messages.push(msg);
+                i = rem;
+            }
+            Err(Err::Incomplete(_)) if !messages.is_empty() => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok((i, messages))
+}
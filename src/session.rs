@@ -0,0 +1,373 @@
+//! # Session tracking
+//!
+//! `TlsSession` consumes the records of both directions of a connection, and builds a summary
+//! of the parameters offered and negotiated during the handshake: versions, server name,
+//! ALPN, cipher suite, key exchange group, resumption, certificates and alerts.
+//!
+//! The key exchange material (randoms and public values) is also collected, see
+//! `TlsKeyExchangeMaterial`.
+//!
+//! Only plaintext messages can be observed: in TLS 1.3, the EncryptedExtensions and
+//! Certificate messages (and so the selected ALPN protocol, the acceptance of early data and
+//! the certificates) are encrypted, and encrypted alerts cannot be read.
+
+use crate::tls::*;
+use crate::tls_alert::TlsMessageAlert;
+use crate::tls_ec::{ECParametersContent, NamedGroup};
+use crate::tls_extensions::*;
+use crate::tls_kx::*;
+use crate::tls_reassembly::{HandshakeReassemblyError, TlsHandshakeReassembler};
+
+/// Parameters offered and negotiated during a handshake
+///
+/// Client values are taken from the last ClientHello (the second one, after a
+/// HelloRetryRequest).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TlsSessionSummary {
+    /// Versions offered by the client (from `supported_versions`, or the ClientHello
+    /// version), without GREASE values
+    pub versions_offered: Vec<TlsVersion>,
+    /// Version selected by the server
+    pub version: Option<TlsVersion>,
+    /// Server name (first host name of the `server_name` extension)
+    pub sni: Option<String>,
+    /// Protocols offered by the client
+    pub alpn_offered: Vec<Vec<u8>>,
+    /// Protocol selected by the server (only visible before TLS 1.3)
+    pub alpn: Option<Vec<u8>>,
+    /// Cipher suites offered by the client
+    pub ciphers_offered: Vec<TlsCipherSuiteID>,
+    /// Cipher suite selected by the server
+    pub cipher: Option<TlsCipherSuiteID>,
+    /// Key exchange group (from the TLS 1.3 `key_share` extension, or the ECDHE parameters of
+    /// the ServerKeyExchange)
+    pub group: Option<NamedGroup>,
+    /// True if the server sent a HelloRetryRequest
+    pub hello_retry_request: bool,
+    /// True if the session was resumed (TLS 1.3: a pre-shared key was selected)
+    pub resumed: bool,
+    /// True if the client sent the `early_data` extension (0-RTT)
+    pub early_data_offered: bool,
+    /// Certificate chain sent by the server (only visible before TLS 1.3)
+    pub server_certificates: Vec<Vec<u8>>,
+    /// Certificate chain sent by the client (only visible before TLS 1.3)
+    pub client_certificates: Vec<Vec<u8>>,
+    /// Plaintext alerts, with `true` if sent by the client
+    pub alerts: Vec<(bool, TlsMessageAlert)>,
+}
+
+/// Key exchange material of a handshake
+///
+/// Public values are copied from the TLS 1.3 `key_share` extensions, or from the
+/// ServerKeyExchange and ClientKeyExchange messages (ECDHE and DHE key exchanges). This can be
+/// used to detect reused key shares or weak random values.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TlsKeyExchangeMaterial {
+    /// Client random (32 bytes)
+    pub client_random: Option<Vec<u8>>,
+    /// Server random (32 bytes)
+    pub server_random: Option<Vec<u8>>,
+    /// Group of the key exchange (only named groups)
+    pub group: Option<NamedGroup>,
+    /// Key shares offered by the client (TLS 1.3)
+    pub client_shares: Vec<(NamedGroup, Vec<u8>)>,
+    /// Public value of the client: key share of the selected group (TLS 1.3), or ECDH point or
+    /// DH public value of the ClientKeyExchange
+    pub client_public: Option<Vec<u8>>,
+    /// Public value of the server: key share (TLS 1.3), or ECDH point or DH public value of the
+    /// ServerKeyExchange
+    pub server_public: Option<Vec<u8>>,
+}
+
+/// Tracker of the handshake of a connection
+///
+/// Records must be given in order for each direction, for ex. using a
+/// `TlsStreamDefragmenter` for each direction. Handshake messages split over several
+/// records are reassembled.
+#[derive(Debug, Default)]
+pub struct TlsSession {
+    client: TlsHandshakeReassembler,
+    server: TlsHandshakeReassembler,
+    /// True if the records sent by the client are encrypted (after ChangeCipherSpec)
+    client_encrypted: bool,
+    server_encrypted: bool,
+    server_hello_done: bool,
+    summary: TlsSessionSummary,
+    key_exchange: TlsKeyExchangeMaterial,
+}
+
+fn get_sni(exts: &[TlsExtension]) -> Option<String> {
+    exts.iter().find_map(|ext| match ext {
+        TlsExtension::SNI(names) => names
+            .iter()
+            .find(|&&(t, _)| t == SNIType::HostName)
+            .map(|&(_, name)| String::from_utf8_lossy(name).into_owned()),
+        _ => None,
+    })
+}
+
+fn get_alpn(exts: &[TlsExtension]) -> Vec<Vec<u8>> {
+    exts.iter()
+        .find_map(|ext| match ext {
+            TlsExtension::ALPN(protocols) => Some(protocols.iter().map(|p| p.to_vec()).collect()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+impl TlsSession {
+    pub fn new() -> Self {
+        TlsSession::default()
+    }
+
+    /// Get the summary of the parameters observed so far
+    pub fn summary(&self) -> &TlsSessionSummary {
+        &self.summary
+    }
+
+    /// Get the key exchange material observed so far
+    pub fn key_exchange(&self) -> &TlsKeyExchangeMaterial {
+        &self.key_exchange
+    }
+
+    /// Update the session with a record
+    ///
+    /// Encrypted records are ignored. An error is returned if a handshake message cannot be
+    /// parsed: the session can still be updated with the next records.
+    pub fn update(
+        &mut self,
+        record: &TlsRawRecord,
+        to_server: bool,
+    ) -> Result<(), HandshakeReassemblyError> {
+        let encrypted = if to_server {
+            self.client_encrypted
+        } else {
+            self.server_encrypted
+        };
+        match record.hdr.record_type {
+            // TLS 1.3 encrypted records always use the ApplicationData type
+            TlsRecordType::Handshake if !encrypted => {
+                let reassembler = if to_server {
+                    &mut self.client
+                } else {
+                    &mut self.server
+                };
+                let messages = reassembler.push(record.data)?;
+                let mut version = None;
+                for msg in &messages {
+                    if let TlsMessage::Handshake(msg) = msg {
+                        if let TlsMessageHandshake::ServerDone(_) = msg {
+                            self.server_hello_done = true;
+                        }
+                        self.summary.update_handshake(msg, to_server);
+                        self.key_exchange
+                            .update_handshake(msg, to_server, &self.summary);
+                        version = self.summary.version;
+                    }
+                }
+                if let Some(version) = version {
+                    self.client.set_version(version);
+                    self.server.set_version(version);
+                }
+            }
+            TlsRecordType::Alert if !encrypted => {
+                if let Ok((_, alerts)) = parse_tls_message_alerts(record.data) {
+                    for alert in alerts {
+                        if let TlsMessage::Alert(alert) = alert {
+                            self.summary.alerts.push((to_server, alert));
+                        }
+                    }
+                }
+            }
+            // ChangeCipherSpec is only sent for middlebox compatibility in TLS 1.3, and can be
+            // received before the version is known (after a HelloRetryRequest)
+            TlsRecordType::ChangeCipherSpec if is_tls12_or_earlier(self.summary.version) => {
+                if to_server {
+                    self.client_encrypted = true;
+                } else {
+                    // in an abbreviated handshake, the server does not send its certificate
+                    self.summary.resumed = !self.server_hello_done;
+                    self.server_encrypted = true;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+impl TlsSessionSummary {
+    fn update_handshake(&mut self, msg: &TlsMessageHandshake, to_server: bool) {
+        match (msg, to_server) {
+            (TlsMessageHandshake::ClientHello(ch), true) => {
+                let exts =
+                    parse_tls_extensions_in(ch.ext.unwrap_or(&[]), ExtensionContext::ClientHello)
+                        .map(|(_, exts)| exts)
+                        .unwrap_or_default();
+                let versions = exts.iter().find_map(|ext| match ext {
+                    TlsExtension::SupportedVersions(v) => Some(v.clone()),
+                    _ => None,
+                });
+                self.versions_offered = versions
+                    .unwrap_or_else(|| vec![ch.version])
+                    .into_iter()
+                    .filter(|v| !v.is_grease())
+                    .collect();
+                self.sni = get_sni(&exts);
+                self.alpn_offered = get_alpn(&exts);
+                self.ciphers_offered = ch.ciphers.clone();
+                self.early_data_offered = exts
+                    .iter()
+                    .any(|ext| matches!(ext, TlsExtension::EarlyData(_)));
+            }
+            (TlsMessageHandshake::ServerHello(sh), false) => {
+                let exts = sh
+                    .parse_extensions()
+                    .map(|(_, exts)| exts)
+                    .unwrap_or_default();
+                if sh.is_hello_retry_request() {
+                    self.hello_retry_request = true;
+                    self.group = sh.get_hrr_selected_group();
+                    return;
+                }
+                self.version = Some(sh.get_supported_version().unwrap_or(sh.version));
+                self.cipher = Some(sh.cipher);
+                self.alpn = get_alpn(&exts).into_iter().next();
+                for ext in &exts {
+                    match ext {
+                        TlsExtension::KeyShareServer(entry) => self.group = Some(entry.group),
+                        TlsExtension::PreSharedKeySelected(_) => self.resumed = true,
+                        _ => (),
+                    }
+                }
+            }
+            (TlsMessageHandshake::Certificate(cert), _) => {
+                let chain = cert.cert_chain.iter().map(|c| c.data.to_vec()).collect();
+                if to_server {
+                    self.client_certificates = chain;
+                } else {
+                    self.server_certificates = chain;
+                }
+            }
+            (TlsMessageHandshake::ServerKeyExchange(ske), false) => {
+                if let Some(ServerKeyExchangeParams::Ecdhe(ecdh, _))
+                | Some(ServerKeyExchangeParams::EcdhAnon(ecdh))
+                | Some(ServerKeyExchangeParams::EcdhePsk { params: ecdh, .. }) =
+                    self.parse_server_key_exchange(ske)
+                {
+                    if let ECParametersContent::NamedGroup(group) = ecdh.curve_params.params_content
+                    {
+                        self.group = Some(group);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Decode a ServerKeyExchange message, using the negotiated cipher suite and version
+    fn parse_server_key_exchange<'a>(
+        &self,
+        ske: &TlsServerKeyExchangeContents<'a>,
+    ) -> Option<ServerKeyExchangeParams<'a>> {
+        let cipher = self.cipher.and_then(|c| c.get_ciphersuite())?;
+        let has_algorithm = matches!(self.version, Some(v) if v.0 >= TlsVersion::Tls12.0);
+        parse_server_key_exchange_params(ske.parameters, cipher, has_algorithm)
+            .ok()
+            .map(|(_, params)| params)
+    }
+}
+
+fn hello_random(rand_time: u32, rand_data: &[u8]) -> Vec<u8> {
+    let mut random = rand_time.to_be_bytes().to_vec();
+    random.extend_from_slice(rand_data);
+    random
+}
+
+impl TlsKeyExchangeMaterial {
+    fn update_handshake(
+        &mut self,
+        msg: &TlsMessageHandshake,
+        to_server: bool,
+        summary: &TlsSessionSummary,
+    ) {
+        match (msg, to_server) {
+            (TlsMessageHandshake::ClientHello(ch), true) => {
+                self.client_random = Some(hello_random(ch.rand_time, ch.rand_data));
+                let exts =
+                    parse_tls_extensions_in(ch.ext.unwrap_or(&[]), ExtensionContext::ClientHello)
+                        .map(|(_, exts)| exts)
+                        .unwrap_or_default();
+                self.client_shares = exts
+                    .iter()
+                    .find_map(|ext| match ext {
+                        TlsExtension::KeyShare(entries) => {
+                            Some(entries.iter().map(|e| (e.group, e.kx.to_vec())).collect())
+                        }
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+            }
+            (TlsMessageHandshake::ServerHello(sh), false) if !sh.is_hello_retry_request() => {
+                self.server_random = Some(hello_random(sh.rand_time, sh.rand_data));
+                let exts = sh
+                    .parse_extensions()
+                    .map(|(_, exts)| exts)
+                    .unwrap_or_default();
+                for ext in &exts {
+                    if let TlsExtension::KeyShareServer(entry) = ext {
+                        self.group = Some(entry.group);
+                        self.server_public = Some(entry.kx.to_vec());
+                        self.client_public = self
+                            .client_shares
+                            .iter()
+                            .find(|(group, _)| *group == entry.group)
+                            .map(|(_, kx)| kx.clone());
+                    }
+                }
+            }
+            (TlsMessageHandshake::ServerKeyExchange(ske), false) => {
+                let public = match summary.parse_server_key_exchange(ske) {
+                    Some(ServerKeyExchangeParams::Ecdhe(ecdh, _))
+                    | Some(ServerKeyExchangeParams::EcdhAnon(ecdh))
+                    | Some(ServerKeyExchangeParams::EcdhePsk { params: ecdh, .. }) => {
+                        if let ECParametersContent::NamedGroup(group) =
+                            ecdh.curve_params.params_content
+                        {
+                            self.group = Some(group);
+                        }
+                        ecdh.public.point
+                    }
+                    Some(ServerKeyExchangeParams::Dhe(dh, _))
+                    | Some(ServerKeyExchangeParams::DhAnon(dh))
+                    | Some(ServerKeyExchangeParams::DhePsk { params: dh, .. }) => dh.dh_ys,
+                    _ => return,
+                };
+                self.server_public = Some(public.to_vec());
+            }
+            (TlsMessageHandshake::ClientKeyExchange(cke), true) => {
+                let public = match cke {
+                    TlsClientKeyExchangeContents::Dh(dh_yc) => *dh_yc,
+                    TlsClientKeyExchangeContents::Ecdh(point) => point.point,
+                    TlsClientKeyExchangeContents::Unknown(data) => {
+                        let cipher = match summary.cipher.and_then(|c| c.get_ciphersuite()) {
+                            Some(cipher) => cipher,
+                            None => return,
+                        };
+                        match parse_client_key_exchange_params(data, cipher) {
+                            Ok((_, ClientKeyExchangeParams::Ecdh(point)))
+                            | Ok((_, ClientKeyExchangeParams::EcdhePsk { point, .. })) => {
+                                point.point
+                            }
+                            Ok((_, ClientKeyExchangeParams::Dh { dh_yc }))
+                            | Ok((_, ClientKeyExchangeParams::DhePsk { dh_yc, .. })) => dh_yc,
+                            _ => return,
+                        }
+                    }
+                };
+                self.client_public = Some(public.to_vec());
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! # SSLv2 parser
+//! Parsing functions for the SSL 2.0 protocol
+//!
+//! SSL 2.0 is described in
+//! [draft-hickman-netscape-ssl-00](https://tools.ietf.org/html/draft-hickman-netscape-ssl-00),
+//! and is prohibited by [RFC6176](https://tools.ietf.org/html/rfc6176). It is still found
+//! in legacy captures, and some clients send a SSLv2-compatible CLIENT-HELLO to negotiate
+//! SSL 3.0 or TLS (see [RFC5246](https://tools.ietf.org/html/rfc5246) appendix E.2).
+
+use nom::bytes::streaming::take;
+use nom::combinator::{map_parser, rest};
+use nom::error::{make_error, ErrorKind};
+use nom::multi::count;
+use nom::number::streaming::{be_u16, be_u24, be_u8};
+use nom::{Err, IResult};
+use rusticata_macros::newtype_enum;
+
+use crate::tls::{TlsCipherSuiteID, TlsVersion};
+
+/// SSLv2 record header
+///
+/// Records with a 2-byte header have no padding. Records with a 3-byte header have a
+/// padding length, and the `is_escape` bit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SSLv2RecordHeader {
+    /// Length of the record data (excluding the header)
+    pub length: u16,
+    /// Length of the padding, at the end of the record data
+    pub padding: u8,
+    pub is_escape: bool,
+}
+
+/// SSLv2 message type
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SSLv2MessageType(pub u8);
+
+newtype_enum! {
+impl debug SSLv2MessageType {
+    Error              = 0,
+    ClientHello        = 1,
+    ClientMasterKey    = 2,
+    ClientFinished     = 3,
+    ServerHello        = 4,
+    ServerVerify       = 5,
+    ServerFinished     = 6,
+    RequestCertificate = 7,
+    ClientCertificate  = 8,
+}
+}
+
+/// SSLv2 certificate type
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SSLv2CertificateType(pub u8);
+
+newtype_enum! {
+impl debug SSLv2CertificateType {
+    X509Certificate = 1,
+}
+}
+
+/// SSLv2 cipher kind (24 bits)
+///
+/// SSLv2-compatible CLIENT-HELLO messages also use this field to carry SSL 3.0/TLS cipher
+/// suites, with the first byte set to 0.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SSLv2CipherKind(pub u32);
+
+newtype_enum! {
+impl debug SSLv2CipherKind {
+    RC4_128_WITH_MD5              = 0x01_0080,
+    RC4_128_EXPORT40_WITH_MD5     = 0x02_0080,
+    RC2_128_CBC_WITH_MD5          = 0x03_0080,
+    RC2_128_CBC_EXPORT40_WITH_MD5 = 0x04_0080,
+    IDEA_128_CBC_WITH_MD5         = 0x05_0080,
+    DES_64_CBC_WITH_MD5           = 0x06_0040,
+    DES_192_EDE3_CBC_WITH_MD5     = 0x07_00c0,
+}
+}
+
+impl SSLv2CipherKind {
+    /// If the cipher kind encodes a SSL 3.0/TLS cipher suite, return its identifier
+    pub fn tls_cipher_id(self) -> Option<TlsCipherSuiteID> {
+        if self.0 >> 16 == 0 {
+            Some(TlsCipherSuiteID(self.0 as u16))
+        } else {
+            None
+        }
+    }
+
+    /// Test if the cipher kind is an export cipher (40-bit secret key)
+    pub fn is_export(self) -> bool {
+        matches!(
+            self,
+            SSLv2CipherKind::RC4_128_EXPORT40_WITH_MD5
+                | SSLv2CipherKind::RC2_128_CBC_EXPORT40_WITH_MD5
+        )
+    }
+}
+
+/// SSLv2 CLIENT-HELLO
+///
+/// If `version` is SSL 3.0 or TLS, this is a SSLv2-compatible hello, and the cipher specs
+/// can contain SSL 3.0/TLS cipher suites (see `SSLv2CipherKind::tls_cipher_id`).
+#[derive(Clone, PartialEq)]
+pub struct SSLv2ClientHello<'a> {
+    pub version: TlsVersion,
+    pub cipher_specs: Vec<SSLv2CipherKind>,
+    pub session_id: Option<&'a [u8]>,
+    pub challenge: &'a [u8],
+}
+
+impl<'a> SSLv2ClientHello<'a> {
+    /// Get the SSL 3.0/TLS cipher suites offered by the client
+    pub fn get_tls_ciphers(&self) -> Vec<TlsCipherSuiteID> {
+        self.cipher_specs
+            .iter()
+            .filter_map(|c| c.tls_cipher_id())
+            .collect()
+    }
+}
+
+/// SSLv2 SERVER-HELLO
+///
+/// If `session_id_hit` is set, the session is resumed, and the certificate and cipher specs
+/// are empty.
+#[derive(Clone, PartialEq)]
+pub struct SSLv2ServerHello<'a> {
+    pub session_id_hit: bool,
+    pub certificate_type: SSLv2CertificateType,
+    pub version: TlsVersion,
+    /// Server certificate (DER-encoded X.509 certificate, for the `X509Certificate` type)
+    pub certificate: &'a [u8],
+    /// Cipher kinds supported by both the client and the server
+    pub cipher_specs: Vec<SSLv2CipherKind>,
+    pub connection_id: &'a [u8],
+}
+
+/// SSLv2 message
+#[derive(Clone, Debug, PartialEq)]
+pub enum SSLv2Message<'a> {
+    ClientHello(SSLv2ClientHello<'a>),
+    ServerHello(SSLv2ServerHello<'a>),
+    /// Message not parsed (or encrypted)
+    Unknown(SSLv2MessageType, &'a [u8]),
+}
+
+/// SSLv2 plaintext record
+#[derive(Clone, Debug, PartialEq)]
+pub struct SSLv2Plaintext<'a> {
+    pub header: SSLv2RecordHeader,
+    pub message: SSLv2Message<'a>,
+    pub padding: &'a [u8],
+}
+
+/// Read SSLv2 record header
+///
+/// The header length (2 or 3 bytes) is given by the most significant bit.
+pub fn parse_ssl2_record_header(i: &[u8]) -> IResult<&[u8], SSLv2RecordHeader> {
+    let (i, len) = be_u16(i)?;
+    if len & 0x8000 != 0 {
+        let header = SSLv2RecordHeader {
+            length: len & 0x7fff,
+            padding: 0,
+            is_escape: false,
+        };
+        Ok((i, header))
+    } else {
+        let (i, padding) = be_u8(i)?;
+        let header = SSLv2RecordHeader {
+            length: len & 0x3fff,
+            padding,
+            is_escape: len & 0x4000 != 0,
+        };
+        Ok((i, header))
+    }
+}
+
+/// Parse the body of a SSLv2 CLIENT-HELLO (after the message type)
+///
+/// ```text
+/// char MSG-CLIENT-HELLO
+/// char CLIENT-VERSION-MSB
+/// char CLIENT-VERSION-LSB
+/// char CIPHER-SPECS-LENGTH-MSB
+/// char CIPHER-SPECS-LENGTH-LSB
+/// char SESSION-ID-LENGTH-MSB
+/// char SESSION-ID-LENGTH-LSB
+/// char CHALLENGE-LENGTH-MSB
+/// char CHALLENGE-LENGTH-LSB
+/// char CIPHER-SPECS-DATA[(MSB<<8)|LSB]
+/// char SESSION-ID-DATA[(MSB<<8)|LSB]
+/// char CHALLENGE-DATA[(MSB<<8)|LSB]
+/// ```
+pub fn parse_ssl2_client_hello(i: &[u8]) -> IResult<&[u8], SSLv2ClientHello> {
+    let (i, version) = TlsVersion::parse(i)?;
+    let (i, cipher_specs_len) = be_u16(i)?;
+    let (i, session_id_len) = be_u16(i)?;
+    let (i, challenge_len) = be_u16(i)?;
+    let (i, cipher_specs) = parse_ssl2_cipher_specs(i, cipher_specs_len)?;
+    let (i, session_id) = take(session_id_len as usize)(i)?;
+    let (i, challenge) = take(challenge_len as usize)(i)?;
+    let hello = SSLv2ClientHello {
+        version,
+        cipher_specs,
+        session_id: if session_id.is_empty() {
+            None
+        } else {
+            Some(session_id)
+        },
+        challenge,
+    };
+    Ok((i, hello))
+}
+
+/// Parse the body of a SSLv2 SERVER-HELLO (after the message type)
+///
+/// ```text
+/// char MSG-SERVER-HELLO
+/// char SESSION-ID-HIT
+/// char CERTIFICATE-TYPE
+/// char SERVER-VERSION-MSB
+/// char SERVER-VERSION-LSB
+/// char CERTIFICATE-LENGTH-MSB
+/// char CERTIFICATE-LENGTH-LSB
+/// char CIPHER-SPECS-LENGTH-MSB
+/// char CIPHER-SPECS-LENGTH-LSB
+/// char CONNECTION-ID-LENGTH-MSB
+/// char CONNECTION-ID-LENGTH-LSB
+/// char CERTIFICATE-DATA[MSB<<8|LSB]
+/// char CIPHER-SPECS-DATA[MSB<<8|LSB]
+/// char CONNECTION-ID-DATA[MSB<<8|LSB]
+/// ```
+pub fn parse_ssl2_server_hello(i: &[u8]) -> IResult<&[u8], SSLv2ServerHello> {
+    let (i, session_id_hit) = be_u8(i)?;
+    let (i, certificate_type) = be_u8(i)?;
+    let (i, version) = TlsVersion::parse(i)?;
+    let (i, certificate_len) = be_u16(i)?;
+    let (i, cipher_specs_len) = be_u16(i)?;
+    let (i, connection_id_len) = be_u16(i)?;
+    let (i, certificate) = take(certificate_len as usize)(i)?;
+    let (i, cipher_specs) = parse_ssl2_cipher_specs(i, cipher_specs_len)?;
+    let (i, connection_id) = take(connection_id_len as usize)(i)?;
+    let hello = SSLv2ServerHello {
+        session_id_hit: session_id_hit != 0,
+        certificate_type: SSLv2CertificateType(certificate_type),
+        version,
+        certificate,
+        cipher_specs,
+        connection_id,
+    };
+    Ok((i, hello))
+}
+
+fn parse_ssl2_cipher_kind(i: &[u8]) -> IResult<&[u8], SSLv2CipherKind> {
+    let (i, kind) = be_u24(i)?;
+    Ok((i, SSLv2CipherKind(kind)))
+}
+
+fn parse_ssl2_cipher_specs(i: &[u8], len: u16) -> IResult<&[u8], Vec<SSLv2CipherKind>> {
+    let (n, r) = (len / 3, len % 3);
+    if r != 0 {
+        return Err(Err::Error(make_error(i, ErrorKind::LengthValue)));
+    }
+    count(parse_ssl2_cipher_kind, n as usize)(i)
+}
+
+/// Parse a SSLv2 message (the record data, without the padding)
+///
+/// Only cleartext messages are decoded. Other messages are returned as `Unknown`.
+pub fn parse_ssl2_message(i: &[u8]) -> IResult<&[u8], SSLv2Message> {
+    let (i, msg_type) = be_u8(i)?;
+    match SSLv2MessageType(msg_type) {
+        SSLv2MessageType::ClientHello => {
+            let (i, hello) = parse_ssl2_client_hello(i)?;
+            Ok((i, SSLv2Message::ClientHello(hello)))
+        }
+        SSLv2MessageType::ServerHello => {
+            let (i, hello) = parse_ssl2_server_hello(i)?;
+            Ok((i, SSLv2Message::ServerHello(hello)))
+        }
+        msg_type => {
+            let (i, data) = rest(i)?;
+            Ok((i, SSLv2Message::Unknown(msg_type, data)))
+        }
+    }
+}
+
+/// Parse one SSLv2 record, as plaintext
+///
+/// Knowing if the record is encrypted (and contains a MAC) is the responsibility of the
+/// caller. The messages used to negotiate the session (CLIENT-HELLO, SERVER-HELLO) are sent
+/// in cleartext.
+pub fn parse_ssl2_plaintext_record(i: &[u8]) -> IResult<&[u8], SSLv2Plaintext> {
+    let (i, header) = parse_ssl2_record_header(i)?;
+    if header.padding as u16 > header.length {
+        return Err(Err::Error(make_error(i, ErrorKind::LengthValue)));
+    }
+    let data_len = (header.length - header.padding as u16) as usize;
+    let (i, message) = map_parser(take(data_len), parse_ssl2_message)(i)?;
+    let (i, padding) = take(header.padding as usize)(i)?;
+    let record = SSLv2Plaintext {
+        header,
+        message,
+        padding,
+    };
+    Ok((i, record))
+}
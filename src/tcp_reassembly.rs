@@ -0,0 +1,132 @@
+//! Reassembly of TCP segments
+//!
+//! This module provides a minimal TCP reassembler for one direction of a connection, to
+//! parse TLS from packet captures. It only handles the payload ordering (sequence numbers,
+//! reordering, retransmissions and overlaps), not the TCP state (handshake, window, FIN/RST).
+//!
+//! This module requires the `tcp` feature.
+
+use std::collections::BTreeMap;
+
+use crate::tls::{TlsRawRecord, TlsRecordLimits};
+use crate::tls_reassembly::{TlsStreamDefragmenter, TlsStreamError, DEFAULT_MAX_STREAM_BUFFER};
+
+/// TCP stream reassembler
+///
+/// Segments are pushed with their sequence number, in any order. The in-order data is passed
+/// to a `TlsStreamDefragmenter`, and complete TLS records are returned.
+///
+/// Data already received is ignored: if segments overlap, the first received data is kept.
+/// Sequence number wrapping is supported.
+///
+/// ```rust
+/// # use tls_parser::TcpStreamReassembler;
+/// # fn f(isn: u32, segments: &[(u32, &[u8])]) {
+/// // sequence number of the first data byte
+/// let mut reassembler = TcpStreamReassembler::new(isn.wrapping_add(1));
+/// for (seq, payload) in segments {
+///     match reassembler.push(*seq, payload) {
+///         Ok(records) => {
+///             for record in records {
+///                 // use record
+///             }
+///         }
+///         Err(e) => eprintln!("reassembly failed: {:?}", e),
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TcpStreamReassembler {
+    /// Sequence number of the next expected byte
+    next_seq: u32,
+    /// Stream offset of the next expected byte
+    offset: u64,
+    /// Out-of-order segments, indexed by stream offset
+    pending: BTreeMap<u64, Vec<u8>>,
+    max_pending: usize,
+    defrag: TlsStreamDefragmenter,
+}
+
+impl TcpStreamReassembler {
+    /// Create a new reassembler, expecting `seq` as the sequence number of the first byte
+    ///
+    /// The maximum size of the out-of-order data is `DEFAULT_MAX_STREAM_BUFFER`.
+    pub fn new(seq: u32) -> Self {
+        TcpStreamReassembler::with_max_size(seq, DEFAULT_MAX_STREAM_BUFFER)
+    }
+
+    /// Create a new reassembler, with a maximum size of `max_size` bytes for the out-of-order
+    /// data, and for the TLS stream defragmenter
+    pub fn with_max_size(seq: u32, max_size: usize) -> Self {
+        TcpStreamReassembler {
+            next_seq: seq,
+            offset: 0,
+            pending: BTreeMap::new(),
+            max_pending: max_size,
+            defrag: TlsStreamDefragmenter::with_max_size(max_size),
+        }
+    }
+
+    /// Set the record length limits of the TLS stream defragmenter
+    pub fn set_record_limits(&mut self, limits: TlsRecordLimits) {
+        self.defrag.set_record_limits(limits);
+    }
+
+    /// Get the sequence number of the next expected byte
+    pub fn next_seq(&self) -> u32 {
+        self.next_seq
+    }
+
+    /// Test if out-of-order segments are waiting for missing data
+    pub fn has_pending_segments(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Add a segment, and return all TLS records completed by the in-order data
+    ///
+    /// Records returned by the previous call are discarded. If the out-of-order data would
+    /// exceed the maximum size, the segment is dropped and `BufferFull` is returned.
+    ///
+    /// When a gap is filled, all the segments now in order are passed to the defragmenter at
+    /// once: its maximum size only applies to the incomplete record kept after this data.
+    pub fn push(&mut self, seq: u32, payload: &[u8]) -> Result<Vec<TlsRawRecord>, TlsStreamError> {
+        // position relative to the next expected byte (can be negative, for retransmissions)
+        let rel = seq.wrapping_sub(self.next_seq) as i32;
+        let mut data = Vec::new();
+        if rel <= 0 {
+            let skip = self.next_seq.wrapping_sub(seq) as usize;
+            if skip < payload.len() {
+                data.extend_from_slice(&payload[skip..]);
+            }
+        } else if !payload.is_empty() {
+            let pending: usize = self.pending.values().map(Vec::len).sum();
+            if pending + payload.len() > self.max_pending {
+                return Err(TlsStreamError::BufferFull);
+            }
+            let offset = self.offset + rel as u64;
+            self.pending
+                .entry(offset)
+                .or_insert_with(|| payload.to_vec());
+        }
+        self.advance(data.len());
+        // append the out-of-order segments which are now in order
+        while let Some((&offset, _)) = self.pending.iter().next() {
+            if offset > self.offset {
+                break;
+            }
+            let segment = self.pending.remove(&offset).expect("pending segment");
+            let skip = (self.offset - offset) as usize;
+            if skip < segment.len() {
+                data.extend_from_slice(&segment[skip..]);
+                self.advance(segment.len() - skip);
+            }
+        }
+        self.defrag.push(&data)
+    }
+
+    fn advance(&mut self, len: usize) {
+        self.next_seq = self.next_seq.wrapping_add(len as u32);
+        self.offset += len as u64;
+    }
+}
@@ -12,17 +12,53 @@ use rusticata_macros::newtype_enum;
 
 use crate::tls_alert::*;
 use crate::tls_ciphers::*;
-use crate::tls_ec::ECPoint;
+use crate::tls_ec::{ECPoint, NamedGroup};
+use crate::tls_extensions::{
+    parse_tls_extension_in, parse_tls_extensions_in, ExtensionContext, TlsExtension,
+};
+use crate::tls_sign_hash::SignatureScheme;
 
 use std::convert::AsRef;
 use std::fmt;
 use std::ops::Deref;
+use std::str::from_utf8;
 
 pub use nom::{Err, IResult};
 
 /// Max record size (RFC8446 5.1)
 pub const MAX_RECORD_LEN: u16 = 1 << 14;
 
+/// Max encrypted record size (RFC8446 5.2)
+///
+/// TLS 1.2 and earlier allow up to 2048 bytes of expansion (RFC5246 6.2.3), but no common
+/// cipher suite uses more than 256 bytes.
+pub const MAX_CIPHERTEXT_RECORD_LEN: u16 = MAX_RECORD_LEN + 256;
+
+/// Record length limits
+///
+/// Records with a length larger than the limit are rejected with an `ErrorKind::TooLarge`
+/// error when reading the header, before waiting for (or copying) the record data.
+///
+/// The default limits are `MAX_RECORD_LEN` and `MAX_CIPHERTEXT_RECORD_LEN`. They can be
+/// raised to accept non-conforming implementations, or lowered if the maximum fragment length
+/// extension was negotiated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TlsRecordLimits {
+    /// Maximum length of a plaintext record
+    pub max_plaintext_len: u16,
+    /// Maximum length of an encrypted record
+    pub max_ciphertext_len: u16,
+}
+
+impl Default for TlsRecordLimits {
+    fn default() -> Self {
+        TlsRecordLimits {
+            max_plaintext_len: MAX_RECORD_LEN,
+            max_ciphertext_len: MAX_CIPHERTEXT_RECORD_LEN,
+        }
+    }
+}
+
 /// Handshake type
 ///
 /// Handshake types are defined in [RFC5246](https://tools.ietf.org/html/rfc5246) and
@@ -33,25 +69,28 @@ pub struct TlsHandshakeType(pub u8);
 
 newtype_enum! {
 impl debug TlsHandshakeType {
-    HelloRequest        = 0x00,
-    ClientHello         = 0x01,
-    ServerHello         = 0x02,
-    NewSessionTicket    = 0x04,
-    EndOfEarlyData      = 0x05,
-    HelloRetryRequest   = 0x06,
-    EncryptedExtensions = 0x08,
-    Certificate         = 0x0b,
-    ServerKeyExchange   = 0x0c,
-    CertificateRequest  = 0x0d,
-    ServerDone          = 0x0e,
-    CertificateVerify   = 0x0f,
-    ClientKeyExchange   = 0x10,
-    Finished            = 0x14,
-    CertificateURL      = 0x15,
-    CertificateStatus   = 0x16,
-    KeyUpdate           = 0x18,
-
-    NextProtocol        = 0x43,
+    HelloRequest          = 0x00,
+    ClientHello           = 0x01,
+    ServerHello           = 0x02,
+    HelloVerifyRequest    = 0x03,
+    NewSessionTicket      = 0x04,
+    EndOfEarlyData        = 0x05,
+    HelloRetryRequest     = 0x06,
+    EncryptedExtensions   = 0x08,
+    Certificate           = 0x0b,
+    ServerKeyExchange     = 0x0c,
+    CertificateRequest    = 0x0d,
+    ServerDone            = 0x0e,
+    CertificateVerify     = 0x0f,
+    ClientKeyExchange     = 0x10,
+    Finished              = 0x14,
+    CertificateURL        = 0x15,
+    CertificateStatus     = 0x16,
+    KeyUpdate             = 0x18,
+    CompressedCertificate = 0x19,
+
+    NextProtocol          = 0x43,
+    MessageHash           = 0xfe,
 }
 }
 
@@ -61,6 +100,15 @@ impl From<TlsHandshakeType> for u8 {
     }
 }
 
+/// Test if a 16-bit value is reserved for GREASE, as defined in
+/// [RFC8701](https://tools.ietf.org/html/rfc8701)
+///
+/// GREASE values are `0x0a0a`, `0x1a1a`, ..., `0xfafa`. They are sent by clients to prevent
+/// extensibility failures, and have no meaning.
+pub fn is_grease_u16(v: u16) -> bool {
+    v & 0x0f0f == 0x0a0a && v >> 8 == v & 0xff
+}
+
 /// TLS version
 ///
 /// Only the TLS version defined in the TLS message header is meaningful, the
@@ -70,6 +118,7 @@ pub struct TlsVersion(pub u16);
 
 newtype_enum! {
 impl debug TlsVersion {
+    Ssl20        = 0x0002,
     Ssl30        = 0x0300,
     Tls10        = 0x0301,
     Tls11        = 0x0302,
@@ -82,9 +131,36 @@ impl debug TlsVersion {
     Tls13Draft21 = 0x7f15,
     Tls13Draft22 = 0x7f16,
     Tls13Draft23 = 0x7f17,
+
+    DTls10       = 0xfeff,
+    DTls11       = 0xfefe,
+    DTls12       = 0xfefd,
+    DTls13       = 0xfefc,
 }
 }
 
+impl TlsVersion {
+    /// Test if the version is a GREASE value (see `is_grease_u16`)
+    pub fn is_grease(self) -> bool {
+        is_grease_u16(self.0)
+    }
+
+    /// Test if the version is TLS 1.3, or a TLS 1.3 draft
+    pub fn is_tls13(self) -> bool {
+        self == TlsVersion::Tls13 || self.0 & 0xff00 == 0x7f00
+    }
+}
+
+/// Test if the version selected by the ServerHello (`None` before the ServerHello) is older
+/// than TLS 1.3
+///
+/// In these versions, a ChangeCipherSpec message changes the keys. In TLS 1.3, it is only sent
+/// for middlebox compatibility, and can be received before the ServerHello (after a
+/// HelloRetryRequest).
+pub(crate) fn is_tls12_or_earlier(version: Option<TlsVersion>) -> bool {
+    matches!(version, Some(v) if !v.is_tls13())
+}
+
 impl From<TlsVersion> for u16 {
     fn from(v: TlsVersion) -> u16 {
         v.0
@@ -125,6 +201,7 @@ impl debug TlsRecordType {
     Handshake        = 0x16,
     ApplicationData  = 0x17,
     Heartbeat        = 0x18,
+    Tls12Cid         = 0x19,
 }
 }
 
@@ -139,8 +216,20 @@ pub struct TlsCompressionID(pub u8);
 
 newtype_enum! {
 impl debug TlsCompressionID {
-    Null = 0x00,
+    Null    = 0x00,
+    Deflate = 0x01, // [RFC3749]
+    LZS     = 0x40, // [RFC3943]
+}
 }
+
+impl TlsCompressionID {
+    /// Test if this is a compression method other than `Null`
+    ///
+    /// TLS compression allows recovering secrets (for ex. cookies) from the size of the
+    /// encrypted records (CRIME attack, CVE-2012-4929), and is prohibited in TLS 1.3.
+    pub fn is_crime_risk(self) -> bool {
+        self != TlsCompressionID::Null
+    }
 }
 
 impl From<TlsCompressionID> for u8 {
@@ -166,9 +255,29 @@ impl AsRef<u8> for TlsCompressionID {
 pub struct TlsCipherSuiteID(pub u16);
 
 impl TlsCipherSuiteID {
+    /// Signaling cipher suite value, sent by a client retrying a connection with a lower
+    /// version ([RFC7507](https://tools.ietf.org/html/rfc7507))
+    pub const TLS_FALLBACK_SCSV: TlsCipherSuiteID = TlsCipherSuiteID(0x5600);
+    /// Signaling cipher suite value, equivalent to an empty `renegotiation_info` extension
+    /// ([RFC5746](https://tools.ietf.org/html/rfc5746) section 3.3)
+    pub const TLS_EMPTY_RENEGOTIATION_INFO_SCSV: TlsCipherSuiteID = TlsCipherSuiteID(0x00ff);
+
     pub fn get_ciphersuite(self) -> Option<&'static TlsCipherSuite> {
         TlsCipherSuite::from_id(self.0)
     }
+
+    /// Test if the cipher suite is a GREASE value (see `is_grease_u16`)
+    pub fn is_grease(self) -> bool {
+        is_grease_u16(self.0)
+    }
+
+    /// Test if the cipher suite is a signaling cipher suite value (SCSV)
+    ///
+    /// SCSV are not real cipher suites, and cannot be negotiated.
+    pub fn is_scsv(self) -> bool {
+        self == TlsCipherSuiteID::TLS_FALLBACK_SCSV
+            || self == TlsCipherSuiteID::TLS_EMPTY_RENEGOTIATION_INFO_SCSV
+    }
 }
 
 impl From<TlsCipherSuiteID> for u16 {
@@ -258,9 +367,179 @@ impl<'a> TlsClientHelloContents<'a> {
     pub fn get_ciphers(&self) -> Vec<Option<&'static TlsCipherSuite>> {
         self.ciphers.iter().map(|&x| x.get_ciphersuite()).collect()
     }
+
+    /// Get the list of cipher suites, with GREASE values removed
+    pub fn get_ciphers_without_grease(&self) -> Vec<TlsCipherSuiteID> {
+        self.ciphers
+            .iter()
+            .filter(|c| !c.is_grease())
+            .cloned()
+            .collect()
+    }
+
+    /// Get the export-grade RSA cipher suites (RSA_EXPORT) offered by the client
+    ///
+    /// See `TlsCipherSuite::is_export_rsa`.
+    pub fn get_export_rsa_ciphers(&self) -> Vec<TlsCipherSuiteID> {
+        self.ciphers
+            .iter()
+            .filter(|c| matches!(c.get_ciphersuite(), Some(cs) if cs.is_export_rsa()))
+            .cloned()
+            .collect()
+    }
+
+    /// Test if the client offers a compression method other than `Null`
+    ///
+    /// See `TlsCompressionID::is_crime_risk`.
+    pub fn has_compression(&self) -> bool {
+        self.comp.iter().any(|c| c.is_crime_risk())
+    }
+
+    /// Test if the client sent `TLS_FALLBACK_SCSV`
+    ///
+    /// The client indicates that this is a fallback connection, with a lower version than
+    /// the highest supported. If the server supports a higher version, it must reject the
+    /// connection with an `InappropriateFallback` alert.
+    pub fn has_fallback_scsv(&self) -> bool {
+        self.ciphers.contains(&TlsCipherSuiteID::TLS_FALLBACK_SCSV)
+    }
+
+    /// Test if the client sent `TLS_EMPTY_RENEGOTIATION_INFO_SCSV`
+    ///
+    /// The client indicates that it supports secure renegotiation, as if it had sent an
+    /// empty `renegotiation_info` extension.
+    pub fn has_renegotiation_info_scsv(&self) -> bool {
+        self.ciphers
+            .contains(&TlsCipherSuiteID::TLS_EMPTY_RENEGOTIATION_INFO_SCSV)
+    }
+
+    /// Test if the client sent the `early_data` extension (TLS 1.3 0-RTT data)
+    ///
+    /// Returns `false` if the extensions cannot be parsed.
+    pub fn has_early_data(&self) -> bool {
+        let exts = match self.ext {
+            Some(ext) => ext,
+            None => return false,
+        };
+        match parse_tls_extensions_in(exts, ExtensionContext::ClientHello) {
+            Ok((_, exts)) => exts
+                .iter()
+                .any(|ext| matches!(ext, TlsExtension::EarlyData(_))),
+            Err(_) => false,
+        }
+    }
+
+    /// Get the SRP user identity, if the `srp` extension is present
+    ///
+    /// Returns `None` if the extensions cannot be parsed, or if the identity is not valid UTF-8.
+    pub fn get_srp_identity(&self) -> Option<&'a str> {
+        let (_, exts) = parse_tls_extensions_in(self.ext?, ExtensionContext::ClientHello).ok()?;
+        exts.iter().find_map(|ext| match *ext {
+            TlsExtension::Srp(identity) => from_utf8(identity).ok(),
+            _ => None,
+        })
+    }
+}
+
+/// A ClientHello message, parsed from possibly truncated data
+///
+/// See `parse_tls_client_hello_truncated`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlsTruncatedClientHello<'a> {
+    /// Message contents. If truncated, the ciphers, compression methods and extensions
+    /// contain only the available data.
+    pub hello: TlsClientHelloContents<'a>,
+    /// Extensions parsed before the truncation point
+    pub extensions: Vec<TlsExtension<'a>>,
+    /// Number of bytes of the message missing from the input (0 if the message is complete)
+    pub missing_bytes: usize,
+}
+
+impl<'a> TlsTruncatedClientHello<'a> {
+    /// Test if the message is truncated
+    pub fn is_truncated(&self) -> bool {
+        self.missing_bytes > 0
+    }
+}
+
+/// Random value of a TLS 1.3 HelloRetryRequest, as defined in
+/// [RFC8446](https://tools.ietf.org/html/rfc8446) section 4.1.3
+///
+/// This is the SHA-256 of "HelloRetryRequest".
+pub const HRR_RANDOM: [u8; 32] = [
+    0xcf, 0x21, 0xad, 0x74, 0xe5, 0x9a, 0x61, 0x11, 0xbe, 0x1d, 0x8c, 0x02, 0x1e, 0x65, 0xb8, 0x91,
+    0xc2, 0xa2, 0x11, 0x16, 0x7a, 0xbb, 0x8c, 0x5e, 0x07, 0x9e, 0x09, 0xe2, 0xc8, 0xa8, 0x33, 0x9c,
+];
+
+/// Last 8 bytes of the ServerHello random, set by a TLS 1.3 server negotiating TLS 1.2
+/// ([RFC8446](https://tools.ietf.org/html/rfc8446) section 4.1.3)
+pub const DOWNGRADE_SENTINEL_TLS12: [u8; 8] = *b"DOWNGRD\x01";
+
+/// Last 8 bytes of the ServerHello random, set by a TLS 1.3 (or TLS 1.2) server negotiating
+/// TLS 1.1 or below ([RFC8446](https://tools.ietf.org/html/rfc8446) section 4.1.3)
+pub const DOWNGRADE_SENTINEL_TLS11: [u8; 8] = *b"DOWNGRD\x00";
+
+/// Downgrade protection value found in a ServerHello random
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsDowngradeSentinel {
+    /// The server supports TLS 1.3, but negotiated TLS 1.2
+    Tls12,
+    /// The server supports TLS 1.2 or above, but negotiated TLS 1.1 or below
+    Tls11,
+}
+
+/// Session ID sent by the server, compared to the ClientHello session ID
+///
+/// See `TlsServerHelloContents::session_id_echo`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsSessionIdEcho {
+    /// TLS 1.2 and below: new session (the client did not send a session ID, or the server
+    /// did not accept it)
+    NewSession,
+    /// TLS 1.2 and below: session resumption (the server echoed the client session ID)
+    Resumption,
+    /// TLS 1.3, without session ID
+    Tls13,
+    /// TLS 1.3 middlebox compatibility mode: the server echoed the (non-empty) client session
+    /// ID. This is not a resumption.
+    Tls13CompatibilityMode,
+    /// TLS 1.3, and the server did not echo the client session ID. The client must abort the
+    /// handshake.
+    Tls13Mismatch,
+}
+
+/// Error returned when the version selected by the server is not valid
+///
+/// See `TlsServerHelloContents::negotiated_version`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsVersionNegotiationError {
+    /// The extensions of the ClientHello or the ServerHello could not be parsed
+    InvalidExtensions,
+    /// The server selected a version not offered by the client
+    NotOffered(TlsVersion),
+    /// The server selected a version before TLS 1.3 (or a GREASE value) in the
+    /// `supported_versions` extension
+    InvalidSupportedVersion(TlsVersion),
+    /// The server selected TLS 1.3 in the legacy version field, without the
+    /// `supported_versions` extension
+    LegacyTls13(TlsVersion),
+}
+
+/// Test if `version` is newer than `max` (DTLS version numbers decrease)
+pub(crate) fn is_version_newer(version: TlsVersion, max: TlsVersion) -> bool {
+    let is_dtls = |v: TlsVersion| v.0 & 0xff00 == 0xfe00;
+    if is_dtls(version) && is_dtls(max) {
+        version.0 < max.0
+    } else {
+        version.0 > max.0
+    }
 }
 
 /// TLS Server Hello (from TLS 1.0 to TLS 1.2)
+///
+/// This structure is also used for TLS 1.3, where the `version` field is set to TLS 1.2 and
+/// the negotiated version is sent in the `supported_versions` extension. A TLS 1.3
+/// HelloRetryRequest also uses this structure, see `is_hello_retry_request`.
 #[derive(Clone, PartialEq)]
 pub struct TlsServerHelloContents<'a> {
     pub version: TlsVersion,
@@ -320,6 +599,163 @@ impl<'a> TlsServerHelloContents<'a> {
     pub fn get_cipher(&self) -> Option<&'static TlsCipherSuite> {
         self.cipher.get_ciphersuite()
     }
+
+    /// Test if this message is a TLS 1.3 HelloRetryRequest
+    ///
+    /// In TLS 1.3, a HelloRetryRequest is sent as a ServerHello with a special random value
+    /// (see `HRR_RANDOM`).
+    pub fn is_hello_retry_request(&self) -> bool {
+        self.rand_time.to_be_bytes() == HRR_RANDOM[..4] && self.rand_data == &HRR_RANDOM[4..]
+    }
+
+    /// Get the version selected by the server in the `supported_versions` extension
+    ///
+    /// This extension is only sent by TLS 1.3 servers. Returns `None` if the extensions cannot
+    /// be parsed, or if the extension is absent.
+    pub fn get_supported_version(&self) -> Option<TlsVersion> {
+        let (_, exts) = self.parse_extensions().ok()?;
+        exts.iter().find_map(|ext| match ext {
+            TlsExtension::SupportedVersions(v) => v.first().copied(),
+            _ => None,
+        })
+    }
+
+    /// Compare the session ID of this message with the session ID of the ClientHello
+    ///
+    /// In TLS 1.2 and below, a server echoing the client session ID resumes the session. In
+    /// TLS 1.3 (detected using the `supported_versions` extension), the server must always
+    /// echo the client session ID, which is only used for middlebox compatibility
+    /// ([RFC8446](https://tools.ietf.org/html/rfc8446) appendix D.4).
+    pub fn session_id_echo(&self, client_hello: &TlsClientHelloContents) -> TlsSessionIdEcho {
+        let client_sid = client_hello.session_id.unwrap_or(&[]);
+        let server_sid = self.session_id.unwrap_or(&[]);
+        let tls13 = matches!(self.get_supported_version(), Some(v) if v.is_tls13());
+        match (tls13, client_sid == server_sid) {
+            (true, false) => TlsSessionIdEcho::Tls13Mismatch,
+            (true, true) if client_sid.is_empty() => TlsSessionIdEcho::Tls13,
+            (true, true) => TlsSessionIdEcho::Tls13CompatibilityMode,
+            (false, true) if !client_sid.is_empty() => TlsSessionIdEcho::Resumption,
+            (false, _) => TlsSessionIdEcho::NewSession,
+        }
+    }
+
+    /// Get the protocol version negotiated with the ClientHello
+    ///
+    /// The rules of [RFC8446](https://tools.ietf.org/html/rfc8446) section 4.2.1 are applied:
+    /// if the server sent the `supported_versions` extension, it contains the negotiated version
+    /// (TLS 1.3 or later), which must have been offered in the `supported_versions` extension of
+    /// the client. Otherwise, the legacy version field contains the negotiated version (TLS 1.2
+    /// or earlier), which must not be greater than the legacy version of the client.
+    ///
+    /// DTLS versions are compared in the reverse order.
+    pub fn negotiated_version(
+        &self,
+        client_hello: &TlsClientHelloContents,
+    ) -> Result<TlsVersion, TlsVersionNegotiationError> {
+        let (_, exts) = self
+            .parse_extensions()
+            .or(Err(TlsVersionNegotiationError::InvalidExtensions))?;
+        let selected = exts.iter().find_map(|ext| match ext {
+            TlsExtension::SupportedVersions(v) => v.first().copied(),
+            _ => None,
+        });
+        let version = match selected {
+            Some(v) => v,
+            None if self.version.is_tls13() || self.version == TlsVersion::DTls13 => {
+                return Err(TlsVersionNegotiationError::LegacyTls13(self.version));
+            }
+            None if is_version_newer(self.version, client_hello.version) => {
+                return Err(TlsVersionNegotiationError::NotOffered(self.version));
+            }
+            None => return Ok(self.version),
+        };
+        if !version.is_tls13() && version != TlsVersion::DTls13 {
+            return Err(TlsVersionNegotiationError::InvalidSupportedVersion(version));
+        }
+        let ext = client_hello.ext.unwrap_or(&[]);
+        let (_, client_exts) = parse_tls_extensions_in(ext, ExtensionContext::ClientHello)
+            .or(Err(TlsVersionNegotiationError::InvalidExtensions))?;
+        let offered = client_exts.iter().any(|ext| match ext {
+            TlsExtension::SupportedVersions(v) => v.contains(&version),
+            _ => false,
+        });
+        if offered {
+            Ok(version)
+        } else {
+            Err(TlsVersionNegotiationError::NotOffered(version))
+        }
+    }
+
+    /// Get the downgrade protection value from the last 8 bytes of the random
+    ///
+    /// A client supporting TLS 1.3 (or TLS 1.2, for the `Tls11` value) receiving a ServerHello
+    /// with a downgrade sentinel for an older version must abort the handshake: this
+    /// indicates that a downgrade attack may be in progress.
+    pub fn get_downgrade_sentinel(&self) -> Option<TlsDowngradeSentinel> {
+        if self.rand_data.len() < 8 {
+            return None;
+        }
+        match &self.rand_data[self.rand_data.len() - 8..] {
+            s if s == DOWNGRADE_SENTINEL_TLS12 => Some(TlsDowngradeSentinel::Tls12),
+            s if s == DOWNGRADE_SENTINEL_TLS11 => Some(TlsDowngradeSentinel::Tls11),
+            _ => None,
+        }
+    }
+
+    /// Parse the extensions of this message
+    ///
+    /// If the message is a HelloRetryRequest, the extensions are parsed using the
+    /// HelloRetryRequest format (for ex., `key_share` contains only the selected group).
+    pub fn parse_extensions(&self) -> IResult<&'a [u8], Vec<TlsExtension<'a>>> {
+        let ctx = if self.is_hello_retry_request() {
+            ExtensionContext::HelloRetryRequest
+        } else {
+            ExtensionContext::ServerHello
+        };
+        parse_tls_extensions_in(self.ext.unwrap_or(&[]), ctx)
+    }
+
+    /// Get the index of the pre-shared key identity selected by the server
+    ///
+    /// Returns `None` if the extensions cannot be parsed, or if the `pre_shared_key` extension
+    /// is absent (the server did not accept a PSK, and rejected the 0-RTT data).
+    pub fn get_selected_psk_identity(&self) -> Option<u16> {
+        let (_, exts) = self.parse_extensions().ok()?;
+        exts.iter().find_map(|ext| match *ext {
+            TlsExtension::PreSharedKeySelected(idx) => Some(idx),
+            _ => None,
+        })
+    }
+
+    /// Get the group selected by the server in a HelloRetryRequest
+    ///
+    /// Returns `None` if the message is not a HelloRetryRequest, if the extensions cannot be
+    /// parsed, or if the `key_share` extension is absent.
+    pub fn get_hrr_selected_group(&self) -> Option<NamedGroup> {
+        if !self.is_hello_retry_request() {
+            return None;
+        }
+        let (_, exts) = self.parse_extensions().ok()?;
+        exts.iter().find_map(|ext| match *ext {
+            TlsExtension::KeyShareHelloRetryRequest(group) => Some(group),
+            _ => None,
+        })
+    }
+
+    /// Get the cookie sent by the server in a HelloRetryRequest
+    ///
+    /// Returns `None` if the message is not a HelloRetryRequest, if the extensions cannot be
+    /// parsed, or if the `cookie` extension is absent.
+    pub fn get_hrr_cookie(&self) -> Option<&'a [u8]> {
+        if !self.is_hello_retry_request() {
+            return None;
+        }
+        let (_, exts) = self.parse_extensions().ok()?;
+        exts.iter().find_map(|ext| match *ext {
+            TlsExtension::Cookie(cookie) => Some(cookie),
+            _ => None,
+        })
+    }
 }
 
 /// Session ticket, as defined in [RFC5077](https://tools.ietf.org/html/rfc5077)
@@ -329,6 +765,34 @@ pub struct TlsNewSessionTicketContent<'a> {
     pub ticket: &'a [u8],
 }
 
+/// Session ticket (TLS 1.3), as defined in [RFC8446](https://tools.ietf.org/html/rfc8446)
+/// section 4.6.1
+#[derive(Clone, PartialEq)]
+pub struct TlsNewSessionTicketV13Content<'a> {
+    /// Ticket lifetime, in seconds
+    pub ticket_lifetime: u32,
+    pub ticket_age_add: u32,
+    pub ticket_nonce: &'a [u8],
+    pub ticket: &'a [u8],
+    pub ext: &'a [u8],
+}
+
+impl<'a> TlsNewSessionTicketV13Content<'a> {
+    /// Get the maximum amount of 0-RTT data the client is allowed to send, from the
+    /// `early_data` extension
+    ///
+    /// Returns `None` if the extension is absent (0-RTT not allowed with this ticket), or if
+    /// the extensions cannot be parsed.
+    pub fn max_early_data_size(&self) -> Option<u32> {
+        let (_, exts) =
+            parse_tls_extensions_in(self.ext, ExtensionContext::NewSessionTicket).ok()?;
+        exts.iter().find_map(|ext| match *ext {
+            TlsExtension::EarlyData(size) => size,
+            _ => None,
+        })
+    }
+}
+
 /// A raw certificate, which should be a DER-encoded X.509 certificate.
 ///
 /// See [RFC5280](https://tools.ietf.org/html/rfc5280) for X509v3 certificate format.
@@ -344,6 +808,74 @@ pub struct TlsCertificateContents<'a> {
     pub cert_chain: Vec<RawCertificate<'a>>,
 }
 
+/// Encrypted extensions (TLS 1.3), as defined in [RFC8446](https://tools.ietf.org/html/rfc8446)
+/// section 4.3.1
+#[derive(Clone, PartialEq)]
+pub struct TlsEncryptedExtensionsContents<'a> {
+    pub ext: &'a [u8],
+}
+
+impl<'a> TlsEncryptedExtensionsContents<'a> {
+    /// Parse the extensions of this message
+    ///
+    /// Use `encrypted_extensions_violations` on the result to check if all extensions are
+    /// allowed in this message.
+    pub fn parse_extensions(&self) -> IResult<&'a [u8], Vec<TlsExtension<'a>>> {
+        parse_tls_extensions_in(self.ext, ExtensionContext::EncryptedExtensions)
+    }
+}
+
+/// Certificate entry (TLS 1.3), as defined in [RFC8446](https://tools.ietf.org/html/rfc8446)
+/// section 4.4.2
+///
+/// The extensions (for ex. `status_request` or `signed_certificate_timestamp`) are unparsed, call
+/// `parse_extensions` to parse them.
+#[derive(Clone, PartialEq)]
+pub struct TlsCertificateEntry<'a> {
+    /// Certificate data, usually a DER-encoded X.509 certificate
+    pub data: &'a [u8],
+    pub ext: &'a [u8],
+}
+
+impl<'a> TlsCertificateEntry<'a> {
+    /// Parse the extensions of this certificate entry
+    pub fn parse_extensions(&self) -> IResult<&'a [u8], Vec<TlsExtension<'a>>> {
+        parse_tls_extensions_in(self.ext, ExtensionContext::Certificate)
+    }
+}
+
+/// The certificate chain (TLS 1.3), as defined in [RFC8446](https://tools.ietf.org/html/rfc8446)
+/// section 4.4.2
+#[derive(Clone, PartialEq)]
+pub struct TlsCertificateV13Contents<'a> {
+    /// Empty, unless the message is a response to a CertificateRequest
+    pub certificate_request_context: &'a [u8],
+    pub cert_list: Vec<TlsCertificateEntry<'a>>,
+}
+
+/// Certificate compression algorithm, as defined in [RFC8879](https://tools.ietf.org/html/rfc8879)
+#[derive(Clone, Copy, PartialEq, Eq, Nom)]
+pub struct CertificateCompressionAlgorithm(pub u16);
+
+newtype_enum! {
+impl debug CertificateCompressionAlgorithm {
+    Zlib   = 0x1,
+    Brotli = 0x2,
+    Zstd   = 0x3,
+}
+}
+
+/// Compressed certificate, as defined in [RFC8879](https://tools.ietf.org/html/rfc8879) section 4
+///
+/// Once decompressed, `compressed_certificate_message` contains the body of a Certificate
+/// message. See `decompress` (requires the `compression` feature).
+#[derive(Clone, PartialEq)]
+pub struct TlsCompressedCertificateContents<'a> {
+    pub algorithm: CertificateCompressionAlgorithm,
+    pub uncompressed_length: u32,
+    pub compressed_certificate_message: &'a [u8],
+}
+
 /// Certificate request, as defined in [RFC5246](https://tools.ietf.org/html/rfc5246) section 7.4.4
 ///
 /// Note: TLS 1.2 adds SignatureAndHashAlgorithm (chapter 7.4.4) but do not declare it in A.4.2
@@ -356,10 +888,27 @@ pub struct TlsCertificateRequestContents<'a> {
     pub unparsed_ca: Vec<&'a [u8]>,
 }
 
+/// Certificate request (TLS 1.3), as defined in [RFC8446](https://tools.ietf.org/html/rfc8446)
+/// section 4.3.2
+#[derive(Clone, PartialEq)]
+pub struct TlsCertificateRequestV13Contents<'a> {
+    /// Empty, unless the request is sent after the handshake (post-handshake authentication).
+    /// The client Certificate message uses the same context.
+    pub certificate_request_context: &'a [u8],
+    pub ext: &'a [u8],
+}
+
+impl<'a> TlsCertificateRequestV13Contents<'a> {
+    /// Parse the extensions of this message
+    pub fn parse_extensions(&self) -> IResult<&'a [u8], Vec<TlsExtension<'a>>> {
+        parse_tls_extensions_in(self.ext, ExtensionContext::CertificateRequest)
+    }
+}
+
 /// Server key exchange parameters
 ///
 /// This is an opaque struct, since the content depends on the selected
-/// key exchange method.
+/// key exchange method. Use `parse_server_key_exchange_params` to decode it.
 #[derive(Clone, PartialEq)]
 pub struct TlsServerKeyExchangeContents<'a> {
     pub parameters: &'a [u8],
@@ -375,6 +924,46 @@ pub enum TlsClientKeyExchangeContents<'a> {
     Unknown(&'a [u8]),
 }
 
+/// Certificate chain type, as defined in [RFC6066](https://tools.ietf.org/html/rfc6066) section 5
+#[derive(Clone, Copy, PartialEq, Eq, Nom)]
+pub struct CertChainType(pub u8);
+
+newtype_enum! {
+impl debug CertChainType {
+    IndividualCerts = 0,
+    PkiPath         = 1,
+}
+}
+
+/// URL of a certificate (or certificate chain), and SHA-1 hash of the DER-encoded data
+#[derive(Clone, PartialEq)]
+pub struct TlsUrlAndHash<'a> {
+    pub url: &'a [u8],
+    pub sha1_hash: &'a [u8],
+}
+
+/// Certificate verify, as defined in [RFC5246](https://tools.ietf.org/html/rfc5246) section
+/// 7.4.8 and [RFC8446](https://tools.ietf.org/html/rfc8446) section 4.4.3
+///
+/// The `TlsMessageHandshake::CertificateVerify` message is unparsed, since its format depends
+/// on the protocol version. Call `parse_certificate_verify` to get this structure.
+#[derive(Clone, PartialEq)]
+pub struct TlsCertificateVerifyContents<'a> {
+    /// Signature algorithm, only present for TLS 1.2 and 1.3
+    pub algorithm: Option<SignatureScheme>,
+    pub signature: &'a [u8],
+}
+
+/// Certificate URL, as defined in [RFC6066](https://tools.ietf.org/html/rfc6066) section 5
+///
+/// Sent by the client instead of a Certificate message, if the `client_certificate_url`
+/// extension was negotiated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlsCertificateUrlContents<'a> {
+    pub chain_type: CertChainType,
+    pub url_and_hash_list: Vec<TlsUrlAndHash<'a>>,
+}
+
 /// Certificate status response, as defined in [RFC6066](https://tools.ietf.org/html/rfc6066) section 8
 #[derive(Clone, Debug, PartialEq)]
 pub struct TlsCertificateStatusContents<'a> {
@@ -409,18 +998,28 @@ pub enum TlsMessageHandshake<'a> {
     ServerHello(TlsServerHelloContents<'a>),
     ServerHelloV13Draft18(TlsServerHelloV13Draft18Contents<'a>),
     NewSessionTicket(TlsNewSessionTicketContent<'a>),
+    NewSessionTicketV13(TlsNewSessionTicketV13Content<'a>),
     EndOfEarlyData,
     HelloRetryRequest(TlsHelloRetryRequestContents<'a>),
+    EncryptedExtensions(TlsEncryptedExtensionsContents<'a>),
     Certificate(TlsCertificateContents<'a>),
+    CertificateV13(TlsCertificateV13Contents<'a>),
     ServerKeyExchange(TlsServerKeyExchangeContents<'a>),
     CertificateRequest(TlsCertificateRequestContents<'a>),
+    CertificateRequestV13(TlsCertificateRequestV13Contents<'a>),
     ServerDone(&'a [u8]),
     CertificateVerify(&'a [u8]),
     ClientKeyExchange(TlsClientKeyExchangeContents<'a>),
     Finished(&'a [u8]),
+    CertificateURL(TlsCertificateUrlContents<'a>),
     CertificateStatus(TlsCertificateStatusContents<'a>),
     NextProtocol(TlsNextProtocolContent<'a>),
     KeyUpdate(u8),
+    CompressedCertificate(TlsCompressedCertificateContents<'a>),
+    /// Synthetic message containing the hash of the first ClientHello, which replaces it in the
+    /// transcript after a HelloRetryRequest ([RFC8446](https://tools.ietf.org/html/rfc8446)
+    /// section 4.4.1). This message is never sent on the wire.
+    MessageHash(&'a [u8]),
 }
 
 /// TLS application data
@@ -439,8 +1038,31 @@ pub struct TlsMessageApplicationData<'a> {
 #[derive(Clone, Debug, PartialEq)]
 pub struct TlsMessageHeartbeat<'a> {
     pub heartbeat_type: TlsHeartbeatMessageType,
+    /// Declared length of the payload
     pub payload_len: u16,
+    /// Payload, truncated to the record data if `payload_len` is too large
     pub payload: &'a [u8],
+    /// Padding (at least 16 bytes, for valid messages)
+    pub padding: &'a [u8],
+}
+
+impl<'a> TlsMessageHeartbeat<'a> {
+    /// Test if the declared payload length exceeds the data in the record
+    ///
+    /// Such messages must be discarded by the receiver ([RFC6520](https://tools.ietf.org/html/rfc6520)
+    /// section 4).
+    pub fn is_payload_length_mismatch(&self) -> bool {
+        self.payload_len as usize > self.payload.len()
+    }
+
+    /// Test if the message is a request with an invalid payload length
+    ///
+    /// This is the signature of the Heartbleed attack (CVE-2014-0160): a vulnerable peer
+    /// replies with `payload_len` bytes of its memory.
+    pub fn is_heartbleed_probe(&self) -> bool {
+        self.heartbeat_type == TlsHeartbeatMessageType::HeartBeatRequest
+            && self.is_payload_length_mismatch()
+    }
 }
 
 /// TLS record header
@@ -473,6 +1095,83 @@ pub struct TlsPlaintext<'a> {
     pub msg: Vec<TlsMessage<'a>>,
 }
 
+impl<'a> TlsPlaintext<'a> {
+    /// Check the record version against the version of the hello messages of this record
+    ///
+    /// See `check_record_version`. Records without ClientHello or ServerHello messages are
+    /// only checked for invalid versions.
+    pub fn record_version_warnings(&self) -> Vec<TlsRecordVersionWarning> {
+        let mut warnings = Vec::new();
+        let mut has_hello = false;
+        for msg in &self.msg {
+            let (version, server_tls13) = match msg {
+                TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)) => (ch.version, false),
+                TlsMessage::Handshake(TlsMessageHandshake::ServerHello(sh)) => {
+                    match sh.get_supported_version() {
+                        Some(v) if v.is_tls13() => (v, true),
+                        _ => (sh.version, false),
+                    }
+                }
+                _ => continue,
+            };
+            has_hello = true;
+            if server_tls13 {
+                if self.hdr.version != TlsVersion::Tls12 {
+                    warnings.push(TlsRecordVersionWarning::Tls13NotLegacyVersion);
+                }
+            } else {
+                warnings.extend(check_record_version(self.hdr.version, Some(version)));
+            }
+        }
+        if !has_hello {
+            warnings.extend(check_record_version(self.hdr.version, None));
+        }
+        warnings
+    }
+}
+
+/// Anomaly of the record layer version
+///
+/// These anomalies are not errors (records are parsed anyway), but can indicate an old or
+/// non-conformant implementation, or a scanning tool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsRecordVersionWarning {
+    /// The record version is not a SSL 3.0 to TLS 1.2 version
+    InvalidVersion(TlsVersion),
+    /// SSL 3.0 record version, with a newer handshake version
+    Ssl30Record,
+    /// The record version is higher than the handshake version
+    HigherThanHandshake,
+    /// A TLS 1.3 ServerHello, not sent with the TLS 1.2 record version
+    /// ([RFC8446](https://tools.ietf.org/html/rfc8446) section 5.1)
+    Tls13NotLegacyVersion,
+}
+
+/// Check the record layer version, compared to the version of the handshake message
+///
+/// `handshake_version` is the version of the ClientHello or ServerHello (the legacy version
+/// field, for TLS 1.3). Using a lower record version is allowed (for ex., a TLS 1.2 or 1.3
+/// ClientHello sent in a TLS 1.0 record).
+pub fn check_record_version(
+    record_version: TlsVersion,
+    handshake_version: Option<TlsVersion>,
+) -> Vec<TlsRecordVersionWarning> {
+    let mut warnings = Vec::new();
+    if record_version.0 < TlsVersion::Ssl30.0 || record_version.0 > TlsVersion::Tls12.0 {
+        warnings.push(TlsRecordVersionWarning::InvalidVersion(record_version));
+        return warnings;
+    }
+    if let Some(version) = handshake_version {
+        if record_version == TlsVersion::Ssl30 && version.0 > TlsVersion::Ssl30.0 {
+            warnings.push(TlsRecordVersionWarning::Ssl30Record);
+        }
+        if record_version.0 > version.0 {
+            warnings.push(TlsRecordVersionWarning::HigherThanHandshake);
+        }
+    }
+    warnings
+}
+
 /// TLS encrypted data
 ///
 /// This struct only contains an opaque pointer (data are encrypted).
@@ -497,7 +1196,7 @@ pub struct TlsRawRecord<'a> {
     pub data: &'a [u8],
 }
 
-fn parse_cipher_suites(i: &[u8], len: usize) -> IResult<&[u8], Vec<TlsCipherSuiteID>> {
+pub(crate) fn parse_cipher_suites(i: &[u8], len: usize) -> IResult<&[u8], Vec<TlsCipherSuiteID>> {
     if len == 0 {
         return Ok((i, Vec::new()));
     }
@@ -511,7 +1210,10 @@ fn parse_cipher_suites(i: &[u8], len: usize) -> IResult<&[u8], Vec<TlsCipherSuit
     Ok((&i[len..], v))
 }
 
-fn parse_compressions_algs(i: &[u8], len: usize) -> IResult<&[u8], Vec<TlsCompressionID>> {
+pub(crate) fn parse_compressions_algs(
+    i: &[u8],
+    len: usize,
+) -> IResult<&[u8], Vec<TlsCompressionID>> {
     if len == 0 {
         return Ok((i, Vec::new()));
     }
@@ -553,7 +1255,9 @@ pub fn parse_tls_record_header(i: &[u8]) -> IResult<&[u8], TlsRecordHeader> {
     TlsRecordHeader::parse(i)
 }
 
-fn parse_tls_handshake_msg_hello_request(i: &[u8]) -> IResult<&[u8], TlsMessageHandshake> {
+pub(crate) fn parse_tls_handshake_msg_hello_request(
+    i: &[u8],
+) -> IResult<&[u8], TlsMessageHandshake> {
     Ok((i, TlsMessageHandshake::HelloRequest))
 }
 
@@ -573,7 +1277,63 @@ fn parse_tls_handshake_msg_client_hello(i: &[u8]) -> IResult<&[u8], TlsMessageHa
     Ok((i, TlsMessageHandshake::ClientHello(content)))
 }
 
-fn parse_tls_handshake_msg_server_hello_tlsv12(i: &[u8]) -> IResult<&[u8], TlsMessageHandshake> {
+/// Read a vector with a `len_size`-bytes length, truncated to the available data
+///
+/// Return the remaining data and the vector content.
+fn take_truncated_vector(i: &[u8], len_size: usize) -> (&[u8], &[u8]) {
+    if i.len() < len_size {
+        return (&[], &[]);
+    }
+    let (len, data) = i.split_at(len_size);
+    let len = len.iter().fold(0, |acc, &b| acc << 8 | b as usize);
+    let (v, rem) = data.split_at(data.len().min(len));
+    (rem, v)
+}
+
+/// Parse a ClientHello handshake message (including the handshake header), accepting
+/// truncated data
+///
+/// This is intended for captures with a small snapshot length, where the ClientHello is cut
+/// in the middle. The message is parsed up to the end of the input: the version, random and
+/// session ID must be present, but the ciphers, compression methods and extensions can be
+/// truncated. Only complete cipher suites and extensions are returned.
+///
+/// The remaining data is empty if the message is truncated.
+pub fn parse_tls_client_hello_truncated(i: &[u8]) -> IResult<&[u8], TlsTruncatedClientHello> {
+    let (i, _) = verify(be_u8, |&ht| ht == u8::from(TlsHandshakeType::ClientHello))(i)?;
+    let (i, hl) = be_u24(i)?;
+    let (data, rem) = i.split_at(i.len().min(hl as usize));
+    let missing_bytes = hl as usize - data.len();
+    let (data, version) = be_u16(data)?;
+    let (data, rand_time) = be_u32(data)?;
+    let (data, rand_data) = take(28usize)(data)?;
+    let (data, sidlen) = verify(be_u8, |&n| n <= 32)(data)?;
+    let (data, sid) = cond(sidlen > 0, take(sidlen as usize))(data)?;
+    // from here, fields are truncated to the available data
+    let (data, ciphers) = take_truncated_vector(data, 2);
+    let (_, ciphers) = parse_cipher_suites(ciphers, ciphers.len() & !1)?;
+    let (data, comp) = take_truncated_vector(data, 1);
+    let (_, comp) = parse_compressions_algs(comp, comp.len())?;
+    let ext = if data.is_empty() {
+        None
+    } else {
+        Some(take_truncated_vector(data, 2).1)
+    };
+    let (_, extensions) = many0(complete(|i| {
+        parse_tls_extension_in(i, ExtensionContext::ClientHello)
+    }))(ext.unwrap_or(&[]))?;
+    let hello = TlsClientHelloContents::new(version, rand_time, rand_data, sid, ciphers, comp, ext);
+    let hello = TlsTruncatedClientHello {
+        hello,
+        extensions,
+        missing_bytes,
+    };
+    Ok((rem, hello))
+}
+
+pub(crate) fn parse_tls_handshake_msg_server_hello_tlsv12(
+    i: &[u8],
+) -> IResult<&[u8], TlsMessageHandshake> {
     let (i, version) = be_u16(i)?;
     let (i, rand_time) = be_u32(i)?;
     let (i, rand_data) = take(28usize)(i)?; // 28 as 32 (aligned) - 4 (time)
@@ -616,7 +1376,7 @@ fn parse_tls_handshake_msg_server_hello(i: &[u8]) -> IResult<&[u8], TlsMessageHa
 }
 
 // RFC 5077   Stateless TLS Session Resumption
-fn parse_tls_handshake_msg_newsessionticket(
+pub(crate) fn parse_tls_handshake_msg_newsessionticket(
     i: &[u8],
     len: usize,
 ) -> IResult<&[u8], TlsMessageHandshake> {
@@ -632,6 +1392,23 @@ fn parse_tls_handshake_msg_newsessionticket(
     Ok((i, TlsMessageHandshake::NewSessionTicket(content)))
 }
 
+// struct {
+//     Extension extensions<0..2^16-1>;
+// } EncryptedExtensions;
+fn parse_tls_handshake_msg_encrypted_extensions(i: &[u8]) -> IResult<&[u8], TlsMessageHandshake> {
+    let (i, ext) = length_data(be_u16)(i)?;
+    let content = TlsEncryptedExtensionsContents { ext };
+    Ok((i, TlsMessageHandshake::EncryptedExtensions(content)))
+}
+
+// struct {} EndOfEarlyData;
+fn parse_tls_handshake_msg_end_of_early_data(i: &[u8]) -> IResult<&[u8], TlsMessageHandshake> {
+    if !i.is_empty() {
+        return Err(Err::Error(make_error(i, ErrorKind::Verify)));
+    }
+    Ok((i, TlsMessageHandshake::EndOfEarlyData))
+}
+
 fn parse_tls_handshake_msg_hello_retry_request(i: &[u8]) -> IResult<&[u8], TlsMessageHandshake> {
     let (i, version) = TlsVersion::parse(i)?;
     let (i, cipher) = map(be_u16, TlsCipherSuiteID)(i)?;
@@ -644,14 +1421,14 @@ fn parse_tls_handshake_msg_hello_retry_request(i: &[u8]) -> IResult<&[u8], TlsMe
     Ok((i, TlsMessageHandshake::HelloRetryRequest(content)))
 }
 
-fn parse_tls_handshake_msg_certificate(i: &[u8]) -> IResult<&[u8], TlsMessageHandshake> {
+pub(crate) fn parse_tls_handshake_msg_certificate(i: &[u8]) -> IResult<&[u8], TlsMessageHandshake> {
     let (i, cert_len) = be_u24(i)?;
     let (i, cert_chain) = map_parser(take(cert_len as usize), parse_certs)(i)?;
     let content = TlsCertificateContents { cert_chain };
     Ok((i, TlsMessageHandshake::Certificate(content)))
 }
 
-fn parse_tls_handshake_msg_serverkeyexchange(
+pub(crate) fn parse_tls_handshake_msg_serverkeyexchange(
     i: &[u8],
     len: usize,
 ) -> IResult<&[u8], TlsMessageHandshake> {
@@ -660,18 +1437,21 @@ fn parse_tls_handshake_msg_serverkeyexchange(
     })(i)
 }
 
-fn parse_tls_handshake_msg_serverdone(i: &[u8], len: usize) -> IResult<&[u8], TlsMessageHandshake> {
+pub(crate) fn parse_tls_handshake_msg_serverdone(
+    i: &[u8],
+    len: usize,
+) -> IResult<&[u8], TlsMessageHandshake> {
     map(take(len), TlsMessageHandshake::ServerDone)(i)
 }
 
-fn parse_tls_handshake_msg_certificateverify(
+pub(crate) fn parse_tls_handshake_msg_certificateverify(
     i: &[u8],
     len: usize,
 ) -> IResult<&[u8], TlsMessageHandshake> {
     map(take(len), TlsMessageHandshake::CertificateVerify)(i)
 }
 
-fn parse_tls_handshake_msg_clientkeyexchange(
+pub(crate) fn parse_tls_handshake_msg_clientkeyexchange(
     i: &[u8],
     len: usize,
 ) -> IResult<&[u8], TlsMessageHandshake> {
@@ -711,25 +1491,73 @@ fn parse_certrequest_full(i: &[u8]) -> IResult<&[u8], TlsMessageHandshake> {
 }
 
 #[inline]
-fn parse_tls_handshake_msg_certificaterequest(i: &[u8]) -> IResult<&[u8], TlsMessageHandshake> {
+pub(crate) fn parse_tls_handshake_msg_certificaterequest(
+    i: &[u8],
+) -> IResult<&[u8], TlsMessageHandshake> {
     alt((
         complete(parse_certrequest_full),
         complete(parse_certrequest_nosigalg),
     ))(i)
 }
 
-fn parse_tls_handshake_msg_finished(i: &[u8], len: usize) -> IResult<&[u8], TlsMessageHandshake> {
+pub(crate) fn parse_tls_handshake_msg_finished(
+    i: &[u8],
+    len: usize,
+) -> IResult<&[u8], TlsMessageHandshake> {
     map(take(len), TlsMessageHandshake::Finished)(i)
 }
 
+fn parse_tls_handshake_msg_message_hash(
+    i: &[u8],
+    len: usize,
+) -> IResult<&[u8], TlsMessageHandshake> {
+    map(take(len), TlsMessageHandshake::MessageHash)(i)
+}
+
+// struct {
+//     opaque url<1..2^16-1>;
+//     uint8 padding;
+//     opaque SHA1Hash[20];
+// } URLAndHash;
+fn parse_url_and_hash(i: &[u8]) -> IResult<&[u8], TlsUrlAndHash> {
+    let (i, url) = length_data(be_u16)(i)?;
+    let (i, _padding) = verify(be_u8, |&b| b == 0x01)(i)?;
+    let (i, sha1_hash) = take(20usize)(i)?;
+    Ok((i, TlsUrlAndHash { url, sha1_hash }))
+}
+
+// struct {
+//     CertChainType type;
+//     URLAndHash url_and_hash_list<1..2^16-1>;
+// } CertificateURL;
+fn parse_tls_handshake_msg_certificateurl(i: &[u8]) -> IResult<&[u8], TlsMessageHandshake> {
+    let (i, chain_type) = CertChainType::parse(i)?;
+    let (i, url_and_hash_list) =
+        map_parser(length_data(be_u16), many1(complete(parse_url_and_hash)))(i)?;
+    let content = TlsCertificateUrlContents {
+        chain_type,
+        url_and_hash_list,
+    };
+    Ok((i, TlsMessageHandshake::CertificateURL(content)))
+}
+
 // Defined in [RFC6066]
 // if status_type == 0, blob is a OCSPResponse, as defined in [RFC2560](https://tools.ietf.org/html/rfc2560)
 // Note that the OCSPResponse object is DER-encoded.
-fn parse_tls_handshake_msg_certificatestatus(i: &[u8]) -> IResult<&[u8], TlsMessageHandshake> {
+pub(crate) fn parse_tls_handshake_msg_certificatestatus(
+    i: &[u8],
+) -> IResult<&[u8], TlsMessageHandshake> {
+    map(
+        parse_certificate_status,
+        TlsMessageHandshake::CertificateStatus,
+    )(i)
+}
+
+pub(crate) fn parse_certificate_status(i: &[u8]) -> IResult<&[u8], TlsCertificateStatusContents> {
     let (i, status_type) = be_u8(i)?;
     let (i, blob) = length_data(be_u24)(i)?;
     let content = TlsCertificateStatusContents { status_type, blob };
-    Ok((i, TlsMessageHandshake::CertificateStatus(content)))
+    Ok((i, content))
 }
 
 /// NextProtocol handshake message, as defined in
@@ -749,49 +1577,251 @@ fn parse_tls_handshake_msg_key_update(i: &[u8]) -> IResult<&[u8], TlsMessageHand
     map(be_u8, TlsMessageHandshake::KeyUpdate)(i)
 }
 
+// struct {
+//      CertificateCompressionAlgorithm algorithm;
+//      uint24 uncompressed_length;
+//      opaque compressed_certificate_message<1..2^24-1>;
+// } CompressedCertificate;
+fn parse_tls_handshake_msg_compressed_certificate(i: &[u8]) -> IResult<&[u8], TlsMessageHandshake> {
+    let (i, algorithm) = CertificateCompressionAlgorithm::parse(i)?;
+    let (i, uncompressed_length) = be_u24(i)?;
+    let (i, compressed_certificate_message) = length_data(be_u24)(i)?;
+    let content = TlsCompressedCertificateContents {
+        algorithm,
+        uncompressed_length,
+        compressed_certificate_message,
+    };
+    Ok((i, TlsMessageHandshake::CompressedCertificate(content)))
+}
+
+/// Parse the body of a TLS 1.3 NewSessionTicket message
+///
+/// ```text
+/// struct {
+///     uint32 ticket_lifetime;
+///     uint32 ticket_age_add;
+///     opaque ticket_nonce<0..255>;
+///     opaque ticket<1..2^16-1>;
+///     Extension extensions<0..2^16-2>;
+/// } NewSessionTicket;
+/// ```
+pub fn parse_tls13_new_session_ticket(i: &[u8]) -> IResult<&[u8], TlsNewSessionTicketV13Content> {
+    let (i, ticket_lifetime) = be_u32(i)?;
+    let (i, ticket_age_add) = be_u32(i)?;
+    let (i, ticket_nonce) = length_data(be_u8)(i)?;
+    let (i, ticket) = length_data(be_u16)(i)?;
+    let (i, ext) = length_data(be_u16)(i)?;
+    let content = TlsNewSessionTicketV13Content {
+        ticket_lifetime,
+        ticket_age_add,
+        ticket_nonce,
+        ticket,
+        ext,
+    };
+    Ok((i, content))
+}
+
+/// Parse the body of a CertificateVerify message
+///
+/// `has_algorithm` must be true for TLS 1.2 and 1.3, where the signature is preceded by the
+/// signature algorithm, and false for previous versions.
+///
+/// ```text
+/// struct {
+///     SignatureScheme algorithm;
+///     opaque signature<0..2^16-1>;
+/// } CertificateVerify;
+/// ```
+pub fn parse_certificate_verify(
+    i: &[u8],
+    has_algorithm: bool,
+) -> IResult<&[u8], TlsCertificateVerifyContents> {
+    let (i, algorithm) = cond(has_algorithm, SignatureScheme::parse)(i)?;
+    let (i, signature) = length_data(be_u16)(i)?;
+    let content = TlsCertificateVerifyContents {
+        algorithm,
+        signature,
+    };
+    Ok((i, content))
+}
+
+fn parse_tls13_certificate_entry(i: &[u8]) -> IResult<&[u8], TlsCertificateEntry> {
+    let (i, data) = length_data(be_u24)(i)?;
+    let (i, ext) = length_data(be_u16)(i)?;
+    Ok((i, TlsCertificateEntry { data, ext }))
+}
+
+/// Parse the body of a TLS 1.3 Certificate message
+///
+/// ```text
+/// struct {
+///     select (certificate_type) {
+///         case RawPublicKey:
+///           /* From RFC 7250 ASN.1_subjectPublicKeyInfo */
+///           opaque ASN1_subjectPublicKeyInfo<1..2^24-1>;
+///
+///         case X509:
+///           opaque cert_data<1..2^24-1>;
+///     };
+///     Extension extensions<0..2^16-1>;
+/// } CertificateEntry;
+///
+/// struct {
+///     opaque certificate_request_context<0..2^8-1>;
+///     CertificateEntry certificate_list<0..2^24-1>;
+/// } Certificate;
+/// ```
+pub fn parse_tls13_certificate(i: &[u8]) -> IResult<&[u8], TlsCertificateV13Contents> {
+    let (i, certificate_request_context) = length_data(be_u8)(i)?;
+    let (i, cert_list) = map_parser(
+        length_data(be_u24),
+        many0(complete(parse_tls13_certificate_entry)),
+    )(i)?;
+    let content = TlsCertificateV13Contents {
+        certificate_request_context,
+        cert_list,
+    };
+    Ok((i, content))
+}
+
+/// Parse a TLS 1.3 CertificateRequest message body
+///
+/// ```text
+/// struct {
+///     opaque certificate_request_context<0..2^8-1>;
+///     Extension extensions<2..2^16-1>;
+/// } CertificateRequest;
+/// ```
+pub fn parse_tls13_certificate_request(
+    i: &[u8],
+) -> IResult<&[u8], TlsCertificateRequestV13Contents> {
+    let (i, certificate_request_context) = length_data(be_u8)(i)?;
+    let (i, ext) = length_data(be_u16)(i)?;
+    let content = TlsCertificateRequestV13Contents {
+        certificate_request_context,
+        ext,
+    };
+    Ok((i, content))
+}
+
 /// Parse a TLS handshake message
 pub fn parse_tls_message_handshake(i: &[u8]) -> IResult<&[u8], TlsMessage> {
     let (i, ht) = be_u8(i)?;
     let (i, hl) = be_u24(i)?;
     let (i, raw_msg) = take(hl)(i)?;
-    let (_, msg) = match TlsHandshakeType(ht) {
+    let (_, msg) = parse_tls_handshake_msg_body(i, TlsHandshakeType(ht), raw_msg)?;
+    Ok((i, TlsMessage::Handshake(msg)))
+}
+
+/// Parse the body of a handshake message, returning the data not used by the message parser
+///
+/// `i` is only used to report an unknown message type.
+pub(crate) fn parse_tls_handshake_msg_body<'a>(
+    i: &'a [u8],
+    ht: TlsHandshakeType,
+    raw_msg: &'a [u8],
+) -> IResult<&'a [u8], TlsMessageHandshake<'a>> {
+    let hl = raw_msg.len();
+    match ht {
         TlsHandshakeType::HelloRequest => parse_tls_handshake_msg_hello_request(raw_msg),
         TlsHandshakeType::ClientHello => parse_tls_handshake_msg_client_hello(raw_msg),
         TlsHandshakeType::ServerHello => parse_tls_handshake_msg_server_hello(raw_msg),
-        TlsHandshakeType::NewSessionTicket => {
-            parse_tls_handshake_msg_newsessionticket(raw_msg, hl as usize)
-        }
-        TlsHandshakeType::EndOfEarlyData => Ok((raw_msg, TlsMessageHandshake::EndOfEarlyData)),
+        TlsHandshakeType::NewSessionTicket => parse_tls_handshake_msg_newsessionticket(raw_msg, hl),
+        TlsHandshakeType::EndOfEarlyData => parse_tls_handshake_msg_end_of_early_data(raw_msg),
         TlsHandshakeType::HelloRetryRequest => parse_tls_handshake_msg_hello_retry_request(raw_msg),
+        TlsHandshakeType::EncryptedExtensions => {
+            parse_tls_handshake_msg_encrypted_extensions(raw_msg)
+        }
         TlsHandshakeType::Certificate => parse_tls_handshake_msg_certificate(raw_msg),
         TlsHandshakeType::ServerKeyExchange => {
-            parse_tls_handshake_msg_serverkeyexchange(raw_msg, hl as usize)
+            parse_tls_handshake_msg_serverkeyexchange(raw_msg, hl)
         }
         TlsHandshakeType::CertificateRequest => parse_tls_handshake_msg_certificaterequest(raw_msg),
-        TlsHandshakeType::ServerDone => parse_tls_handshake_msg_serverdone(raw_msg, hl as usize),
+        TlsHandshakeType::ServerDone => parse_tls_handshake_msg_serverdone(raw_msg, hl),
         TlsHandshakeType::CertificateVerify => {
-            parse_tls_handshake_msg_certificateverify(raw_msg, hl as usize)
+            parse_tls_handshake_msg_certificateverify(raw_msg, hl)
         }
         TlsHandshakeType::ClientKeyExchange => {
-            parse_tls_handshake_msg_clientkeyexchange(raw_msg, hl as usize)
+            parse_tls_handshake_msg_clientkeyexchange(raw_msg, hl)
         }
-        TlsHandshakeType::Finished => parse_tls_handshake_msg_finished(raw_msg, hl as usize),
-        // TlsHandshakeType::CertificateURL => parse_tls_handshake_msg_certificateurl(raw_msg),
+        TlsHandshakeType::Finished => parse_tls_handshake_msg_finished(raw_msg, hl),
+        TlsHandshakeType::CertificateURL => parse_tls_handshake_msg_certificateurl(raw_msg),
         TlsHandshakeType::CertificateStatus => parse_tls_handshake_msg_certificatestatus(raw_msg),
         TlsHandshakeType::KeyUpdate => parse_tls_handshake_msg_key_update(raw_msg),
+        TlsHandshakeType::CompressedCertificate => {
+            parse_tls_handshake_msg_compressed_certificate(raw_msg)
+        }
         TlsHandshakeType::NextProtocol => parse_tls_handshake_msg_next_protocol(raw_msg),
+        TlsHandshakeType::MessageHash => parse_tls_handshake_msg_message_hash(raw_msg, hl),
         _ => Err(Err::Error(make_error(i, ErrorKind::Switch))),
+    }
+}
+
+/// Parse a TLS handshake message, using the negotiated protocol version
+///
+/// Some messages (NewSessionTicket, Certificate and CertificateRequest) have a different format
+/// in TLS 1.3. If `version` is TLS 1.3 (or a TLS 1.3 draft), these messages are returned as
+/// `NewSessionTicketV13`, `CertificateV13` and `CertificateRequestV13`. Other messages, or
+/// other versions, are parsed using `parse_tls_message_handshake`.
+///
+/// Note that in TLS 1.3 these messages are encrypted, so this function can only be used on
+/// decrypted data.
+pub fn parse_tls_message_handshake_with_version(
+    i: &[u8],
+    version: TlsVersion,
+) -> IResult<&[u8], TlsMessage> {
+    let tls13 = version.is_tls13();
+    let (rem, ht) = be_u8(i)?;
+    let ht = TlsHandshakeType(ht);
+    if !tls13
+        || !matches!(
+            ht,
+            TlsHandshakeType::NewSessionTicket
+                | TlsHandshakeType::Certificate
+                | TlsHandshakeType::CertificateRequest
+        )
+    {
+        return parse_tls_message_handshake(i);
+    }
+    let (rem, hl) = be_u24(rem)?;
+    let (rem, raw_msg) = take(hl)(rem)?;
+    let (_, msg) = match ht {
+        TlsHandshakeType::NewSessionTicket => map(
+            parse_tls13_new_session_ticket,
+            TlsMessageHandshake::NewSessionTicketV13,
+        )(raw_msg),
+        TlsHandshakeType::Certificate => {
+            map(parse_tls13_certificate, TlsMessageHandshake::CertificateV13)(raw_msg)
+        }
+        _ => map(
+            parse_tls13_certificate_request,
+            TlsMessageHandshake::CertificateRequestV13,
+        )(raw_msg),
     }?;
-    Ok((i, TlsMessage::Handshake(msg)))
+    Ok((rem, TlsMessage::Handshake(msg)))
 }
 
 /// Parse a TLS changecipherspec message
-// XXX add extra verification hdr.len == 1
+///
+/// This function does not check the record length, see
+/// `parse_tls_message_changecipherspec_strict`.
 pub fn parse_tls_message_changecipherspec(i: &[u8]) -> IResult<&[u8], TlsMessage> {
     let (i, _) = verify(be_u8, |&tag| tag == 0x01)(i)?;
     Ok((i, TlsMessage::ChangeCipherSpec))
 }
 
+/// Parse the data of a TLS changecipherspec record
+///
+/// The record must contain exactly one byte, with value 1. Other contents (used by some
+/// attack tools to hide data) are rejected, with the error kind `LengthValue` if the length
+/// is not 1, or `Verify` if the value is not 1.
+pub fn parse_tls_message_changecipherspec_strict(i: &[u8]) -> IResult<&[u8], TlsMessage> {
+    if i.len() != 1 {
+        return Err(Err::Error(make_error(i, ErrorKind::LengthValue)));
+    }
+    parse_tls_message_changecipherspec(i)
+}
+
 /// Parse a TLS alert message
 // XXX add extra verification hdr.len == 2
 pub fn parse_tls_message_alert(i: &[u8]) -> IResult<&[u8], TlsMessage> {
@@ -804,6 +1834,17 @@ pub fn parse_tls_message_alert(i: &[u8]) -> IResult<&[u8], TlsMessage> {
     Ok((i, alert))
 }
 
+/// Parse all TLS alert messages of a record
+///
+/// A record can contain several alerts. Since an alert is 2 bytes long, the record
+/// length must be even.
+pub fn parse_tls_message_alerts(i: &[u8]) -> IResult<&[u8], Vec<TlsMessage>> {
+    if i.is_empty() || i.len() & 1 != 0 {
+        return Err(Err::Error(make_error(i, ErrorKind::LengthValue)));
+    }
+    many1(complete(parse_tls_message_alert))(i)
+}
+
 /// Parse a TLS applicationdata message
 ///
 /// Read the entire input as applicationdata
@@ -813,6 +1854,10 @@ pub fn parse_tls_message_applicationdata(i: &[u8]) -> IResult<&[u8], TlsMessage>
 }
 
 /// Parse a TLS heartbeat message
+///
+/// Read the entire input (the record data) as a heartbeat message. If the declared payload
+/// length is larger than the data, the message is still returned, with a truncated payload
+/// (see `TlsMessageHeartbeat::is_heartbleed_probe`).
 pub fn parse_tls_message_heartbeat(
     i: &[u8],
     tls_plaintext_len: u16,
@@ -822,13 +1867,14 @@ pub fn parse_tls_message_heartbeat(
     if tls_plaintext_len < 3 {
         return Err(Err::Error(make_error(i, ErrorKind::Verify)));
     }
-    let (i, payload) = take(payload_len as usize)(i)?;
+    let (payload, padding) = i.split_at(i.len().min(payload_len as usize));
     let v = vec![TlsMessage::Heartbeat(TlsMessageHeartbeat {
         heartbeat_type,
         payload_len,
         payload,
+        padding,
     })];
-    Ok((i, v))
+    Ok((&[], v))
 }
 
 /// Given data and a TLS record header, parse content.
@@ -841,8 +1887,8 @@ pub fn parse_tls_message_heartbeat(
 #[allow(clippy::trivially_copy_pass_by_ref)] // TlsRecordHeader is only 6 bytes, but we prefer not breaking current API
 pub fn parse_tls_record_with_header<'i, 'hdr>(i:&'i [u8], hdr:&'hdr TlsRecordHeader ) -> IResult<&'i [u8], Vec<TlsMessage<'i>>> {
     match hdr.record_type {
-        TlsRecordType::ChangeCipherSpec => many1(complete(parse_tls_message_changecipherspec))(i),
-        TlsRecordType::Alert            => many1(complete(parse_tls_message_alert))(i),
+        TlsRecordType::ChangeCipherSpec => map(parse_tls_message_changecipherspec_strict, |m| vec![m])(i),
+        TlsRecordType::Alert            => parse_tls_message_alerts(i),
         TlsRecordType::Handshake        => many1(complete(parse_tls_message_handshake))(i),
         TlsRecordType::ApplicationData  => many1(complete(parse_tls_message_applicationdata))(i),
         TlsRecordType::Heartbeat        => parse_tls_message_heartbeat(i, hdr.len),
@@ -853,8 +1899,16 @@ pub fn parse_tls_record_with_header<'i, 'hdr>(i:&'i [u8], hdr:&'hdr TlsRecordHea
 /// Parse one packet only, as plaintext
 /// A single record can contain multiple messages, they must share the same record type
 pub fn parse_tls_plaintext(i: &[u8]) -> IResult<&[u8], TlsPlaintext> {
+    parse_tls_plaintext_with_limits(i, &TlsRecordLimits::default())
+}
+
+/// Parse one packet only, as plaintext, with the specified record length limits
+pub fn parse_tls_plaintext_with_limits<'a>(
+    i: &'a [u8],
+    limits: &TlsRecordLimits,
+) -> IResult<&'a [u8], TlsPlaintext<'a>> {
     let (i, hdr) = parse_tls_record_header(i)?;
-    if hdr.len > MAX_RECORD_LEN {
+    if hdr.len > limits.max_plaintext_len {
         return Err(Err::Error(make_error(i, ErrorKind::TooLarge)));
     }
     let (i, msg) = map_parser(take(hdr.len as usize), |i| {
@@ -865,8 +1919,16 @@ pub fn parse_tls_plaintext(i: &[u8]) -> IResult<&[u8], TlsPlaintext> {
 
 /// Parse one packet only, as encrypted content
 pub fn parse_tls_encrypted(i: &[u8]) -> IResult<&[u8], TlsEncrypted> {
+    parse_tls_encrypted_with_limits(i, &TlsRecordLimits::default())
+}
+
+/// Parse one packet only, as encrypted content, with the specified record length limits
+pub fn parse_tls_encrypted_with_limits<'a>(
+    i: &'a [u8],
+    limits: &TlsRecordLimits,
+) -> IResult<&'a [u8], TlsEncrypted<'a>> {
     let (i, hdr) = parse_tls_record_header(i)?;
-    if hdr.len > MAX_RECORD_LEN {
+    if hdr.len > limits.max_ciphertext_len {
         return Err(Err::Error(make_error(i, ErrorKind::TooLarge)));
     }
     let (i, blob) = take(hdr.len as usize)(i)?;
@@ -879,15 +1941,92 @@ pub fn parse_tls_encrypted(i: &[u8]) -> IResult<&[u8], TlsEncrypted> {
 /// This function is used to get the record type, and to make sure the record is
 /// complete (not fragmented).
 /// After calling this function, use `parse_tls_record_with_header` to parse content.
+///
+/// Since the record can be encrypted, its length is checked against the ciphertext limit.
 pub fn parse_tls_raw_record(i: &[u8]) -> IResult<&[u8], TlsRawRecord> {
+    parse_tls_raw_record_with_limits(i, &TlsRecordLimits::default())
+}
+
+/// Read TLS record envelope, with the specified record length limits
+pub fn parse_tls_raw_record_with_limits<'a>(
+    i: &'a [u8],
+    limits: &TlsRecordLimits,
+) -> IResult<&'a [u8], TlsRawRecord<'a>> {
     let (i, hdr) = parse_tls_record_header(i)?;
-    if hdr.len > MAX_RECORD_LEN {
+    if hdr.len > limits.max_ciphertext_len {
         return Err(Err::Error(make_error(i, ErrorKind::TooLarge)));
     }
     let (i, data) = take(hdr.len as usize)(i)?;
     Ok((i, TlsRawRecord { hdr, data }))
 }
 
+/// Iterator over the TLS records of a buffer
+///
+/// Each item is a record header, the record data, and the range of the record (including
+/// the header) in the buffer. Record data is not parsed, and nothing is allocated.
+///
+/// Iteration stops at the first incomplete or invalid record. Use `remaining` to get the
+/// data not yet returned (for ex. to wait for more data).
+///
+/// ```rust
+/// # use tls_parser::{TlsRecordIterator, TlsRecordType};
+/// # fn f(buffer: &[u8]) {
+/// let mut iter = TlsRecordIterator::new(buffer);
+/// for (hdr, data, range) in &mut iter {
+///     if hdr.record_type == TlsRecordType::Handshake {
+///         // use data, or buffer[range]
+///     }
+/// }
+/// if !iter.remaining().is_empty() {
+///     // incomplete or invalid record
+/// }
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TlsRecordIterator<'a> {
+    data: &'a [u8],
+    offset: usize,
+    limits: TlsRecordLimits,
+}
+
+impl<'a> TlsRecordIterator<'a> {
+    /// Create an iterator over the records of `data`
+    pub fn new(data: &'a [u8]) -> Self {
+        TlsRecordIterator::with_limits(data, TlsRecordLimits::default())
+    }
+
+    /// Create an iterator over the records of `data`, with the specified record length limits
+    pub fn with_limits(data: &'a [u8], limits: TlsRecordLimits) -> Self {
+        TlsRecordIterator {
+            data,
+            offset: 0,
+            limits,
+        }
+    }
+
+    /// Get the data not returned by the iterator
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.offset..]
+    }
+
+    /// Get the offset of the next record in the buffer
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a> Iterator for TlsRecordIterator<'a> {
+    type Item = (TlsRecordHeader, &'a [u8], std::ops::Range<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let i = self.remaining();
+        let (rem, record) = parse_tls_raw_record_with_limits(i, &self.limits).ok()?;
+        let start = self.offset;
+        self.offset += i.len() - rem.len();
+        Some((record.hdr, record.data, start..self.offset))
+    }
+}
+
 /// Parse one packet only, as plaintext
 /// This function is deprecated. Use `parse_tls_plaintext` instead.
 ///
@@ -57,6 +57,99 @@ impl display TlsAlertDescription {
 }
 }
 
+impl TlsAlertDescription {
+    /// Test if the alert must always be sent with the fatal level
+    ///
+    /// Alerts marked as always fatal in [RFC5246](https://tools.ietf.org/html/rfc5246)
+    /// section 7.2 or by the RFC defining them. Note that in TLS 1.3, all alerts except
+    /// `CloseNotify` and `UserCancelled` are fatal, regardless of the severity sent.
+    pub fn is_fatal_by_spec(self) -> bool {
+        matches!(
+            self,
+            TlsAlertDescription::UnexpectedMessage
+                | TlsAlertDescription::BadRecordMac
+                | TlsAlertDescription::DecryptionFailed
+                | TlsAlertDescription::RecordOverflow
+                | TlsAlertDescription::DecompressionFailure
+                | TlsAlertDescription::HandshakeFailure
+                | TlsAlertDescription::IllegalParameter
+                | TlsAlertDescription::UnknownCa
+                | TlsAlertDescription::AccessDenied
+                | TlsAlertDescription::DecodeError
+                | TlsAlertDescription::DecryptError
+                | TlsAlertDescription::ExportRestriction
+                | TlsAlertDescription::ProtocolVersion
+                | TlsAlertDescription::InsufficientSecurity
+                | TlsAlertDescription::InternalError
+                | TlsAlertDescription::InappropriateFallback
+                | TlsAlertDescription::MissingExtension
+                | TlsAlertDescription::UnsupportedExtension
+                | TlsAlertDescription::UnknownPskIdentity
+                | TlsAlertDescription::CertificateRequired
+                | TlsAlertDescription::NoApplicationProtocol
+        )
+    }
+
+    /// Get the category of the alert
+    pub fn category(self) -> TlsAlertCategory {
+        match self {
+            TlsAlertDescription::CloseNotify | TlsAlertDescription::UserCancelled => {
+                TlsAlertCategory::Closure
+            }
+            TlsAlertDescription::HandshakeFailure
+            | TlsAlertDescription::IllegalParameter
+            | TlsAlertDescription::AccessDenied
+            | TlsAlertDescription::ExportRestriction
+            | TlsAlertDescription::ProtocolVersion
+            | TlsAlertDescription::InsufficientSecurity
+            | TlsAlertDescription::InappropriateFallback
+            | TlsAlertDescription::NoRenegotiation
+            | TlsAlertDescription::MissingExtension
+            | TlsAlertDescription::UnsupportedExtension
+            | TlsAlertDescription::UnrecognizedName
+            | TlsAlertDescription::UnknownPskIdentity
+            | TlsAlertDescription::NoApplicationProtocol => TlsAlertCategory::HandshakeFailure,
+            TlsAlertDescription::NoCertificate
+            | TlsAlertDescription::BadCertificate
+            | TlsAlertDescription::UnsupportedCertificate
+            | TlsAlertDescription::CertificateRevoked
+            | TlsAlertDescription::CertificateExpired
+            | TlsAlertDescription::CertificateUnknown
+            | TlsAlertDescription::UnknownCa
+            | TlsAlertDescription::CertUnobtainable
+            | TlsAlertDescription::BadCertStatusResponse
+            | TlsAlertDescription::BadCertHashValue
+            | TlsAlertDescription::CertificateRequired => TlsAlertCategory::Certificate,
+            TlsAlertDescription::BadRecordMac
+            | TlsAlertDescription::DecryptionFailed
+            | TlsAlertDescription::RecordOverflow
+            | TlsAlertDescription::DecompressionFailure
+            | TlsAlertDescription::DecryptError => TlsAlertCategory::Record,
+            TlsAlertDescription::UnexpectedMessage
+            | TlsAlertDescription::DecodeError
+            | TlsAlertDescription::InternalError => TlsAlertCategory::Protocol,
+            _ => TlsAlertCategory::Unknown,
+        }
+    }
+}
+
+/// Category of a TLS alert description
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsAlertCategory {
+    /// Connection closure (`close_notify`, `user_canceled`)
+    Closure,
+    /// Negotiation of the connection parameters failed
+    HandshakeFailure,
+    /// The peer certificate was rejected, or is missing
+    Certificate,
+    /// Record protection or decryption failed
+    Record,
+    /// Unexpected or malformed message, or internal error
+    Protocol,
+    /// Unknown alert description
+    Unknown,
+}
+
 /// TLS alert message
 #[derive(Clone, PartialEq)]
 pub struct TlsMessageAlert {
@@ -66,6 +159,13 @@ pub struct TlsMessageAlert {
     pub code: TlsAlertDescription,
 }
 
+impl TlsMessageAlert {
+    /// Test if the alert is fatal, either by its severity or by its description
+    pub fn is_fatal(&self) -> bool {
+        self.severity == TlsAlertSeverity::Fatal || self.code.is_fatal_by_spec()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::tls_alert::*;
@@ -97,4 +197,32 @@ mod tests {
         let b = TlsAlertDescription(a_u8);
         assert_eq!(b, TlsAlertDescription::HandshakeFailure);
     }
+
+    #[test]
+    fn test_tlsalert_classification() {
+        assert!(TlsAlertDescription::HandshakeFailure.is_fatal_by_spec());
+        assert!(!TlsAlertDescription::CloseNotify.is_fatal_by_spec());
+        assert!(!TlsAlertDescription::CertificateExpired.is_fatal_by_spec());
+        assert_eq!(
+            TlsAlertDescription::CloseNotify.category(),
+            TlsAlertCategory::Closure
+        );
+        assert_eq!(
+            TlsAlertDescription::UnknownCa.category(),
+            TlsAlertCategory::Certificate
+        );
+        assert_eq!(
+            TlsAlertDescription::ProtocolVersion.category(),
+            TlsAlertCategory::HandshakeFailure
+        );
+        assert_eq!(
+            TlsAlertDescription(0xff).category(),
+            TlsAlertCategory::Unknown
+        );
+        let alert = TlsMessageAlert {
+            severity: TlsAlertSeverity::Warning,
+            code: TlsAlertDescription::BadRecordMac,
+        };
+        assert!(alert.is_fatal());
+    }
 } // mod tests
@@ -0,0 +1,502 @@
+//! # ClientHello builder
+//!
+//! `TlsClientHelloBuilder` builds a ClientHello record from a list of parameters (versions,
+//! cipher suites, groups, extensions and their order), and adds the values some clients
+//! insert in each message: GREASE values ([RFC8701](https://tools.ietf.org/html/rfc8701)),
+//! a random permutation of the extensions, and padding
+//! ([RFC7685](https://tools.ietf.org/html/rfc7685)).
+//!
+//! `BrowserPreset` provides the parameters of recent browsers, to reproduce the layout (and
+//! the JA3/JA4 fingerprints) of their ClientHello messages, for ex. to write probes or to test
+//! fingerprint-based filtering. Only the layout is reproduced: key shares and the encrypted
+//! ClientHello payload are random bytes, so the messages cannot be used to complete a
+//! handshake.
+//!
+//! All random values (session id, key shares, GREASE values, permutation) are derived from
+//! the seed given to `build`, so the same seed always gives the same record.
+//!
+//! This module requires the `serialize` feature.
+
+use cookie_factory::gen_simple;
+
+use crate::tls::*;
+use crate::tls_ec::NamedGroup;
+use crate::tls_extensions::*;
+use crate::tls_serialize::*;
+use crate::tls_sign_hash::SignatureScheme;
+
+/// Browser whose ClientHello is reproduced by `TlsClientHelloBuilder::preset`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrowserPreset {
+    /// Chrome 131 (BoringSSL): GREASE and permuted extensions
+    Chrome131,
+    /// Firefox 133 (NSS)
+    Firefox133,
+    /// Safari 18: GREASE and padding
+    Safari18,
+}
+
+/// Extension of a ClientHello built by `TlsClientHelloBuilder`
+///
+/// The content of most extensions is generated from the parameters of the builder.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TlsBuilderExtension {
+    /// `server_name`, omitted if the builder has no server name
+    ServerName,
+    /// `application_layer_protocol_negotiation`, using the `alpn` protocols
+    Alpn,
+    /// `supported_groups`, using the `groups`
+    SupportedGroups,
+    /// `signature_algorithms`, using the `signature_algorithms`
+    SignatureAlgorithms,
+    /// `key_share`, with random shares for the `key_shares` groups
+    KeyShare,
+    /// `supported_versions`, using the `supported_versions`
+    SupportedVersions,
+    /// GREASE `encrypted_client_hello`, with a random payload of the given length
+    EchGrease(usize),
+    /// Extension with a fixed content
+    Raw(TlsExtensionType, Vec<u8>),
+}
+
+/// Builder of ClientHello records
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlsClientHelloBuilder {
+    /// Version of the record header
+    pub record_version: TlsVersion,
+    /// `legacy_version` of the ClientHello
+    pub version: TlsVersion,
+    pub ciphers: Vec<TlsCipherSuiteID>,
+    pub server_name: Option<String>,
+    pub alpn: Vec<Vec<u8>>,
+    pub groups: Vec<NamedGroup>,
+    /// Groups of the key shares, and length of the share
+    pub key_shares: Vec<(NamedGroup, usize)>,
+    pub signature_algorithms: Vec<SignatureScheme>,
+    pub supported_versions: Vec<TlsVersion>,
+    /// Extensions, in order
+    pub extensions: Vec<TlsBuilderExtension>,
+    /// Add GREASE values to the cipher suites, groups, key shares and versions, and a GREASE
+    /// extension at the start and at the end of the extensions
+    pub grease: bool,
+    /// Randomly reorder the extensions (except GREASE and padding)
+    pub permute_extensions: bool,
+    /// Add a padding extension if the ClientHello is between 256 and 511 bytes long
+    pub padding: bool,
+}
+
+/// Cipher suites of the presets, without GREASE
+#[rustfmt::skip]
+const CHROME_CIPHERS: &[u16] = &[
+    0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9,
+    0xcca8, 0xc013, 0xc014, 0x009c, 0x009d, 0x002f, 0x0035,
+];
+#[rustfmt::skip]
+const FIREFOX_CIPHERS: &[u16] = &[
+    0x1301, 0x1303, 0x1302, 0xc02b, 0xc02f, 0xcca9, 0xcca8, 0xc02c,
+    0xc030, 0xc00a, 0xc009, 0xc013, 0xc014, 0x009c, 0x009d, 0x002f,
+    0x0035,
+];
+#[rustfmt::skip]
+const SAFARI_CIPHERS: &[u16] = &[
+    0x1301, 0x1302, 0x1303, 0xc02c, 0xc02b, 0xcca9, 0xc030, 0xc02f,
+    0xcca8, 0xc00a, 0xc009, 0xc014, 0xc013, 0x009d, 0x009c, 0x0035,
+    0x002f, 0xc008, 0xc012, 0x000a,
+];
+
+/// Signature algorithms of the presets
+#[rustfmt::skip]
+const CHROME_SIG_ALGS: &[u16] = &[
+    0x0403, 0x0804, 0x0401, 0x0503, 0x0805, 0x0501, 0x0806, 0x0601,
+];
+#[rustfmt::skip]
+const FIREFOX_SIG_ALGS: &[u16] = &[
+    0x0403, 0x0503, 0x0603, 0x0804, 0x0805, 0x0806, 0x0401, 0x0501,
+    0x0601, 0x0203, 0x0201,
+];
+// 0x0805 is sent twice
+#[rustfmt::skip]
+const SAFARI_SIG_ALGS: &[u16] = &[
+    0x0403, 0x0804, 0x0401, 0x0503, 0x0203, 0x0805, 0x0805, 0x0501,
+    0x0806, 0x0601, 0x0201,
+];
+
+/// Deterministic generator of the random values of a ClientHello (xorshift64*)
+struct SeededRng(u64);
+
+impl SeededRng {
+    fn new(seed: &[u8; 32]) -> Self {
+        let state = seed.iter().fold(0x9e37_79b9_7f4a_7c15_u64, |acc, &b| {
+            (acc ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        });
+        // xorshift has a fixed point at 0
+        SeededRng(state | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| (self.next_u64() >> 56) as u8).collect()
+    }
+
+    fn grease(&mut self) -> u16 {
+        0x0a0a + 0x1010 * self.below(16) as u16
+    }
+}
+
+fn push_extension(v: &mut Vec<u8>, ext_type: TlsExtensionType, data: &[u8]) {
+    v.extend_from_slice(&ext_type.0.to_be_bytes());
+    v.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    v.extend_from_slice(data);
+}
+
+/// Prefix `data` with its length, on two bytes
+fn with_len16(data: &[u8]) -> Vec<u8> {
+    let mut v = (data.len() as u16).to_be_bytes().to_vec();
+    v.extend_from_slice(data);
+    v
+}
+
+/// GREASE values of a ClientHello
+struct GreaseValues {
+    cipher: u16,
+    group: u16,
+    version: u16,
+    first_ext: u16,
+    last_ext: u16,
+}
+
+impl TlsClientHelloBuilder {
+    /// Create a builder for a TLS 1.2 ClientHello, without cipher suites nor extensions
+    pub fn new() -> Self {
+        TlsClientHelloBuilder {
+            record_version: TlsVersion::Tls10,
+            version: TlsVersion::Tls12,
+            ciphers: Vec::new(),
+            server_name: None,
+            alpn: Vec::new(),
+            groups: Vec::new(),
+            key_shares: Vec::new(),
+            signature_algorithms: Vec::new(),
+            supported_versions: Vec::new(),
+            extensions: Vec::new(),
+            grease: false,
+            permute_extensions: false,
+            padding: false,
+        }
+    }
+
+    /// Create a builder reproducing the ClientHello of a browser, for the server `server_name`
+    pub fn preset(preset: BrowserPreset, server_name: &str) -> Self {
+        use self::TlsBuilderExtension::*;
+        let raw = |ext_type: TlsExtensionType, data: &[u8]| Raw(ext_type, data.to_vec());
+        let (ciphers, sig_algs) = match preset {
+            BrowserPreset::Chrome131 => (CHROME_CIPHERS, CHROME_SIG_ALGS),
+            BrowserPreset::Firefox133 => (FIREFOX_CIPHERS, FIREFOX_SIG_ALGS),
+            BrowserPreset::Safari18 => (SAFARI_CIPHERS, SAFARI_SIG_ALGS),
+        };
+        let mut builder = TlsClientHelloBuilder {
+            ciphers: ciphers.iter().map(|&c| TlsCipherSuiteID(c)).collect(),
+            server_name: Some(server_name.to_owned()),
+            alpn: vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+            signature_algorithms: sig_algs.iter().map(|&s| SignatureScheme(s)).collect(),
+            supported_versions: vec![TlsVersion::Tls13, TlsVersion::Tls12],
+            ..TlsClientHelloBuilder::new()
+        };
+        let status_request = raw(TlsExtensionType::StatusRequest, &[1, 0, 0, 0, 0]);
+        let ec_point_formats = raw(TlsExtensionType::EcPointFormats, &[1, 0]);
+        let psk_modes = raw(TlsExtensionType::PskExchangeModes, &[1, 1]);
+        let renegotiation_info = raw(TlsExtensionType::RenegotiationInfo, &[0]);
+        let ems = raw(TlsExtensionType::ExtendedMasterSecret, &[]);
+        match preset {
+            BrowserPreset::Chrome131 => {
+                builder.groups = vec![
+                    NamedGroup::X25519MLKEM768,
+                    NamedGroup::EcdhX25519,
+                    NamedGroup::Secp256r1,
+                    NamedGroup::Secp384r1,
+                ];
+                builder.key_shares = vec![
+                    (NamedGroup::X25519MLKEM768, 1216),
+                    (NamedGroup::EcdhX25519, 32),
+                ];
+                builder.extensions = vec![
+                    ServerName,
+                    ems,
+                    renegotiation_info,
+                    SupportedGroups,
+                    ec_point_formats,
+                    raw(TlsExtensionType::SessionTicketTLS, &[]),
+                    Alpn,
+                    status_request,
+                    SignatureAlgorithms,
+                    raw(TlsExtensionType::SignedCertificateTimestamp, &[]),
+                    KeyShare,
+                    psk_modes,
+                    SupportedVersions,
+                    // brotli
+                    raw(TlsExtensionType::CompressCertificate, &[2, 0, 2]),
+                    // ALPS, for h2
+                    raw(
+                        TlsExtensionType::ApplicationSettings,
+                        &[0, 3, 2, b'h', b'2'],
+                    ),
+                    EchGrease(176),
+                ];
+                builder.grease = true;
+                builder.permute_extensions = true;
+            }
+            BrowserPreset::Firefox133 => {
+                builder.groups = vec![
+                    NamedGroup::X25519MLKEM768,
+                    NamedGroup::EcdhX25519,
+                    NamedGroup::Secp256r1,
+                    NamedGroup::Secp384r1,
+                    NamedGroup::Secp521r1,
+                    NamedGroup::Ffdhe2048,
+                    NamedGroup::Ffdhe3072,
+                ];
+                builder.key_shares = vec![
+                    (NamedGroup::X25519MLKEM768, 1216),
+                    (NamedGroup::EcdhX25519, 32),
+                    (NamedGroup::Secp256r1, 65),
+                ];
+                builder.extensions = vec![
+                    ServerName,
+                    ems,
+                    renegotiation_info,
+                    SupportedGroups,
+                    ec_point_formats,
+                    raw(TlsExtensionType::SessionTicketTLS, &[]),
+                    Alpn,
+                    status_request,
+                    raw(
+                        TlsExtensionType::DelegatedCredential,
+                        &[0, 8, 0x04, 0x03, 0x05, 0x03, 0x06, 0x03, 0x02, 0x03],
+                    ),
+                    KeyShare,
+                    SupportedVersions,
+                    SignatureAlgorithms,
+                    psk_modes,
+                    raw(TlsExtensionType::RecordSizeLimit, &[0x40, 0x01]),
+                    // zlib, brotli and zstd
+                    raw(
+                        TlsExtensionType::CompressCertificate,
+                        &[6, 0, 1, 0, 2, 0, 3],
+                    ),
+                    EchGrease(239),
+                ];
+            }
+            BrowserPreset::Safari18 => {
+                builder.groups = vec![
+                    NamedGroup::EcdhX25519,
+                    NamedGroup::Secp256r1,
+                    NamedGroup::Secp384r1,
+                    NamedGroup::Secp521r1,
+                ];
+                builder.key_shares = vec![(NamedGroup::EcdhX25519, 32)];
+                builder.supported_versions = vec![
+                    TlsVersion::Tls13,
+                    TlsVersion::Tls12,
+                    TlsVersion::Tls11,
+                    TlsVersion::Tls10,
+                ];
+                builder.extensions = vec![
+                    ServerName,
+                    ems,
+                    renegotiation_info,
+                    SupportedGroups,
+                    ec_point_formats,
+                    Alpn,
+                    status_request,
+                    SignatureAlgorithms,
+                    raw(TlsExtensionType::SignedCertificateTimestamp, &[]),
+                    KeyShare,
+                    psk_modes,
+                    SupportedVersions,
+                    // zlib
+                    raw(TlsExtensionType::CompressCertificate, &[2, 0, 1]),
+                ];
+                builder.grease = true;
+                builder.padding = true;
+            }
+        }
+        builder
+    }
+
+    /// Build the content of an extension
+    fn extension_data(
+        &self,
+        ext: &TlsBuilderExtension,
+        grease: &GreaseValues,
+        rng: &mut SeededRng,
+    ) -> Result<Option<(TlsExtensionType, Vec<u8>)>, GenError> {
+        let ext = match ext {
+            TlsBuilderExtension::ServerName => {
+                let host = match self.server_name {
+                    Some(ref host) => host,
+                    None => return Ok(None),
+                };
+                let sni = TlsExtension::SNI(vec![(SNIType::HostName, host.as_bytes())]);
+                let buf = gen_simple(gen_tls_extension(&sni), Vec::new())?;
+                // without the extension header
+                (TlsExtensionType::ServerName, buf[4..].to_vec())
+            }
+            TlsBuilderExtension::Alpn => {
+                let mut list = Vec::new();
+                for proto in &self.alpn {
+                    list.push(proto.len() as u8);
+                    list.extend_from_slice(proto);
+                }
+                (
+                    TlsExtensionType::ApplicationLayerProtocolNegotiation,
+                    with_len16(&list),
+                )
+            }
+            TlsBuilderExtension::SupportedGroups => {
+                let mut list = Vec::new();
+                if self.grease {
+                    list.extend_from_slice(&grease.group.to_be_bytes());
+                }
+                for group in &self.groups {
+                    list.extend_from_slice(&group.0.to_be_bytes());
+                }
+                (TlsExtensionType::SupportedGroups, with_len16(&list))
+            }
+            TlsBuilderExtension::SignatureAlgorithms => {
+                let mut list = Vec::new();
+                for scheme in &self.signature_algorithms {
+                    list.extend_from_slice(&scheme.0.to_be_bytes());
+                }
+                (TlsExtensionType::SignatureAlgorithms, with_len16(&list))
+            }
+            TlsBuilderExtension::KeyShare => {
+                let mut list = Vec::new();
+                if self.grease {
+                    list.extend_from_slice(&grease.group.to_be_bytes());
+                    list.extend_from_slice(&[0, 1, 0]);
+                }
+                for &(group, len) in &self.key_shares {
+                    let mut share = rng.bytes(len);
+                    // uncompressed point
+                    if group.0 >= 23 && group.0 <= 25 && len > 0 {
+                        share[0] = 4;
+                    }
+                    list.extend_from_slice(&group.0.to_be_bytes());
+                    list.extend_from_slice(&with_len16(&share));
+                }
+                (TlsExtensionType::KeyShare, with_len16(&list))
+            }
+            TlsBuilderExtension::SupportedVersions => {
+                let mut list = Vec::new();
+                if self.grease {
+                    list.extend_from_slice(&grease.version.to_be_bytes());
+                }
+                for version in &self.supported_versions {
+                    list.extend_from_slice(&version.0.to_be_bytes());
+                }
+                let mut data = vec![list.len() as u8];
+                data.extend_from_slice(&list);
+                (TlsExtensionType::SupportedVersions, data)
+            }
+            TlsBuilderExtension::EchGrease(payload_len) => {
+                // outer ClientHello, HKDF-SHA256 and AES-128-GCM, random config id
+                let mut data = vec![0, 0, 1, 0, 1];
+                data.extend_from_slice(&rng.bytes(1));
+                data.extend_from_slice(&with_len16(&rng.bytes(32)));
+                data.extend_from_slice(&with_len16(&rng.bytes(*payload_len)));
+                (TlsExtensionType::EncryptedClientHello, data)
+            }
+            TlsBuilderExtension::Raw(ext_type, data) => (*ext_type, data.clone()),
+        };
+        Ok(Some(ext))
+    }
+
+    /// Build the ClientHello record, using `seed` as the client random and to derive the
+    /// other random values
+    pub fn build(&self, seed: &[u8; 32]) -> Result<Vec<u8>, GenError> {
+        let mut rng = SeededRng::new(seed);
+        let session_id = rng.bytes(32);
+        let first_ext = rng.grease();
+        let mut last_ext = rng.grease();
+        if last_ext == first_ext {
+            last_ext ^= 0x1010;
+        }
+        let grease = GreaseValues {
+            cipher: rng.grease(),
+            group: rng.grease(),
+            version: rng.grease(),
+            first_ext,
+            last_ext,
+        };
+        let mut ciphers = self.ciphers.clone();
+        if self.grease {
+            ciphers.insert(0, TlsCipherSuiteID(grease.cipher));
+        }
+        let mut extensions = Vec::new();
+        for ext in &self.extensions {
+            if let Some(ext) = self.extension_data(ext, &grease, &mut rng)? {
+                extensions.push(ext);
+            }
+        }
+        if self.permute_extensions {
+            for i in (1..extensions.len()).rev() {
+                extensions.swap(i, rng.below(i + 1));
+            }
+        }
+        let mut ext = Vec::new();
+        if self.grease {
+            push_extension(&mut ext, TlsExtensionType(grease.first_ext), &[]);
+        }
+        for (ext_type, data) in &extensions {
+            push_extension(&mut ext, *ext_type, data);
+        }
+        if self.grease {
+            push_extension(&mut ext, TlsExtensionType(grease.last_ext), &[0]);
+        }
+        if self.padding {
+            // message header, version, random, session id, ciphers, compression and
+            // extensions
+            let len = 4 + 2 + 32 + 1 + session_id.len() + 2 + 2 * ciphers.len() + 2 + 2 + ext.len();
+            // same rule as BoringSSL: work around servers failing on messages between 256
+            // and 511 bytes
+            if len > 0xff && len < 0x200 {
+                let padding_len = (0x200 - len).saturating_sub(4).max(1);
+                push_extension(&mut ext, TlsExtensionType::Padding, &vec![0; padding_len]);
+            }
+        }
+        let ch = TlsClientHelloContents {
+            version: self.version,
+            rand_time: u32::from_be_bytes([seed[0], seed[1], seed[2], seed[3]]),
+            rand_data: &seed[4..],
+            session_id: Some(&session_id),
+            ciphers,
+            comp: vec![TlsCompressionID::Null],
+            ext: Some(&ext),
+        };
+        let record = TlsPlaintext {
+            hdr: TlsRecordHeader {
+                record_type: TlsRecordType::Handshake,
+                version: self.record_version,
+                len: 0,
+            },
+            msg: vec![TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch))],
+        };
+        gen_simple(gen_tls_plaintext(&record), Vec::new())
+    }
+}
+
+impl Default for TlsClientHelloBuilder {
+    fn default() -> Self {
+        TlsClientHelloBuilder::new()
+    }
+}
@@ -0,0 +1,53 @@
+//! Certificate decompression, as defined in [RFC8879](https://tools.ietf.org/html/rfc8879)
+//!
+//! This module is only available with the `compression` feature.
+
+use std::io::Read;
+
+use crate::tls::{CertificateCompressionAlgorithm, TlsCompressedCertificateContents};
+
+/// Maximum size allocated before decompressing data
+const MAX_PREALLOC: usize = 1 << 16;
+
+/// Error returned when decompressing a `CompressedCertificate` message
+#[derive(Debug, PartialEq)]
+pub enum CertificateDecompressionError {
+    /// The compression algorithm is not supported
+    UnsupportedAlgorithm(CertificateCompressionAlgorithm),
+    /// The compressed data is invalid
+    InvalidData,
+    /// The decompressed data does not have the announced length
+    LengthMismatch,
+}
+
+impl<'a> TlsCompressedCertificateContents<'a> {
+    /// Decompress the certificate message
+    ///
+    /// On success, return the body of the Certificate message (without the handshake header).
+    /// Decompression stops after `uncompressed_length` bytes, so the returned data cannot be
+    /// larger than announced.
+    pub fn decompress(&self) -> Result<Vec<u8>, CertificateDecompressionError> {
+        let data = self.compressed_certificate_message;
+        let expected_len = self.uncompressed_length as usize;
+        let limit = u64::from(self.uncompressed_length) + 1;
+        let mut out = Vec::with_capacity(expected_len.min(MAX_PREALLOC));
+        let res = match self.algorithm {
+            CertificateCompressionAlgorithm::Zlib => flate2::read::ZlibDecoder::new(data)
+                .take(limit)
+                .read_to_end(&mut out),
+            CertificateCompressionAlgorithm::Brotli => {
+                brotli_decompressor::Decompressor::new(data, 4096)
+                    .take(limit)
+                    .read_to_end(&mut out)
+            }
+            CertificateCompressionAlgorithm::Zstd => zstd::stream::read::Decoder::new(data)
+                .and_then(|d| d.take(limit).read_to_end(&mut out)),
+            alg => return Err(CertificateDecompressionError::UnsupportedAlgorithm(alg)),
+        };
+        res.map_err(|_| CertificateDecompressionError::InvalidData)?;
+        if out.len() != expected_len {
+            return Err(CertificateDecompressionError::LengthMismatch);
+        }
+        Ok(out)
+    }
+}
@@ -120,6 +120,18 @@ impl TlsCipherSuite {
     pub fn from_name<'a>(name: &'a str) -> Option<&'static TlsCipherSuite> {
         CIPHERS.values().find(|&v| v.name == name)
     }
+
+    /// Test if the cipher suite is export-grade (40-bit encryption keys, and 512-bit key
+    /// exchange)
+    pub fn is_export(&self) -> bool {
+        self.name.contains("_EXPORT")
+    }
+
+    /// Test if the cipher suite is an export-grade RSA suite (RSA_EXPORT), where the server
+    /// can send a temporary 512-bit RSA key (see FREAK)
+    pub fn is_export_rsa(&self) -> bool {
+        self.kx == TlsCipherKx::Rsa && self.au == TlsCipherAu::Rsa && self.is_export()
+    }
 }
 
 #[cfg(test)]
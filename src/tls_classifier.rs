@@ -0,0 +1,237 @@
+//! Classification of TLS records
+//!
+//! After the ChangeCipherSpec (TLS 1.2) or the ServerHello (TLS 1.3), records are encrypted
+//! and their content cannot be parsed. In TLS 1.3, all encrypted records also have the
+//! ApplicationData type. `TlsRecordClassifier` follows the connection to label each record
+//! using its header, for ex. to keep statistics per record class.
+//!
+//! TLS 1.3 0-RTT data (sent by the client before the end of the handshake, and which can be
+//! replayed) is reported separately.
+//!
+//! Encrypted handshake records cannot be parsed, but are counted: `handshake_status` tells
+//! if the handshake continues encrypted (and was not truncated), with the number of records
+//! and bytes seen.
+
+use crate::tls::*;
+
+/// Class of a TLS record
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsRecordClass {
+    /// Cleartext handshake messages
+    PlaintextHandshake,
+    /// Encrypted handshake messages (for ex. Finished, or the TLS 1.3 server flight)
+    EncryptedHandshake,
+    ChangeCipherSpec,
+    /// Application data (encrypted, except if the connection state is unknown)
+    ApplicationData,
+    /// TLS 1.3 0-RTT application data, sent by the client before the handshake is complete
+    EarlyData,
+    /// Alert, cleartext or encrypted (before TLS 1.3, the record type is not encrypted)
+    Alert,
+    /// TLS 1.3 encrypted record, with the length of an encrypted alert
+    ProbableEncryptedAlert,
+    Heartbeat,
+    /// Unknown record type
+    Unknown,
+}
+
+/// Number of encrypted handshake records and bytes sent in one direction
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TlsEncryptedHandshakeStats {
+    pub records: usize,
+    /// Sum of the record lengths (excluding the record headers)
+    pub bytes: usize,
+}
+
+/// Status of the handshake, as seen by the record classifier
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsHandshakeStatus {
+    /// Handshake messages are in cleartext (or no handshake message was seen)
+    Plaintext,
+    /// Handshake continues encrypted: messages cannot be parsed, but the handshake was not
+    /// truncated
+    ContinuesEncrypted {
+        to_server: TlsEncryptedHandshakeStats,
+        to_client: TlsEncryptedHandshakeStats,
+    },
+}
+
+/// Length of a TLS 1.3 encrypted alert record: 2 bytes of alert, 1 byte of content type,
+/// and the AEAD tag (16 bytes, or 8 bytes for the CCM_8 cipher suites)
+const TLS13_ENCRYPTED_ALERT_LEN: [u16; 2] = [19, 11];
+
+/// Length of a TLS 1.3 encrypted EndOfEarlyData record: 4 bytes of handshake header, 1 byte
+/// of content type, and the AEAD tag
+const TLS13_END_OF_EARLY_DATA_LEN: [u16; 2] = [21, 13];
+
+/// TLS record classifier
+///
+/// Records of both directions must be passed to `classify`, in order. Plaintext handshake
+/// records are parsed to detect TLS 1.3 (from the ServerHello).
+///
+/// Encrypted records are classified using heuristics:
+/// - TLS 1.2: handshake records received after the ChangeCipherSpec are encrypted. A
+///   ChangeCipherSpec received before the ServerHello (TLS 1.3 middlebox compatibility mode,
+///   after a HelloRetryRequest) is ignored.
+/// - TLS 1.3: the server records are encrypted handshake messages until the client sends
+///   its first encrypted record, which is assumed to be its Finished message (client
+///   certificates sent in separate records are not detected). Records with the length of an
+///   encrypted alert are reported as `ProbableEncryptedAlert`.
+/// - TLS 1.3 0-RTT: if the ClientHello has the `early_data` extension, the client records are
+///   0-RTT data until the first record with the length of an encrypted EndOfEarlyData
+///   message. The 0-RTT phase ends earlier if the server sends a HelloRetryRequest, or a
+///   ServerHello without a `pre_shared_key` extension (0-RTT data is rejected).
+#[derive(Clone, Debug, Default)]
+pub struct TlsRecordClassifier {
+    /// Version selected by the ServerHello (not a HelloRetryRequest)
+    version: Option<TlsVersion>,
+    /// The client is sending 0-RTT data
+    early_data: bool,
+    /// Records are encrypted, for each direction (indexed by `to_server`)
+    encrypted: [bool; 2],
+    /// Number of TLS 1.3 encrypted records sent by the client
+    client_encrypted_records: usize,
+    /// Encrypted handshake records, for each direction (indexed by `to_server`)
+    encrypted_handshake: [TlsEncryptedHandshakeStats; 2],
+}
+
+impl TlsRecordClassifier {
+    pub fn new() -> Self {
+        TlsRecordClassifier::default()
+    }
+
+    /// Test if the ServerHello selected TLS 1.3
+    pub fn is_tls13(&self) -> bool {
+        matches!(self.version, Some(v) if v.is_tls13())
+    }
+
+    /// Test if the client is in the 0-RTT phase (between a ClientHello with the `early_data`
+    /// extension and the EndOfEarlyData message)
+    pub fn is_early_data(&self) -> bool {
+        self.early_data
+    }
+
+    /// Get the status of the handshake
+    ///
+    /// After the ServerHello (TLS 1.3) or a ChangeCipherSpec (TLS 1.2), the handshake
+    /// continues encrypted, and the encrypted handshake records are counted.
+    pub fn handshake_status(&self) -> TlsHandshakeStatus {
+        if self.encrypted == [false, false] {
+            return TlsHandshakeStatus::Plaintext;
+        }
+        TlsHandshakeStatus::ContinuesEncrypted {
+            to_server: self.encrypted_handshake[1],
+            to_client: self.encrypted_handshake[0],
+        }
+    }
+
+    /// Classify a record, and update the connection state
+    pub fn classify(
+        &mut self,
+        hdr: &TlsRecordHeader,
+        data: &[u8],
+        to_server: bool,
+    ) -> TlsRecordClass {
+        let class = self.classify_record(hdr, data, to_server);
+        if class == TlsRecordClass::EncryptedHandshake {
+            let stats = &mut self.encrypted_handshake[to_server as usize];
+            stats.records += 1;
+            stats.bytes += hdr.len as usize;
+        }
+        class
+    }
+
+    fn classify_record(
+        &mut self,
+        hdr: &TlsRecordHeader,
+        data: &[u8],
+        to_server: bool,
+    ) -> TlsRecordClass {
+        let dir = to_server as usize;
+        let encrypted = self.encrypted[dir];
+        match hdr.record_type {
+            TlsRecordType::Handshake if encrypted => TlsRecordClass::EncryptedHandshake,
+            TlsRecordType::Handshake => {
+                self.update_handshake(hdr, data, to_server);
+                TlsRecordClass::PlaintextHandshake
+            }
+            TlsRecordType::ChangeCipherSpec => {
+                // TLS 1.3 ChangeCipherSpec messages are only sent for compatibility. They can
+                // also be sent before the ServerHello (after a HelloRetryRequest), when the
+                // version is not known yet.
+                if is_tls12_or_earlier(self.version) {
+                    self.encrypted[dir] = true;
+                }
+                TlsRecordClass::ChangeCipherSpec
+            }
+            TlsRecordType::Alert => TlsRecordClass::Alert,
+            TlsRecordType::ApplicationData if to_server && self.early_data => {
+                // EndOfEarlyData can only be sent after the server Finished
+                if self.is_tls13() && TLS13_END_OF_EARLY_DATA_LEN.contains(&hdr.len) {
+                    self.early_data = false;
+                    TlsRecordClass::EncryptedHandshake
+                } else {
+                    TlsRecordClass::EarlyData
+                }
+            }
+            TlsRecordType::ApplicationData if self.is_tls13() && encrypted => {
+                if to_server {
+                    self.client_encrypted_records += 1;
+                }
+                // the client Finished is the last handshake message
+                let handshake = if to_server {
+                    self.client_encrypted_records == 1
+                } else {
+                    self.client_encrypted_records == 0
+                };
+                if TLS13_ENCRYPTED_ALERT_LEN.contains(&hdr.len) {
+                    TlsRecordClass::ProbableEncryptedAlert
+                } else if handshake {
+                    TlsRecordClass::EncryptedHandshake
+                } else {
+                    TlsRecordClass::ApplicationData
+                }
+            }
+            TlsRecordType::ApplicationData => TlsRecordClass::ApplicationData,
+            TlsRecordType::Heartbeat => TlsRecordClass::Heartbeat,
+            _ => TlsRecordClass::Unknown,
+        }
+    }
+
+    fn update_handshake(&mut self, hdr: &TlsRecordHeader, data: &[u8], to_server: bool) {
+        let msgs = match parse_tls_record_with_header(data, hdr) {
+            Ok((_, msgs)) => msgs,
+            Err(_) => return,
+        };
+        for msg in &msgs {
+            match msg {
+                TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)) if to_server => {
+                    self.early_data = ch.has_early_data();
+                }
+                TlsMessage::Handshake(TlsMessageHandshake::ServerHelloV13Draft18(sh))
+                    if !to_server =>
+                {
+                    self.set_version(sh.version);
+                }
+                TlsMessage::Handshake(TlsMessageHandshake::ServerHello(sh)) if !to_server => {
+                    if sh.is_hello_retry_request() {
+                        self.early_data = false;
+                        continue;
+                    }
+                    self.set_version(sh.get_supported_version().unwrap_or(sh.version));
+                    if self.is_tls13() && sh.get_selected_psk_identity().is_none() {
+                        self.early_data = false;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn set_version(&mut self, version: TlsVersion) {
+        self.version = Some(version);
+        if version.is_tls13() {
+            self.encrypted = [true, true];
+        }
+    }
+}
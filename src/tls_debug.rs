@@ -3,13 +3,66 @@ use std::str::from_utf8;
 
 use rusticata_macros::debug::HexSlice;
 
+use crate::dtls::*;
+use crate::ssl2::*;
 use crate::tls::*;
 use crate::tls_alert::*;
 use crate::tls_dh::*;
 use crate::tls_ec::*;
+use crate::tls_ech::*;
 use crate::tls_extensions::*;
+use crate::tls_kx::*;
 use crate::tls_sign_hash::*;
 
+// ------------------------- dtls.rs ------------------------------
+impl<'a> fmt::Debug for DTLSClientHello<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("DTLSClientHello")
+            .field("version", &self.version)
+            .field("random", &HexSlice(self.random))
+            .field("session_id", &self.session_id.map(HexSlice))
+            .field("cookie", &HexSlice(self.cookie))
+            .field("ciphers", &self.ciphers)
+            .field("comp", &self.comp)
+            .field("ext", &self.ext.map(HexSlice))
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for DTLSHelloVerifyRequest<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("DTLSHelloVerifyRequest")
+            .field("server_version", &self.server_version)
+            .field("cookie", &HexSlice(self.cookie))
+            .finish()
+    }
+}
+
+// ------------------------- ssl2.rs ------------------------------
+impl<'a> fmt::Debug for SSLv2ClientHello<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("SSLv2ClientHello")
+            .field("version", &self.version)
+            .field("cipher_specs", &self.cipher_specs)
+            .field("session_id", &self.session_id.map(HexSlice))
+            .field("challenge", &HexSlice(self.challenge))
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for SSLv2ServerHello<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("SSLv2ServerHello")
+            .field("session_id_hit", &self.session_id_hit)
+            .field("certificate_type", &self.certificate_type)
+            .field("version", &self.version)
+            .field("certificate", &HexSlice(self.certificate))
+            .field("cipher_specs", &self.cipher_specs)
+            .field("connection_id", &HexSlice(self.connection_id))
+            .finish()
+    }
+}
+
 // ------------------------- tls.rs ------------------------------
 impl<'a> fmt::Debug for TlsClientHelloContents<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
@@ -59,6 +112,18 @@ impl<'a> fmt::Debug for TlsHelloRetryRequestContents<'a> {
     }
 }
 
+impl<'a> fmt::Debug for TlsNewSessionTicketV13Content<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("TlsNewSessionTicketV13Content")
+            .field("ticket_lifetime", &self.ticket_lifetime)
+            .field("ticket_age_add", &self.ticket_age_add)
+            .field("ticket_nonce", &HexSlice(self.ticket_nonce))
+            .field("ticket", &HexSlice(self.ticket))
+            .field("ext", &HexSlice(self.ext))
+            .finish()
+    }
+}
+
 impl<'a> fmt::Debug for RawCertificate<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.debug_struct("RawCertificate")
@@ -67,6 +132,68 @@ impl<'a> fmt::Debug for RawCertificate<'a> {
     }
 }
 
+impl<'a> fmt::Debug for TlsEncryptedExtensionsContents<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("TlsEncryptedExtensionsContents")
+            .field("ext", &HexSlice(self.ext))
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for TlsCertificateEntry<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("TlsCertificateEntry")
+            .field("data", &HexSlice(self.data))
+            .field("ext", &HexSlice(self.ext))
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for TlsCertificateV13Contents<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("TlsCertificateV13Contents")
+            .field(
+                "certificate_request_context",
+                &HexSlice(self.certificate_request_context),
+            )
+            .field("cert_list", &self.cert_list)
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for TlsCertificateRequestV13Contents<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("TlsCertificateRequestV13Contents")
+            .field(
+                "certificate_request_context",
+                &HexSlice(self.certificate_request_context),
+            )
+            .field("ext", &HexSlice(self.ext))
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for TlsCertificateVerifyContents<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("TlsCertificateVerifyContents")
+            .field("algorithm", &self.algorithm)
+            .field("signature", &HexSlice(self.signature))
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for TlsUrlAndHash<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("TlsUrlAndHash")
+            .field(
+                "url",
+                &from_utf8(self.url).unwrap_or("<error decoding utf8 string>"),
+            )
+            .field("sha1_hash", &HexSlice(self.sha1_hash))
+            .finish()
+    }
+}
+
 impl<'a> fmt::Debug for TlsServerKeyExchangeContents<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.debug_struct("TlsServerKeyExchangeContents")
@@ -99,6 +226,19 @@ impl fmt::Debug for TlsRecordHeader {
     }
 }
 
+impl<'a> fmt::Debug for TlsCompressedCertificateContents<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("TlsCompressedCertificateContents")
+            .field("algorithm", &self.algorithm)
+            .field("uncompressed_length", &self.uncompressed_length)
+            .field(
+                "compressed_certificate_message",
+                &HexSlice(self.compressed_certificate_message),
+            )
+            .finish()
+    }
+}
+
 // ------------------------- tls_alert.rs ------------------------------
 impl fmt::Debug for TlsMessageAlert {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
@@ -146,7 +286,70 @@ impl<'a> fmt::Debug for ECParameters<'a> {
     }
 }
 
+// ------------------------- tls_ech.rs ------------------------------
+impl<'a> fmt::Debug for HpkeKeyConfig<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("HpkeKeyConfig")
+            .field("config_id", &self.config_id)
+            .field("kem_id", &self.kem_id)
+            .field("public_key", &HexSlice(self.public_key))
+            .field("cipher_suites", &self.cipher_suites)
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for EchConfigContents<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let public_name = from_utf8(self.public_name).unwrap_or("<error decoding utf8 string>");
+        fmt.debug_struct("EchConfigContents")
+            .field("key_config", &self.key_config)
+            .field("maximum_name_length", &self.maximum_name_length)
+            .field("public_name", &public_name)
+            .field("extensions", &self.extensions)
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for EchClientHello<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EchClientHello::Outer {
+                cipher_suite,
+                config_id,
+                enc,
+                payload,
+            } => fmt
+                .debug_struct("EchClientHello::Outer")
+                .field("cipher_suite", &cipher_suite)
+                .field("config_id", &config_id)
+                .field("enc", &HexSlice(enc))
+                .field("payload", &HexSlice(payload))
+                .finish(),
+            EchClientHello::Inner => write!(fmt, "EchClientHello::Inner"),
+        }
+    }
+}
+
 // ------------------------- tls_extensions.rs ------------------------------
+fn signature_schemes_to_strings(v: &[SignatureScheme]) -> Vec<String> {
+    v.iter()
+        .map(|&alg| {
+            let s = format!("{}", alg);
+            if s.starts_with("SignatureScheme") {
+                format!(
+                    "{}",
+                    SignatureAndHashAlgorithm {
+                        hash: HashAlgorithm(alg.hash_alg()),
+                        sign: SignAlgorithm(alg.sign_alg())
+                    }
+                )
+            } else {
+                s
+            }
+        })
+        .collect()
+}
+
 impl<'a> fmt::Debug for TlsExtension<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -161,61 +364,89 @@ impl<'a> fmt::Debug for TlsExtension<'a> {
                 write!(fmt, "TlsExtension::SNI({:?})", v)
             }
             TlsExtension::MaxFragmentLength(l) => {
-                write!(fmt, "TlsExtension::MaxFragmentLength({})", l)
+                write!(fmt, "TlsExtension::MaxFragmentLength({:?})", l)
+            }
+            TlsExtension::ClientCertificateUrl => write!(fmt, "TlsExtension::ClientCertificateUrl"),
+            TlsExtension::TrustedCaKeys(ref v) => {
+                write!(fmt, "TlsExtension::TrustedCaKeys({:?})", v)
             }
-            TlsExtension::StatusRequest(data) => {
+            TlsExtension::StatusRequest(ref data) => {
                 write!(fmt, "TlsExtension::StatusRequest({:?})", data)
             }
+            TlsExtension::UserMapping(ref v) => write!(fmt, "TlsExtension::UserMapping({:?})", v),
+            TlsExtension::StatusRequestV2(ref v) => {
+                write!(fmt, "TlsExtension::StatusRequestV2({:?})", v)
+            }
             TlsExtension::EllipticCurves(ref v) => {
                 let v2: Vec<_> = v.iter().map(|&curve| format!("{}", curve)).collect();
                 write!(fmt, "TlsExtension::EllipticCurves({:?})", v2)
             }
-            TlsExtension::EcPointFormats(v) => write!(fmt, "TlsExtension::EcPointFormats({:?})", v),
+            TlsExtension::EcPointFormats(ref v) => {
+                write!(fmt, "TlsExtension::EcPointFormats({:?})", v)
+            }
             TlsExtension::SignatureAlgorithms(ref v) => {
-                let v2: Vec<_> = v
-                    .iter()
-                    .map(|&alg| {
-                        let s = format!("{}", SignatureScheme(alg));
-                        if s.starts_with("SignatureScheme") {
-                            format!(
-                                "{}",
-                                SignatureAndHashAlgorithm {
-                                    hash: HashAlgorithm((alg >> 8) as u8),
-                                    sign: SignAlgorithm((alg & 0xff) as u8)
-                                }
-                            )
-                        } else {
-                            s
-                        }
-                    })
-                    .collect();
+                let v2 = signature_schemes_to_strings(v);
                 write!(fmt, "TlsExtension::SignatureAlgorithms({:?})", v2)
             }
+            TlsExtension::SignatureAlgorithmsCert(ref v) => {
+                let v2 = signature_schemes_to_strings(v);
+                write!(fmt, "TlsExtension::SignatureAlgorithmsCert({:?})", v2)
+            }
+            TlsExtension::CompressCertificate(ref v) => {
+                write!(fmt, "TlsExtension::CompressCertificate({:?})", v)
+            }
+            TlsExtension::Srp(identity) => write!(
+                fmt,
+                "TlsExtension::Srp({:?})",
+                from_utf8(identity).unwrap_or("<error decoding utf8 string>")
+            ),
+            TlsExtension::CachedInfo(ref v) => write!(fmt, "TlsExtension::CachedInfo({:?})", v),
             TlsExtension::SessionTicket(data) => {
                 write!(fmt, "TlsExtension::SessionTicket(data={:?})", data)
             }
-            TlsExtension::RecordSizeLimit(data) => {
-                write!(fmt, "TlsExtension::RecordSizeLimit(data={})", data)
+            TlsExtension::DelegatedCredentials(ref v) => {
+                write!(fmt, "TlsExtension::DelegatedCredentials({:?})", v)
+            }
+            TlsExtension::DelegatedCredential(ref dc) => {
+                write!(fmt, "TlsExtension::DelegatedCredential({:?})", dc)
+            }
+            TlsExtension::RecordSizeLimit(l) => {
+                write!(fmt, "TlsExtension::RecordSizeLimit(limit={})", l.0)
             }
             TlsExtension::KeyShareOld(data) => {
                 write!(fmt, "TlsExtension::KeyShareOld(data={:?})", HexSlice(data))
             }
-            TlsExtension::KeyShare(data) => {
-                write!(fmt, "TlsExtension::KeyShare(data={:?})", HexSlice(data))
+            TlsExtension::KeyShare(ref v) => write!(fmt, "TlsExtension::KeyShare({:?})", v),
+            TlsExtension::KeyShareServer(ref e) => {
+                write!(fmt, "TlsExtension::KeyShareServer({:?})", e)
+            }
+            TlsExtension::KeyShareHelloRetryRequest(group) => {
+                write!(
+                    fmt,
+                    "TlsExtension::KeyShareHelloRetryRequest(group={})",
+                    group
+                )
             }
-            TlsExtension::PreSharedKey(data) => {
-                write!(fmt, "TlsExtension::PreSharedKey(data={:?})", HexSlice(data))
+            TlsExtension::PreSharedKey(ref offer) => {
+                write!(fmt, "TlsExtension::PreSharedKey({:?})", offer)
+            }
+            TlsExtension::PreSharedKeySelected(idx) => {
+                write!(fmt, "TlsExtension::PreSharedKeySelected({})", idx)
             }
             TlsExtension::EarlyData(o) => write!(fmt, "TlsExtension::EarlyData({:?})", o),
             TlsExtension::SupportedVersions(ref v) => {
                 let v2: Vec<_> = v.iter().map(|c| format!("{}", c)).collect();
                 write!(fmt, "TlsExtension::SupportedVersions(v={:?})", v2)
             }
-            TlsExtension::Cookie(data) => write!(fmt, "TlsExtension::Cookie(data={:?})", data),
+            TlsExtension::Cookie(data) => {
+                write!(fmt, "TlsExtension::Cookie(data={:?})", HexSlice(data))
+            }
             TlsExtension::PskExchangeModes(ref v) => {
                 write!(fmt, "TlsExtension::PskExchangeModes({:?})", v)
             }
-            TlsExtension::Heartbeat(mode) => write!(fmt, "TlsExtension::Heartbeat(mode={})", mode),
+            TlsExtension::Heartbeat(mode) => {
+                write!(fmt, "TlsExtension::Heartbeat(mode={:?})", mode)
+            }
             TlsExtension::ALPN(ref v) => {
                 let v: Vec<_> = v
                     .iter()
@@ -223,14 +454,37 @@ impl<'a> fmt::Debug for TlsExtension<'a> {
                     .collect();
                 write!(fmt, "TlsExtension::ALPN({:?})", v)
             }
-            TlsExtension::SignedCertificateTimestamp(data) => write!(
-                fmt,
-                "TlsExtension::SignedCertificateTimestamp(data={:?})",
-                data
-            ),
+            TlsExtension::SignedCertificateTimestamp(ref v) => {
+                write!(fmt, "TlsExtension::SignedCertificateTimestamp({:?})", v)
+            }
+            TlsExtension::ClientCertificateType(ref v) => {
+                write!(fmt, "TlsExtension::ClientCertificateType({:?})", v)
+            }
+            TlsExtension::ClientCertificateTypeSelected(t) => {
+                write!(fmt, "TlsExtension::ClientCertificateTypeSelected({:?})", t)
+            }
+            TlsExtension::ServerCertificateType(ref v) => {
+                write!(fmt, "TlsExtension::ServerCertificateType({:?})", v)
+            }
+            TlsExtension::ServerCertificateTypeSelected(t) => {
+                write!(fmt, "TlsExtension::ServerCertificateTypeSelected({:?})", t)
+            }
             TlsExtension::Padding(data) => write!(fmt, "TlsExtension::Padding(data={:?})", data),
             TlsExtension::EncryptThenMac => write!(fmt, "TlsExtension::EncryptThenMac"),
             TlsExtension::ExtendedMasterSecret => write!(fmt, "TlsExtension::ExtendedMasterSecret"),
+            TlsExtension::TokenBinding {
+                major,
+                minor,
+                ref key_parameters,
+            } => write!(
+                fmt,
+                "TlsExtension::TokenBinding{{version: {}.{}, key_parameters: {:?}}}",
+                major, minor, key_parameters
+            ),
+            TlsExtension::CertificateAuthorities(ref v) => {
+                let v: Vec<_> = v.iter().map(|dn| HexSlice(dn)).collect();
+                write!(fmt, "TlsExtension::CertificateAuthorities({:?})", v)
+            }
             TlsExtension::OidFilters(ref v) => {
                 let v: Vec<_> = v.iter().map(|c| format!("{:?}", c)).collect();
                 write!(fmt, "TlsExtension::OidFilters({:?})", v)
@@ -239,9 +493,52 @@ impl<'a> fmt::Debug for TlsExtension<'a> {
             TlsExtension::NextProtocolNegotiation => {
                 write!(fmt, "TlsExtension::NextProtocolNegotiation")
             }
+            TlsExtension::NextProtocolNegotiationServer(ref v) => {
+                let v: Vec<_> = v
+                    .iter()
+                    .map(|c| from_utf8(c).unwrap_or("<error decoding utf8 string>"))
+                    .collect();
+                write!(fmt, "TlsExtension::NextProtocolNegotiationServer({:?})", v)
+            }
+            TlsExtension::ApplicationSettings(ref v) => {
+                let v: Vec<_> = v
+                    .iter()
+                    .map(|c| from_utf8(c).unwrap_or("<error decoding utf8 string>"))
+                    .collect();
+                write!(fmt, "TlsExtension::ApplicationSettings({:?})", v)
+            }
+            TlsExtension::ApplicationSettingsData(data) => write!(
+                fmt,
+                "TlsExtension::ApplicationSettingsData({:?})",
+                HexSlice(data)
+            ),
+            TlsExtension::ChannelId(ref channel_id) => {
+                write!(fmt, "TlsExtension::ChannelId({:?})", channel_id)
+            }
+            TlsExtension::ConnectionId(cid) => {
+                write!(fmt, "TlsExtension::ConnectionId({:?})", HexSlice(cid))
+            }
+            TlsExtension::QuicTransportParameters(ref v) => {
+                write!(fmt, "TlsExtension::QuicTransportParameters({:?})", v)
+            }
             TlsExtension::RenegotiationInfo(data) => {
                 write!(fmt, "TlsExtension::RenegotiationInfo(data={:?})", data)
             }
+            TlsExtension::UseSrtp { ref profiles, mki } => write!(
+                fmt,
+                "TlsExtension::UseSrtp{{profiles: {:?}, mki: {:?}}}",
+                profiles,
+                HexSlice(mki)
+            ),
+            TlsExtension::CertificateStatus(ref status) => {
+                write!(fmt, "TlsExtension::CertificateStatus({:?})", status)
+            }
+            TlsExtension::SupportedEktCiphers(ref v) => {
+                write!(fmt, "TlsExtension::SupportedEktCiphers({:?})", v)
+            }
+            TlsExtension::SupportedEktCiphersSelected(t) => {
+                write!(fmt, "TlsExtension::SupportedEktCiphersSelected({:?})", t)
+            }
             TlsExtension::EncryptedServerName {
                 ciphersuite, group, ..
             } => write!(
@@ -249,6 +546,20 @@ impl<'a> fmt::Debug for TlsExtension<'a> {
                 "TlsExtension::EncryptedServerName{{cipher: {:?}, group: {:?} ..}}",
                 ciphersuite, group
             ),
+            TlsExtension::EncryptedClientHello(ref ech) => {
+                write!(fmt, "TlsExtension::EncryptedClientHello({:?})", ech)
+            }
+            TlsExtension::EchRetryConfigs(data) => write!(
+                fmt,
+                "TlsExtension::EchRetryConfigs(data={:?})",
+                HexSlice(data)
+            ),
+            TlsExtension::EchConfirmation(data) => {
+                write!(fmt, "TlsExtension::EchConfirmation({:?})", HexSlice(data))
+            }
+            TlsExtension::EchOuterExtensions(ref v) => {
+                write!(fmt, "TlsExtension::EchOuterExtensions({:?})", v)
+            }
             TlsExtension::Grease(t, data) => write!(
                 fmt,
                 "TlsExtension::Grease(0x{:x},data={:?})",
@@ -264,6 +575,123 @@ impl<'a> fmt::Debug for TlsExtension<'a> {
     }
 }
 
+impl<'a> fmt::Debug for CachedObject<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("CachedObject")
+            .field("cached_type", &self.cached_type)
+            .field("hash_value", &self.hash_value.map(HexSlice))
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for TrustedAuthority<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("TrustedAuthority")
+            .field("identifier_type", &self.identifier_type)
+            .field("identifier", &HexSlice(self.identifier))
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for OcspStatusRequest<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let v: Vec<_> = self.responder_id_list.iter().map(|r| HexSlice(r)).collect();
+        fmt.debug_struct("OcspStatusRequest")
+            .field("responder_id_list", &v)
+            .field("request_extensions", &HexSlice(self.request_extensions))
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for SignedCertificateTimestamp<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("SignedCertificateTimestamp")
+            .field("version", &self.version)
+            .field("id", &HexSlice(self.id))
+            .field("timestamp", &self.timestamp)
+            .field("extensions", &HexSlice(self.extensions))
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for ChannelId<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("ChannelId")
+            .field("x", &HexSlice(self.x))
+            .field("y", &HexSlice(self.y))
+            .field("r", &HexSlice(self.r))
+            .field("s", &HexSlice(self.s))
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for KeyShareEntry<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("KeyShareEntry")
+            .field("group", &self.group)
+            .field("kx", &HexSlice(self.kx))
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for QuicTransportParameter<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.integer_value() {
+            Some(v) if self.id.is_integer() => write!(fmt, "{}={}", self.id, v),
+            _ => write!(fmt, "{}={:?}", self.id, HexSlice(self.value)),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for DelegatedCredential<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("DelegatedCredential")
+            .field("valid_time", &self.valid_time)
+            .field(
+                "expected_cert_verify_algorithm",
+                &self.expected_cert_verify_algorithm,
+            )
+            .field(
+                "asn1_subject_public_key_info",
+                &HexSlice(self.asn1_subject_public_key_info),
+            )
+            .field("algorithm", &self.algorithm)
+            .field("signature", &HexSlice(self.signature))
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for PskIdentity<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("PskIdentity")
+            .field("identity", &HexSlice(self.identity))
+            .field("obfuscated_ticket_age", &self.obfuscated_ticket_age)
+            .finish()
+    }
+}
+
+impl<'a> fmt::Debug for PreSharedKeyOffer<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let binders: Vec<_> = self.binders.iter().map(|b| HexSlice(b)).collect();
+        fmt.debug_struct("PreSharedKeyOffer")
+            .field("identities", &self.identities)
+            .field("binders", &binders)
+            .finish()
+    }
+}
+
+// ------------------------- tls_kx.rs ------------------------------
+impl<'a> fmt::Debug for ServerRSAParams<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("ServerRSAParams")
+            .field("modulus size", &self.modulus_bits())
+            .field("rsa_modulus", &HexSlice(self.rsa_modulus))
+            .field("rsa_exponent", &HexSlice(self.rsa_exponent))
+            .finish()
+    }
+}
+
 // ------------------------- tls_sign_hash.rs ------------------------------
 impl fmt::Display for SignatureAndHashAlgorithm {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
@@ -0,0 +1,997 @@
+//! # Record decryption
+//!
+//! `TlsSessionDecryptor` decrypts the records of a session, using the secrets logged by one of
+//! the peers (see `TlsKeyLogSecrets`). The keys are derived from the secrets and from the
+//! parameters of the handshake (randoms, cipher suite, version), so all records of the session
+//! must be given in order, including the plaintext handshake records.
+//!
+//! TLS 1.2 (from the master secret) and TLS 1.3 (from the traffic secrets) are supported, with
+//! the AEAD cipher suites: AES-GCM and ChaCha20-Poly1305. TLS 1.2 AES-CBC cipher suites (with
+//! HMAC-SHA1, HMAC-SHA256 or HMAC-SHA384, and the `encrypt_then_mac` extension) are also
+//! supported. TLS 1.3 early data (0-RTT) is not supported.
+//!
+//! The decrypted records can be parsed using `TlsDecryptedRecord::parse_messages`.
+
+use std::fmt;
+
+use aes::{Aes128, Aes256};
+use aes_gcm::aead::{Aead, NewAead, Payload};
+use aes_gcm::{Aes128Gcm, Aes256Gcm};
+use block_modes::block_padding::NoPadding;
+use block_modes::{BlockMode, Cbc};
+use chacha20poly1305::ChaCha20Poly1305;
+use hmac::{Hmac, Mac, NewMac};
+use nom::combinator::complete;
+use nom::multi::many1;
+use nom::IResult;
+use sha1::Sha1;
+use sha2::{Sha256, Sha384};
+
+use crate::tls::*;
+use crate::tls_ciphers::*;
+use crate::tls_extensions::TlsExtension;
+use crate::tls_key_schedule::*;
+use crate::tls_keylog::TlsKeyLogSecrets;
+use crate::tls_transcript::TlsHashAlgorithm;
+
+/// Length of the AEAD authentication tag
+const TAG_LEN: usize = 16;
+
+/// AES block size
+const BLOCK_LEN: usize = 16;
+
+/// Error returned when a record cannot be decrypted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsDecryptError {
+    /// The cipher suite is unknown, or does not use an algorithm supported for decryption
+    UnsupportedCipher,
+    /// The negotiated version is older than TLS 1.2
+    UnsupportedVersion,
+    /// The secret needed to derive the keys was not logged
+    MissingSecret,
+    /// An encrypted record was received before the keys could be derived (for ex., TLS 1.3
+    /// early data)
+    NoKeys,
+    /// The record is too short, or (TLS 1.3) does not contain a content type
+    InvalidRecord,
+    /// The record authentication failed (wrong secret, or modified record), or its padding
+    /// is invalid
+    AuthenticationFailed,
+}
+
+/// Decrypted record
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlsDecryptedRecord {
+    /// Content type (in TLS 1.3, the inner content type)
+    pub record_type: TlsRecordType,
+    /// Negotiated version
+    pub version: TlsVersion,
+    /// Plaintext content
+    pub data: Vec<u8>,
+}
+
+impl TlsDecryptedRecord {
+    /// Parse the messages of the record
+    ///
+    /// TLS 1.3 handshake messages are parsed using `parse_tls_message_handshake_with_version`.
+    pub fn parse_messages(&self) -> IResult<&[u8], Vec<TlsMessage>> {
+        if self.record_type == TlsRecordType::Handshake && self.version.is_tls13() {
+            let version = self.version;
+            many1(complete(move |i| {
+                parse_tls_message_handshake_with_version(i, version)
+            }))(&self.data)
+        } else {
+            let hdr = TlsRecordHeader {
+                record_type: self.record_type,
+                version: self.version,
+                len: self.data.len() as u16,
+            };
+            parse_tls_record_with_header(&self.data, &hdr)
+        }
+    }
+}
+
+/// TLS 1.2 PRF, as defined in [RFC5246](https://tools.ietf.org/html/rfc5246) section 5
+fn prf(alg: TlsHashAlgorithm, secret: &[u8], label: &[u8], seed: &[u8], len: usize) -> Vec<u8> {
+    let seed = [label, seed].concat();
+    let mut out = Vec::with_capacity(len);
+    let mut a = hmac(alg, secret, &[&seed]);
+    while out.len() < len {
+        out.extend_from_slice(&hmac(alg, secret, &[&a, &seed]));
+        a = hmac(alg, secret, &[&a]);
+    }
+    out.truncate(len);
+    out
+}
+
+/// Get the key length of a supported AEAD cipher suite
+fn aead_key_len(cipher: &TlsCipherSuite) -> Result<usize, TlsDecryptError> {
+    match (cipher.enc, cipher.enc_mode, cipher.enc_size) {
+        (TlsCipherEnc::Aes, TlsCipherEncMode::Gcm, 128) => Ok(16),
+        (TlsCipherEnc::Aes, TlsCipherEncMode::Gcm, 256) => Ok(32),
+        (TlsCipherEnc::Chacha20_Poly1305, _, _) => Ok(32),
+        _ => Err(TlsDecryptError::UnsupportedCipher),
+    }
+}
+
+enum AeadKey {
+    Aes128Gcm(Box<Aes128Gcm>),
+    Aes256Gcm(Box<Aes256Gcm>),
+    ChaCha20Poly1305(Box<ChaCha20Poly1305>),
+}
+
+// do not print the keys
+impl fmt::Debug for AeadKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AeadKey::Aes128Gcm(_) => f.write_str("Aes128Gcm"),
+            AeadKey::Aes256Gcm(_) => f.write_str("Aes256Gcm"),
+            AeadKey::ChaCha20Poly1305(_) => f.write_str("ChaCha20Poly1305"),
+        }
+    }
+}
+
+impl AeadKey {
+    fn new(cipher: &TlsCipherSuite, key: &[u8]) -> Result<Self, TlsDecryptError> {
+        let key_len = aead_key_len(cipher)?;
+        let res = match (cipher.enc, key_len) {
+            (TlsCipherEnc::Chacha20_Poly1305, _) => ChaCha20Poly1305::new_from_slice(key)
+                .map(|k| AeadKey::ChaCha20Poly1305(Box::new(k))),
+            (_, 16) => Aes128Gcm::new_from_slice(key).map(|k| AeadKey::Aes128Gcm(Box::new(k))),
+            _ => Aes256Gcm::new_from_slice(key).map(|k| AeadKey::Aes256Gcm(Box::new(k))),
+        };
+        res.or(Err(TlsDecryptError::UnsupportedCipher))
+    }
+
+    fn decrypt(&self, nonce: &[u8], aad: &[u8], msg: &[u8]) -> Result<Vec<u8>, TlsDecryptError> {
+        let mut n = [0u8; 12];
+        n.copy_from_slice(nonce);
+        let nonce = &n.into();
+        let payload = Payload { msg, aad };
+        let res = match self {
+            AeadKey::Aes128Gcm(k) => k.decrypt(nonce, payload),
+            AeadKey::Aes256Gcm(k) => k.decrypt(nonce, payload),
+            AeadKey::ChaCha20Poly1305(k) => k.decrypt(nonce, payload),
+        };
+        res.or(Err(TlsDecryptError::AuthenticationFailed))
+    }
+}
+
+/// Get the MAC length of a supported CBC cipher suite
+fn mac_len(cipher: &TlsCipherSuite) -> Result<usize, TlsDecryptError> {
+    match cipher.mac {
+        TlsCipherMac::HmacSha1 | TlsCipherMac::HmacSha256 | TlsCipherMac::HmacSha384 => {
+            Ok(cipher.mac_size as usize / 8)
+        }
+        _ => Err(TlsDecryptError::UnsupportedCipher),
+    }
+}
+
+fn record_mac(mac: TlsCipherMac, key: &[u8], data: &[&[u8]]) -> Vec<u8> {
+    macro_rules! hmac_with {
+        ($hash:ty) => {{
+            let mut mac = Hmac::<$hash>::new_from_slice(key).expect("HMAC accepts all key sizes");
+            for d in data {
+                mac.update(d);
+            }
+            mac.finalize().into_bytes().to_vec()
+        }};
+    }
+    match mac {
+        TlsCipherMac::HmacSha1 => hmac_with!(Sha1),
+        TlsCipherMac::HmacSha384 => hmac_with!(Sha384),
+        _ => hmac_with!(Sha256),
+    }
+}
+
+/// Keys of a TLS 1.2 AES-CBC cipher suite
+struct CbcKeys {
+    key: Vec<u8>,
+    mac: TlsCipherMac,
+    mac_key: Vec<u8>,
+    encrypt_then_mac: bool,
+}
+
+// do not print the keys
+impl fmt::Debug for CbcKeys {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CbcKeys")
+            .field("key_len", &self.key.len())
+            .field("mac", &self.mac)
+            .field("encrypt_then_mac", &self.encrypt_then_mac)
+            .finish()
+    }
+}
+
+impl CbcKeys {
+    fn new(
+        cipher: &TlsCipherSuite,
+        key: &[u8],
+        mac_key: &[u8],
+        encrypt_then_mac: bool,
+    ) -> Result<Self, TlsDecryptError> {
+        match (cipher.enc, cipher.enc_mode, key.len()) {
+            (TlsCipherEnc::Aes, TlsCipherEncMode::Cbc, 16)
+            | (TlsCipherEnc::Aes, TlsCipherEncMode::Cbc, 32) => (),
+            _ => return Err(TlsDecryptError::UnsupportedCipher),
+        }
+        Ok(CbcKeys {
+            key: key.to_vec(),
+            mac: cipher.mac,
+            mac_key: mac_key.to_vec(),
+            encrypt_then_mac,
+        })
+    }
+
+    /// Decrypt `data` (a multiple of the block size), without removing the padding
+    fn decrypt(&self, iv: &[u8], data: &[u8]) -> Result<Vec<u8>, TlsDecryptError> {
+        let res = if self.key.len() == 16 {
+            Cbc::<Aes128, NoPadding>::new_from_slices(&self.key, iv).map(|c| c.decrypt_vec(data))
+        } else {
+            Cbc::<Aes256, NoPadding>::new_from_slices(&self.key, iv).map(|c| c.decrypt_vec(data))
+        };
+        match res {
+            Ok(Ok(plaintext)) => Ok(plaintext),
+            _ => Err(TlsDecryptError::InvalidRecord),
+        }
+    }
+
+    fn mac(&self, seq: u64, hdr: &TlsRecordHeader, data: &[u8]) -> Vec<u8> {
+        let mut pseudo_hdr = seq.to_be_bytes().to_vec();
+        pseudo_hdr.push(hdr.record_type.0);
+        pseudo_hdr.extend_from_slice(&hdr.version.0.to_be_bytes());
+        pseudo_hdr.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        record_mac(self.mac, &self.mac_key, &[&pseudo_hdr, data])
+    }
+}
+
+#[derive(Debug)]
+enum RecordCipher {
+    Tls13(AeadKey),
+    Aead(AeadKey),
+    Cbc(CbcKeys),
+}
+
+/// Keys of one direction
+#[derive(Debug)]
+struct RecordKeys {
+    cipher: RecordCipher,
+    /// Static IV (TLS 1.3, and ChaCha20-Poly1305), or implicit part of the nonce (TLS 1.2
+    /// AES-GCM)
+    iv: Vec<u8>,
+    seq: u64,
+}
+
+impl RecordKeys {
+    fn tls13(cipher: &TlsCipherSuite, secret: &[u8]) -> Result<Self, TlsDecryptError> {
+        let keys = TlsTrafficKeys::new(cipher, secret);
+        Ok(RecordKeys {
+            cipher: RecordCipher::Tls13(AeadKey::new(cipher, &keys.key)?),
+            iv: keys.iv,
+            seq: 0,
+        })
+    }
+
+    /// Nonce built from the static IV and the sequence number
+    fn nonce(&self) -> Vec<u8> {
+        let mut nonce = self.iv.clone();
+        let offset = nonce.len() - 8;
+        for (n, s) in nonce[offset..].iter_mut().zip(&self.seq.to_be_bytes()) {
+            *n ^= s;
+        }
+        nonce
+    }
+
+    fn decrypt(
+        &mut self,
+        hdr: &TlsRecordHeader,
+        data: &[u8],
+    ) -> Result<(TlsRecordType, Vec<u8>), TlsDecryptError> {
+        let res = match self.cipher {
+            RecordCipher::Tls13(ref aead) => self.decrypt_tls13(aead, hdr, data),
+            RecordCipher::Aead(ref aead) => self.decrypt_tls12(aead, hdr, data),
+            RecordCipher::Cbc(ref cbc) => self.decrypt_tls12_cbc(cbc, hdr, data),
+        };
+        self.seq = self.seq.wrapping_add(1);
+        res
+    }
+
+    fn decrypt_tls13(
+        &self,
+        aead: &AeadKey,
+        hdr: &TlsRecordHeader,
+        data: &[u8],
+    ) -> Result<(TlsRecordType, Vec<u8>), TlsDecryptError> {
+        let mut aad = vec![hdr.record_type.0];
+        aad.extend_from_slice(&hdr.version.0.to_be_bytes());
+        aad.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        let mut plaintext = aead.decrypt(&self.nonce(), &aad, data)?;
+        // the content type is the last non-zero byte, followed by the padding
+        let pos = plaintext
+            .iter()
+            .rposition(|&b| b != 0)
+            .ok_or(TlsDecryptError::InvalidRecord)?;
+        let record_type = TlsRecordType(plaintext[pos]);
+        plaintext.truncate(pos);
+        Ok((record_type, plaintext))
+    }
+
+    fn decrypt_tls12(
+        &self,
+        aead: &AeadKey,
+        hdr: &TlsRecordHeader,
+        data: &[u8],
+    ) -> Result<(TlsRecordType, Vec<u8>), TlsDecryptError> {
+        // AES-GCM uses an explicit nonce, sent before the ciphertext [RFC5288] 3
+        let (nonce, ciphertext) = if self.iv.len() == 4 {
+            if data.len() < 8 {
+                return Err(TlsDecryptError::InvalidRecord);
+            }
+            ([&self.iv[..], &data[..8]].concat(), &data[8..])
+        } else {
+            (self.nonce(), data)
+        };
+        if ciphertext.len() < TAG_LEN {
+            return Err(TlsDecryptError::InvalidRecord);
+        }
+        let mut aad = self.seq.to_be_bytes().to_vec();
+        aad.push(hdr.record_type.0);
+        aad.extend_from_slice(&hdr.version.0.to_be_bytes());
+        aad.extend_from_slice(&((ciphertext.len() - TAG_LEN) as u16).to_be_bytes());
+        let plaintext = aead.decrypt(&nonce, &aad, ciphertext)?;
+        Ok((hdr.record_type, plaintext))
+    }
+
+    /// Decrypt a CBC record: explicit IV, then the encrypted content, MAC and padding, or
+    /// (encrypt_then_mac) the encrypted content and padding, then the MAC [RFC7366] 3
+    fn decrypt_tls12_cbc(
+        &self,
+        cbc: &CbcKeys,
+        hdr: &TlsRecordHeader,
+        data: &[u8],
+    ) -> Result<(TlsRecordType, Vec<u8>), TlsDecryptError> {
+        let mac_len = cbc.mac_key.len();
+        let min_len = if cbc.encrypt_then_mac {
+            2 * BLOCK_LEN + mac_len
+        } else {
+            BLOCK_LEN + mac_len + 1
+        };
+        if data.len() < min_len {
+            return Err(TlsDecryptError::InvalidRecord);
+        }
+        let data = if cbc.encrypt_then_mac {
+            let (data, mac) = data.split_at(data.len() - mac_len);
+            if cbc.mac(self.seq, hdr, data) != mac {
+                return Err(TlsDecryptError::AuthenticationFailed);
+            }
+            data
+        } else {
+            data
+        };
+        let (iv, ciphertext) = data.split_at(BLOCK_LEN);
+        let mut plaintext = cbc.decrypt(iv, ciphertext)?;
+        // padding_length + 1 bytes, all set to padding_length [RFC5246] 6.2.3.2
+        let padding_len = plaintext[plaintext.len() - 1] as usize + 1;
+        let trailer_len = if cbc.encrypt_then_mac {
+            padding_len
+        } else {
+            padding_len + mac_len
+        };
+        let content_len = plaintext
+            .len()
+            .checked_sub(trailer_len)
+            .ok_or(TlsDecryptError::AuthenticationFailed)?;
+        let padding = &plaintext[plaintext.len() - padding_len..];
+        if padding.iter().any(|&b| b as usize + 1 != padding_len) {
+            return Err(TlsDecryptError::AuthenticationFailed);
+        }
+        if !cbc.encrypt_then_mac {
+            let (content, mac) = plaintext.split_at(content_len);
+            if cbc.mac(self.seq, hdr, content) != mac[..mac_len] {
+                return Err(TlsDecryptError::AuthenticationFailed);
+            }
+        }
+        plaintext.truncate(content_len);
+        Ok((hdr.record_type, plaintext))
+    }
+}
+
+#[derive(Debug, Default)]
+struct DirectionState {
+    /// Handshake data not yet processed (incomplete message)
+    handshake: Vec<u8>,
+    /// Keys, or the reason why they could not be derived (`None` if not encrypted yet)
+    keys: Option<Result<RecordKeys, TlsDecryptError>>,
+    /// Secret of the current keys (TLS 1.3)
+    traffic_secret: Vec<u8>,
+    /// The Finished message of the handshake was received (TLS 1.3)
+    finished: bool,
+}
+
+/// Decryptor of the records of a session
+///
+/// Records of both directions must be given in order to `update`. Plaintext records are not
+/// modified, but are used to get the parameters of the handshake.
+///
+/// The secrets can be set after the ClientHello was received (to look them up using the
+/// client random), but must be set before the ServerHello (TLS 1.3) or the ChangeCipherSpec
+/// (TLS 1.2).
+#[derive(Debug, Default)]
+pub struct TlsSessionDecryptor {
+    secrets: TlsKeyLogSecrets,
+    client_random: Option<Vec<u8>>,
+    server_random: Option<Vec<u8>>,
+    cipher: Option<&'static TlsCipherSuite>,
+    version: Option<TlsVersion>,
+    /// The `encrypt_then_mac` extension was negotiated
+    encrypt_then_mac: bool,
+    client: DirectionState,
+    server: DirectionState,
+}
+
+impl TlsSessionDecryptor {
+    pub fn new(secrets: TlsKeyLogSecrets) -> Self {
+        TlsSessionDecryptor {
+            secrets,
+            ..TlsSessionDecryptor::default()
+        }
+    }
+
+    /// Set the secrets of the session
+    pub fn set_secrets(&mut self, secrets: TlsKeyLogSecrets) {
+        self.secrets = secrets;
+    }
+
+    /// Get the client random (32 bytes), if the ClientHello was received
+    pub fn client_random(&self) -> Option<&[u8]> {
+        self.client_random.as_deref()
+    }
+
+    /// Get the negotiated version, if the ServerHello was received
+    pub fn version(&self) -> Option<TlsVersion> {
+        self.version
+    }
+
+    fn is_tls13(&self) -> bool {
+        matches!(self.version, Some(v) if v.is_tls13())
+    }
+
+    fn direction(&mut self, to_server: bool) -> &mut DirectionState {
+        if to_server {
+            &mut self.client
+        } else {
+            &mut self.server
+        }
+    }
+
+    /// Update the decryptor with a record, and return the decrypted record
+    ///
+    /// Plaintext records return `Ok(None)`, and must be parsed as usual.
+    pub fn update(
+        &mut self,
+        record: &TlsRawRecord,
+        to_server: bool,
+    ) -> Result<Option<TlsDecryptedRecord>, TlsDecryptError> {
+        let tls13 = self.is_tls13();
+        let record_type = record.hdr.record_type;
+        // TLS 1.3 encrypted records always use the ApplicationData type
+        let encrypted = if tls13 {
+            record_type == TlsRecordType::ApplicationData
+        } else {
+            record_type != TlsRecordType::ChangeCipherSpec
+        };
+        let dir = self.direction(to_server);
+        let (record_type, data) = match dir.keys {
+            Some(Ok(ref mut keys)) if encrypted => keys.decrypt(&record.hdr, record.data)?,
+            Some(Err(e)) if encrypted => return Err(e),
+            None if record_type == TlsRecordType::ApplicationData => {
+                return Err(TlsDecryptError::NoKeys)
+            }
+            _ => {
+                match record_type {
+                    // in TLS 1.3, ChangeCipherSpec is only sent for middlebox compatibility,
+                    // and can be received before the ServerHello (after a HelloRetryRequest)
+                    TlsRecordType::ChangeCipherSpec if is_tls12_or_earlier(self.version) => {
+                        let keys = self.tls12_keys(to_server);
+                        self.direction(to_server).keys = Some(keys);
+                    }
+                    TlsRecordType::Handshake => self.update_handshake(record.data, to_server),
+                    _ => (),
+                }
+                return Ok(None);
+            }
+        };
+        if record_type == TlsRecordType::Handshake {
+            self.update_handshake(&data, to_server);
+        }
+        let version = self.version.unwrap_or(record.hdr.version);
+        Ok(Some(TlsDecryptedRecord {
+            record_type,
+            version,
+            data,
+        }))
+    }
+
+    /// Process the handshake messages (plaintext or decrypted) sent by a peer
+    fn update_handshake(&mut self, data: &[u8], to_server: bool) {
+        let mut buffer = std::mem::take(&mut self.direction(to_server).handshake);
+        buffer.extend_from_slice(data);
+        let mut i = &buffer[..];
+        while i.len() >= 4 {
+            let len = (i[1] as usize) << 16 | (i[2] as usize) << 8 | i[3] as usize;
+            if i.len() < 4 + len {
+                break;
+            }
+            let (msg, rem) = i.split_at(4 + len);
+            self.update_handshake_msg(msg, to_server);
+            i = rem;
+        }
+        let rem = i.to_vec();
+        self.direction(to_server).handshake = rem;
+    }
+
+    fn update_handshake_msg(&mut self, msg: &[u8], to_server: bool) {
+        let finished = if to_server {
+            self.client.finished
+        } else {
+            self.server.finished
+        };
+        match TlsHandshakeType(msg[0]) {
+            TlsHandshakeType::ClientHello if to_server && msg.len() >= 4 + 2 + 32 => {
+                self.client_random = Some(msg[6..38].to_vec());
+            }
+            TlsHandshakeType::ServerHello if !to_server => {
+                if let Ok((_, TlsMessage::Handshake(TlsMessageHandshake::ServerHello(sh)))) =
+                    parse_tls_message_handshake(msg)
+                {
+                    if sh.is_hello_retry_request() {
+                        return;
+                    }
+                    let mut random = sh.rand_time.to_be_bytes().to_vec();
+                    random.extend_from_slice(sh.rand_data);
+                    self.server_random = Some(random);
+                    self.cipher = sh.cipher.get_ciphersuite();
+                    self.version = Some(sh.get_supported_version().unwrap_or(sh.version));
+                    self.encrypt_then_mac = match sh.parse_extensions() {
+                        Ok((_, exts)) => exts
+                            .iter()
+                            .any(|ext| matches!(ext, TlsExtension::EncryptThenMac)),
+                        Err(_) => false,
+                    };
+                    if self.is_tls13() {
+                        let client = self.secrets.client_handshake_traffic_secret.clone();
+                        let server = self.secrets.server_handshake_traffic_secret.clone();
+                        self.set_tls13_keys(client, true);
+                        self.set_tls13_keys(server, false);
+                    }
+                }
+            }
+            // later Finished messages are sent for post-handshake authentication, and do not
+            // change the keys
+            TlsHandshakeType::Finished if self.is_tls13() && !finished => {
+                self.direction(to_server).finished = true;
+                let secret = if to_server {
+                    self.secrets.client_traffic_secret_0.clone()
+                } else {
+                    self.secrets.server_traffic_secret_0.clone()
+                };
+                self.set_tls13_keys(secret, to_server);
+            }
+            // [RFC8446] 7.2
+            TlsHandshakeType::KeyUpdate if self.is_tls13() => {
+                let alg = self.cipher.map(TlsHashAlgorithm::from_ciphersuite);
+                let dir = self.direction(to_server);
+                let secret = alg.map(|alg| next_traffic_secret(alg, &dir.traffic_secret));
+                self.set_tls13_keys(secret, to_server);
+            }
+            _ => (),
+        }
+    }
+
+    fn set_tls13_keys(&mut self, secret: Option<Vec<u8>>, to_server: bool) {
+        let keys = match (self.cipher, &secret) {
+            (None, _) => Err(TlsDecryptError::UnsupportedCipher),
+            (_, None) => Err(TlsDecryptError::MissingSecret),
+            (Some(cipher), Some(secret)) => RecordKeys::tls13(cipher, secret),
+        };
+        let dir = self.direction(to_server);
+        dir.keys = Some(keys);
+        dir.traffic_secret = secret.unwrap_or_default();
+    }
+
+    /// Derive the keys of a peer from the master secret [RFC5246] 6.3
+    fn tls12_keys(&self, to_server: bool) -> Result<RecordKeys, TlsDecryptError> {
+        let cipher = self.cipher.ok_or(TlsDecryptError::UnsupportedCipher)?;
+        let master_secret = self
+            .secrets
+            .master_secret
+            .as_ref()
+            .ok_or(TlsDecryptError::MissingSecret)?;
+        let (client_random, server_random) = match (&self.client_random, &self.server_random) {
+            (Some(c), Some(s)) => (c, s),
+            _ => return Err(TlsDecryptError::NoKeys),
+        };
+        // the PRF of older versions is not supported
+        match self.version {
+            Some(TlsVersion::Tls12) => (),
+            _ => return Err(TlsDecryptError::UnsupportedVersion),
+        }
+        let seed = [&server_random[..], &client_random[..]].concat();
+        let alg = TlsHashAlgorithm::from_ciphersuite(cipher);
+        if cipher.enc_mode == TlsCipherEncMode::Cbc {
+            let key_len = cipher.enc_size as usize / 8;
+            let mac_len = mac_len(cipher)?;
+            let len = 2 * mac_len + 2 * key_len;
+            let block = prf(alg, master_secret, b"key expansion", &seed, len);
+            let (client_mac_key, rem) = block.split_at(mac_len);
+            let (server_mac_key, rem) = rem.split_at(mac_len);
+            let (client_key, server_key) = rem.split_at(key_len);
+            let (key, mac_key) = if to_server {
+                (client_key, client_mac_key)
+            } else {
+                (server_key, server_mac_key)
+            };
+            let cbc = CbcKeys::new(cipher, key, mac_key, self.encrypt_then_mac)?;
+            return Ok(RecordKeys {
+                cipher: RecordCipher::Cbc(cbc),
+                iv: Vec::new(),
+                seq: 0,
+            });
+        }
+        let key_len = aead_key_len(cipher)?;
+        // ChaCha20-Poly1305 uses a 12-byte static IV [RFC7905] 2
+        let iv_len = if cipher.enc == TlsCipherEnc::Chacha20_Poly1305 {
+            12
+        } else {
+            4
+        };
+        let len = 2 * key_len + 2 * iv_len;
+        let block = prf(alg, master_secret, b"key expansion", &seed, len);
+        let (client_key, rem) = block.split_at(key_len);
+        let (server_key, rem) = rem.split_at(key_len);
+        let (client_iv, server_iv) = rem.split_at(iv_len);
+        let (key, iv) = if to_server {
+            (client_key, client_iv)
+        } else {
+            (server_key, server_iv)
+        };
+        Ok(RecordKeys {
+            cipher: RecordCipher::Aead(AeadKey::new(cipher, key)?),
+            iv: iv.to_vec(),
+            seq: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ClientHello record, with the client random `[0x11; 32]`
+    fn client_hello() -> Vec<u8> {
+        let mut ch = vec![
+            0x16, 0x03, 0x01, 0x00, 0x2d, 0x01, 0x00, 0x00, 0x29, 0x03, 0x03,
+        ];
+        ch.extend_from_slice(&[0x11; 32]);
+        ch.extend_from_slice(&[0x00, 0x00, 0x02, 0x13, 0x01, 0x01, 0x00]);
+        ch
+    }
+
+    /// TLS 1.3 ServerHello record, selecting TLS_AES_128_GCM_SHA256
+    fn server_hello_tls13() -> Vec<u8> {
+        let mut sh = vec![
+            0x16, 0x03, 0x03, 0x00, 0x32, 0x02, 0x00, 0x00, 0x2e, 0x03, 0x03,
+        ];
+        sh.extend_from_slice(&[0x22; 32]);
+        sh.extend_from_slice(&[
+            0x00, 0x13, 0x01, 0x00, 0x00, 0x06, 0x00, 0x2b, 0x00, 0x02, 0x03, 0x04,
+        ]);
+        sh
+    }
+
+    fn update(
+        decryptor: &mut TlsSessionDecryptor,
+        data: &[u8],
+        to_server: bool,
+    ) -> Result<Option<TlsDecryptedRecord>, TlsDecryptError> {
+        let (rem, record) = parse_tls_raw_record(data).expect("parsing failed");
+        assert!(rem.is_empty());
+        decryptor.update(&record, to_server)
+    }
+
+    /// Encrypt a TLS 1.3 record using TLS_AES_128_GCM_SHA256
+    fn encrypt_tls13(secret: &[u8], seq: u64, inner: &[u8]) -> Vec<u8> {
+        let cipher = TlsCipherSuite::from_id(0x1301).expect("unknown cipher suite");
+        let TlsTrafficKeys { key, iv: mut nonce } = TlsTrafficKeys::new(cipher, secret);
+        for (n, s) in nonce[4..].iter_mut().zip(&seq.to_be_bytes()) {
+            *n ^= s;
+        }
+        let len = (inner.len() + TAG_LEN) as u16;
+        let mut record = vec![0x17, 0x03, 0x03];
+        record.extend_from_slice(&len.to_be_bytes());
+        let aead = Aes128Gcm::new_from_slice(&key).expect("invalid key length");
+        let payload = Payload {
+            msg: inner,
+            aad: &record[..],
+        };
+        let mut n = [0u8; 12];
+        n.copy_from_slice(&nonce);
+        let ciphertext = aead.encrypt(&n.into(), payload).expect("encryption failed");
+        record.extend_from_slice(&ciphertext);
+        record
+    }
+
+    #[test]
+    fn test_decrypt_tls13() {
+        let secrets = TlsKeyLogSecrets {
+            client_handshake_traffic_secret: Some(vec![1; 32]),
+            server_handshake_traffic_secret: Some(vec![2; 32]),
+            server_traffic_secret_0: Some(vec![4; 32]),
+            ..TlsKeyLogSecrets::default()
+        };
+        let mut decryptor = TlsSessionDecryptor::new(secrets);
+        let ch = client_hello();
+        let sh = server_hello_tls13();
+        // EncryptedExtensions and Finished, with 2 bytes of padding
+        let mut inner = vec![0x08, 0x00, 0x00, 0x02, 0x00, 0x00, 0x14, 0x00, 0x00, 0x20];
+        inner.extend_from_slice(&[0x33; 32]);
+        inner.extend_from_slice(&[0x16, 0x00, 0x00]);
+        let server_hs = encrypt_tls13(&[2; 32], 0, &inner);
+        let server_app = encrypt_tls13(&[4; 32], 0, b"hello\x17");
+        let mut tampered = encrypt_tls13(&[4; 32], 1, b"hello\x17");
+        tampered[5] ^= 1;
+
+        assert_eq!(update(&mut decryptor, &ch, true), Ok(None));
+        assert_eq!(update(&mut decryptor, &sh, false), Ok(None));
+        let record = update(&mut decryptor, &server_hs, false)
+            .expect("decryption failed")
+            .expect("record not decrypted");
+        assert_eq!(record.record_type, TlsRecordType::Handshake);
+        assert_eq!(record.version, TlsVersion::Tls13);
+        assert_eq!(record.data, &inner[..inner.len() - 3]);
+        let (_, msgs) = record.parse_messages().expect("parsing messages failed");
+        assert_eq!(msgs.len(), 2);
+        // the server Finished installs the application traffic keys
+        let record = update(&mut decryptor, &server_app, false)
+            .expect("decryption failed")
+            .expect("record not decrypted");
+        assert_eq!(record.record_type, TlsRecordType::ApplicationData);
+        assert_eq!(record.data, b"hello");
+        assert_eq!(
+            update(&mut decryptor, &tampered, false),
+            Err(TlsDecryptError::AuthenticationFailed)
+        );
+        assert_eq!(decryptor.client_random(), Some(&[0x11; 32][..]));
+        assert_eq!(decryptor.version(), Some(TlsVersion::Tls13));
+    }
+
+    #[test]
+    fn test_decrypt_tls13_hello_retry_request() {
+        let secrets = TlsKeyLogSecrets {
+            server_handshake_traffic_secret: Some(vec![2; 32]),
+            ..TlsKeyLogSecrets::default()
+        };
+        let mut decryptor = TlsSessionDecryptor::new(secrets);
+        let mut hrr = vec![
+            0x16, 0x03, 0x03, 0x00, 0x32, 0x02, 0x00, 0x00, 0x2e, 0x03, 0x03,
+        ];
+        hrr.extend_from_slice(&HRR_RANDOM);
+        hrr.extend_from_slice(&[
+            0x00, 0x13, 0x01, 0x00, 0x00, 0x06, 0x00, 0x2b, 0x00, 0x02, 0x03, 0x04,
+        ]);
+        // middlebox compatibility ChangeCipherSpec messages, before the ServerHello
+        let ccs = &[0x14, 0x03, 0x03, 0x00, 0x01, 0x01];
+        let flow: &[(&[u8], bool)] = &[
+            (&client_hello(), true),
+            (&hrr, false),
+            (ccs, false),
+            (ccs, true),
+            (&client_hello(), true),
+            (&server_hello_tls13(), false),
+        ];
+        for &(data, to_server) in flow {
+            assert_eq!(update(&mut decryptor, data, to_server), Ok(None));
+        }
+        assert_eq!(decryptor.version(), Some(TlsVersion::Tls13));
+        let inner = &[0x08, 0x00, 0x00, 0x02, 0x00, 0x00, 0x16];
+        let server_hs = encrypt_tls13(&[2; 32], 0, inner);
+        let record = update(&mut decryptor, &server_hs, false)
+            .expect("decryption failed")
+            .expect("record not decrypted");
+        assert_eq!(record.data, &inner[..6]);
+    }
+
+    #[test]
+    fn test_decrypt_tls13_post_handshake_auth() {
+        let secrets = TlsKeyLogSecrets {
+            client_handshake_traffic_secret: Some(vec![1; 32]),
+            server_handshake_traffic_secret: Some(vec![2; 32]),
+            client_traffic_secret_0: Some(vec![3; 32]),
+            server_traffic_secret_0: Some(vec![4; 32]),
+            ..TlsKeyLogSecrets::default()
+        };
+        let mut decryptor = TlsSessionDecryptor::new(secrets);
+        let mut finished = vec![0x14, 0x00, 0x00, 0x20];
+        finished.extend_from_slice(&[0x33; 32]);
+        let mut server_hs = vec![0x08, 0x00, 0x00, 0x02, 0x00, 0x00];
+        server_hs.extend_from_slice(&finished);
+        server_hs.push(0x16);
+        let client_hs = [&finished[..], &[0x16]].concat();
+        // CertificateRequest, then empty Certificate and Finished
+        let request = &[0x0d, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x16];
+        let mut client_auth = vec![0x0b, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00];
+        client_auth.extend_from_slice(&client_hs);
+        let flow = &[
+            (encrypt_tls13(&[2; 32], 0, &server_hs), false),
+            (encrypt_tls13(&[1; 32], 0, &client_hs), true),
+            (encrypt_tls13(&[3; 32], 0, b"hello\x17"), true),
+            (encrypt_tls13(&[4; 32], 0, request), false),
+            (encrypt_tls13(&[3; 32], 1, &client_auth), true),
+        ];
+        assert_eq!(update(&mut decryptor, &client_hello(), true), Ok(None));
+        assert_eq!(
+            update(&mut decryptor, &server_hello_tls13(), false),
+            Ok(None)
+        );
+        for (data, to_server) in flow {
+            let record = update(&mut decryptor, data, *to_server).expect("decryption failed");
+            assert!(record.is_some());
+        }
+        // the client keys were not reset by the second Finished
+        let data = encrypt_tls13(&[3; 32], 2, b"again\x17");
+        let record = update(&mut decryptor, &data, true)
+            .expect("decryption failed")
+            .expect("record not decrypted");
+        assert_eq!(record.data, b"again");
+    }
+
+    /// Start a TLS 1.2 session (master secret `[0x44; 48]`), and return the client key block
+    fn tls12_session(
+        version: u16,
+        cipher: u16,
+        encrypt_then_mac: bool,
+        key_block_len: usize,
+    ) -> (TlsSessionDecryptor, Vec<u8>) {
+        let secrets = TlsKeyLogSecrets {
+            master_secret: Some(vec![0x44; 48]),
+            ..TlsKeyLogSecrets::default()
+        };
+        let mut decryptor = TlsSessionDecryptor::new(secrets);
+        let ext: &[u8] = if encrypt_then_mac {
+            &[0x00, 0x04, 0x00, 0x16, 0x00, 0x00]
+        } else {
+            &[]
+        };
+        let len = 38 + ext.len() as u8;
+        let mut sh = vec![0x16, 0x03, 0x03, 0x00, len + 4, 0x02, 0x00, 0x00, len];
+        sh.extend_from_slice(&version.to_be_bytes());
+        sh.extend_from_slice(&[0x22; 32]);
+        sh.push(0x00);
+        sh.extend_from_slice(&cipher.to_be_bytes());
+        sh.push(0x00);
+        sh.extend_from_slice(ext);
+        let ccs = &[0x14, 0x03, 0x03, 0x00, 0x01, 0x01];
+        assert_eq!(update(&mut decryptor, &client_hello(), true), Ok(None));
+        assert_eq!(update(&mut decryptor, &sh, false), Ok(None));
+        assert_eq!(update(&mut decryptor, ccs, true), Ok(None));
+        let seed = [[0x22; 32], [0x11; 32]].concat();
+        let alg = TlsHashAlgorithm::Sha256;
+        let key_block = prf(alg, &[0x44; 48], b"key expansion", &seed, key_block_len);
+        (decryptor, key_block)
+    }
+
+    /// Client Finished message
+    const FINISHED: &[u8] = &[
+        0x14, 0x00, 0x00, 0x0c, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b,
+        0x0c,
+    ];
+
+    fn record(data: &[u8]) -> Vec<u8> {
+        let mut record = vec![0x16, 0x03, 0x03];
+        record.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        record.extend_from_slice(data);
+        record
+    }
+
+    #[test]
+    fn test_decrypt_tls12_gcm() {
+        // TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256
+        let (mut decryptor, key_block) = tls12_session(0x0303, 0xc02f, false, 40);
+        let explicit_nonce = [0x99; 8];
+        let nonce = [&key_block[32..36], &explicit_nonce[..]].concat();
+        let mut n = [0u8; 12];
+        n.copy_from_slice(&nonce);
+        let aad = [&[0; 8][..], &[0x16, 0x03, 0x03, 0x00, 0x10]].concat();
+        let aead = Aes128Gcm::new_from_slice(&key_block[..16]).expect("invalid key length");
+        let payload = Payload {
+            msg: FINISHED,
+            aad: &aad,
+        };
+        let ciphertext = aead.encrypt(&n.into(), payload).expect("encryption failed");
+        let data = record(&[&explicit_nonce[..], &ciphertext].concat());
+        let record = update(&mut decryptor, &data, true)
+            .expect("decryption failed")
+            .expect("record not decrypted");
+        assert_eq!(record.record_type, TlsRecordType::Handshake);
+        assert_eq!(record.data, FINISHED);
+        // the sequence number was incremented
+        assert_eq!(
+            update(&mut decryptor, &data, true),
+            Err(TlsDecryptError::AuthenticationFailed)
+        );
+        // server records are not encrypted yet
+        let alert = &[0x15, 0x03, 0x03, 0x00, 0x02, 0x02, 0x28];
+        assert_eq!(update(&mut decryptor, alert, false), Ok(None));
+    }
+
+    #[test]
+    fn test_decrypt_tls12_cbc() {
+        // TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA: MAC keys (20 bytes), then encryption keys
+        for &encrypt_then_mac in &[false, true] {
+            let (mut decryptor, key_block) = tls12_session(0x0303, 0xc013, encrypt_then_mac, 72);
+            let (mac_key, key) = (&key_block[..20], &key_block[40..56]);
+            let iv = [0x55; 16];
+            let cbc = Cbc::<Aes128, NoPadding>::new_from_slices(key, &iv).expect("invalid key");
+            let mac = |data: &[u8]| {
+                let hdr = [
+                    &[0; 8][..],
+                    &[0x16, 0x03, 0x03],
+                    &(data.len() as u16).to_be_bytes(),
+                ];
+                record_mac(TlsCipherMac::HmacSha1, mac_key, &[&hdr.concat(), data])
+            };
+            let data = if encrypt_then_mac {
+                let plaintext = [FINISHED, &[0x0f; 16]].concat();
+                let ciphertext = [&iv[..], &cbc.encrypt_vec(&plaintext)].concat();
+                let mac = mac(&ciphertext);
+                [ciphertext, mac].concat()
+            } else {
+                let plaintext = [FINISHED, &mac(FINISHED), &[0x0b; 12]].concat();
+                [&iv[..], &cbc.encrypt_vec(&plaintext)].concat()
+            };
+            let mut tampered = record(&data);
+            let record = update(&mut decryptor, &record(&data), true)
+                .expect("decryption failed")
+                .expect("record not decrypted");
+            assert_eq!(record.data, FINISHED);
+            let (_, msgs) = record.parse_messages().expect("parsing messages failed");
+            assert!(matches!(
+                msgs[0],
+                TlsMessage::Handshake(TlsMessageHandshake::Finished(_))
+            ));
+            // the last byte is the padding length, or the MAC
+            let last = tampered.len() - 1;
+            tampered[last] ^= 1;
+            assert_eq!(
+                update(&mut decryptor, &tampered, true),
+                Err(TlsDecryptError::AuthenticationFailed)
+            );
+        }
+        let (mut decryptor, _) = tls12_session(0x0302, 0xc013, false, 0);
+        assert_eq!(
+            update(&mut decryptor, &record(&[0; 48]), true),
+            Err(TlsDecryptError::UnsupportedVersion)
+        );
+    }
+
+    #[test]
+    fn test_prf_sha256() {
+        let secret = &[
+            0x9b, 0xbe, 0x43, 0x6b, 0xa9, 0x40, 0xf0, 0x17, 0xb1, 0x76, 0x52, 0x84, 0x9a, 0x71,
+            0xdb, 0x35,
+        ];
+        let seed = &[
+            0xa0, 0xba, 0x9f, 0x93, 0x6c, 0xda, 0x31, 0x18, 0x27, 0xa6, 0xf7, 0x96, 0xff, 0xd5,
+            0x19, 0x8c,
+        ];
+        let output = prf(TlsHashAlgorithm::Sha256, secret, b"test label", seed, 100);
+        assert_eq!(
+            &output[..16],
+            &[
+                0xe3, 0xf2, 0x29, 0xba, 0x72, 0x7b, 0xe1, 0x7b, 0x8d, 0x12, 0x26, 0x20, 0x55, 0x7c,
+                0xd4, 0x53
+            ]
+        );
+        assert_eq!(&output[96..], &[0x87, 0x34, 0x7b, 0x66]);
+    }
+}
@@ -3,6 +3,8 @@ use nom::number::streaming::be_u16;
 use nom::IResult;
 use nom_derive::Nom;
 
+use crate::tls_dh_groups::KNOWN_DH_GROUPS;
+
 /// Diffie-Hellman parameters, defined in [RFC5246] section 7.4.3
 #[derive(PartialEq, Nom)]
 pub struct ServerDHParams<'a> {
@@ -17,6 +19,84 @@ pub struct ServerDHParams<'a> {
     pub dh_ys: &'a [u8],
 }
 
+/// Get the size, in bits, of a big-endian unsigned integer
+pub(crate) fn integer_bits(i: &[u8]) -> usize {
+    match i.iter().position(|&b| b != 0) {
+        Some(idx) => (i.len() - idx) * 8 - i[idx].leading_zeros() as usize,
+        None => 0,
+    }
+}
+
+/// Risk of an attack on a Diffie-Hellman group, from the lowest to the highest
+///
+/// See [Imperfect Forward Secrecy: How Diffie-Hellman Fails in Practice](https://weakdh.org/)
+/// (Logjam).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DhGroupRisk {
+    /// Group of 2048 bits or more
+    Low,
+    /// Group of less than 2048 bits, not a well-known group
+    Moderate,
+    /// Well-known group of less than 2048 bits. A precomputation on the prime allows
+    /// breaking all the connections using this group.
+    CommonPrime,
+    /// Group of less than 1024 bits
+    Weak,
+    /// Export-grade group (512 bits or less), vulnerable to Logjam
+    Export,
+}
+
+/// Analysis of a Diffie-Hellman group
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DhGroupAnalysis {
+    /// Size of the prime, in bits
+    pub bits: usize,
+    /// Name of the group, if the prime is a well-known prime
+    pub known_group: Option<&'static str>,
+    pub risk: DhGroupRisk,
+}
+
+impl<'a> ServerDHParams<'a> {
+    /// Get the size of the prime modulus, in bits
+    pub fn prime_bits(&self) -> usize {
+        integer_bits(self.dh_p)
+    }
+
+    /// Get the name of the group, if the prime is a well-known prime
+    ///
+    /// Known groups are the Oakley and MODP groups up to 2048 bits
+    /// ([RFC2409](https://tools.ietf.org/html/rfc2409),
+    /// [RFC3526](https://tools.ietf.org/html/rfc3526)), the `ffdhe2048` group
+    /// ([RFC7919](https://tools.ietf.org/html/rfc7919)), and the default 512-bit export primes
+    /// of OpenSSL and mod_ssl.
+    pub fn known_group(&self) -> Option<&'static str> {
+        let idx = self.dh_p.iter().position(|&b| b != 0)?;
+        let p = &self.dh_p[idx..];
+        KNOWN_DH_GROUPS
+            .iter()
+            .find(|&&(_, prime)| prime == p)
+            .map(|&(name, _)| name)
+    }
+
+    /// Measure the prime and look for a well-known group, to evaluate the risk of an attack
+    pub fn analyze_group(&self) -> DhGroupAnalysis {
+        let bits = self.prime_bits();
+        let known_group = self.known_group();
+        let risk = match bits {
+            0..=512 => DhGroupRisk::Export,
+            513..=1023 => DhGroupRisk::Weak,
+            1024..=2047 if known_group.is_some() => DhGroupRisk::CommonPrime,
+            1024..=2047 => DhGroupRisk::Moderate,
+            _ => DhGroupRisk::Low,
+        };
+        DhGroupAnalysis {
+            bits,
+            known_group,
+            risk,
+        }
+    }
+}
+
 #[inline]
 pub fn parse_dh_params(i: &[u8]) -> IResult<&[u8], ServerDHParams> {
     ServerDHParams::parse(i)
@@ -0,0 +1,131 @@
+//! # ClientHello comparison
+//!
+//! `TlsClientHelloContents::diff` reports the structural differences between two
+//! ClientHello messages: version, cipher suites, extensions and extension contents. This is
+//! useful to check that a TLS client stack still sends the same ClientHello, or to follow the
+//! evolution of a known client.
+
+use nom::combinator::complete;
+use nom::multi::{length_data, many0};
+use nom::number::complete::be_u16;
+use nom::IResult;
+
+use crate::tls::*;
+use crate::tls_extensions::TlsExtensionType;
+
+/// Differences between two lists of values
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TlsListDiff<T> {
+    /// Values present only in the new list
+    pub added: Vec<T>,
+    /// Values present only in the old list
+    pub removed: Vec<T>,
+    /// True if the values present in both lists are not in the same order
+    pub reordered: bool,
+}
+
+impl<T: PartialEq + Copy> TlsListDiff<T> {
+    fn new(old: &[T], new: &[T]) -> Self {
+        let added = new.iter().filter(|v| !old.contains(v)).copied().collect();
+        let removed = old.iter().filter(|v| !new.contains(v)).copied().collect();
+        let old_common = old.iter().filter(|v| new.contains(v));
+        let new_common = new.iter().filter(|v| old.contains(v));
+        TlsListDiff {
+            added,
+            removed,
+            reordered: !old_common.eq(new_common),
+        }
+    }
+
+    /// Test if both lists contain the same values, in the same order
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && !self.reordered
+    }
+}
+
+/// Extension present in both ClientHello messages, with a different content
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TlsExtensionChange<'a> {
+    pub ext_type: TlsExtensionType,
+    /// Extension data in the old message
+    pub old: &'a [u8],
+    /// Extension data in the new message
+    pub new: &'a [u8],
+}
+
+/// Differences between two ClientHello messages
+///
+/// Extensions are compared by type and raw content, so GREASE values are reported as any
+/// other value. If an extension type appears more than once, only the first occurrence is
+/// compared.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TlsClientHelloDiff<'a> {
+    /// Old and new `legacy_version`, if different
+    pub version: Option<(TlsVersion, TlsVersion)>,
+    pub ciphers: TlsListDiff<TlsCipherSuiteID>,
+    pub compression: TlsListDiff<TlsCompressionID>,
+    pub extensions: TlsListDiff<TlsExtensionType>,
+    pub changed_extensions: Vec<TlsExtensionChange<'a>>,
+}
+
+impl<'a> TlsClientHelloDiff<'a> {
+    /// Test if both messages have the same structure
+    ///
+    /// Random values and session IDs are not compared.
+    pub fn is_empty(&self) -> bool {
+        self.version.is_none()
+            && self.ciphers.is_empty()
+            && self.compression.is_empty()
+            && self.extensions.is_empty()
+            && self.changed_extensions.is_empty()
+    }
+}
+
+fn parse_raw_extension(i: &[u8]) -> IResult<&[u8], (TlsExtensionType, &[u8])> {
+    let (i, ext_type) = be_u16(i)?;
+    let (i, ext_data) = length_data(be_u16)(i)?;
+    Ok((i, (TlsExtensionType(ext_type), ext_data)))
+}
+
+/// Split an extension block into (type, data) pairs, ignoring trailing invalid data
+pub(crate) fn raw_extensions(i: &[u8]) -> Vec<(TlsExtensionType, &[u8])> {
+    many0(complete(parse_raw_extension))(i)
+        .map(|(_, exts)| exts)
+        .unwrap_or_default()
+}
+
+impl<'a> TlsClientHelloContents<'a> {
+    /// Compare this ClientHello (the old one) with another ClientHello (the new one)
+    pub fn diff<'b>(&'b self, new: &'b TlsClientHelloContents) -> TlsClientHelloDiff<'b> {
+        let old_exts = raw_extensions(self.ext.unwrap_or(&[]));
+        let new_exts = raw_extensions(new.ext.unwrap_or(&[]));
+        let old_types: Vec<_> = old_exts.iter().map(|&(t, _)| t).collect();
+        let new_types: Vec<_> = new_exts.iter().map(|&(t, _)| t).collect();
+        let mut changed_extensions = Vec::new();
+        for (idx, &(ext_type, old_data)) in old_exts.iter().enumerate() {
+            if old_types[..idx].contains(&ext_type) {
+                continue;
+            }
+            if let Some(&(_, new_data)) = new_exts.iter().find(|&&(t, _)| t == ext_type) {
+                if old_data != new_data {
+                    changed_extensions.push(TlsExtensionChange {
+                        ext_type,
+                        old: old_data,
+                        new: new_data,
+                    });
+                }
+            }
+        }
+        TlsClientHelloDiff {
+            version: if self.version != new.version {
+                Some((self.version, new.version))
+            } else {
+                None
+            },
+            ciphers: TlsListDiff::new(&self.ciphers, &new.ciphers),
+            compression: TlsListDiff::new(&self.comp, &new.comp),
+            extensions: TlsListDiff::new(&old_types, &new_types),
+            changed_extensions,
+        }
+    }
+}
@@ -5,6 +5,8 @@ use nom::{Err, IResult};
 use nom_derive::Nom;
 use rusticata_macros::newtype_enum;
 
+use crate::tls::is_grease_u16;
+
 /// Named curves, as defined in [RFC4492](https://tools.ietf.org/html/rfc4492), [RFC7027](https://tools.ietf.org/html/rfc7027), [RFC7919](https://tools.ietf.org/html/rfc7919) and
 /// [IANA Supported Groups
 /// Registry](https://www.iana.org/assignments/tls-parameters/tls-parameters.xhtml#tls-parameters-8)
@@ -52,6 +54,14 @@ impl debug NamedGroup {
     Ffdhe4096 = 0x102,
     Ffdhe6144 = 0x103,
     Ffdhe8192 = 0x104,
+    MLKEM512 = 0x200,
+    MLKEM768 = 0x201,
+    MLKEM1024 = 0x202,
+    SecP256r1MLKEM768 = 0x11eb,
+    X25519MLKEM768 = 0x11ec,
+    SecP384r1MLKEM1024 = 0x11ed,
+    X25519Kyber768Draft00 = 0x6399,
+    SecP256r1Kyber768Draft00 = 0x639a,
     ArbitraryExplicitPrimeCurves = 0xFF01,
     ArbitraryExplicitChar2Curves = 0xFF02,
 }
@@ -93,6 +103,27 @@ impl NamedGroup {
             _ => None,
         }
     }
+
+    /// Test if the group is a GREASE value (see `is_grease_u16`)
+    pub fn is_grease(self) -> bool {
+        is_grease_u16(self.0)
+    }
+
+    /// Test if the group is a post-quantum key encapsulation mechanism, either standalone
+    /// (ML-KEM) or combined with a classical group (hybrid)
+    pub fn is_post_quantum(self) -> bool {
+        matches!(
+            self,
+            NamedGroup::MLKEM512
+                | NamedGroup::MLKEM768
+                | NamedGroup::MLKEM1024
+                | NamedGroup::SecP256r1MLKEM768
+                | NamedGroup::X25519MLKEM768
+                | NamedGroup::SecP384r1MLKEM1024
+                | NamedGroup::X25519Kyber768Draft00
+                | NamedGroup::SecP256r1Kyber768Draft00
+        )
+    }
 }
 
 /// Elliptic curve
@@ -120,6 +151,20 @@ impl display ECCurveType {
 }
 }
 
+/// EC point formats, as defined in the
+/// [IANA EC Point Formats
+/// Registry](https://www.iana.org/assignments/tls-parameters/tls-parameters.xhtml#tls-parameters-9)
+#[derive(Clone, Copy, PartialEq, Eq, Nom)]
+pub struct ECPointFormat(pub u8);
+
+newtype_enum! {
+impl debug ECPointFormat {
+    Uncompressed            = 0,
+    AnsiX962CompressedPrime = 1,
+    AnsiX962CompressedChar2 = 2,
+}
+}
+
 /// EC Point
 #[derive(Clone, Debug, PartialEq, Nom)]
 pub struct ECPoint<'a> {
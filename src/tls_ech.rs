@@ -0,0 +1,262 @@
+//!
+//! Encrypted Client Hello (ECH), defined in
+//! [draft-ietf-tls-esni](https://datatracker.ietf.org/doc/draft-ietf-tls-esni/)
+//!
+//! HPKE identifiers are defined in [RFC9180](https://tools.ietf.org/html/rfc9180)
+
+use nom::bytes::streaming::take;
+use nom::combinator::{complete, map_parser};
+use nom::error::{make_error, ErrorKind};
+use nom::multi::{length_data, many0, many1};
+use nom::number::streaming::{be_u16, be_u8};
+use nom::{Err, IResult};
+use nom_derive::Nom;
+use rusticata_macros::newtype_enum;
+
+/// HPKE Key Encapsulation Mechanism identifier, as defined in the [IANA HPKE KEM
+/// Identifiers](https://www.iana.org/assignments/hpke/hpke.xhtml#hpke-kem-ids) registry
+#[derive(Clone, Copy, PartialEq, Eq, Nom)]
+pub struct HpkeKemId(pub u16);
+
+newtype_enum! {
+impl debug HpkeKemId {
+    DhkemP256HkdfSha256   = 0x0010,
+    DhkemP384HkdfSha384   = 0x0011,
+    DhkemP521HkdfSha512   = 0x0012,
+    DhkemX25519HkdfSha256 = 0x0020,
+    DhkemX448HkdfSha512   = 0x0021,
+}
+}
+
+/// HPKE Key Derivation Function identifier, as defined in the [IANA HPKE KDF
+/// Identifiers](https://www.iana.org/assignments/hpke/hpke.xhtml#hpke-kdf-ids) registry
+#[derive(Clone, Copy, PartialEq, Eq, Nom)]
+pub struct HpkeKdfId(pub u16);
+
+newtype_enum! {
+impl debug HpkeKdfId {
+    HkdfSha256 = 0x0001,
+    HkdfSha384 = 0x0002,
+    HkdfSha512 = 0x0003,
+}
+}
+
+/// HPKE AEAD identifier, as defined in the [IANA HPKE AEAD
+/// Identifiers](https://www.iana.org/assignments/hpke/hpke.xhtml#hpke-aead-ids) registry
+#[derive(Clone, Copy, PartialEq, Eq, Nom)]
+pub struct HpkeAeadId(pub u16);
+
+newtype_enum! {
+impl debug HpkeAeadId {
+    Aes128Gcm        = 0x0001,
+    Aes256Gcm        = 0x0002,
+    ChaCha20Poly1305 = 0x0003,
+    ExportOnly       = 0xffff,
+}
+}
+
+/// HPKE symmetric cipher suite (KDF and AEAD)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HpkeSymmetricCipherSuite {
+    pub kdf_id: HpkeKdfId,
+    pub aead_id: HpkeAeadId,
+}
+
+/// HPKE public key and algorithms used to encrypt the ClientHelloInner
+#[derive(Clone, PartialEq)]
+pub struct HpkeKeyConfig<'a> {
+    pub config_id: u8,
+    pub kem_id: HpkeKemId,
+    pub public_key: &'a [u8],
+    pub cipher_suites: Vec<HpkeSymmetricCipherSuite>,
+}
+
+/// Extension of an ECHConfig
+#[derive(Clone, Debug, PartialEq)]
+pub struct EchConfigExtension<'a> {
+    pub ext_type: u16,
+    pub data: &'a [u8],
+}
+
+/// Contents of an ECHConfig (version 0xfe0d)
+#[derive(Clone, PartialEq)]
+pub struct EchConfigContents<'a> {
+    pub key_config: HpkeKeyConfig<'a>,
+    pub maximum_name_length: u8,
+    pub public_name: &'a [u8],
+    pub extensions: Vec<EchConfigExtension<'a>>,
+}
+
+/// ECH configuration, as published in the `ech` parameter of HTTPS/SVCB DNS records or sent
+/// in `retry_configs`
+///
+/// Configurations with an unknown version are kept as opaque data.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EchConfig<'a> {
+    V0xfe0d(EchConfigContents<'a>),
+    Unknown { version: u16, data: &'a [u8] },
+}
+
+impl<'a> EchConfig<'a> {
+    /// Get the version of the configuration
+    pub fn version(&self) -> u16 {
+        match *self {
+            EchConfig::V0xfe0d(_) => 0xfe0d,
+            EchConfig::Unknown { version, .. } => version,
+        }
+    }
+}
+
+/// Content of the `encrypted_client_hello` extension, sent in a ClientHello
+#[derive(Clone, PartialEq)]
+pub enum EchClientHello<'a> {
+    /// ClientHelloOuter, carrying the encrypted ClientHelloInner
+    Outer {
+        cipher_suite: HpkeSymmetricCipherSuite,
+        config_id: u8,
+        enc: &'a [u8],
+        payload: &'a [u8],
+    },
+    /// ClientHelloInner (only visible after decryption)
+    Inner,
+}
+
+// struct {
+//     HpkeKdfId kdf_id;
+//     HpkeAeadId aead_id;
+// } HpkeSymmetricCipherSuite;
+pub fn parse_hpke_symmetric_cipher_suite(i: &[u8]) -> IResult<&[u8], HpkeSymmetricCipherSuite> {
+    let (i, kdf_id) = HpkeKdfId::parse(i)?;
+    let (i, aead_id) = HpkeAeadId::parse(i)?;
+    Ok((i, HpkeSymmetricCipherSuite { kdf_id, aead_id }))
+}
+
+// enum { outer(0), inner(1) } ECHClientHelloType;
+//
+// struct {
+//    ECHClientHelloType type;
+//    select (ECHClientHello.type) {
+//        case outer:
+//            HpkeSymmetricCipherSuite cipher_suite;
+//            uint8 config_id;
+//            opaque enc<0..2^16-1>;
+//            opaque payload<1..2^16-1>;
+//        case inner:
+//            Empty;
+//    };
+// } ECHClientHello;
+pub fn parse_ech_client_hello(i: &[u8]) -> IResult<&[u8], EchClientHello> {
+    let (i, ty) = be_u8(i)?;
+    match ty {
+        0 => {
+            let (i, cipher_suite) = parse_hpke_symmetric_cipher_suite(i)?;
+            let (i, config_id) = be_u8(i)?;
+            let (i, enc) = length_data(be_u16)(i)?;
+            let (i, payload) = length_data(be_u16)(i)?;
+            let ech = EchClientHello::Outer {
+                cipher_suite,
+                config_id,
+                enc,
+                payload,
+            };
+            Ok((i, ech))
+        }
+        1 => Ok((i, EchClientHello::Inner)),
+        _ => Err(Err::Error(make_error(i, ErrorKind::Switch))),
+    }
+}
+
+// struct {
+//     opaque confirmation[8];
+// } ECHHelloRetryRequest;
+pub(crate) fn parse_ech_hrr_confirmation(i: &[u8]) -> IResult<&[u8], &[u8]> {
+    take(8usize)(i)
+}
+
+// struct {
+//     uint8 config_id;
+//     HpkeKemId kem_id;
+//     HpkePublicKey public_key;
+//     HpkeSymmetricCipherSuite cipher_suites<4..2^16-4>;
+// } HpkeKeyConfig;
+fn parse_hpke_key_config(i: &[u8]) -> IResult<&[u8], HpkeKeyConfig> {
+    let (i, config_id) = be_u8(i)?;
+    let (i, kem_id) = HpkeKemId::parse(i)?;
+    let (i, public_key) = length_data(be_u16)(i)?;
+    let (i, cipher_suites) = map_parser(
+        length_data(be_u16),
+        many1(complete(parse_hpke_symmetric_cipher_suite)),
+    )(i)?;
+    let key_config = HpkeKeyConfig {
+        config_id,
+        kem_id,
+        public_key,
+        cipher_suites,
+    };
+    Ok((i, key_config))
+}
+
+// struct {
+//     ECHConfigExtensionType type;
+//     opaque data<0..2^16-1>;
+// } ECHConfigExtension;
+fn parse_ech_config_extension(i: &[u8]) -> IResult<&[u8], EchConfigExtension> {
+    let (i, ext_type) = be_u16(i)?;
+    let (i, data) = length_data(be_u16)(i)?;
+    Ok((i, EchConfigExtension { ext_type, data }))
+}
+
+// struct {
+//     HpkeKeyConfig key_config;
+//     uint8 maximum_name_length;
+//     opaque public_name<1..255>;
+//     ECHConfigExtension extensions<0..2^16-1>;
+// } ECHConfigContents;
+fn parse_ech_config_contents(i: &[u8]) -> IResult<&[u8], EchConfigContents> {
+    let (i, key_config) = parse_hpke_key_config(i)?;
+    let (i, maximum_name_length) = be_u8(i)?;
+    let (i, public_name) = length_data(be_u8)(i)?;
+    let (i, extensions) = map_parser(
+        length_data(be_u16),
+        many0(complete(parse_ech_config_extension)),
+    )(i)?;
+    let contents = EchConfigContents {
+        key_config,
+        maximum_name_length,
+        public_name,
+        extensions,
+    };
+    Ok((i, contents))
+}
+
+// struct {
+//     uint16 version;
+//     uint16 length;
+//     select (ECHConfig.version) {
+//       case 0xfe0d: ECHConfigContents contents;
+//     }
+// } ECHConfig;
+pub fn parse_ech_config(i: &[u8]) -> IResult<&[u8], EchConfig> {
+    let (i, version) = be_u16(i)?;
+    let (i, data) = length_data(be_u16)(i)?;
+    match version {
+        0xfe0d => {
+            let (rem, contents) = parse_ech_config_contents(data)?;
+            if !rem.is_empty() {
+                return Err(Err::Error(make_error(rem, ErrorKind::Eof)));
+            }
+            Ok((i, EchConfig::V0xfe0d(contents)))
+        }
+        _ => Ok((i, EchConfig::Unknown { version, data })),
+    }
+}
+
+/// Parse an ECHConfigList, as published in HTTPS/SVCB DNS records (`ech` parameter) or sent by
+/// the server in the `encrypted_client_hello` extension (`retry_configs`)
+///
+/// ```text
+/// ECHConfig ECHConfigList<4..2^16-1>;
+/// ```
+pub fn parse_ech_config_list(i: &[u8]) -> IResult<&[u8], Vec<EchConfig>> {
+    map_parser(length_data(be_u16), many1(complete(parse_ech_config)))(i)
+}
@@ -5,19 +5,26 @@
 //! - [RFC6066](https://tools.ietf.org/html/rfc6066)
 //! - [RFC7366](https://tools.ietf.org/html/rfc7366)
 //! - [RFC7627](https://tools.ietf.org/html/rfc7627)
+//! - [draft-ietf-tls-esni](https://datatracker.ietf.org/doc/draft-ietf-tls-esni/)
 
+use nom::branch::alt;
 use nom::bytes::streaming::{tag, take};
-use nom::combinator::{complete, cond, map, map_parser, opt, verify};
+use nom::combinator::{all_consuming, complete, cond, map, map_parser, rest, verify};
 use nom::error::{make_error, ErrorKind};
 use nom::multi::{length_data, many0};
-use nom::number::streaming::{be_u16, be_u32, be_u8};
+use nom::number::streaming::{be_u16, be_u24, be_u32, be_u64, be_u8};
 use nom::{Err, IResult};
 use nom_derive::Nom;
 use rusticata_macros::newtype_enum;
 use std::convert::From;
 
-use crate::tls::{parse_tls_versions, TlsCipherSuiteID, TlsVersion};
-use crate::tls_ec::{parse_named_groups, NamedGroup};
+use crate::tls::{
+    is_grease_u16, parse_certificate_status, parse_tls_versions, CertificateCompressionAlgorithm,
+    TlsCertificateStatusContents, TlsCipherSuiteID, TlsVersion,
+};
+use crate::tls_ec::{parse_named_groups, ECPointFormat, NamedGroup};
+use crate::tls_ech::{parse_ech_client_hello, parse_ech_hrr_confirmation, EchClientHello};
+use crate::tls_sign_hash::{parse_digitally_signed, DigitallySigned, SignatureScheme};
 
 /// TLS extension types,
 /// defined in the [IANA Transport Layer Security (TLS)
@@ -30,11 +37,11 @@ newtype_enum! {
 impl display TlsExtensionType {
     ServerName                          = 0x0000, // [RFC6066]
     MaxFragmentLength                   = 0x0001,
-    ClientCertificate                   = 0x0002,
-    TrustedCaKeys                       = 0x0003,
+    ClientCertificate                   = 0x0002, // [RFC6066]
+    TrustedCaKeys                       = 0x0003, // [RFC6066]
     TruncatedHMac                       = 0x0004,
     StatusRequest                       = 0x0005, // [RFC6066]
-    UserMapping                         = 0x0006,
+    UserMapping                         = 0x0006, // [RFC4681]
     ClientAuthz                         = 0x0007,
     ServerAuthz                         = 0x0008,
     CertType                            = 0x0009,
@@ -43,22 +50,26 @@ impl display TlsExtensionType {
     Srp                                 = 0x000c, // [RFC5054]
     SignatureAlgorithms                 = 0x000d,
     UseSrtp                             = 0x000e,
-    Heartbeat                           = 0x000f,
+    Heartbeat                           = 0x000f, // [RFC6520]
     ApplicationLayerProtocolNegotiation = 0x0010, // [RFC7301]
-    StatusRequestv2                     = 0x0011,
+    StatusRequestv2                     = 0x0011, // [RFC6961]
     SignedCertificateTimestamp          = 0x0012,
-    ClientCertificateType               = 0x0013,
-    ServerCertificateType               = 0x0014,
+    ClientCertificateType               = 0x0013, // [RFC7250]
+    ServerCertificateType               = 0x0014, // [RFC7250]
     Padding                             = 0x0015, // [RFC7685]
     EncryptThenMac                      = 0x0016,
     ExtendedMasterSecret                = 0x0017,
-    TokenBinding                        = 0x0018,
-    CachedInfo                          = 0x0019,
+    TokenBinding                        = 0x0018, // [RFC8472]
+    CachedInfo                          = 0x0019, // [RFC7924]
 
+    CompressCertificate                 = 0x001b, // [RFC8879]
     RecordSizeLimit                     = 0x001c, // [RFC8449]
 
+    DelegatedCredential                 = 0x0022, // [RFC9345]
     SessionTicketTLS                    = 0x0023,
 
+    SupportedEktCiphers                 = 0x0027, // [RFC8870]
+
     KeyShareOld                         = 0x0028, // move to 51 in TLS 1.3 draft 23
     PreSharedKey                        = 0x0029,
     EarlyData                           = 0x002a,
@@ -69,13 +80,24 @@ impl display TlsExtensionType {
     CertificateAuthorities              = 0x002f,
     OidFilters                          = 0x0030,
     PostHandshakeAuth                   = 0x0031, // TLS 1.3 draft 20
-    SigAlgorithmsCert                   = 0x0032, // TLS 1.3 draft 23
+    SigAlgorithmsCert                   = 0x0032, // TLS 1.3 draft 23, [RFC8446]
     KeyShare                            = 0x0033, // TLS 1.3 draft 23
 
+    ConnectionIdDeprecated              = 0x0035, // draft-ietf-tls-dtls-connection-id
+    ConnectionId                        = 0x0036, // [RFC9146]
+
+    QuicTransportParameters             = 0x0039, // [RFC9001]
+
     NextProtocolNegotiation             = 0x3374,
+    ApplicationSettings                 = 0x4469, // draft-vvv-tls-alps
+    ChannelIdOld                        = 0x754f, // draft-balfanz-tls-channelid-00
+    ChannelId                           = 0x7550, // draft-balfanz-tls-channelid-01
 
     Grease                              = 0xfafa,
 
+    EchOuterExtensions                  = 0xfd00, // draft-ietf-tls-esni
+    EncryptedClientHello                = 0xfe0d, // draft-ietf-tls-esni
+
     RenegotiationInfo                   = 0xff01,
     EncryptedServerName                 = 0xffce, // draft-ietf-tls-esni
 }
@@ -85,6 +107,78 @@ impl TlsExtensionType {
     pub fn from_u16(t: u16) -> TlsExtensionType {
         TlsExtensionType(t)
     }
+
+    /// Test if the extension type is a GREASE value (see `is_grease_u16`)
+    pub fn is_grease(self) -> bool {
+        is_grease_u16(self.0)
+    }
+
+    /// Get the TLS 1.3 handshake messages in which the extension may appear
+    ///
+    /// This is the table of [RFC8446](https://tools.ietf.org/html/rfc8446) section 4.2, completed
+    /// with the extensions defined by later RFCs. Extensions not defined for TLS 1.3 can only
+    /// appear in the ClientHello (to negotiate an older version). Returns `None` if the
+    /// extension type is unknown (including GREASE values).
+    pub fn tls13_contexts(self) -> Option<&'static [ExtensionContext]> {
+        use ExtensionContext::*;
+        let contexts: &[ExtensionContext] = match self {
+            TlsExtensionType::ServerName
+            | TlsExtensionType::MaxFragmentLength
+            | TlsExtensionType::SupportedGroups
+            | TlsExtensionType::UseSrtp
+            | TlsExtensionType::Heartbeat
+            | TlsExtensionType::ApplicationLayerProtocolNegotiation
+            | TlsExtensionType::ClientCertificateType
+            | TlsExtensionType::ServerCertificateType
+            | TlsExtensionType::RecordSizeLimit
+            | TlsExtensionType::SupportedEktCiphers
+            | TlsExtensionType::QuicTransportParameters
+            | TlsExtensionType::ApplicationSettings => &[ClientHello, EncryptedExtensions],
+            TlsExtensionType::StatusRequest
+            | TlsExtensionType::SignedCertificateTimestamp
+            | TlsExtensionType::DelegatedCredential => {
+                &[ClientHello, CertificateRequest, Certificate]
+            }
+            TlsExtensionType::SignatureAlgorithms
+            | TlsExtensionType::CertificateAuthorities
+            | TlsExtensionType::SigAlgorithmsCert
+            | TlsExtensionType::CompressCertificate => &[ClientHello, CertificateRequest],
+            TlsExtensionType::KeyShare | TlsExtensionType::SupportedVersions => {
+                &[ClientHello, ServerHello, HelloRetryRequest]
+            }
+            TlsExtensionType::PreSharedKey | TlsExtensionType::ConnectionId => {
+                &[ClientHello, ServerHello]
+            }
+            TlsExtensionType::EarlyData => &[ClientHello, EncryptedExtensions, NewSessionTicket],
+            TlsExtensionType::Cookie => &[ClientHello, HelloRetryRequest],
+            TlsExtensionType::EncryptedClientHello => {
+                &[ClientHello, HelloRetryRequest, EncryptedExtensions]
+            }
+            TlsExtensionType::OidFilters => &[CertificateRequest],
+            TlsExtensionType::Padding
+            | TlsExtensionType::PskExchangeModes
+            | TlsExtensionType::PostHandshakeAuth => &[ClientHello],
+            // not defined for TLS 1.3
+            TlsExtensionType::ClientCertificate
+            | TlsExtensionType::TrustedCaKeys
+            | TlsExtensionType::TruncatedHMac
+            | TlsExtensionType::UserMapping
+            | TlsExtensionType::ClientAuthz
+            | TlsExtensionType::ServerAuthz
+            | TlsExtensionType::CertType
+            | TlsExtensionType::EcPointFormats
+            | TlsExtensionType::Srp
+            | TlsExtensionType::StatusRequestv2
+            | TlsExtensionType::EncryptThenMac
+            | TlsExtensionType::ExtendedMasterSecret
+            | TlsExtensionType::CachedInfo
+            | TlsExtensionType::SessionTicketTLS
+            | TlsExtensionType::NextProtocolNegotiation
+            | TlsExtensionType::RenegotiationInfo => &[ClientHello],
+            _ => return None,
+        };
+        Some(contexts)
+    }
 }
 
 impl From<TlsExtensionType> for u16 {
@@ -93,39 +187,128 @@ impl From<TlsExtensionType> for u16 {
     }
 }
 
+/// Handshake message carrying a list of extensions
+///
+/// Some TLS 1.3 extensions (for ex. `supported_versions`, `key_share` or `pre_shared_key`) have
+/// a different content depending on the message they are found in. When the message is known,
+/// it should be given to `parse_tls_extensions_in` so the correct format is selected, instead
+/// of guessing it from the extension length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtensionContext {
+    ClientHello,
+    ServerHello,
+    HelloRetryRequest,
+    EncryptedExtensions,
+    NewSessionTicket,
+    Certificate,
+    CertificateRequest,
+}
+
 /// TLS extensions
 ///
 #[derive(Clone, PartialEq)]
 pub enum TlsExtension<'a> {
     SNI(Vec<(SNIType, &'a [u8])>),
-    MaxFragmentLength(u8),
-    StatusRequest(Option<(CertificateStatusType, &'a [u8])>),
+    MaxFragmentLength(MaxFragmentLength),
+    /// Client certificate URLs support
+    ClientCertificateUrl,
+    /// Trusted certificate authorities (ClientHello), empty in ServerHello
+    TrustedCaKeys(Vec<TrustedAuthority<'a>>),
+    StatusRequest(Option<CertificateStatusRequest<'a>>),
+    /// Certificate status (OCSP response), sent in the `status_request` extension of a TLS 1.3
+    /// CertificateEntry
+    CertificateStatus(TlsCertificateStatusContents<'a>),
+    UserMapping(Vec<UserMappingType>),
+    /// Certificate status requests (ClientHello), empty in ServerHello
+    StatusRequestV2(Vec<CertificateStatusRequest<'a>>),
     EllipticCurves(Vec<NamedGroup>),
-    EcPointFormats(&'a [u8]),
-    SignatureAlgorithms(Vec<u16>),
-    RecordSizeLimit(u16),
+    EcPointFormats(Vec<ECPointFormat>),
+    SignatureAlgorithms(Vec<SignatureScheme>),
+    /// Signature algorithms accepted in certificates
+    SignatureAlgorithmsCert(Vec<SignatureScheme>),
+    RecordSizeLimit(RecordSizeLimit),
+    /// Cached objects (hash values are only present in ClientHello)
+    CachedInfo(Vec<CachedObject<'a>>),
+    /// SRP user identity (UTF-8 encoded)
+    Srp(&'a [u8]),
+    CompressCertificate(Vec<CertificateCompressionAlgorithm>),
     SessionTicket(&'a [u8]),
+    /// Signature algorithms accepted for delegated credentials (ClientHello, CertificateRequest)
+    DelegatedCredentials(Vec<SignatureScheme>),
+    /// Delegated credential of the end-entity certificate (Certificate)
+    DelegatedCredential(DelegatedCredential<'a>),
     KeyShareOld(&'a [u8]),
-    KeyShare(&'a [u8]),
-    PreSharedKey(&'a [u8]),
+    /// Key shares offered by the client (ClientHello)
+    KeyShare(Vec<KeyShareEntry<'a>>),
+    /// Key share selected by the server (ServerHello)
+    KeyShareServer(KeyShareEntry<'a>),
+    /// Group selected by the server (HelloRetryRequest)
+    KeyShareHelloRetryRequest(NamedGroup),
+    /// Pre-shared keys offered by the client (ClientHello)
+    PreSharedKey(PreSharedKeyOffer<'a>),
+    /// Index of the identity selected by the server (ServerHello)
+    PreSharedKeySelected(u16),
     EarlyData(Option<u32>),
     SupportedVersions(Vec<TlsVersion>),
     Cookie(&'a [u8]),
-    PskExchangeModes(Vec<u8>),
-    Heartbeat(u8),
+    PskExchangeModes(Vec<PskKeyExchangeMode>),
+    Heartbeat(HeartbeatMode),
     ALPN(Vec<&'a [u8]>),
 
-    SignedCertificateTimestamp(Option<&'a [u8]>),
+    SignedCertificateTimestamp(Option<Vec<SignedCertificateTimestamp<'a>>>),
+    /// Client certificate types supported by the client (ClientHello)
+    ClientCertificateType(Vec<CertificateType>),
+    /// Client certificate type selected by the server (ServerHello, EncryptedExtensions)
+    ClientCertificateTypeSelected(CertificateType),
+    /// Server certificate types supported by the client (ClientHello)
+    ServerCertificateType(Vec<CertificateType>),
+    /// Server certificate type selected by the server (ServerHello, EncryptedExtensions)
+    ServerCertificateTypeSelected(CertificateType),
     Padding(&'a [u8]),
     EncryptThenMac,
     ExtendedMasterSecret,
+    /// Token Binding protocol version and key parameters, as defined in [RFC8472]
+    TokenBinding {
+        major: u8,
+        minor: u8,
+        key_parameters: Vec<TokenBindingKeyParameters>,
+    },
 
+    /// DER-encoded distinguished names of the accepted certificate authorities
+    CertificateAuthorities(Vec<&'a [u8]>),
     OidFilters(Vec<OidFilter<'a>>),
     PostHandshakeAuth,
 
     NextProtocolNegotiation,
+    /// Protocols advertised by the server (ServerHello)
+    NextProtocolNegotiationServer(Vec<&'a [u8]>),
+
+    /// ALPN protocols supporting application settings (ClientHello)
+    ApplicationSettings(Vec<&'a [u8]>),
+    /// Application settings for the negotiated protocol (EncryptedExtensions)
+    ApplicationSettingsData(&'a [u8]),
+
+    /// Channel ID support (empty, ClientHello and ServerHello) or the client Channel ID
+    /// (EncryptedExtensions)
+    ChannelId(Option<ChannelId<'a>>),
+
+    /// DTLS connection ID, as defined in [RFC9146]
+    ConnectionId(&'a [u8]),
+
+    /// QUIC transport parameters, as defined in [RFC9000] section 18
+    QuicTransportParameters(Vec<QuicTransportParameter<'a>>),
 
     RenegotiationInfo(&'a [u8]),
+    /// DTLS-SRTP protection profiles and MKI, as defined in [RFC5764]
+    UseSrtp {
+        profiles: Vec<SrtpProtectionProfile>,
+        mki: &'a [u8],
+    },
+    /// EKT ciphers supported by the client (ClientHello), as defined in [RFC8870]
+    SupportedEktCiphers(Vec<EktCipherType>),
+    /// EKT cipher selected by the server (ServerHello, EncryptedExtensions)
+    SupportedEktCiphersSelected(EktCipherType),
+
     EncryptedServerName {
         ciphersuite: TlsCipherSuiteID,
         group: NamedGroup,
@@ -133,6 +316,15 @@ pub enum TlsExtension<'a> {
         record_digest: &'a [u8],
         encrypted_sni: &'a [u8],
     },
+    /// Encrypted Client Hello (ClientHello)
+    EncryptedClientHello(EchClientHello<'a>),
+    /// ECHConfigList sent by the server to retry ECH (EncryptedExtensions), see
+    /// `parse_ech_config_list`
+    EchRetryConfigs(&'a [u8]),
+    /// ECH acceptance confirmation (HelloRetryRequest)
+    EchConfirmation(&'a [u8]),
+    /// Extensions copied from the ClientHelloOuter (ClientHelloInner)
+    EchOuterExtensions(Vec<TlsExtensionType>),
 
     Grease(u16, &'a [u8]),
 
@@ -145,47 +337,185 @@ impl<'a> From<&'a TlsExtension<'a>> for TlsExtensionType {
         match *ext {
             TlsExtension::SNI(_)                        => TlsExtensionType::ServerName,
             TlsExtension::MaxFragmentLength(_)          => TlsExtensionType::MaxFragmentLength,
+            TlsExtension::ClientCertificateUrl          => TlsExtensionType::ClientCertificate,
+            TlsExtension::TrustedCaKeys(_)              => TlsExtensionType::TrustedCaKeys,
             TlsExtension::StatusRequest(_)              => TlsExtensionType::StatusRequest,
+            TlsExtension::CertificateStatus(_)          => TlsExtensionType::StatusRequest,
+            TlsExtension::UserMapping(_)                => TlsExtensionType::UserMapping,
+            TlsExtension::StatusRequestV2(_)            => TlsExtensionType::StatusRequestv2,
             TlsExtension::EllipticCurves(_)             => TlsExtensionType::SupportedGroups,
             TlsExtension::EcPointFormats(_)             => TlsExtensionType::EcPointFormats,
             TlsExtension::SignatureAlgorithms(_)        => TlsExtensionType::SignatureAlgorithms,
+            TlsExtension::SignatureAlgorithmsCert(_)    => TlsExtensionType::SigAlgorithmsCert,
             TlsExtension::SessionTicket(_)              => TlsExtensionType::SessionTicketTLS,
+            TlsExtension::DelegatedCredentials(_)       => TlsExtensionType::DelegatedCredential,
+            TlsExtension::DelegatedCredential(_)        => TlsExtensionType::DelegatedCredential,
             TlsExtension::RecordSizeLimit(_)            => TlsExtensionType::RecordSizeLimit,
+            TlsExtension::CachedInfo(_)                 => TlsExtensionType::CachedInfo,
+            TlsExtension::Srp(_)                        => TlsExtensionType::Srp,
+            TlsExtension::CompressCertificate(_)        => TlsExtensionType::CompressCertificate,
             TlsExtension::KeyShareOld(_)                => TlsExtensionType::KeyShareOld,
             TlsExtension::KeyShare(_)                   => TlsExtensionType::KeyShare,
+            TlsExtension::KeyShareServer(_)             => TlsExtensionType::KeyShare,
+            TlsExtension::KeyShareHelloRetryRequest(_)  => TlsExtensionType::KeyShare,
             TlsExtension::PreSharedKey(_)               => TlsExtensionType::PreSharedKey,
+            TlsExtension::PreSharedKeySelected(_)       => TlsExtensionType::PreSharedKey,
             TlsExtension::EarlyData(_)                  => TlsExtensionType::EarlyData,
             TlsExtension::SupportedVersions(_)          => TlsExtensionType::SupportedVersions,
             TlsExtension::Cookie(_)                     => TlsExtensionType::Cookie,
             TlsExtension::PskExchangeModes(_)           => TlsExtensionType::PskExchangeModes,
             TlsExtension::Heartbeat(_)                  => TlsExtensionType::Heartbeat,
+            TlsExtension::UseSrtp{..}                   => TlsExtensionType::UseSrtp,
+            TlsExtension::SupportedEktCiphers(_)        => TlsExtensionType::SupportedEktCiphers,
+            TlsExtension::SupportedEktCiphersSelected(_) => TlsExtensionType::SupportedEktCiphers,
             TlsExtension::ALPN(_)                       => TlsExtensionType::ApplicationLayerProtocolNegotiation,
             TlsExtension::SignedCertificateTimestamp(_) => TlsExtensionType::SignedCertificateTimestamp,
+            TlsExtension::ClientCertificateType(_)      => TlsExtensionType::ClientCertificateType,
+            TlsExtension::ClientCertificateTypeSelected(_) => TlsExtensionType::ClientCertificateType,
+            TlsExtension::ServerCertificateType(_)      => TlsExtensionType::ServerCertificateType,
+            TlsExtension::ServerCertificateTypeSelected(_) => TlsExtensionType::ServerCertificateType,
             TlsExtension::Padding(_)                    => TlsExtensionType::Padding,
             TlsExtension::EncryptThenMac                => TlsExtensionType::EncryptThenMac,
             TlsExtension::ExtendedMasterSecret          => TlsExtensionType::ExtendedMasterSecret,
+            TlsExtension::TokenBinding{..}              => TlsExtensionType::TokenBinding,
+            TlsExtension::CertificateAuthorities(_)     => TlsExtensionType::CertificateAuthorities,
             TlsExtension::OidFilters(_)                 => TlsExtensionType::OidFilters,
             TlsExtension::PostHandshakeAuth             => TlsExtensionType::PostHandshakeAuth,
             TlsExtension::NextProtocolNegotiation       => TlsExtensionType::NextProtocolNegotiation,
+            TlsExtension::NextProtocolNegotiationServer(_) => TlsExtensionType::NextProtocolNegotiation,
+            TlsExtension::ApplicationSettings(_)        => TlsExtensionType::ApplicationSettings,
+            TlsExtension::ApplicationSettingsData(_)    => TlsExtensionType::ApplicationSettings,
+            TlsExtension::ChannelId(_)                  => TlsExtensionType::ChannelId,
+            TlsExtension::ConnectionId(_)               => TlsExtensionType::ConnectionId,
+            TlsExtension::QuicTransportParameters(_)    => TlsExtensionType::QuicTransportParameters,
             TlsExtension::RenegotiationInfo(_)          => TlsExtensionType::RenegotiationInfo,
             TlsExtension::EncryptedServerName{..}       => TlsExtensionType::EncryptedServerName,
+            TlsExtension::EncryptedClientHello(_)       => TlsExtensionType::EncryptedClientHello,
+            TlsExtension::EchRetryConfigs(_)            => TlsExtensionType::EncryptedClientHello,
+            TlsExtension::EchConfirmation(_)            => TlsExtensionType::EncryptedClientHello,
+            TlsExtension::EchOuterExtensions(_)         => TlsExtensionType::EchOuterExtensions,
             TlsExtension::Grease(_,_)                   => TlsExtensionType::Grease,
             TlsExtension::Unknown(x,_)                  => x
         }
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// Key share entry, as defined in [RFC8446](https://tools.ietf.org/html/rfc8446) section 4.2.8
+#[derive(Clone, PartialEq)]
 pub struct KeyShareEntry<'a> {
     pub group: NamedGroup, // NamedGroup
     pub kx: &'a [u8],      // Key Exchange Data
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Nom)]
+/// PSK identity, as defined in [RFC8446](https://tools.ietf.org/html/rfc8446) section 4.2.11
+#[derive(Clone, PartialEq)]
+pub struct PskIdentity<'a> {
+    pub identity: &'a [u8],
+    pub obfuscated_ticket_age: u32,
+}
+
+/// Pre-shared keys offered by the client, as defined in
+/// [RFC8446](https://tools.ietf.org/html/rfc8446) section 4.2.11
+///
+/// Binders are stored in the same order as identities.
+#[derive(Clone, PartialEq)]
+pub struct PreSharedKeyOffer<'a> {
+    pub identities: Vec<PskIdentity<'a>>,
+    pub binders: Vec<&'a [u8]>,
+}
+
+/// Delegated credential, as defined in [RFC9345](https://tools.ietf.org/html/rfc9345) section 4
+#[derive(Clone, PartialEq)]
+pub struct DelegatedCredential<'a> {
+    /// Validity time, in seconds from the notBefore time of the delegation certificate
+    pub valid_time: u32,
+    pub expected_cert_verify_algorithm: SignatureScheme,
+    /// DER-encoded SubjectPublicKeyInfo
+    pub asn1_subject_public_key_info: &'a [u8],
+    pub algorithm: SignatureScheme,
+    pub signature: &'a [u8],
+}
+
+/// Signed Certificate Timestamp, as defined in [RFC6962](https://tools.ietf.org/html/rfc6962)
+/// section 3.2
+#[derive(Clone, PartialEq)]
+pub struct SignedCertificateTimestamp<'a> {
+    pub version: u8,
+    /// SHA-256 hash of the log's public key
+    pub id: &'a [u8],
+    /// Milliseconds since the epoch
+    pub timestamp: u64,
+    pub extensions: &'a [u8],
+    pub signature: DigitallySigned<'a>,
+}
+
+/// Client Channel ID, as defined in
+/// [draft-balfanz-tls-channelid](https://tools.ietf.org/html/draft-balfanz-tls-channelid-01)
+///
+/// The key is a P-256 public key, and the signature is an ECDSA signature of the handshake
+/// hash.
+#[derive(Clone, PartialEq)]
+pub struct ChannelId<'a> {
+    pub x: &'a [u8],
+    pub y: &'a [u8],
+    pub r: &'a [u8],
+    pub s: &'a [u8],
+}
+
+/// SRTP protection profile, as defined in the [IANA DTLS-SRTP Protection
+/// Profiles](https://www.iana.org/assignments/srtp-protection/srtp-protection.xhtml) registry
+#[derive(Clone, Copy, PartialEq, Eq, Nom)]
+pub struct SrtpProtectionProfile(pub u16);
+
+newtype_enum! {
+impl debug SrtpProtectionProfile {
+    SRTP_AES128_CM_HMAC_SHA1_80                 = 0x0001, // [RFC5764]
+    SRTP_AES128_CM_HMAC_SHA1_32                 = 0x0002,
+    SRTP_NULL_HMAC_SHA1_80                      = 0x0005,
+    SRTP_NULL_HMAC_SHA1_32                      = 0x0006,
+    SRTP_AEAD_AES_128_GCM                       = 0x0007, // [RFC7714]
+    SRTP_AEAD_AES_256_GCM                       = 0x0008,
+    DOUBLE_AEAD_AES_128_GCM_AEAD_AES_128_GCM    = 0x0009, // [RFC8723]
+    DOUBLE_AEAD_AES_256_GCM_AEAD_AES_256_GCM    = 0x000a,
+    SRTP_ARIA_128_CTR_HMAC_SHA1_80              = 0x000b, // [RFC8269]
+    SRTP_ARIA_128_CTR_HMAC_SHA1_32              = 0x000c,
+    SRTP_ARIA_256_CTR_HMAC_SHA1_80              = 0x000d,
+    SRTP_ARIA_256_CTR_HMAC_SHA1_32              = 0x000e,
+    SRTP_AEAD_ARIA_128_GCM                      = 0x000f,
+    SRTP_AEAD_ARIA_256_GCM                      = 0x0010,
+}
+}
+
+/// EKT cipher type, as defined in [RFC8870](https://tools.ietf.org/html/rfc8870) section 5.2
+#[derive(Clone, Copy, PartialEq, Eq, Nom)]
+pub struct EktCipherType(pub u8);
+
+newtype_enum! {
+impl debug EktCipherType {
+    AesKeyWrap128 = 1,
+    AesKeyWrap256 = 2,
+}
+}
+
+/// Token Binding key parameters, as defined in [RFC8472](https://tools.ietf.org/html/rfc8472)
+#[derive(Clone, Copy, PartialEq, Eq, Nom)]
+pub struct TokenBindingKeyParameters(pub u8);
+
+newtype_enum! {
+impl debug TokenBindingKeyParameters {
+    Rsa2048Pkcs15 = 0,
+    Rsa2048Pss    = 1,
+    EcdsaP256     = 2,
+}
+}
+
+/// PSK key exchange mode, as defined in [RFC8446](https://tools.ietf.org/html/rfc8446)
+/// section 4.2.9
+#[derive(Clone, Copy, PartialEq, Eq, Nom)]
 pub struct PskKeyExchangeMode(pub u8);
 
 newtype_enum! {
-impl PskKeyExchangeMode {
+impl debug PskKeyExchangeMode {
     Psk    = 0,
     PskDhe = 1,
 }
@@ -200,13 +530,204 @@ impl display SNIType {
 }
 }
 
+/// Maximum fragment length, as defined in [RFC6066](https://tools.ietf.org/html/rfc6066)
+/// section 4
+///
+/// Values not defined by the RFC are kept in `Other`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaxFragmentLength {
+    /// 2^9 bytes
+    Length512,
+    /// 2^10 bytes
+    Length1024,
+    /// 2^11 bytes
+    Length2048,
+    /// 2^12 bytes
+    Length4096,
+    Other(u8),
+}
+
+impl MaxFragmentLength {
+    /// Get the maximum fragment length in bytes, or `None` for an illegal value
+    pub fn length(self) -> Option<usize> {
+        match self {
+            MaxFragmentLength::Length512 => Some(1 << 9),
+            MaxFragmentLength::Length1024 => Some(1 << 10),
+            MaxFragmentLength::Length2048 => Some(1 << 11),
+            MaxFragmentLength::Length4096 => Some(1 << 12),
+            MaxFragmentLength::Other(_) => None,
+        }
+    }
+}
+
+impl From<u8> for MaxFragmentLength {
+    fn from(v: u8) -> MaxFragmentLength {
+        match v {
+            1 => MaxFragmentLength::Length512,
+            2 => MaxFragmentLength::Length1024,
+            3 => MaxFragmentLength::Length2048,
+            4 => MaxFragmentLength::Length4096,
+            _ => MaxFragmentLength::Other(v),
+        }
+    }
+}
+
+impl From<MaxFragmentLength> for u8 {
+    fn from(v: MaxFragmentLength) -> u8 {
+        match v {
+            MaxFragmentLength::Length512 => 1,
+            MaxFragmentLength::Length1024 => 2,
+            MaxFragmentLength::Length2048 => 3,
+            MaxFragmentLength::Length4096 => 4,
+            MaxFragmentLength::Other(v) => v,
+        }
+    }
+}
+
+/// Record size limit, as defined in [RFC8449](https://tools.ietf.org/html/rfc8449)
+///
+/// For TLS 1.3, the limit includes the content type and padding of the inner plaintext.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Nom)]
+pub struct RecordSizeLimit(pub u16);
+
+impl RecordSizeLimit {
+    /// Smallest legal value
+    pub const MIN: u16 = 64;
+    /// Largest value for TLS 1.2 and earlier versions (2^14)
+    pub const MAX_TLS12: u16 = 1 << 14;
+    /// Largest value for TLS 1.3 (2^14 + 1)
+    pub const MAX_TLS13: u16 = (1 << 14) + 1;
+
+    /// Test if the value respects the RFC8449 bounds for the negotiated protocol version
+    ///
+    /// Values smaller than 64 are always illegal. Note that a client may legitimately
+    /// advertise a larger value than the protocol maximum, since it does not know the version
+    /// that will be negotiated, so the upper bound should only be checked on the server value.
+    pub fn is_valid(self, tls13: bool) -> bool {
+        let max = if tls13 {
+            Self::MAX_TLS13
+        } else {
+            Self::MAX_TLS12
+        };
+        self.0 >= Self::MIN && self.0 <= max
+    }
+}
+
+/// Heartbeat mode, as defined in [RFC6520](https://tools.ietf.org/html/rfc6520) section 2
+#[derive(Clone, Copy, PartialEq, Eq, Nom)]
+pub struct HeartbeatMode(pub u8);
+
+newtype_enum! {
+impl debug HeartbeatMode {
+    PeerAllowedToSend    = 1,
+    PeerNotAllowedToSend = 2,
+}
+}
+
+/// User mapping type, as defined in [RFC4681](https://tools.ietf.org/html/rfc4681)
+#[derive(Clone, Copy, PartialEq, Eq, Nom)]
+pub struct UserMappingType(pub u8);
+
+newtype_enum! {
+impl debug UserMappingType {
+    UpnDomainHint = 64,
+}
+}
+
+/// Certificate type, as defined in the [IANA TLS Certificate
+/// Types](https://www.iana.org/assignments/tls-extensiontype-values/tls-extensiontype-values.xhtml#tls-extensiontype-values-3)
+/// registry
+#[derive(Clone, Copy, PartialEq, Eq, Nom)]
+pub struct CertificateType(pub u8);
+
+newtype_enum! {
+impl debug CertificateType {
+    X509         = 0,
+    OpenPGP      = 1, // [RFC6091]
+    RawPublicKey = 2, // [RFC7250]
+    Ieee1609Dot2 = 3, // [RFC8902]
+}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Nom)]
+pub struct CachedInformationType(pub u8);
+
+newtype_enum! {
+impl debug CachedInformationType {
+    Cert    = 1,
+    CertReq = 2,
+}
+}
+
+/// Cached object, as defined in [RFC7924](https://tools.ietf.org/html/rfc7924) section 3
+///
+/// The hash value is only sent by the client.
+#[derive(Clone, PartialEq)]
+pub struct CachedObject<'a> {
+    pub cached_type: CachedInformationType,
+    pub hash_value: Option<&'a [u8]>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Nom)]
+pub struct TrustedAuthorityIdentifierType(pub u8);
+
+newtype_enum! {
+impl debug TrustedAuthorityIdentifierType {
+    PreAgreed    = 0,
+    KeySha1Hash  = 1,
+    X509Name     = 2,
+    CertSha1Hash = 3,
+}
+}
+
+/// Trusted certificate authority, as defined in [RFC6066](https://tools.ietf.org/html/rfc6066)
+/// section 6
+///
+/// The identifier is empty (`PreAgreed`), a SHA-1 hash (`KeySha1Hash`, `CertSha1Hash`) or a
+/// DER-encoded distinguished name (`X509Name`).
+#[derive(Clone, PartialEq)]
+pub struct TrustedAuthority<'a> {
+    pub identifier_type: TrustedAuthorityIdentifierType,
+    pub identifier: &'a [u8],
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Nom)]
 pub struct CertificateStatusType(pub u8);
 
 newtype_enum! {
 impl debug CertificateStatusType {
-    OCSP = 1,
+    OCSP      = 1,
+    OCSPMulti = 2, // [RFC6961]
+}
+}
+
+/// OCSP status request, as defined in [RFC6066](https://tools.ietf.org/html/rfc6066) section 8
+///
+/// Responder IDs and request extensions are DER-encoded.
+#[derive(Clone, PartialEq)]
+pub struct OcspStatusRequest<'a> {
+    pub responder_id_list: Vec<&'a [u8]>,
+    pub request_extensions: &'a [u8],
 }
+
+/// Certificate status request, as sent in the `status_request` and `status_request_v2`
+/// extensions
+#[derive(Clone, Debug, PartialEq)]
+pub enum CertificateStatusRequest<'a> {
+    Ocsp(OcspStatusRequest<'a>),
+    /// Request for the status of all certificates of the chain ([RFC6961])
+    OcspMulti(OcspStatusRequest<'a>),
+    Unknown(CertificateStatusType, &'a [u8]),
+}
+
+impl<'a> CertificateStatusRequest<'a> {
+    pub fn status_type(&self) -> CertificateStatusType {
+        match *self {
+            CertificateStatusRequest::Ocsp(_) => CertificateStatusType::OCSP,
+            CertificateStatusRequest::OcspMulti(_) => CertificateStatusType::OCSPMulti,
+            CertificateStatusRequest::Unknown(status_type, _) => status_type,
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -215,6 +736,65 @@ pub struct OidFilter<'a> {
     pub cert_ext_val: &'a [u8],
 }
 
+/// QUIC transport parameter identifier, as defined in the [IANA QUIC Transport Parameters
+/// Registry](https://www.iana.org/assignments/quic/quic.xhtml#quic-transport)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuicTransportParameterId(pub u64);
+
+newtype_enum! {
+impl display QuicTransportParameterId {
+    OriginalDestinationConnectionId = 0x00, // [RFC9000]
+    MaxIdleTimeout                  = 0x01,
+    StatelessResetToken             = 0x02,
+    MaxUdpPayloadSize               = 0x03,
+    InitialMaxData                  = 0x04,
+    InitialMaxStreamDataBidiLocal   = 0x05,
+    InitialMaxStreamDataBidiRemote  = 0x06,
+    InitialMaxStreamDataUni         = 0x07,
+    InitialMaxStreamsBidi           = 0x08,
+    InitialMaxStreamsUni            = 0x09,
+    AckDelayExponent                = 0x0a,
+    MaxAckDelay                     = 0x0b,
+    DisableActiveMigration          = 0x0c,
+    PreferredAddress                = 0x0d,
+    ActiveConnectionIdLimit         = 0x0e,
+    InitialSourceConnectionId       = 0x0f,
+    RetrySourceConnectionId         = 0x10,
+    VersionInformation              = 0x11, // [RFC9368]
+    MaxDatagramFrameSize            = 0x20, // [RFC9221]
+    GreaseQuicBit                   = 0x2ab2, // [RFC9287]
+}
+}
+
+impl QuicTransportParameterId {
+    /// Test if the parameter value is encoded as a variable-length integer
+    pub fn is_integer(self) -> bool {
+        matches!(self.0, 0x01 | 0x03..=0x0b | 0x0e | 0x20)
+    }
+}
+
+/// QUIC transport parameter, as defined in [RFC9000](https://tools.ietf.org/html/rfc9000)
+/// section 18
+///
+/// The value is not interpreted. For integer parameters, use `integer_value`.
+#[derive(Clone, PartialEq)]
+pub struct QuicTransportParameter<'a> {
+    pub id: QuicTransportParameterId,
+    pub value: &'a [u8],
+}
+
+impl<'a> QuicTransportParameter<'a> {
+    /// Decode the value as a variable-length integer
+    ///
+    /// Returns `None` if the value is not a valid integer encoding.
+    pub fn integer_value(&self) -> Option<u64> {
+        match parse_quic_varint(self.value) {
+            Ok(([], v)) => Some(v),
+            _ => None,
+        }
+    }
+}
+
 // struct {
 //     NameType name_type;
 //     select (name_type) {
@@ -250,9 +830,127 @@ pub fn parse_tls_extension_sni(i: &[u8]) -> IResult<&[u8], TlsExtension> {
     map_parser(length_data(be_u16), parse_tls_extension_sni_content)(i)
 }
 
+/// Error returned when converting a SNI host name to a string
+#[derive(Debug, PartialEq)]
+pub enum SniError {
+    /// The name type is not `host_name`
+    UnsupportedNameType(SNIType),
+    /// The host name is empty
+    EmptyHostName,
+    /// The host name contains non-ASCII bytes
+    NotAscii,
+    /// The host name contains an invalid internationalized (`xn--`) label
+    ///
+    /// This error is only returned by functions of the `idna` feature.
+    InvalidIdna,
+}
+
+/// Get a SNI entry as a host name string
+///
+/// [RFC6066](https://tools.ietf.org/html/rfc6066) section 3 requires host names to be
+/// ASCII-encoded, so any other byte is an error. Internationalized names are sent as A-labels
+/// (`xn--...`), and are returned unchanged.
+pub fn sni_host_name(name_type: SNIType, name: &[u8]) -> Result<&str, SniError> {
+    if name_type != SNIType::HostName {
+        return Err(SniError::UnsupportedNameType(name_type));
+    }
+    if name.is_empty() {
+        return Err(SniError::EmptyHostName);
+    }
+    if !name.is_ascii() {
+        return Err(SniError::NotAscii);
+    }
+    // ASCII is always valid UTF-8
+    std::str::from_utf8(name).or(Err(SniError::NotAscii))
+}
+
+/// Get all host names from the content of a `server_name` extension (`TlsExtension::SNI`)
+///
+/// Entries with a name type other than `host_name` are ignored. An error is returned if any
+/// host name is invalid (see `sni_host_name`).
+pub fn sni_host_names<'a>(v: &[(SNIType, &'a [u8])]) -> Result<Vec<&'a str>, SniError> {
+    v.iter()
+        .filter(|(t, _)| *t == SNIType::HostName)
+        .map(|&(t, name)| sni_host_name(t, name))
+        .collect()
+}
+
+/// Client certificate URLs [RFC6066]
+fn parse_tls_extension_client_certificate_url_content(
+    i: &[u8],
+    ext_len: u16,
+) -> IResult<&[u8], TlsExtension> {
+    if ext_len != 0 {
+        return Err(Err::Error(make_error(i, ErrorKind::Verify)));
+    }
+    Ok((i, TlsExtension::ClientCertificateUrl))
+}
+
+// struct {
+//     IdentifierType identifier_type;
+//     select (identifier_type) {
+//         case pre_agreed: struct {};
+//         case key_sha1_hash: SHA1Hash;
+//         case x509_name: DistinguishedName;
+//         case cert_sha1_hash: SHA1Hash;
+//     } identifier;
+// } TrustedAuthority;
+fn parse_trusted_authority(i: &[u8]) -> IResult<&[u8], TrustedAuthority> {
+    let (i, identifier_type) = TrustedAuthorityIdentifierType::parse(i)?;
+    let (i, identifier) = match identifier_type {
+        TrustedAuthorityIdentifierType::PreAgreed => (i, &i[..0]),
+        TrustedAuthorityIdentifierType::KeySha1Hash
+        | TrustedAuthorityIdentifierType::CertSha1Hash => take(20usize)(i)?,
+        TrustedAuthorityIdentifierType::X509Name => length_data(be_u16)(i)?,
+        _ => return Err(Err::Error(make_error(i, ErrorKind::Switch))),
+    };
+    let authority = TrustedAuthority {
+        identifier_type,
+        identifier,
+    };
+    Ok((i, authority))
+}
+
+/// Trusted CA indication [RFC6066]
+//
+// struct {
+//     TrustedAuthority trusted_authorities_list<0..2^16-1>;
+// } TrustedAuthorities;
+fn parse_tls_extension_trusted_ca_keys_content(
+    i: &[u8],
+    ext_len: u16,
+) -> IResult<&[u8], TlsExtension> {
+    match ext_len {
+        0 => Ok((i, TlsExtension::TrustedCaKeys(Vec::new()))),
+        _ => map(
+            map_parser(
+                length_data(be_u16),
+                many0(complete(parse_trusted_authority)),
+            ),
+            TlsExtension::TrustedCaKeys,
+        )(i),
+    }
+}
+
+/// User mapping [RFC4681]
+//
+// struct {
+//     UserMappingType user_mapping_types<1..2^8-1>;
+// } UserMappingTypeList;
+fn parse_tls_extension_user_mapping_content(i: &[u8]) -> IResult<&[u8], TlsExtension> {
+    let (i, v) = map_parser(length_data(be_u8), many0(complete(UserMappingType::parse)))(i)?;
+    Ok((i, TlsExtension::UserMapping(v)))
+}
+
 /// Max fragment length [RFC6066]
+//
+// enum{
+//     2^9(1), 2^10(2), 2^11(3), 2^12(4), (255)
+// } MaxFragmentLength;
 pub fn parse_tls_extension_max_fragment_length_content(i: &[u8]) -> IResult<&[u8], TlsExtension> {
-    map(be_u8, TlsExtension::MaxFragmentLength)(i)
+    map(be_u8, |v| {
+        TlsExtension::MaxFragmentLength(MaxFragmentLength::from(v))
+    })(i)
 }
 
 /// Max fragment length [RFC6066]
@@ -264,21 +962,104 @@ pub fn parse_tls_extension_max_fragment_length(i: &[u8]) -> IResult<&[u8], TlsEx
     )(i)
 }
 
+// opaque ResponderID<1..2^16-1>;
+// opaque Extensions<0..2^16-1>;
+//
+// struct {
+//     ResponderID responder_id_list<0..2^16-1>;
+//     Extensions  request_extensions;
+// } OCSPStatusRequest;
+pub fn parse_ocsp_status_request(i: &[u8]) -> IResult<&[u8], OcspStatusRequest> {
+    let (i, responder_id_list) =
+        map_parser(length_data(be_u16), many0(complete(length_data(be_u16))))(i)?;
+    let (i, request_extensions) = length_data(be_u16)(i)?;
+    let request = OcspStatusRequest {
+        responder_id_list,
+        request_extensions,
+    };
+    Ok((i, request))
+}
+
+fn parse_certificate_status_request_body(
+    i: &[u8],
+    status_type: CertificateStatusType,
+) -> IResult<&[u8], CertificateStatusRequest> {
+    match status_type {
+        CertificateStatusType::OCSP => {
+            map(parse_ocsp_status_request, CertificateStatusRequest::Ocsp)(i)
+        }
+        CertificateStatusType::OCSPMulti => map(
+            parse_ocsp_status_request,
+            CertificateStatusRequest::OcspMulti,
+        )(i),
+        _ => map(rest, |d| CertificateStatusRequest::Unknown(status_type, d))(i),
+    }
+}
+
+// struct {
+//     CertificateStatusType status_type;
+//     select (status_type) {
+//         case ocsp: OCSPStatusRequest;
+//     } request;
+// } CertificateStatusRequest;
+fn parse_certificate_status_request(i: &[u8]) -> IResult<&[u8], CertificateStatusRequest> {
+    let (i, status_type) = CertificateStatusType::parse(i)?;
+    parse_certificate_status_request_body(i, status_type)
+}
+
+// struct {
+//     CertificateStatusType status_type;
+//     uint16 request_length; /* Length of request field in bytes */
+//     select (status_type) {
+//         case ocsp: OCSPStatusRequest;
+//         case ocsp_multi: OCSPStatusRequest;
+//     } request;
+// } CertificateStatusRequestItemV2;
+fn parse_certificate_status_request_item_v2(i: &[u8]) -> IResult<&[u8], CertificateStatusRequest> {
+    let (i, status_type) = CertificateStatusType::parse(i)?;
+    map_parser(length_data(be_u16), move |d| {
+        parse_certificate_status_request_body(d, status_type)
+    })(i)
+}
+
 /// Status Request [RFC6066]
 fn parse_tls_extension_status_request_content(
     i: &[u8],
     ext_len: u16,
+    ctx: Option<ExtensionContext>,
 ) -> IResult<&[u8], TlsExtension> {
-    match ext_len {
-        0 => Ok((i, TlsExtension::StatusRequest(None))),
-        _ => {
-            let (i, status_type) = be_u8(i)?;
-            let (i, request) = take(ext_len - 1)(i)?;
-            Ok((
-                i,
-                TlsExtension::StatusRequest(Some((CertificateStatusType(status_type), request))),
-            ))
+    match (ctx, ext_len) {
+        // TLS 1.3: the OCSP response is sent in the certificate entry (RFC8446 section 4.4.2.1)
+        (Some(ExtensionContext::Certificate), _) => {
+            map(parse_certificate_status, TlsExtension::CertificateStatus)(i)
         }
+        (_, 0) => Ok((i, TlsExtension::StatusRequest(None))),
+        // XXX if the context is unknown, a CertificateStatus is parsed as a request
+        _ => map(parse_certificate_status_request, |r| {
+            TlsExtension::StatusRequest(Some(r))
+        })(i),
+    }
+}
+
+/// Multiple Certificate Status Request [RFC6961]
+//
+// struct {
+//     CertificateStatusRequestItemV2
+//                      certificate_status_req_list<1..2^16-1>;
+// } CertificateStatusRequestListV2;
+fn parse_tls_extension_status_request_v2_content(
+    i: &[u8],
+    ext_len: u16,
+) -> IResult<&[u8], TlsExtension> {
+    match ext_len {
+        0 => Ok((i, TlsExtension::StatusRequestV2(Vec::new()))),
+        _ => map(
+            map_parser(
+                length_data(be_u16),
+                many0(complete(parse_certificate_status_request_item_v2)),
+            ),
+            TlsExtension::StatusRequestV2,
+        )(i),
     }
 }
 
@@ -286,7 +1067,7 @@ pub fn parse_tls_extension_status_request(i: &[u8]) -> IResult<&[u8], TlsExtensi
     let (i, _) = tag([0x00, 0x05])(i)?;
     let (i, ext_len) = be_u16(i)?;
     map_parser(take(ext_len), move |d| {
-        parse_tls_extension_status_request_content(d, ext_len)
+        parse_tls_extension_status_request_content(d, ext_len, None)
     })(i)
 }
 
@@ -305,21 +1086,37 @@ pub fn parse_tls_extension_elliptic_curves(i: &[u8]) -> IResult<&[u8], TlsExtens
     )(i)
 }
 
+// struct {
+//     ECPointFormat ec_point_format_list<1..2^8-1>
+// } ECPointFormatList;
 pub fn parse_tls_extension_ec_point_formats_content(i: &[u8]) -> IResult<&[u8], TlsExtension> {
-    map(length_data(be_u8), TlsExtension::EcPointFormats)(i)
+    let (i, v) = map_parser(length_data(be_u8), many0(complete(ECPointFormat::parse)))(i)?;
+    Ok((i, TlsExtension::EcPointFormats(v)))
 }
 
 pub fn parse_tls_extension_ec_point_formats(i: &[u8]) -> IResult<&[u8], TlsExtension> {
-    let (i, _) = tag([0x00, 0x0a])(i)?;
+    let (i, _) = tag([0x00, 0x0b])(i)?;
     map_parser(
         length_data(be_u16),
         parse_tls_extension_ec_point_formats_content,
     )(i)
 }
 
+/// SRP identity, defined in [RFC5054]
+//
+// opaque srp_I<1..2^8-1>;
+fn parse_tls_extension_srp_content(i: &[u8]) -> IResult<&[u8], TlsExtension> {
+    map(length_data(be_u8), TlsExtension::Srp)(i)
+}
+
+// SignatureScheme supported_signature_algorithms<2..2^16-2>;
+fn parse_signature_scheme_list(i: &[u8]) -> IResult<&[u8], Vec<SignatureScheme>> {
+    map_parser(length_data(be_u16), many0(complete(SignatureScheme::parse)))(i)
+}
+
 pub fn parse_tls_extension_signature_algorithms_content(i: &[u8]) -> IResult<&[u8], TlsExtension> {
-    let (i, l) = map_parser(length_data(be_u16), many0(complete(be_u16)))(i)?;
-    Ok((i, TlsExtension::SignatureAlgorithms(l))) // XXX SignatureAlgorithms or SignatureScheme
+    let (i, l) = parse_signature_scheme_list(i)?;
+    Ok((i, TlsExtension::SignatureAlgorithms(l)))
 }
 
 pub fn parse_tls_extension_signature_algorithms(i: &[u8]) -> IResult<&[u8], TlsExtension> {
@@ -330,12 +1127,75 @@ pub fn parse_tls_extension_signature_algorithms(i: &[u8]) -> IResult<&[u8], TlsE
     )(i)
 }
 
+/// Use SRTP, defined in [RFC5764]
+//
+// uint8 SRTPProtectionProfile[2];
+//
+// struct {
+//    SRTPProtectionProfiles SRTPProtectionProfiles;
+//    opaque srtp_mki<0..255>;
+// } UseSRTPData;
+//
+// SRTPProtectionProfile SRTPProtectionProfiles<2..2^16-1>;
+fn parse_tls_extension_use_srtp_content(i: &[u8]) -> IResult<&[u8], TlsExtension> {
+    let (i, profiles) = map_parser(
+        length_data(be_u16),
+        many0(complete(SrtpProtectionProfile::parse)),
+    )(i)?;
+    let (i, mki) = length_data(be_u8)(i)?;
+    Ok((i, TlsExtension::UseSrtp { profiles, mki }))
+}
+
+/// Supported EKT ciphers, defined in [RFC8870]
+//
+// struct {
+//     select (Handshake.msg_type) {
+//         case client_hello:
+//             EKTCipherType supported_ciphers<1..255>;
+//         case server_hello:
+//             EKTCipherType selected_cipher;
+//         case encrypted_extensions:
+//             EKTCipherType selected_cipher;
+//     };
+// } SupportedEKTCiphers;
+fn parse_tls_extension_supported_ekt_ciphers_content(
+    i: &[u8],
+    ext_len: u16,
+    ctx: Option<ExtensionContext>,
+) -> IResult<&[u8], TlsExtension> {
+    let parse_offered = map_parser(length_data(be_u8), many0(complete(EktCipherType::parse)));
+    match ctx {
+        Some(ExtensionContext::ClientHello) => {
+            map(parse_offered, TlsExtension::SupportedEktCiphers)(i)
+        }
+        Some(ExtensionContext::ServerHello) | Some(ExtensionContext::EncryptedExtensions) => {
+            map(
+                EktCipherType::parse,
+                TlsExtension::SupportedEktCiphersSelected,
+            )(i)
+        }
+        Some(_) => Err(Err::Error(make_error(i, ErrorKind::Verify))),
+        // XXX the content depends on the current message type
+        // a list has at least 2 bytes (length and one item)
+        None if ext_len == 1 => map(
+            EktCipherType::parse,
+            TlsExtension::SupportedEktCiphersSelected,
+        )(i),
+        None => map(parse_offered, TlsExtension::SupportedEktCiphers)(i),
+    }
+}
+
+/// Heartbeat [RFC6520]
+//
+// struct {
+//    HeartbeatMode mode;
+// } HeartbeatExtension;
 pub fn parse_tls_extension_heartbeat_content(i: &[u8]) -> IResult<&[u8], TlsExtension> {
-    map(be_u8, TlsExtension::Heartbeat)(i)
+    map(HeartbeatMode::parse, TlsExtension::Heartbeat)(i)
 }
 
 pub fn parse_tls_extension_heartbeat(i: &[u8]) -> IResult<&[u8], TlsExtension> {
-    let (i, _) = tag([0x00, 0x0d])(i)?;
+    let (i, _) = tag([0x00, 0x0f])(i)?;
     let (i, ext_len) = verify(be_u16, |&n| n == 1)(i)?;
     map_parser(take(ext_len), parse_tls_extension_heartbeat_content)(i)
 }
@@ -344,9 +1204,14 @@ fn parse_protocol_name(i: &[u8]) -> IResult<&[u8], &[u8]> {
     length_data(be_u8)(i)
 }
 
+// ProtocolName protocol_name_list<2..2^16-1>
+fn parse_protocol_name_list(i: &[u8]) -> IResult<&[u8], Vec<&[u8]>> {
+    map_parser(length_data(be_u16), many0(complete(parse_protocol_name)))(i)
+}
+
 /// Defined in [RFC7301]
 pub fn parse_tls_extension_alpn_content(i: &[u8]) -> IResult<&[u8], TlsExtension> {
-    let (i, v) = map_parser(length_data(be_u16), many0(complete(parse_protocol_name)))(i)?;
+    let (i, v) = parse_protocol_name_list(i)?;
     Ok((i, TlsExtension::ALPN(v)))
 }
 
@@ -355,16 +1220,127 @@ fn parse_tls_extension_padding_content(i: &[u8], ext_len: u16) -> IResult<&[u8],
     map(take(ext_len), TlsExtension::Padding)(i)
 }
 
+// struct {
+//     Version sct_version;
+//     LogID id;
+//     uint64 timestamp;
+//     CtExtensions extensions;
+//     digitally-signed struct {
+//         ...
+//     };
+// } SignedCertificateTimestamp;
+fn parse_signed_certificate_timestamp(i: &[u8]) -> IResult<&[u8], SignedCertificateTimestamp> {
+    let (i, version) = be_u8(i)?;
+    let (i, id) = take(32usize)(i)?;
+    let (i, timestamp) = be_u64(i)?;
+    let (i, extensions) = length_data(be_u16)(i)?;
+    let (i, signature) = parse_digitally_signed(i)?;
+    let sct = SignedCertificateTimestamp {
+        version,
+        id,
+        timestamp,
+        extensions,
+        signature,
+    };
+    Ok((i, sct))
+}
+
+/// Parse a SignedCertificateTimestampList, as defined in [RFC6962] section 3.3
+///
+/// The same list is also found in the X.509v3 SCT extension and in OCSP responses. An error
+/// is returned if one of the entries is malformed.
+///
+/// ```text
+/// opaque SerializedSCT<1..2^16-1>;
+///
+/// struct {
+///     SerializedSCT sct_list <1..2^16-1>;
+/// } SignedCertificateTimestampList;
+/// ```
+pub fn parse_signed_certificate_timestamp_list(
+    i: &[u8],
+) -> IResult<&[u8], Vec<SignedCertificateTimestamp>> {
+    map_parser(
+        length_data(be_u16),
+        all_consuming(many0(complete(map_parser(
+            length_data(be_u16),
+            parse_signed_certificate_timestamp,
+        )))),
+    )(i)
+}
+
 /// Defined in [RFC6962]
 pub fn parse_tls_extension_signed_certificate_timestamp_content(
     i: &[u8],
 ) -> IResult<&[u8], TlsExtension> {
+    // empty in the ClientHello
     map(
-        opt(complete(length_data(be_u16))),
+        cond(!i.is_empty(), parse_signed_certificate_timestamp_list),
         TlsExtension::SignedCertificateTimestamp,
     )(i)
 }
 
+// struct {
+//     select(ClientOrServerExtension) {
+//         case client:
+//           CertificateType client_certificate_types<1..2^8-1>;
+//         case server:
+//           CertificateType client_certificate_type;
+//     }
+// } ClientCertTypeExtension;
+//
+// (ServerCertTypeExtension has the same format)
+fn parse_certificate_type_content<'a>(
+    i: &'a [u8],
+    ext_len: u16,
+    ctx: Option<ExtensionContext>,
+    offered: fn(Vec<CertificateType>) -> TlsExtension<'a>,
+    selected: fn(CertificateType) -> TlsExtension<'a>,
+) -> IResult<&'a [u8], TlsExtension<'a>> {
+    let parse_offered = map_parser(length_data(be_u8), many0(complete(CertificateType::parse)));
+    match ctx {
+        Some(ExtensionContext::ClientHello) => map(parse_offered, offered)(i),
+        Some(ExtensionContext::ServerHello) | Some(ExtensionContext::EncryptedExtensions) => {
+            map(CertificateType::parse, selected)(i)
+        }
+        Some(_) => Err(Err::Error(make_error(i, ErrorKind::Verify))),
+        // XXX the content depends on the current message type
+        // a list has at least 2 bytes (length and one item)
+        None if ext_len == 1 => map(CertificateType::parse, selected)(i),
+        None => map(parse_offered, offered)(i),
+    }
+}
+
+/// Client certificate type, defined in [RFC7250]
+fn parse_tls_extension_client_certificate_type_content(
+    i: &[u8],
+    ext_len: u16,
+    ctx: Option<ExtensionContext>,
+) -> IResult<&[u8], TlsExtension> {
+    parse_certificate_type_content(
+        i,
+        ext_len,
+        ctx,
+        TlsExtension::ClientCertificateType,
+        TlsExtension::ClientCertificateTypeSelected,
+    )
+}
+
+/// Server certificate type, defined in [RFC7250]
+fn parse_tls_extension_server_certificate_type_content(
+    i: &[u8],
+    ext_len: u16,
+    ctx: Option<ExtensionContext>,
+) -> IResult<&[u8], TlsExtension> {
+    parse_certificate_type_content(
+        i,
+        ext_len,
+        ctx,
+        TlsExtension::ServerCertificateType,
+        TlsExtension::ServerCertificateTypeSelected,
+    )
+}
+
 /// Encrypt-then-MAC is defined in [RFC7366]
 fn parse_tls_extension_encrypt_then_mac_content(
     i: &[u8],
@@ -405,9 +1381,171 @@ pub fn parse_tls_extension_extended_master_secret(i: &[u8]) -> IResult<&[u8], Tl
     })(i)
 }
 
-/// Extended Record Size Limit is defined in [RFC7627]
+/// Token Binding negotiation, defined in [RFC8472]
+//
+// struct {
+//     uint8 major;
+//     uint8 minor;
+// } TB_ProtocolVersion;
+//
+// struct {
+//     TB_ProtocolVersion token_binding_version;
+//     TokenBindingKeyParameters key_parameters_list<1..2^8-1>
+// } TokenBindingParameters;
+fn parse_tls_extension_token_binding_content(i: &[u8]) -> IResult<&[u8], TlsExtension> {
+    let (i, major) = be_u8(i)?;
+    let (i, minor) = be_u8(i)?;
+    let (i, key_parameters) = map_parser(
+        length_data(be_u8),
+        many0(complete(TokenBindingKeyParameters::parse)),
+    )(i)?;
+    let ext = TlsExtension::TokenBinding {
+        major,
+        minor,
+        key_parameters,
+    };
+    Ok((i, ext))
+}
+
+/// Record Size Limit is defined in [RFC8449]
+//
+// uint16 RecordSizeLimit;
 fn parse_tls_extension_record_size_limit(i: &[u8]) -> IResult<&[u8], TlsExtension> {
-    map(be_u16, TlsExtension::RecordSizeLimit)(i)
+    map(RecordSizeLimit::parse, TlsExtension::RecordSizeLimit)(i)
+}
+
+fn parse_cached_object_client(i: &[u8]) -> IResult<&[u8], CachedObject> {
+    let (i, cached_type) = CachedInformationType::parse(i)?;
+    let (i, hash_value) = length_data(be_u8)(i)?;
+    let object = CachedObject {
+        cached_type,
+        hash_value: Some(hash_value),
+    };
+    Ok((i, object))
+}
+
+fn parse_cached_object_server(i: &[u8]) -> IResult<&[u8], CachedObject> {
+    let (i, cached_type) = CachedInformationType::parse(i)?;
+    let object = CachedObject {
+        cached_type,
+        hash_value: None,
+    };
+    Ok((i, object))
+}
+
+/// Cached information, defined in [RFC7924]
+//
+// struct {
+//     select (type) {
+//         case client:
+//             CachedInformationType type;
+//             opaque hash_value<1..255>;
+//         case server:
+//             CachedInformationType type;
+//     } body;
+// } CachedObject;
+//
+// struct {
+//     CachedObject cached_info<1..2^16-1>;
+// } CachedInformation;
+fn parse_tls_extension_cached_info_content(
+    i: &[u8],
+    ctx: Option<ExtensionContext>,
+) -> IResult<&[u8], TlsExtension> {
+    let parse_client = map_parser(
+        length_data(be_u16),
+        all_consuming(many0(complete(parse_cached_object_client))),
+    );
+    let parse_server = map_parser(
+        length_data(be_u16),
+        all_consuming(many0(complete(parse_cached_object_server))),
+    );
+    match ctx {
+        Some(ExtensionContext::ClientHello) => map(parse_client, TlsExtension::CachedInfo)(i),
+        Some(ExtensionContext::ServerHello) | Some(ExtensionContext::EncryptedExtensions) => {
+            map(parse_server, TlsExtension::CachedInfo)(i)
+        }
+        Some(_) => Err(Err::Error(make_error(i, ErrorKind::Verify))),
+        // XXX the content depends on the current message type
+        // try the client format first, server objects have no hash value
+        None => map(alt((parse_client, parse_server)), TlsExtension::CachedInfo)(i),
+    }
+}
+
+/// Certificate compression, defined in [RFC8879]
+//
+// struct {
+//     CertificateCompressionAlgorithm algorithms<2..2^8-2>;
+// } CertificateCompressionAlgorithms;
+fn parse_tls_extension_compress_certificate_content(i: &[u8]) -> IResult<&[u8], TlsExtension> {
+    let (i, v) = map_parser(
+        length_data(be_u8),
+        many0(complete(CertificateCompressionAlgorithm::parse)),
+    )(i)?;
+    Ok((i, TlsExtension::CompressCertificate(v)))
+}
+
+// struct {
+//   uint32 valid_time;
+//   SignatureScheme dc_cert_verify_algorithm;
+//   opaque ASN1_subjectPublicKeyInfo<1..2^24-1>;
+// } Credential;
+//
+// struct {
+//   Credential cred;
+//   SignatureScheme algorithm;
+//   opaque signature<1..2^16-1>;
+// } DelegatedCredential;
+fn parse_delegated_credential(i: &[u8]) -> IResult<&[u8], DelegatedCredential> {
+    let (i, valid_time) = be_u32(i)?;
+    let (i, expected_cert_verify_algorithm) = SignatureScheme::parse(i)?;
+    let (i, asn1_subject_public_key_info) = length_data(be_u24)(i)?;
+    let (i, algorithm) = SignatureScheme::parse(i)?;
+    let (i, signature) = length_data(be_u16)(i)?;
+    let dc = DelegatedCredential {
+        valid_time,
+        expected_cert_verify_algorithm,
+        asn1_subject_public_key_info,
+        algorithm,
+        signature,
+    };
+    Ok((i, dc))
+}
+
+fn parse_delegated_credentials_sigalgs(i: &[u8]) -> IResult<&[u8], TlsExtension> {
+    let (i, l) = parse_signature_scheme_list(i)?;
+    Ok((i, TlsExtension::DelegatedCredentials(l)))
+}
+
+/// Delegated credentials, defined in [RFC9345]
+// XXX the content depends on the current message type
+// XXX ClientHello and CertificateRequest start with the list length
+fn parse_tls_extension_delegated_credential_content(
+    i: &[u8],
+    ext_len: u16,
+    ctx: Option<ExtensionContext>,
+) -> IResult<&[u8], TlsExtension> {
+    match ctx {
+        Some(ExtensionContext::ClientHello) | Some(ExtensionContext::CertificateRequest) => {
+            parse_delegated_credentials_sigalgs(i)
+        }
+        Some(ExtensionContext::Certificate) => map(
+            parse_delegated_credential,
+            TlsExtension::DelegatedCredential,
+        )(i),
+        Some(_) => Err(Err::Error(make_error(i, ErrorKind::Verify))),
+        None => {
+            let (_, list_len) = be_u16(i)?;
+            if list_len as usize + 2 == ext_len as usize {
+                parse_delegated_credentials_sigalgs(i)
+            } else {
+                map(
+                    parse_delegated_credential,
+                    TlsExtension::DelegatedCredential,
+                )(i)
+            }
+        }
+    }
 }
 
 fn parse_tls_extension_session_ticket_content(
@@ -432,42 +1570,168 @@ fn parse_tls_extension_key_share_old_content(
     map(take(ext_len), TlsExtension::KeyShareOld)(i)
 }
 
-fn parse_tls_extension_key_share_content(i: &[u8], ext_len: u16) -> IResult<&[u8], TlsExtension> {
-    map(take(ext_len), TlsExtension::KeyShare)(i)
+// struct {
+//     NamedGroup group;
+//     opaque key_exchange<1..2^16-1>;
+// } KeyShareEntry;
+fn parse_key_share_entry(i: &[u8]) -> IResult<&[u8], KeyShareEntry> {
+    let (i, group) = NamedGroup::parse(i)?;
+    let (i, kx) = length_data(be_u16)(i)?;
+    Ok((i, KeyShareEntry { group, kx }))
+}
+
+// TLS 1.3 (RFC8446 section 4.2.8)
+//       struct {
+//           KeyShareEntry client_shares<0..2^16-1>;
+//       } KeyShareClientHello;
+//
+//       struct {
+//           NamedGroup selected_group;
+//       } KeyShareHelloRetryRequest;
+//
+//       struct {
+//           KeyShareEntry server_share;
+//       } KeyShareServerHello;
+// XXX the content depends on the current message type
+// XXX HelloRetryRequest has length 2, ClientHello starts with the list length
+fn parse_tls_extension_key_share_content(
+    i: &[u8],
+    ext_len: u16,
+    ctx: Option<ExtensionContext>,
+) -> IResult<&[u8], TlsExtension> {
+    match ctx {
+        Some(ExtensionContext::ClientHello) => parse_key_share_client_hello(i),
+        Some(ExtensionContext::ServerHello) => {
+            map(parse_key_share_entry, TlsExtension::KeyShareServer)(i)
+        }
+        Some(ExtensionContext::HelloRetryRequest) => {
+            map(NamedGroup::parse, TlsExtension::KeyShareHelloRetryRequest)(i)
+        }
+        _ => parse_tls_extension_key_share_guess(i, ext_len),
+    }
+}
+
+fn parse_key_share_client_hello(i: &[u8]) -> IResult<&[u8], TlsExtension> {
+    let (i, v) = map_parser(length_data(be_u16), many0(complete(parse_key_share_entry)))(i)?;
+    Ok((i, TlsExtension::KeyShare(v)))
+}
+
+fn parse_tls_extension_key_share_guess(i: &[u8], ext_len: u16) -> IResult<&[u8], TlsExtension> {
+    if ext_len == 2 {
+        // an empty client_shares list is also 2 bytes long, but 0 is not a valid group
+        let (i, group) = be_u16(i)?;
+        if group == 0 {
+            return Ok((i, TlsExtension::KeyShare(Vec::new())));
+        }
+        return Ok((
+            i,
+            TlsExtension::KeyShareHelloRetryRequest(NamedGroup(group)),
+        ));
+    }
+    let (_, list_len) = be_u16(i)?;
+    if list_len as usize + 2 == ext_len as usize {
+        parse_key_share_client_hello(i)
+    } else {
+        map(parse_key_share_entry, TlsExtension::KeyShareServer)(i)
+    }
 }
 
 pub fn parse_tls_extension_key_share(i: &[u8]) -> IResult<&[u8], TlsExtension> {
     let (i, _) = tag([0x00, 0x33])(i)?;
     let (i, ext_len) = be_u16(i)?;
     map_parser(take(ext_len), move |d| {
-        parse_tls_extension_key_share_content(d, ext_len)
+        parse_tls_extension_key_share_content(d, ext_len, None)
     })(i)
 }
 
+// struct {
+//     opaque identity<1..2^16-1>;
+//     uint32 obfuscated_ticket_age;
+// } PskIdentity;
+fn parse_psk_identity(i: &[u8]) -> IResult<&[u8], PskIdentity> {
+    let (i, identity) = length_data(be_u16)(i)?;
+    let (i, obfuscated_ticket_age) = be_u32(i)?;
+    let psk_identity = PskIdentity {
+        identity,
+        obfuscated_ticket_age,
+    };
+    Ok((i, psk_identity))
+}
+
+// TLS 1.3 (RFC8446 section 4.2.11)
+//       opaque PskBinderEntry<32..255>;
+//
+//       struct {
+//           PskIdentity identities<7..2^16-1>;
+//           PskBinderEntry binders<33..2^16-1>;
+//       } OfferedPsks;
+//
+//       struct {
+//           select (Handshake.msg_type) {
+//               case client_hello: OfferedPsks;
+//               case server_hello: uint16 selected_identity;
+//           };
+//       } PreSharedKeyExtension;
+// XXX the content depends on the current message type
+// XXX server_hello has length 2, which is too short for OfferedPsks
 fn parse_tls_extension_pre_shared_key_content(
     i: &[u8],
     ext_len: u16,
+    ctx: Option<ExtensionContext>,
 ) -> IResult<&[u8], TlsExtension> {
-    map(take(ext_len), TlsExtension::PreSharedKey)(i)
+    match ctx {
+        Some(ExtensionContext::ClientHello) => parse_offered_psks(i),
+        Some(ExtensionContext::ServerHello) => map(be_u16, TlsExtension::PreSharedKeySelected)(i),
+        _ if ext_len == 2 => map(be_u16, TlsExtension::PreSharedKeySelected)(i),
+        _ => parse_offered_psks(i),
+    }
+}
+
+fn parse_offered_psks(i: &[u8]) -> IResult<&[u8], TlsExtension> {
+    let (i, identities) = map_parser(length_data(be_u16), many0(complete(parse_psk_identity)))(i)?;
+    let (i, binders) = map_parser(length_data(be_u16), many0(complete(length_data(be_u8))))(i)?;
+    let offer = PreSharedKeyOffer {
+        identities,
+        binders,
+    };
+    Ok((i, TlsExtension::PreSharedKey(offer)))
 }
 
 pub fn parse_tls_extension_pre_shared_key(i: &[u8]) -> IResult<&[u8], TlsExtension> {
-    let (i, _) = tag([0x00, 0x28])(i)?;
+    let (i, _) = tag([0x00, 0x29])(i)?;
     let (i, ext_len) = be_u16(i)?;
     map_parser(take(ext_len), move |d| {
-        parse_tls_extension_pre_shared_key_content(d, ext_len)
+        parse_tls_extension_pre_shared_key_content(d, ext_len, None)
     })(i)
 }
 
-fn parse_tls_extension_early_data_content(i: &[u8], ext_len: u16) -> IResult<&[u8], TlsExtension> {
-    map(cond(ext_len > 0, be_u32), TlsExtension::EarlyData)(i)
+// TLS 1.3 (RFC8446 section 4.2.10)
+//       struct {
+//           select (Handshake.msg_type) {
+//               case new_session_ticket:   uint32 max_early_data_size;
+//               case client_hello:         Empty;
+//               case encrypted_extensions: Empty;
+//           };
+//       } EarlyDataIndication;
+fn parse_tls_extension_early_data_content(
+    i: &[u8],
+    ext_len: u16,
+    ctx: Option<ExtensionContext>,
+) -> IResult<&[u8], TlsExtension> {
+    match ctx {
+        Some(ExtensionContext::NewSessionTicket) => {
+            map(be_u32, |x| TlsExtension::EarlyData(Some(x)))(i)
+        }
+        Some(_) if ext_len != 0 => Err(Err::Error(make_error(i, ErrorKind::Verify))),
+        _ => map(cond(ext_len > 0, be_u32), TlsExtension::EarlyData)(i),
+    }
 }
 
 pub fn parse_tls_extension_early_data(i: &[u8]) -> IResult<&[u8], TlsExtension> {
     let (i, _) = tag([0x00, 0x2a])(i)?;
     let (i, ext_len) = be_u16(i)?;
     map_parser(take(ext_len), move |d| {
-        parse_tls_extension_early_data_content(d, ext_len)
+        parse_tls_extension_early_data_content(d, ext_len, None)
     })(i)
 }
 
@@ -486,8 +1750,14 @@ pub fn parse_tls_extension_early_data(i: &[u8]) -> IResult<&[u8], TlsExtension>
 fn parse_tls_extension_supported_versions_content(
     i: &[u8],
     ext_len: u16,
+    ctx: Option<ExtensionContext>,
 ) -> IResult<&[u8], TlsExtension> {
-    if ext_len == 2 {
+    let selected_version = match ctx {
+        Some(ExtensionContext::ClientHello) => false,
+        Some(ExtensionContext::ServerHello) | Some(ExtensionContext::HelloRetryRequest) => true,
+        _ => ext_len == 2,
+    };
+    if selected_version {
         map(be_u16, |x| {
             TlsExtension::SupportedVersions(vec![TlsVersion(x)])
         })(i)
@@ -505,12 +1775,22 @@ pub fn parse_tls_extension_supported_versions(i: &[u8]) -> IResult<&[u8], TlsExt
     let (i, _) = tag([0x00, 0x2b])(i)?;
     let (i, ext_len) = be_u16(i)?;
     map_parser(take(ext_len), move |d| {
-        parse_tls_extension_supported_versions_content(d, ext_len)
+        parse_tls_extension_supported_versions_content(d, ext_len, None)
     })(i)
 }
 
+/// Cookie, defined in [RFC8446] section 4.2.2
+//
+// struct {
+//     opaque cookie<1..2^16-1>;
+// } Cookie;
 fn parse_tls_extension_cookie_content(i: &[u8], ext_len: u16) -> IResult<&[u8], TlsExtension> {
-    map(take(ext_len), TlsExtension::Cookie)(i)
+    map(
+        verify(length_data(be_u16), |c: &[u8]| {
+            !c.is_empty() && c.len() + 2 == ext_len as usize
+        }),
+        TlsExtension::Cookie,
+    )(i)
 }
 
 pub fn parse_tls_extension_cookie(i: &[u8]) -> IResult<&[u8], TlsExtension> {
@@ -524,8 +1804,11 @@ pub fn parse_tls_extension_cookie(i: &[u8]) -> IResult<&[u8], TlsExtension> {
 pub fn parse_tls_extension_psk_key_exchange_modes_content(
     i: &[u8],
 ) -> IResult<&[u8], TlsExtension> {
-    let (i, v) = length_data(be_u8)(i)?;
-    Ok((i, TlsExtension::PskExchangeModes(v.to_vec())))
+    let (i, v) = map_parser(
+        length_data(be_u8),
+        many0(complete(PskKeyExchangeMode::parse)),
+    )(i)?;
+    Ok((i, TlsExtension::PskExchangeModes(v)))
 }
 
 pub fn parse_tls_extension_psk_key_exchange_modes(i: &[u8]) -> IResult<&[u8], TlsExtension> {
@@ -538,11 +1821,78 @@ pub fn parse_tls_extension_psk_key_exchange_modes(i: &[u8]) -> IResult<&[u8], Tl
 }
 
 /// Defined in RFC-draft-agl-tls-nextprotoneg-03. Deprecated in favour of ALPN.
-fn parse_tls_extension_npn_content(i: &[u8], ext_len: u16) -> IResult<&[u8], TlsExtension> {
-    if ext_len != 0 {
+///
+/// The extension is empty in ClientHello. In ServerHello, it contains the list of
+/// advertised protocols (without a length for the list).
+fn parse_tls_extension_npn_content(
+    i: &[u8],
+    ext_len: u16,
+    ctx: Option<ExtensionContext>,
+) -> IResult<&[u8], TlsExtension> {
+    match ctx {
+        Some(ExtensionContext::ClientHello) if ext_len != 0 => {
+            Err(Err::Error(make_error(i, ErrorKind::Verify)))
+        }
+        Some(ExtensionContext::ClientHello) => Ok((i, TlsExtension::NextProtocolNegotiation)),
+        Some(ExtensionContext::ServerHello) => map(
+            many0(complete(parse_protocol_name)),
+            TlsExtension::NextProtocolNegotiationServer,
+        )(i),
+        Some(_) => Err(Err::Error(make_error(i, ErrorKind::Verify))),
+        // XXX the content depends on the current message type
+        None if ext_len == 0 => Ok((i, TlsExtension::NextProtocolNegotiation)),
+        None => map(
+            many0(complete(parse_protocol_name)),
+            TlsExtension::NextProtocolNegotiationServer,
+        )(i),
+    }
+}
+
+/// Connection ID, defined in [RFC9146]
+//
+// struct {
+//     opaque cid<0..2^8-1>;
+// } ConnectionId;
+fn parse_tls_extension_connection_id_content(i: &[u8]) -> IResult<&[u8], TlsExtension> {
+    map(length_data(be_u8), TlsExtension::ConnectionId)(i)
+}
+
+/// Parse a QUIC variable-length integer, as defined in [RFC9000] section 16
+///
+/// The two most significant bits of the first byte encode the length of the integer.
+pub fn parse_quic_varint(i: &[u8]) -> IResult<&[u8], u64> {
+    let (_, first) = be_u8(i)?;
+    let len = 1usize << (first >> 6);
+    let (i, bytes) = take(len)(i)?;
+    let v = bytes[1..]
+        .iter()
+        .fold(u64::from(first & 0x3f), |acc, &b| (acc << 8) | u64::from(b));
+    Ok((i, v))
+}
+
+// struct {
+//   TransportParameterId id (i);
+//   TransportParameterLength length (i);
+//   TransportParameterValue value (..);
+// } TransportParameter;
+fn parse_quic_transport_parameter(i: &[u8]) -> IResult<&[u8], QuicTransportParameter> {
+    let (i, id) = map(parse_quic_varint, QuicTransportParameterId)(i)?;
+    let (i, len) = parse_quic_varint(i)?;
+    let (i, value) = take(len as usize)(i)?;
+    Ok((i, QuicTransportParameter { id, value }))
+}
+
+/// QUIC Transport Parameters, defined in [RFC9001] section 8.2
+///
+/// The extension content is a sequence of transport parameters, without any length prefix.
+pub fn parse_tls_extension_quic_transport_parameters_content(
+    i: &[u8],
+) -> IResult<&[u8], TlsExtension> {
+    let (i, v) = many0(complete(parse_quic_transport_parameter))(i)?;
+    if !i.is_empty() {
         return Err(Err::Error(make_error(i, ErrorKind::Verify)));
     }
-    Ok((i, TlsExtension::NextProtocolNegotiation))
+    Ok((i, TlsExtension::QuicTransportParameters(v)))
 }
 
 /// Renegotiation Info, defined in [RFC5746]
@@ -567,6 +1917,119 @@ pub fn parse_tls_extension_encrypted_server_name(i: &[u8]) -> IResult<&[u8], Tls
     Ok((i, esn))
 }
 
+// struct {
+//     select (Handshake.msg_type) {
+//         case client_hello:          ECHClientHello;
+//         case encrypted_extensions:  ECHConfigList retry_configs;
+//         case hello_retry_request:   opaque confirmation[8];
+//     };
+// } EncryptedClientHello;
+// XXX the content depends on the current message type
+// XXX retry_configs start with the list length, HelloRetryRequest has length 8
+fn parse_tls_extension_encrypted_client_hello_content(
+    i: &[u8],
+    ext_len: u16,
+    ctx: Option<ExtensionContext>,
+) -> IResult<&[u8], TlsExtension> {
+    match ctx {
+        Some(ExtensionContext::ClientHello) => {
+            map(parse_ech_client_hello, TlsExtension::EncryptedClientHello)(i)
+        }
+        Some(ExtensionContext::EncryptedExtensions) => {
+            map(take(ext_len), TlsExtension::EchRetryConfigs)(i)
+        }
+        Some(ExtensionContext::HelloRetryRequest) => {
+            map(parse_ech_hrr_confirmation, TlsExtension::EchConfirmation)(i)
+        }
+        Some(_) => Err(Err::Error(make_error(i, ErrorKind::Verify))),
+        None if ext_len == 8 => map(parse_ech_hrr_confirmation, TlsExtension::EchConfirmation)(i),
+        None => {
+            let (_, list_len) = be_u16(i)?;
+            if list_len as usize + 2 == ext_len as usize {
+                map(take(ext_len), TlsExtension::EchRetryConfigs)(i)
+            } else {
+                map(parse_ech_client_hello, TlsExtension::EncryptedClientHello)(i)
+            }
+        }
+    }
+}
+
+/// Application-Layer Protocol Settings, defined in draft-vvv-tls-alps
+//
+// struct {
+//     ProtocolName supported_protocols<2..2^16-1>;
+// } ApplicationSettingsSupport;
+//
+// struct {
+//     opaque application_settings<0..2^16-1>;
+// } ApplicationSettings;
+fn parse_tls_extension_application_settings_content(
+    i: &[u8],
+    ext_len: u16,
+    ctx: Option<ExtensionContext>,
+) -> IResult<&[u8], TlsExtension> {
+    match ctx {
+        Some(ExtensionContext::ClientHello) => {
+            map(parse_protocol_name_list, TlsExtension::ApplicationSettings)(i)
+        }
+        Some(ExtensionContext::EncryptedExtensions) => {
+            map(take(ext_len), TlsExtension::ApplicationSettingsData)(i)
+        }
+        Some(_) => Err(Err::Error(make_error(i, ErrorKind::Verify))),
+        None => {
+            // XXX the content depends on the current message type
+            // assume ClientHello if the protocol list covers the whole extension
+            match be_u16::<_, ()>(i) {
+                Ok((_, list_len)) if list_len as usize + 2 == ext_len as usize => {
+                    map(parse_protocol_name_list, TlsExtension::ApplicationSettings)(i)
+                }
+                _ => map(take(ext_len), TlsExtension::ApplicationSettingsData)(i),
+            }
+        }
+    }
+}
+
+/// Channel ID, defined in draft-balfanz-tls-channelid
+//
+// struct {
+//     opaque x[32];
+//     opaque y[32];
+//     opaque r[32];
+//     opaque s[32];
+// } ChannelIDExtension;
+fn parse_tls_extension_channel_id_content(i: &[u8], ext_len: u16) -> IResult<&[u8], TlsExtension> {
+    match ext_len {
+        0 => Ok((i, TlsExtension::ChannelId(None))),
+        128 => {
+            let (i, x) = take(32usize)(i)?;
+            let (i, y) = take(32usize)(i)?;
+            let (i, r) = take(32usize)(i)?;
+            let (i, s) = take(32usize)(i)?;
+            let channel_id = ChannelId { x, y, r, s };
+            Ok((i, TlsExtension::ChannelId(Some(channel_id))))
+        }
+        _ => Err(Err::Error(make_error(i, ErrorKind::Verify))),
+    }
+}
+
+// ExtensionType OuterExtensions<2..254>;
+fn parse_tls_extension_ech_outer_extensions_content(i: &[u8]) -> IResult<&[u8], TlsExtension> {
+    let (i, v) = map_parser(length_data(be_u8), many0(complete(TlsExtensionType::parse)))(i)?;
+    Ok((i, TlsExtension::EchOuterExtensions(v)))
+}
+
+/// Certificate authorities, defined in [RFC8446] section 4.2.4
+//
+// opaque DistinguishedName<1..2^16-1>;
+//
+// struct {
+//     DistinguishedName authorities<3..2^16-1>;
+// } CertificateAuthoritiesExtension;
+fn parse_tls_extension_certificate_authorities_content(i: &[u8]) -> IResult<&[u8], TlsExtension> {
+    let (i, v) = map_parser(length_data(be_u16), many0(complete(length_data(be_u16))))(i)?;
+    Ok((i, TlsExtension::CertificateAuthorities(v)))
+}
+
 fn parse_tls_oid_filter(i: &[u8]) -> IResult<&[u8], OidFilter> {
     let (i, cert_ext_oid) = length_data(be_u8)(i)?;
     let (i, cert_ext_val) = length_data(be_u16)(i)?;
@@ -583,6 +2046,12 @@ fn parse_tls_extension_oid_filters(i: &[u8]) -> IResult<&[u8], TlsExtension> {
     Ok((i, TlsExtension::OidFilters(v)))
 }
 
+/// Signature algorithms for certificates, defined in [RFC8446] section 4.2.3
+fn parse_tls_extension_signature_algorithms_cert_content(i: &[u8]) -> IResult<&[u8], TlsExtension> {
+    let (i, l) = parse_signature_scheme_list(i)?;
+    Ok((i, TlsExtension::SignatureAlgorithmsCert(l)))
+}
+
 /// Defined in TLS 1.3 draft 20
 fn parse_tls_extension_post_handshake_auth_content(
     i: &[u8],
@@ -603,39 +2072,61 @@ pub fn parse_tls_extension_unknown(i: &[u8]) -> IResult<&[u8], TlsExtension> {
     ))
 }
 
-fn parse_tls_extension_with_type(
+pub(crate) fn parse_tls_extension_with_type(
     i: &[u8],
     ext_type: u16,
     ext_len: u16,
+    ctx: Option<ExtensionContext>,
 ) -> IResult<&[u8], TlsExtension> {
-    if ext_type & 0x0f0f == 0x0a0a {
+    if is_grease_u16(ext_type) {
         return map(take(ext_len), |d| TlsExtension::Grease(ext_type, d))(i);
     }
     match ext_type {
         0x0000 => parse_tls_extension_sni_content(i),
         0x0001 => parse_tls_extension_max_fragment_length_content(i),
-        0x0005 => parse_tls_extension_status_request_content(i, ext_len),
+        0x0002 => parse_tls_extension_client_certificate_url_content(i, ext_len),
+        0x0003 => parse_tls_extension_trusted_ca_keys_content(i, ext_len),
+        0x0005 => parse_tls_extension_status_request_content(i, ext_len, ctx),
+        0x0006 => parse_tls_extension_user_mapping_content(i),
         0x000a => parse_tls_extension_elliptic_curves_content(i),
         0x000b => parse_tls_extension_ec_point_formats_content(i),
+        0x000c => parse_tls_extension_srp_content(i),
         0x000d => parse_tls_extension_signature_algorithms_content(i),
+        0x000e => parse_tls_extension_use_srtp_content(i),
         0x000f => parse_tls_extension_heartbeat_content(i),
         0x0010 => parse_tls_extension_alpn_content(i),
+        0x0011 => parse_tls_extension_status_request_v2_content(i, ext_len),
         0x0012 => parse_tls_extension_signed_certificate_timestamp_content(i),
+        0x0013 => parse_tls_extension_client_certificate_type_content(i, ext_len, ctx),
+        0x0014 => parse_tls_extension_server_certificate_type_content(i, ext_len, ctx),
         0x0015 => parse_tls_extension_padding_content(i, ext_len),
         0x0016 => parse_tls_extension_encrypt_then_mac_content(i, ext_len),
         0x0017 => parse_tls_extension_extended_master_secret_content(i, ext_len),
+        0x0018 => parse_tls_extension_token_binding_content(i),
+        0x0019 => parse_tls_extension_cached_info_content(i, ctx),
+        0x001b => parse_tls_extension_compress_certificate_content(i),
         0x001c => parse_tls_extension_record_size_limit(i),
+        0x0022 => parse_tls_extension_delegated_credential_content(i, ext_len, ctx),
         0x0023 => parse_tls_extension_session_ticket_content(i, ext_len),
+        0x0027 => parse_tls_extension_supported_ekt_ciphers_content(i, ext_len, ctx),
         0x0028 => parse_tls_extension_key_share_old_content(i, ext_len),
-        0x0029 => parse_tls_extension_pre_shared_key_content(i, ext_len),
-        0x002a => parse_tls_extension_early_data_content(i, ext_len),
-        0x002b => parse_tls_extension_supported_versions_content(i, ext_len),
+        0x0029 => parse_tls_extension_pre_shared_key_content(i, ext_len, ctx),
+        0x002a => parse_tls_extension_early_data_content(i, ext_len, ctx),
+        0x002b => parse_tls_extension_supported_versions_content(i, ext_len, ctx),
         0x002c => parse_tls_extension_cookie_content(i, ext_len),
         0x002d => parse_tls_extension_psk_key_exchange_modes_content(i),
+        0x002f => parse_tls_extension_certificate_authorities_content(i),
         0x0030 => parse_tls_extension_oid_filters(i),
         0x0031 => parse_tls_extension_post_handshake_auth_content(i, ext_len),
-        0x0033 => parse_tls_extension_key_share_content(i, ext_len),
-        0x3374 => parse_tls_extension_npn_content(i, ext_len),
+        0x0032 => parse_tls_extension_signature_algorithms_cert_content(i),
+        0x0033 => parse_tls_extension_key_share_content(i, ext_len, ctx),
+        0x0036 => parse_tls_extension_connection_id_content(i),
+        0x0039 => parse_tls_extension_quic_transport_parameters_content(i),
+        0x3374 => parse_tls_extension_npn_content(i, ext_len, ctx),
+        0x4469 => parse_tls_extension_application_settings_content(i, ext_len, ctx),
+        0x7550 => parse_tls_extension_channel_id_content(i, ext_len),
+        0xfd00 => parse_tls_extension_ech_outer_extensions_content(i),
+        0xfe0d => parse_tls_extension_encrypted_client_hello_content(i, ext_len, ctx),
         0xff01 => parse_tls_extension_renegotiation_info_content(i),
         0xffce => parse_tls_extension_encrypted_server_name(i),
         _ => map(take(ext_len), |ext_data| {
@@ -644,14 +2135,152 @@ fn parse_tls_extension_with_type(
     }
 }
 
-pub fn parse_tls_extension(i: &[u8]) -> IResult<&[u8], TlsExtension> {
+fn parse_tls_extension_ctx(
+    i: &[u8],
+    ctx: Option<ExtensionContext>,
+) -> IResult<&[u8], TlsExtension> {
     let (i, ext_type) = be_u16(i)?;
     let (i, ext_len) = be_u16(i)?;
     map_parser(take(ext_len), move |d| {
-        parse_tls_extension_with_type(d, ext_type, ext_len)
+        parse_tls_extension_with_type(d, ext_type, ext_len, ctx)
     })(i)
 }
 
+/// Parse a single TLS extension
+///
+/// If the extension content depends on the handshake message, the format is guessed
+/// from the extension length. Use `parse_tls_extension_in` if the message is known.
+pub fn parse_tls_extension(i: &[u8]) -> IResult<&[u8], TlsExtension> {
+    parse_tls_extension_ctx(i, None)
+}
+
+/// Parse a single TLS extension, found in the handshake message `ctx`
+pub fn parse_tls_extension_in(i: &[u8], ctx: ExtensionContext) -> IResult<&[u8], TlsExtension> {
+    parse_tls_extension_ctx(i, Some(ctx))
+}
+
+/// Parse a list of TLS extensions
+///
+/// See `parse_tls_extension` for the extensions depending on the handshake message.
 pub fn parse_tls_extensions(i: &[u8]) -> IResult<&[u8], Vec<TlsExtension>> {
     many0(complete(parse_tls_extension))(i)
 }
+
+/// Parse a list of TLS extensions, found in the handshake message `ctx`
+pub fn parse_tls_extensions_in(
+    i: &[u8],
+    ctx: ExtensionContext,
+) -> IResult<&[u8], Vec<TlsExtension>> {
+    many0(complete(|d| parse_tls_extension_in(d, ctx)))(i)
+}
+
+/// Test if the extension content only uses values allowed by the specifications
+///
+/// `ctx` is the message containing the extension, if known. The upper bound of
+/// `record_size_limit` is only checked on the server value (ServerHello or
+/// EncryptedExtensions).
+pub(crate) fn is_valid_strict(ext: &TlsExtension, ctx: Option<ExtensionContext>) -> bool {
+    match *ext {
+        TlsExtension::MaxFragmentLength(l) => l.length().is_some(),
+        TlsExtension::RecordSizeLimit(l) => match ctx {
+            Some(ExtensionContext::ServerHello) => l.is_valid(false),
+            Some(ExtensionContext::EncryptedExtensions) => l.is_valid(true),
+            _ => l.0 >= RecordSizeLimit::MIN,
+        },
+        TlsExtension::PskExchangeModes(ref v) => v
+            .iter()
+            .all(|&m| m == PskKeyExchangeMode::Psk || m == PskKeyExchangeMode::PskDhe),
+        TlsExtension::Heartbeat(mode) => matches!(
+            mode,
+            HeartbeatMode::PeerAllowedToSend | HeartbeatMode::PeerNotAllowedToSend
+        ),
+        _ => true,
+    }
+}
+
+/// Test if the extension can be sent in the TLS 1.3 EncryptedExtensions message
+///
+/// Allowed extensions are given by `TlsExtensionType::tls13_contexts`. Unknown extensions
+/// (including GREASE values) are always allowed.
+fn is_allowed_in_encrypted_extensions(ext: &TlsExtension) -> bool {
+    if let TlsExtension::Unknown(_, _) = ext {
+        return true;
+    }
+    match TlsExtensionType::from(ext).tls13_contexts() {
+        Some(contexts) => contexts.contains(&ExtensionContext::EncryptedExtensions),
+        None => true,
+    }
+}
+
+/// Get the types of the extensions that are not allowed in the TLS 1.3 EncryptedExtensions
+/// message
+///
+/// For ex., `key_share` or `supported_versions` must be sent in the ServerHello, and are
+/// violations if found in EncryptedExtensions. Unknown extensions are not reported.
+pub fn encrypted_extensions_violations(exts: &[TlsExtension]) -> Vec<TlsExtensionType> {
+    exts.iter()
+        .filter(|ext| !is_allowed_in_encrypted_extensions(ext))
+        .map(TlsExtensionType::from)
+        .collect()
+}
+
+/// Parse a single TLS extension, rejecting values not allowed by the specifications
+///
+/// The default parsers accept unknown or illegal values (for ex. an unknown heartbeat mode),
+/// which is useful to analyze traffic. This function returns a `Verify` error instead.
+///
+/// If `ctx` is `EncryptedExtensions`, extensions not allowed in this message are also rejected
+/// (see `encrypted_extensions_violations`).
+///
+/// If `ctx` is `None`, the format of extensions depending on the handshake message is guessed.
+pub fn parse_tls_extension_strict(
+    i: &[u8],
+    ctx: Option<ExtensionContext>,
+) -> IResult<&[u8], TlsExtension> {
+    let (rem, ext) = parse_tls_extension_ctx(i, ctx)?;
+    if !is_valid_strict(&ext, ctx) {
+        return Err(Err::Error(make_error(i, ErrorKind::Verify)));
+    }
+    if ctx == Some(ExtensionContext::EncryptedExtensions)
+        && !is_allowed_in_encrypted_extensions(&ext)
+    {
+        return Err(Err::Error(make_error(i, ErrorKind::Verify)));
+    }
+    Ok((rem, ext))
+}
+
+/// Parse a list of TLS extensions, rejecting values not allowed by the specifications
+///
+/// See `parse_tls_extension_strict`. All input must be consumed.
+pub fn parse_tls_extensions_strict(
+    i: &[u8],
+    ctx: Option<ExtensionContext>,
+) -> IResult<&[u8], Vec<TlsExtension>> {
+    all_consuming(many0(complete(|d| parse_tls_extension_strict(d, ctx))))(i)
+}
+
+/// Remove GREASE values from a list of parsed extensions
+///
+/// GREASE extensions are dropped, and GREASE values are removed from the lists of the
+/// `supported_groups`, `signature_algorithms`, `supported_versions` and `key_share` extensions.
+/// The order of the remaining values is preserved.
+pub fn remove_grease<'a>(exts: &[TlsExtension<'a>]) -> Vec<TlsExtension<'a>> {
+    exts.iter()
+        .filter_map(|ext| match ext {
+            TlsExtension::Grease(_, _) => None,
+            TlsExtension::EllipticCurves(v) => Some(TlsExtension::EllipticCurves(
+                v.iter().filter(|g| !g.is_grease()).cloned().collect(),
+            )),
+            TlsExtension::SignatureAlgorithms(v) => Some(TlsExtension::SignatureAlgorithms(
+                v.iter().filter(|s| !is_grease_u16(s.0)).cloned().collect(),
+            )),
+            TlsExtension::SupportedVersions(v) => Some(TlsExtension::SupportedVersions(
+                v.iter().filter(|v| !v.is_grease()).cloned().collect(),
+            )),
+            TlsExtension::KeyShare(v) => Some(TlsExtension::KeyShare(
+                v.iter().filter(|k| !k.group.is_grease()).cloned().collect(),
+            )),
+            _ => Some(ext.clone()),
+        })
+        .collect()
+}
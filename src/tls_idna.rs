@@ -0,0 +1,17 @@
+//! Decoding of internationalized SNI host names, using [idna](https://crates.io/crates/idna)
+//!
+//! This module is only available with the `idna` feature.
+
+use crate::tls_extensions::{sni_host_name, SNIType, SniError};
+
+/// Get a SNI entry as a Unicode host name
+///
+/// The host name is first validated using `sni_host_name`, then A-labels (`xn--...`) are
+/// decoded from punycode, following [UTS #46](https://www.unicode.org/reports/tr46/).
+pub fn sni_host_name_unicode(name_type: SNIType, name: &[u8]) -> Result<String, SniError> {
+    let ascii = sni_host_name(name_type, name)?;
+    match idna::domain_to_unicode(ascii) {
+        (s, Ok(())) => Ok(s),
+        (_, Err(_)) => Err(SniError::InvalidIdna),
+    }
+}
@@ -0,0 +1,389 @@
+//! # TLS 1.3 key schedule
+//!
+//! The TLS 1.3 secrets are derived from the (EC)DHE shared secret and the optional PSK, using
+//! HKDF and the transcript hash ([RFC8446](https://tools.ietf.org/html/rfc8446) section 7.1):
+//!
+//! ```text
+//! PSK -> HKDF-Extract = Early Secret
+//!          +-> Derive-Secret(., "c e traffic", ClientHello)
+//!          v
+//! (EC)DHE -> HKDF-Extract = Handshake Secret
+//!          +-> Derive-Secret(., "c hs traffic", ClientHello...ServerHello)
+//!          +-> Derive-Secret(., "s hs traffic", ClientHello...ServerHello)
+//!          v
+//! 0 -> HKDF-Extract = Master Secret
+//!          +-> Derive-Secret(., "c ap traffic", ClientHello...server Finished)
+//!          +-> Derive-Secret(., "s ap traffic", ClientHello...server Finished)
+//!          +-> Derive-Secret(., "exp master", ClientHello...server Finished)
+//!          +-> Derive-Secret(., "res master", ClientHello...client Finished)
+//! ```
+//!
+//! The traffic keys and IVs of the records are derived from the traffic secrets (see
+//! `TlsTrafficKeys`). The transcript hashes can be computed using a `TlsTranscript`.
+
+use hmac::{Hmac, Mac, NewMac};
+use sha2::{Sha256, Sha384};
+
+use crate::tls_ciphers::{TlsCipherEnc, TlsCipherSuite};
+use crate::tls_keylog::TlsKeyLogSecrets;
+use crate::tls_transcript::TlsHashAlgorithm;
+
+pub(crate) fn hmac(alg: TlsHashAlgorithm, key: &[u8], data: &[&[u8]]) -> Vec<u8> {
+    macro_rules! hmac_with {
+        ($hash:ty) => {{
+            let mut mac = Hmac::<$hash>::new_from_slice(key).expect("HMAC accepts all key sizes");
+            for d in data {
+                mac.update(d);
+            }
+            mac.finalize().into_bytes().to_vec()
+        }};
+    }
+    match alg {
+        TlsHashAlgorithm::Sha256 => hmac_with!(Sha256),
+        TlsHashAlgorithm::Sha384 => hmac_with!(Sha384),
+    }
+}
+
+/// HKDF-Extract, as defined in [RFC5869](https://tools.ietf.org/html/rfc5869)
+///
+/// An empty salt is replaced by a string of zeros of the length of the hash.
+pub fn hkdf_extract(alg: TlsHashAlgorithm, salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    if salt.is_empty() {
+        hmac(alg, &vec![0; alg.output_len()], &[ikm])
+    } else {
+        hmac(alg, salt, &[ikm])
+    }
+}
+
+/// HKDF-Expand, as defined in [RFC5869](https://tools.ietf.org/html/rfc5869)
+///
+/// Panics if `len` is greater than 255 times the length of the hash.
+pub fn hkdf_expand(alg: TlsHashAlgorithm, prk: &[u8], info: &[u8], len: usize) -> Vec<u8> {
+    assert!(
+        len <= 255 * alg.output_len(),
+        "HKDF-Expand length too large"
+    );
+    let mut okm = Vec::with_capacity(len);
+    let mut t = Vec::new();
+    for counter in 1..=255u8 {
+        if okm.len() >= len {
+            break;
+        }
+        t = hmac(alg, prk, &[&t, info, &[counter]]);
+        okm.extend_from_slice(&t);
+    }
+    okm.truncate(len);
+    okm
+}
+
+/// HKDF-Expand-Label, as defined in [RFC8446](https://tools.ietf.org/html/rfc8446) section 7.1
+///
+/// `label` does not include the `tls13 ` prefix.
+///
+/// Panics if `len` does not fit in 16 bits, or if the label (with its prefix) or the context is
+/// longer than 255 bytes.
+pub fn hkdf_expand_label(
+    alg: TlsHashAlgorithm,
+    secret: &[u8],
+    label: &[u8],
+    context: &[u8],
+    len: usize,
+) -> Vec<u8> {
+    assert!(len <= 0xffff, "HKDF-Expand-Label length too large");
+    assert!(6 + label.len() <= 255, "HKDF-Expand-Label label too long");
+    assert!(context.len() <= 255, "HKDF-Expand-Label context too long");
+    let mut info = (len as u16).to_be_bytes().to_vec();
+    info.push((6 + label.len()) as u8);
+    info.extend_from_slice(b"tls13 ");
+    info.extend_from_slice(label);
+    info.push(context.len() as u8);
+    info.extend_from_slice(context);
+    hkdf_expand(alg, secret, &info, len)
+}
+
+/// Derive-Secret, as defined in [RFC8446](https://tools.ietf.org/html/rfc8446) section 7.1
+///
+/// `transcript_hash` is the hash of the messages (for ex., from `TlsTranscript::hash`).
+pub fn derive_secret(
+    alg: TlsHashAlgorithm,
+    secret: &[u8],
+    label: &[u8],
+    transcript_hash: &[u8],
+) -> Vec<u8> {
+    hkdf_expand_label(alg, secret, label, transcript_hash, alg.output_len())
+}
+
+/// Compute the next application traffic secret, after a KeyUpdate
+pub fn next_traffic_secret(alg: TlsHashAlgorithm, secret: &[u8]) -> Vec<u8> {
+    hkdf_expand_label(alg, secret, b"traffic upd", b"", alg.output_len())
+}
+
+/// Traffic key and IV, derived from a traffic secret
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlsTrafficKeys {
+    pub key: Vec<u8>,
+    pub iv: Vec<u8>,
+}
+
+impl TlsTrafficKeys {
+    /// Derive the keys of the TLS 1.3 cipher suite `cipher` from a traffic secret
+    pub fn new(cipher: &TlsCipherSuite, secret: &[u8]) -> Self {
+        let alg = TlsHashAlgorithm::from_ciphersuite(cipher);
+        // the ChaCha20 key size is not in the cipher suite table
+        let key_len = if cipher.enc == TlsCipherEnc::Chacha20_Poly1305 {
+            32
+        } else {
+            cipher.enc_size as usize / 8
+        };
+        TlsTrafficKeys {
+            key: hkdf_expand_label(alg, secret, b"key", b"", key_len),
+            iv: hkdf_expand_label(alg, secret, b"iv", b"", 12),
+        }
+    }
+}
+
+/// Key schedule of a TLS 1.3 connection
+///
+/// The early, handshake and master secrets are computed when the key schedule is created.
+/// The traffic secrets are derived from them using the transcript hash.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TlsKeySchedule {
+    alg: TlsHashAlgorithm,
+    early_secret: Vec<u8>,
+    handshake_secret: Vec<u8>,
+    master_secret: Vec<u8>,
+}
+
+impl TlsKeySchedule {
+    /// Create the key schedule from the PSK (if any) and the (EC)DHE shared secret
+    ///
+    /// `shared_secret` is empty if no (EC)DHE key exchange was done (`psk_ke` mode).
+    pub fn new(alg: TlsHashAlgorithm, psk: Option<&[u8]>, shared_secret: &[u8]) -> Self {
+        let zeros = vec![0; alg.output_len()];
+        let early_secret = hkdf_extract(alg, &[], psk.unwrap_or(&zeros));
+        let empty_hash = alg.hash(&[]);
+        let salt = derive_secret(alg, &early_secret, b"derived", &empty_hash);
+        let ikm = if shared_secret.is_empty() {
+            &zeros
+        } else {
+            shared_secret
+        };
+        let handshake_secret = hkdf_extract(alg, &salt, ikm);
+        let salt = derive_secret(alg, &handshake_secret, b"derived", &empty_hash);
+        let master_secret = hkdf_extract(alg, &salt, &zeros);
+        TlsKeySchedule {
+            alg,
+            early_secret,
+            handshake_secret,
+            master_secret,
+        }
+    }
+
+    /// Get the hash algorithm
+    pub fn algorithm(&self) -> TlsHashAlgorithm {
+        self.alg
+    }
+
+    pub fn early_secret(&self) -> &[u8] {
+        &self.early_secret
+    }
+
+    pub fn handshake_secret(&self) -> &[u8] {
+        &self.handshake_secret
+    }
+
+    pub fn master_secret(&self) -> &[u8] {
+        &self.master_secret
+    }
+
+    /// Derive the client early traffic secret (0-RTT), using the hash of the ClientHello
+    pub fn client_early_traffic_secret(&self, transcript_hash: &[u8]) -> Vec<u8> {
+        derive_secret(
+            self.alg,
+            &self.early_secret,
+            b"c e traffic",
+            transcript_hash,
+        )
+    }
+
+    /// Derive the client handshake traffic secret, using the hash of the messages up to the
+    /// ServerHello
+    pub fn client_handshake_traffic_secret(&self, transcript_hash: &[u8]) -> Vec<u8> {
+        derive_secret(
+            self.alg,
+            &self.handshake_secret,
+            b"c hs traffic",
+            transcript_hash,
+        )
+    }
+
+    /// Derive the server handshake traffic secret, using the hash of the messages up to the
+    /// ServerHello
+    pub fn server_handshake_traffic_secret(&self, transcript_hash: &[u8]) -> Vec<u8> {
+        derive_secret(
+            self.alg,
+            &self.handshake_secret,
+            b"s hs traffic",
+            transcript_hash,
+        )
+    }
+
+    /// Derive the first client application traffic secret, using the hash of the messages up
+    /// to the server Finished
+    pub fn client_application_traffic_secret_0(&self, transcript_hash: &[u8]) -> Vec<u8> {
+        derive_secret(
+            self.alg,
+            &self.master_secret,
+            b"c ap traffic",
+            transcript_hash,
+        )
+    }
+
+    /// Derive the first server application traffic secret, using the hash of the messages up
+    /// to the server Finished
+    pub fn server_application_traffic_secret_0(&self, transcript_hash: &[u8]) -> Vec<u8> {
+        derive_secret(
+            self.alg,
+            &self.master_secret,
+            b"s ap traffic",
+            transcript_hash,
+        )
+    }
+
+    /// Derive the exporter master secret, using the hash of the messages up to the server
+    /// Finished
+    pub fn exporter_master_secret(&self, transcript_hash: &[u8]) -> Vec<u8> {
+        derive_secret(
+            self.alg,
+            &self.master_secret,
+            b"exp master",
+            transcript_hash,
+        )
+    }
+
+    /// Derive the resumption master secret, using the hash of the messages up to the client
+    /// Finished
+    pub fn resumption_master_secret(&self, transcript_hash: &[u8]) -> Vec<u8> {
+        derive_secret(
+            self.alg,
+            &self.master_secret,
+            b"res master",
+            transcript_hash,
+        )
+    }
+
+    /// Derive the secrets of the session, as they would be written in a key log file
+    ///
+    /// `handshake_hash` is the hash of the messages up to the ServerHello, and
+    /// `application_hash` the hash of the messages up to the server Finished. The early
+    /// traffic secret is not derived.
+    pub fn keylog_secrets(
+        &self,
+        handshake_hash: &[u8],
+        application_hash: &[u8],
+    ) -> TlsKeyLogSecrets {
+        TlsKeyLogSecrets {
+            client_handshake_traffic_secret: Some(
+                self.client_handshake_traffic_secret(handshake_hash),
+            ),
+            server_handshake_traffic_secret: Some(
+                self.server_handshake_traffic_secret(handshake_hash),
+            ),
+            client_traffic_secret_0: Some(
+                self.client_application_traffic_secret_0(application_hash),
+            ),
+            server_traffic_secret_0: Some(
+                self.server_application_traffic_secret_0(application_hash),
+            ),
+            exporter_secret: Some(self.exporter_master_secret(application_hash)),
+            ..TlsKeyLogSecrets::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hkdf() {
+        // RFC 5869, test case 1
+        let ikm = &[0x0b; 22];
+        let salt = &[
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c,
+        ];
+        let prk = &[
+            0x07, 0x77, 0x09, 0x36, 0x2c, 0x2e, 0x32, 0xdf, 0x0d, 0xdc, 0x3f, 0x0d, 0xc4, 0x7b,
+            0xba, 0x63, 0x90, 0xb6, 0xc7, 0x3b, 0xb5, 0x0f, 0x9c, 0x31, 0x22, 0xec, 0x84, 0x4a,
+            0xd7, 0xc2, 0xb3, 0xe5,
+        ];
+        assert_eq!(
+            &hkdf_extract(TlsHashAlgorithm::Sha256, salt, ikm)[..],
+            &prk[..]
+        );
+        let info = &[0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9];
+        let okm = &[
+            0x3c, 0xb2, 0x5f, 0x25, 0xfa, 0xac, 0xd5, 0x7a, 0x90, 0x43, 0x4f, 0x64, 0xd0, 0x36,
+            0x2f, 0x2a, 0x2d, 0x2d, 0x0a, 0x90, 0xcf, 0x1a, 0x5a, 0x4c, 0x5d, 0xb0, 0x2d, 0x56,
+            0xec, 0xc4, 0xc5, 0xbf, 0x34, 0x00, 0x72, 0x08, 0xd5, 0xb8, 0x87, 0x18, 0x58, 0x65,
+        ];
+        assert_eq!(
+            &hkdf_expand(TlsHashAlgorithm::Sha256, prk, info, 42)[..],
+            &okm[..]
+        );
+        assert_eq!(
+            hkdf_expand(TlsHashAlgorithm::Sha256, prk, info, 255 * 32).len(),
+            255 * 32
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_hkdf_expand_too_long() {
+        hkdf_expand(TlsHashAlgorithm::Sha256, &[0; 32], b"", 255 * 32 + 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_hkdf_expand_label_too_long() {
+        hkdf_expand_label(TlsHashAlgorithm::Sha256, &[0; 32], &[b'a'; 250], b"", 32);
+    }
+
+    #[test]
+    fn test_key_schedule() {
+        // early secret and its "derived" secret, without PSK [RFC8448] 3
+        let early_secret = &[
+            0x33, 0xad, 0x0a, 0x1c, 0x60, 0x7e, 0xc0, 0x3b, 0x09, 0xe6, 0xcd, 0x98, 0x93, 0x68,
+            0x0c, 0xe2, 0x10, 0xad, 0xf3, 0x00, 0xaa, 0x1f, 0x26, 0x60, 0xe1, 0xb2, 0x2e, 0x10,
+            0xf1, 0x70, 0xf9, 0x2a,
+        ];
+        let derived = &[
+            0x6f, 0x26, 0x15, 0xa1, 0x08, 0xc7, 0x02, 0xc5, 0x67, 0x8f, 0x54, 0xfc, 0x9d, 0xba,
+            0xb6, 0x97, 0x16, 0xc0, 0x76, 0x18, 0x9c, 0x48, 0x25, 0x0c, 0xeb, 0xea, 0xc3, 0x57,
+            0x6c, 0x36, 0x11, 0xba,
+        ];
+        let alg = TlsHashAlgorithm::Sha256;
+        let ks = TlsKeySchedule::new(alg, None, &[0x42; 32]);
+        assert_eq!(ks.early_secret(), &early_secret[..]);
+        let empty_hash = alg.hash(&[]);
+        assert_eq!(
+            &derive_secret(alg, ks.early_secret(), b"derived", &empty_hash)[..],
+            &derived[..]
+        );
+        assert_eq!(ks, TlsKeySchedule::new(alg, Some(&[0; 32]), &[0x42; 32]));
+        assert_ne!(ks, TlsKeySchedule::new(alg, None, &[]));
+        let secrets = ks.keylog_secrets(&[1; 32], &[2; 32]);
+        assert_eq!(
+            secrets.server_handshake_traffic_secret,
+            Some(ks.server_handshake_traffic_secret(&[1; 32]))
+        );
+        assert_eq!(
+            secrets.client_traffic_secret_0,
+            Some(ks.client_application_traffic_secret_0(&[2; 32]))
+        );
+        assert_eq!(secrets.master_secret, None);
+        let cipher = TlsCipherSuite::from_id(0x1303).expect("unknown cipher suite");
+        let keys = TlsTrafficKeys::new(cipher, &ks.client_handshake_traffic_secret(&[1; 32]));
+        assert_eq!((keys.key.len(), keys.iv.len()), (32, 12));
+    }
+}
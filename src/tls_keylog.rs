@@ -0,0 +1,146 @@
+//! # Key log secrets
+//!
+//! TLS libraries (NSS, OpenSSL, BoringSSL, etc.) can log the secrets of each session to a file,
+//! usually using the `SSLKEYLOGFILE` environment variable. These secrets allow a passive
+//! observer to decrypt the session (see `TlsSessionDecryptor`, with the `decrypt` feature).
+//!
+//! The key log file is a text file (the NSS key log format), with one secret per line. Empty
+//! lines and lines starting with `#` are ignored. Each line has a label, the client random of
+//! the session and the secret, in hexadecimal:
+//!
+//! ```text
+//! # TLS 1.2
+//! CLIENT_RANDOM <client random> <master secret>
+//! # TLS 1.3
+//! CLIENT_HANDSHAKE_TRAFFIC_SECRET <client random> <secret>
+//! SERVER_HANDSHAKE_TRAFFIC_SECRET <client random> <secret>
+//! CLIENT_TRAFFIC_SECRET_0 <client random> <secret>
+//! SERVER_TRAFFIC_SECRET_0 <client random> <secret>
+//! ```
+//!
+//! `TlsKeyLog` parses the file, and groups the secrets by session. Lines with other labels
+//! (for ex. `RSA`, keyed by the encrypted premaster secret) are ignored.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Secrets of a session, as written in a key log file
+///
+/// TLS 1.2 and earlier sessions only have a master secret. TLS 1.3 sessions have a secret for
+/// each traffic key.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TlsKeyLogSecrets {
+    /// Master secret (`CLIENT_RANDOM`), TLS 1.2 and earlier
+    pub master_secret: Option<Vec<u8>>,
+    /// `CLIENT_EARLY_TRAFFIC_SECRET` (TLS 1.3 0-RTT)
+    pub client_early_traffic_secret: Option<Vec<u8>>,
+    /// `CLIENT_HANDSHAKE_TRAFFIC_SECRET`
+    pub client_handshake_traffic_secret: Option<Vec<u8>>,
+    /// `SERVER_HANDSHAKE_TRAFFIC_SECRET`
+    pub server_handshake_traffic_secret: Option<Vec<u8>>,
+    /// `CLIENT_TRAFFIC_SECRET_0`
+    pub client_traffic_secret_0: Option<Vec<u8>>,
+    /// `SERVER_TRAFFIC_SECRET_0`
+    pub server_traffic_secret_0: Option<Vec<u8>>,
+    /// `EXPORTER_SECRET`
+    pub exporter_secret: Option<Vec<u8>>,
+}
+
+/// Error returned when loading a key log file
+#[derive(Debug)]
+pub enum TlsKeyLogError {
+    Io(io::Error),
+    /// The line (starting at 1) is not a valid entry
+    InvalidLine(usize),
+}
+
+impl From<io::Error> for TlsKeyLogError {
+    fn from(e: io::Error) -> Self {
+        TlsKeyLogError::Io(e)
+    }
+}
+
+fn parse_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 == 1 || !s.is_ascii() {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Secrets of a key log file, indexed by client random
+#[derive(Clone, Debug, Default)]
+pub struct TlsKeyLog {
+    sessions: HashMap<Vec<u8>, TlsKeyLogSecrets>,
+}
+
+impl TlsKeyLog {
+    /// Create an empty key log
+    pub fn new() -> Self {
+        TlsKeyLog::default()
+    }
+
+    /// Load a key log file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, TlsKeyLogError> {
+        let data = fs::read_to_string(path)?;
+        TlsKeyLog::parse(&data)
+    }
+
+    /// Load a key log from the content of a file
+    pub fn parse(data: &str) -> Result<Self, TlsKeyLogError> {
+        let mut keylog = TlsKeyLog::new();
+        for (idx, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            keylog
+                .add_line(line)
+                .ok_or(TlsKeyLogError::InvalidLine(idx + 1))?;
+        }
+        Ok(keylog)
+    }
+
+    fn add_line(&mut self, line: &str) -> Option<()> {
+        let mut parts = line.split_whitespace();
+        let label = parts.next()?;
+        // the fields of other labels are not checked
+        let field: fn(&mut TlsKeyLogSecrets) -> &mut Option<Vec<u8>> = match label {
+            "CLIENT_RANDOM" => |s| &mut s.master_secret,
+            "CLIENT_EARLY_TRAFFIC_SECRET" => |s| &mut s.client_early_traffic_secret,
+            "CLIENT_HANDSHAKE_TRAFFIC_SECRET" => |s| &mut s.client_handshake_traffic_secret,
+            "SERVER_HANDSHAKE_TRAFFIC_SECRET" => |s| &mut s.server_handshake_traffic_secret,
+            "CLIENT_TRAFFIC_SECRET_0" => |s| &mut s.client_traffic_secret_0,
+            "SERVER_TRAFFIC_SECRET_0" => |s| &mut s.server_traffic_secret_0,
+            "EXPORTER_SECRET" => |s| &mut s.exporter_secret,
+            _ => return Some(()),
+        };
+        let client_random = parse_hex(parts.next()?)?;
+        let secret = parse_hex(parts.next()?)?;
+        if parts.next().is_some() {
+            return None;
+        }
+        let secrets = self.sessions.entry(client_random).or_default();
+        *field(secrets) = Some(secret);
+        Some(())
+    }
+
+    /// Get the secrets of the session using the client random `client_random`
+    pub fn get(&self, client_random: &[u8]) -> Option<&TlsKeyLogSecrets> {
+        self.sessions.get(client_random)
+    }
+
+    /// Get the number of sessions
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Test if the key log has no sessions
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+}
@@ -0,0 +1,208 @@
+//! Decoding of key exchange messages, depending on the negotiated cipher suite
+//!
+//! The content of the ServerKeyExchange and ClientKeyExchange messages depends on the key
+//! exchange method of the cipher suite selected in the ServerHello, so these messages are
+//! stored unparsed (see `TlsServerKeyExchangeContents`). The functions of this module decode
+//! them once the cipher suite is known.
+
+use nom::error::{make_error, ErrorKind};
+use nom::multi::length_data;
+use nom::number::streaming::be_u16;
+use nom::{Err, IResult};
+use nom_derive::Nom;
+
+use crate::tls_ciphers::{TlsCipherAu, TlsCipherKx, TlsCipherSuite};
+use crate::tls_dh::{integer_bits, parse_dh_params, ServerDHParams};
+use crate::tls_ec::{parse_ecdh_params, ECPoint, ServerECDHParams};
+use crate::tls_sign_hash::{parse_content_and_signature, DigitallySigned};
+
+/// Temporary RSA key, sent by the server for export cipher suites (RSA_EXPORT)
+///
+/// Defined in [RFC2246](https://tools.ietf.org/html/rfc2246) section 7.4.3: if the server
+/// certificate key is larger than 512 bits, the server sends a 512-bit RSA key to be used for
+/// the key exchange.
+#[derive(PartialEq, Nom)]
+pub struct ServerRSAParams<'a> {
+    #[nom(Parse = "length_data(be_u16)")]
+    pub rsa_modulus: &'a [u8],
+    #[nom(Parse = "length_data(be_u16)")]
+    pub rsa_exponent: &'a [u8],
+}
+
+impl<'a> ServerRSAParams<'a> {
+    /// Get the size of the RSA modulus, in bits
+    pub fn modulus_bits(&self) -> usize {
+        integer_bits(self.rsa_modulus)
+    }
+}
+
+#[inline]
+pub fn parse_rsa_params(i: &[u8]) -> IResult<&[u8], ServerRSAParams> {
+    ServerRSAParams::parse(i)
+}
+
+/// Server key exchange parameters, decoded according to the key exchange method
+#[derive(Debug, PartialEq)]
+pub enum ServerKeyExchangeParams<'a> {
+    /// Temporary RSA key, signed by the server (RSA_EXPORT)
+    RsaExport(ServerRSAParams<'a>, DigitallySigned<'a>),
+    /// Ephemeral Diffie-Hellman parameters, signed by the server (DHE_RSA, DHE_DSS)
+    Dhe(ServerDHParams<'a>, DigitallySigned<'a>),
+    /// Ephemeral Elliptic Curve Diffie-Hellman parameters, signed by the server (ECDHE_RSA,
+    /// ECDHE_ECDSA)
+    Ecdhe(ServerECDHParams<'a>, DigitallySigned<'a>),
+    /// Anonymous Diffie-Hellman parameters (DH_anon)
+    DhAnon(ServerDHParams<'a>),
+    /// Anonymous Elliptic Curve Diffie-Hellman parameters (ECDH_anon)
+    EcdhAnon(ServerECDHParams<'a>),
+    /// PSK identity hint, as defined in [RFC4279](https://tools.ietf.org/html/rfc4279)
+    /// (PSK, RSA_PSK)
+    Psk { identity_hint: &'a [u8] },
+    /// PSK identity hint and Diffie-Hellman parameters (DHE_PSK)
+    DhePsk {
+        identity_hint: &'a [u8],
+        params: ServerDHParams<'a>,
+    },
+    /// PSK identity hint and Elliptic Curve Diffie-Hellman parameters, as defined in
+    /// [RFC5489](https://tools.ietf.org/html/rfc5489) (ECDHE_PSK)
+    EcdhePsk {
+        identity_hint: &'a [u8],
+        params: ServerECDHParams<'a>,
+    },
+}
+
+/// Parse the content of a ServerKeyExchange message, for the negotiated cipher suite
+///
+/// `has_algorithm` must be true for TLS 1.2, where the signature is preceded by the signature
+/// algorithm, and false for previous versions.
+///
+/// Key exchange methods where the ServerKeyExchange message is not sent (static RSA, DH or
+/// ECDH) or not supported (SRP) return a `Switch` error.
+pub fn parse_server_key_exchange_params<'a>(
+    i: &'a [u8],
+    cipher: &TlsCipherSuite,
+    has_algorithm: bool,
+) -> IResult<&'a [u8], ServerKeyExchangeParams<'a>> {
+    match (cipher.kx, cipher.au) {
+        (TlsCipherKx::Dhe, TlsCipherAu::Psk) => {
+            let (i, identity_hint) = length_data(be_u16)(i)?;
+            let (i, params) = parse_dh_params(i)?;
+            let kx = ServerKeyExchangeParams::DhePsk {
+                identity_hint,
+                params,
+            };
+            Ok((i, kx))
+        }
+        (TlsCipherKx::Ecdhe, TlsCipherAu::Psk) => {
+            let (i, identity_hint) = length_data(be_u16)(i)?;
+            let (i, params) = parse_ecdh_params(i)?;
+            let kx = ServerKeyExchangeParams::EcdhePsk {
+                identity_hint,
+                params,
+            };
+            Ok((i, kx))
+        }
+        (TlsCipherKx::Psk, _) | (TlsCipherKx::Rsa, TlsCipherAu::Psk) => {
+            let (i, identity_hint) = length_data(be_u16)(i)?;
+            Ok((i, ServerKeyExchangeParams::Psk { identity_hint }))
+        }
+        (TlsCipherKx::Rsa, TlsCipherAu::Rsa) if cipher.is_export() => {
+            let (i, (params, signature)) =
+                parse_content_and_signature(i, parse_rsa_params, has_algorithm)?;
+            Ok((i, ServerKeyExchangeParams::RsaExport(params, signature)))
+        }
+        (TlsCipherKx::Dhe, _) => {
+            let (i, (params, signature)) =
+                parse_content_and_signature(i, parse_dh_params, has_algorithm)?;
+            Ok((i, ServerKeyExchangeParams::Dhe(params, signature)))
+        }
+        (TlsCipherKx::Ecdhe, _) => {
+            let (i, (params, signature)) =
+                parse_content_and_signature(i, parse_ecdh_params, has_algorithm)?;
+            Ok((i, ServerKeyExchangeParams::Ecdhe(params, signature)))
+        }
+        (TlsCipherKx::Dh, TlsCipherAu::Null) => {
+            let (i, params) = parse_dh_params(i)?;
+            Ok((i, ServerKeyExchangeParams::DhAnon(params)))
+        }
+        (TlsCipherKx::Ecdh, TlsCipherAu::Null) | (TlsCipherKx::Aecdh, _) => {
+            let (i, params) = parse_ecdh_params(i)?;
+            Ok((i, ServerKeyExchangeParams::EcdhAnon(params)))
+        }
+        _ => Err(Err::Error(make_error(i, ErrorKind::Switch))),
+    }
+}
+
+/// Client key exchange parameters, decoded according to the key exchange method
+#[derive(Debug, PartialEq)]
+pub enum ClientKeyExchangeParams<'a> {
+    /// Premaster secret, encrypted with the server RSA public key (RSA)
+    Rsa { encrypted_pms: &'a [u8] },
+    /// Client Diffie-Hellman public value (DHE, DH_anon)
+    Dh { dh_yc: &'a [u8] },
+    /// Client Elliptic Curve Diffie-Hellman public value (ECDHE, ECDH_anon)
+    Ecdh(ECPoint<'a>),
+    /// PSK identity, as defined in [RFC4279](https://tools.ietf.org/html/rfc4279) (PSK)
+    Psk { identity: &'a [u8] },
+    /// PSK identity and encrypted premaster secret (RSA_PSK)
+    RsaPsk {
+        identity: &'a [u8],
+        encrypted_pms: &'a [u8],
+    },
+    /// PSK identity and client Diffie-Hellman public value (DHE_PSK)
+    DhePsk { identity: &'a [u8], dh_yc: &'a [u8] },
+    /// PSK identity and client Elliptic Curve Diffie-Hellman public value (ECDHE_PSK)
+    EcdhePsk {
+        identity: &'a [u8],
+        point: ECPoint<'a>,
+    },
+}
+
+/// Parse the content of a ClientKeyExchange message, for the negotiated cipher suite
+///
+/// The encrypted premaster secret is expected with a length prefix (TLS 1.0 and later). Key
+/// exchange methods not supported (for ex. SRP, or static DH with a client certificate) return
+/// a `Switch` error.
+pub fn parse_client_key_exchange_params<'a>(
+    i: &'a [u8],
+    cipher: &TlsCipherSuite,
+) -> IResult<&'a [u8], ClientKeyExchangeParams<'a>> {
+    match (cipher.kx, cipher.au) {
+        (TlsCipherKx::Rsa, TlsCipherAu::Psk) => {
+            let (i, identity) = length_data(be_u16)(i)?;
+            let (i, encrypted_pms) = length_data(be_u16)(i)?;
+            let kx = ClientKeyExchangeParams::RsaPsk {
+                identity,
+                encrypted_pms,
+            };
+            Ok((i, kx))
+        }
+        (TlsCipherKx::Dhe, TlsCipherAu::Psk) => {
+            let (i, identity) = length_data(be_u16)(i)?;
+            let (i, dh_yc) = length_data(be_u16)(i)?;
+            Ok((i, ClientKeyExchangeParams::DhePsk { identity, dh_yc }))
+        }
+        (TlsCipherKx::Ecdhe, TlsCipherAu::Psk) => {
+            let (i, identity) = length_data(be_u16)(i)?;
+            let (i, point) = ECPoint::parse(i)?;
+            Ok((i, ClientKeyExchangeParams::EcdhePsk { identity, point }))
+        }
+        (TlsCipherKx::Psk, _) => {
+            let (i, identity) = length_data(be_u16)(i)?;
+            Ok((i, ClientKeyExchangeParams::Psk { identity }))
+        }
+        (TlsCipherKx::Rsa, _) => {
+            let (i, encrypted_pms) = length_data(be_u16)(i)?;
+            Ok((i, ClientKeyExchangeParams::Rsa { encrypted_pms }))
+        }
+        (TlsCipherKx::Dhe, _) | (TlsCipherKx::Dh, TlsCipherAu::Null) => {
+            let (i, dh_yc) = length_data(be_u16)(i)?;
+            Ok((i, ClientKeyExchangeParams::Dh { dh_yc }))
+        }
+        (TlsCipherKx::Ecdhe, _) | (TlsCipherKx::Ecdh, _) | (TlsCipherKx::Aecdh, _) => {
+            let (i, point) = ECPoint::parse(i)?;
+            Ok((i, ClientKeyExchangeParams::Ecdh(point)))
+        }
+        _ => Err(Err::Error(make_error(i, ErrorKind::Switch))),
+    }
+}
@@ -0,0 +1,326 @@
+//! Decoding of OCSP responses, as defined in [RFC6960](https://tools.ietf.org/html/rfc6960),
+//! using [x509-parser](https://crates.io/crates/x509-parser)
+//!
+//! OCSP responses are stapled by the server in the CertificateStatus message (TLS 1.2), or in
+//! the `status_request` extension of a certificate entry (TLS 1.3).
+//!
+//! This module is only available with the `x509` feature.
+
+use rusticata_macros::newtype_enum;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::der_parser::ber::{parse_ber_integer, BitStringObject, Class, Tag};
+use x509_parser::der_parser::der::*;
+use x509_parser::error::{X509Error, X509Result};
+use x509_parser::nom::combinator::{complete, opt};
+use x509_parser::nom::multi::many0;
+use x509_parser::nom::{Err, Offset};
+use x509_parser::time::ASN1Time;
+use x509_parser::traits::FromDer;
+use x509_parser::x509::{AlgorithmIdentifier, ReasonCode, X509Name};
+
+use crate::tls::TlsCertificateStatusContents;
+use crate::tls_extensions::CertificateStatusType;
+
+/// DER encoding of the `id-pkix-ocsp-basic` OID (1.3.6.1.5.5.7.48.1.1)
+const OID_PKIX_OCSP_BASIC: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01];
+
+/// OCSP response status, as defined in [RFC6960](https://tools.ietf.org/html/rfc6960) section 4.2.1
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct OcspResponseStatus(pub u32);
+
+newtype_enum! {
+impl debug OcspResponseStatus {
+    Successful       = 0,
+    MalformedRequest = 1,
+    InternalError    = 2,
+    TryLater         = 3,
+    SigRequired      = 5,
+    Unauthorized     = 6,
+}
+}
+
+/// An OCSP response
+///
+/// `basic` is only present if the status is `Successful` and the response type is
+/// `id-pkix-ocsp-basic` (the only type defined by RFC6960).
+#[derive(Debug, PartialEq)]
+pub struct OcspResponse<'a> {
+    pub status: OcspResponseStatus,
+    pub basic: Option<BasicOcspResponse<'a>>,
+}
+
+/// A basic OCSP response, signed by the responder
+#[derive(Debug, PartialEq)]
+pub struct BasicOcspResponse<'a> {
+    pub tbs_response_data: OcspResponseData<'a>,
+    pub signature_algorithm: AlgorithmIdentifier<'a>,
+    pub signature_value: BitStringObject<'a>,
+    /// Certificates provided to help the client verify the responder's signature
+    pub certs: Vec<X509Certificate<'a>>,
+}
+
+/// Signed part of a basic OCSP response
+#[derive(Debug, PartialEq)]
+pub struct OcspResponseData<'a> {
+    pub version: u32,
+    pub responder_id: OcspResponderId<'a>,
+    pub produced_at: ASN1Time,
+    pub responses: Vec<OcspSingleResponse<'a>>,
+    /// Raw DER encoding, covered by the signature
+    pub raw: &'a [u8],
+}
+
+/// Identification of the OCSP responder
+#[derive(Debug, PartialEq)]
+pub enum OcspResponderId<'a> {
+    ByName(X509Name<'a>),
+    /// SHA-1 hash of the responder's public key
+    ByKey(&'a [u8]),
+}
+
+/// Status of a single certificate
+#[derive(Debug, PartialEq)]
+pub struct OcspSingleResponse<'a> {
+    pub cert_id: OcspCertId<'a>,
+    pub cert_status: OcspCertStatus,
+    pub this_update: ASN1Time,
+    pub next_update: Option<ASN1Time>,
+}
+
+/// Identification of a certificate, by its issuer and serial number
+#[derive(Debug, PartialEq)]
+pub struct OcspCertId<'a> {
+    pub hash_algorithm: AlgorithmIdentifier<'a>,
+    pub issuer_name_hash: &'a [u8],
+    pub issuer_key_hash: &'a [u8],
+    /// Raw (big-endian) serial number
+    pub serial: &'a [u8],
+}
+
+/// Revocation status of a certificate
+#[derive(Debug, PartialEq)]
+pub enum OcspCertStatus {
+    Good,
+    Revoked {
+        revocation_time: ASN1Time,
+        revocation_reason: Option<ReasonCode>,
+    },
+    Unknown,
+}
+
+impl<'a> TlsCertificateStatusContents<'a> {
+    /// Decode the OCSP response carried by the message
+    ///
+    /// Return `None` if the status type is not `ocsp`.
+    pub fn ocsp_response(&self) -> Option<Result<OcspResponse<'a>, X509Error>> {
+        if self.status_type != CertificateStatusType::OCSP.0 {
+            return None;
+        }
+        Some(parse_ocsp_response(self.blob))
+    }
+}
+
+/// Decode a DER-encoded OCSPResponse
+///
+/// The input must contain exactly one response.
+pub fn parse_ocsp_response(i: &[u8]) -> Result<OcspResponse, X509Error> {
+    match parse_ocsp_response_der(i) {
+        Ok(([], resp)) => Ok(resp),
+        Ok(_) | Err(Err::Incomplete(_)) => Err(X509Error::InvalidCertificate),
+        Err(Err::Error(e)) | Err(Err::Failure(e)) => Err(e),
+    }
+}
+
+// OCSPResponse ::= SEQUENCE {
+//    responseStatus         OCSPResponseStatus,
+//    responseBytes          [0] EXPLICIT ResponseBytes OPTIONAL }
+fn parse_ocsp_response_der(i: &[u8]) -> X509Result<OcspResponse> {
+    parse_der_sequence_defined_g(|i, _| {
+        let (i, status) = parse_der_enum(i).map_err(Err::convert)?;
+        let status = OcspResponseStatus(status.as_u32().map_err(X509Error::from)?);
+        let (i, basic) = opt(complete(parse_der_tagged_explicit_g(0, |i, _| {
+            parse_response_bytes(i)
+        })))(i)?;
+        let resp = OcspResponse {
+            status,
+            basic: basic.flatten(),
+        };
+        Ok((i, resp))
+    })(i)
+}
+
+// ResponseBytes ::= SEQUENCE {
+//    responseType   OBJECT IDENTIFIER,
+//    response       OCTET STRING }
+fn parse_response_bytes(i: &[u8]) -> X509Result<Option<BasicOcspResponse>> {
+    parse_der_sequence_defined_g(|i, _| {
+        let (i, response_type) = parse_der_oid(i).map_err(Err::convert)?;
+        let (i, response) = parse_octet_string(i)?;
+        if response_type.as_oid().map_err(X509Error::from)?.as_bytes() != OID_PKIX_OCSP_BASIC {
+            return Ok((i, None));
+        }
+        let (_, basic) = parse_basic_ocsp_response(response)?;
+        Ok((i, Some(basic)))
+    })(i)
+}
+
+// BasicOCSPResponse ::= SEQUENCE {
+//    tbsResponseData      ResponseData,
+//    signatureAlgorithm   AlgorithmIdentifier,
+//    signature            BIT STRING,
+//    certs            [0] EXPLICIT SEQUENCE OF Certificate OPTIONAL }
+fn parse_basic_ocsp_response(i: &[u8]) -> X509Result<BasicOcspResponse> {
+    parse_der_sequence_defined_g(|i, _| {
+        let (i, tbs_response_data) = parse_response_data(i)?;
+        let (i, signature_algorithm) = AlgorithmIdentifier::from_der(i)?;
+        let (i, signature) = parse_der_bitstring(i).map_err(Err::convert)?;
+        let signature_value = signature.as_bitstring().map_err(X509Error::from)?;
+        let (i, certs) = opt(complete(parse_der_tagged_explicit_g(0, |i, _| {
+            parse_der_sequence_defined_g(|i, _| many0(complete(X509Certificate::from_der))(i))(i)
+        })))(i)?;
+        let resp = BasicOcspResponse {
+            tbs_response_data,
+            signature_algorithm,
+            signature_value,
+            certs: certs.unwrap_or_default(),
+        };
+        Ok((i, resp))
+    })(i)
+}
+
+// ResponseData ::= SEQUENCE {
+//    version              [0] EXPLICIT Version DEFAULT v1,
+//    responderID              ResponderID,
+//    producedAt               GeneralizedTime,
+//    responses                SEQUENCE OF SingleResponse,
+//    responseExtensions   [1] EXPLICIT Extensions OPTIONAL }
+fn parse_response_data(i: &[u8]) -> X509Result<OcspResponseData> {
+    let (rem, mut data) = parse_der_sequence_defined_g(|i, _| {
+        let (i, version) = opt(complete(parse_der_tagged_explicit_g(0, |i, _| {
+            parse_der_u32(i).or(Err(Err::Error(X509Error::InvalidVersion)))
+        })))(i)?;
+        let (i, responder_id) = parse_responder_id(i)?;
+        let (i, produced_at) = ASN1Time::from_der(i)?;
+        let (i, responses) =
+            parse_der_sequence_defined_g(|i, _| many0(complete(parse_single_response))(i))(i)?;
+        // response extensions are ignored
+        let data = OcspResponseData {
+            version: version.unwrap_or(0),
+            responder_id,
+            produced_at,
+            responses,
+            raw: &[],
+        };
+        Ok((i, data))
+    })(i)?;
+    data.raw = &i[..i.offset(rem)];
+    Ok((rem, data))
+}
+
+// ResponderID ::= CHOICE {
+//    byName   [1] Name,
+//    byKey    [2] KeyHash }
+fn parse_responder_id(i: &[u8]) -> X509Result<OcspResponderId> {
+    let (_, hdr) = der_read_element_header(i).map_err(Err::convert)?;
+    match hdr.tag() {
+        Tag(1) => parse_der_tagged_explicit_g(1, |i, _| {
+            let (i, name) = X509Name::from_der(i)?;
+            Ok((i, OcspResponderId::ByName(name)))
+        })(i),
+        Tag(2) => parse_der_tagged_explicit_g(2, |i, _| {
+            let (i, hash) = parse_octet_string(i)?;
+            Ok((i, OcspResponderId::ByKey(hash)))
+        })(i),
+        _ => Err(Err::Error(X509Error::InvalidX509Name)),
+    }
+}
+
+// SingleResponse ::= SEQUENCE {
+//    certID                       CertID,
+//    certStatus                   CertStatus,
+//    thisUpdate                   GeneralizedTime,
+//    nextUpdate         [0]       EXPLICIT GeneralizedTime OPTIONAL,
+//    singleExtensions   [1]       EXPLICIT Extensions OPTIONAL }
+fn parse_single_response(i: &[u8]) -> X509Result<OcspSingleResponse> {
+    parse_der_sequence_defined_g(|i, _| {
+        let (i, cert_id) = parse_cert_id(i)?;
+        let (i, cert_status) = parse_cert_status(i)?;
+        let (i, this_update) = ASN1Time::from_der(i)?;
+        let (i, next_update) = opt(complete(parse_der_tagged_explicit_g(0, |i, _| {
+            ASN1Time::from_der(i)
+        })))(i)?;
+        // single extensions are ignored
+        let resp = OcspSingleResponse {
+            cert_id,
+            cert_status,
+            this_update,
+            next_update,
+        };
+        Ok((i, resp))
+    })(i)
+}
+
+// CertID ::= SEQUENCE {
+//    hashAlgorithm       AlgorithmIdentifier,
+//    issuerNameHash      OCTET STRING, -- Hash of issuer's DN
+//    issuerKeyHash       OCTET STRING, -- Hash of issuer's public key
+//    serialNumber        CertificateSerialNumber }
+fn parse_cert_id(i: &[u8]) -> X509Result<OcspCertId> {
+    parse_der_sequence_defined_g(|i, _| {
+        let (i, hash_algorithm) = AlgorithmIdentifier::from_der(i)?;
+        let (i, issuer_name_hash) = parse_octet_string(i)?;
+        let (i, issuer_key_hash) = parse_octet_string(i)?;
+        // some responders encode leading zeroes, so accept BER integers (like x509-parser)
+        let (i, serial) = parse_ber_integer(i).or(Err(X509Error::InvalidSerial))?;
+        let serial = serial.as_slice().or(Err(X509Error::InvalidSerial))?;
+        let cert_id = OcspCertId {
+            hash_algorithm,
+            issuer_name_hash,
+            issuer_key_hash,
+            serial,
+        };
+        Ok((i, cert_id))
+    })(i)
+}
+
+// CertStatus ::= CHOICE {
+//    good        [0]     IMPLICIT NULL,
+//    revoked     [1]     IMPLICIT RevokedInfo,
+//    unknown     [2]     IMPLICIT UnknownInfo }
+//
+// RevokedInfo ::= SEQUENCE {
+//    revocationTime              GeneralizedTime,
+//    revocationReason    [0]     EXPLICIT CRLReason OPTIONAL }
+fn parse_cert_status(i: &[u8]) -> X509Result<OcspCertStatus> {
+    parse_der_container(|i, hdr| {
+        if hdr.class() != Class::ContextSpecific {
+            return Err(Err::Error(X509Error::InvalidAttributes));
+        }
+        match hdr.tag() {
+            Tag(0) => Ok((i, OcspCertStatus::Good)),
+            Tag(1) => {
+                let (i, revocation_time) = ASN1Time::from_der(i)?;
+                let (i, reason) = opt(complete(parse_der_tagged_explicit_g(0, |i, _| {
+                    parse_der_enum(i).map_err(Err::convert)
+                })))(i)?;
+                let revocation_reason = match reason {
+                    Some(obj) => Some(ReasonCode(obj.as_u32().map_err(X509Error::from)? as u8)),
+                    None => None,
+                };
+                let status = OcspCertStatus::Revoked {
+                    revocation_time,
+                    revocation_reason,
+                };
+                Ok((i, status))
+            }
+            Tag(2) => Ok((i, OcspCertStatus::Unknown)),
+            _ => Err(Err::Error(X509Error::InvalidAttributes)),
+        }
+    })(i)
+}
+
+fn parse_octet_string(i: &[u8]) -> X509Result<&[u8]> {
+    let (i, obj) = parse_der_octetstring(i).map_err(Err::convert)?;
+    let s = obj.as_slice().map_err(X509Error::from)?;
+    Ok((i, s))
+}
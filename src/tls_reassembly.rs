@@ -0,0 +1,485 @@
+//! Reassembly of TLS records and TLS and DTLS handshake messages
+//!
+//! TLS records can be split across several TCP segments (or reads): `TlsStreamDefragmenter`
+//! buffers the data of a stream until the records are complete.
+//!
+//! Handshake messages can be fragmented over several records (for ex. a large Certificate
+//! message), and a record can contain several messages. The parsing functions of
+//! [tls.rs](src/tls.rs) work on complete messages, so fragments must be buffered first.
+//!
+//! DTLS handshake messages carry their own fragment offset and length (see
+//! [RFC6347](https://tools.ietf.org/html/rfc6347) section 4.2.3), and fragments can be
+//! received out of order, duplicated, or overlapping.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use nom::combinator::complete;
+use nom::error::ErrorKind;
+use nom::multi::many0;
+
+use crate::dtls::*;
+use crate::tls::*;
+
+/// Default maximum size of the buffered data
+pub const DEFAULT_MAX_HANDSHAKE_BUFFER: usize = 1 << 17;
+
+/// Default maximum size of the buffered stream data
+pub const DEFAULT_MAX_STREAM_BUFFER: usize = 1 << 17;
+
+/// Error returned by the stream defragmenter
+#[derive(Debug, PartialEq)]
+pub enum TlsStreamError {
+    /// Buffered data would exceed the maximum size
+    BufferFull,
+    /// The record length exceeds the record length limits
+    RecordTooLarge,
+    /// The record header is invalid
+    InvalidRecord,
+}
+
+/// TLS stream defragmenter
+///
+/// Chunks of a TCP stream (of any size) are pushed in order, and complete records are
+/// returned as soon as they are available. Incomplete records are kept in an internal buffer
+/// until the next call.
+///
+/// Records are returned as raw records: the content can be parsed with
+/// `parse_tls_record_with_header` if the record is not encrypted, and handshake messages
+/// split over several records can be reassembled using `TlsHandshakeReassembler`.
+///
+/// ```rust
+/// # use tls_parser::{parse_tls_record_with_header, TlsStreamDefragmenter};
+/// # fn f(chunks: &[&[u8]]) {
+/// let mut defrag = TlsStreamDefragmenter::new();
+/// for chunk in chunks {
+///     match defrag.push(chunk) {
+///         Ok(records) => {
+///             for record in records {
+///                 let res = parse_tls_record_with_header(record.data, &record.hdr);
+///                 // use res
+///             }
+///         }
+///         Err(e) => eprintln!("defragmentation failed: {:?}", e),
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TlsStreamDefragmenter {
+    buffer: Vec<u8>,
+    /// Number of bytes of `buffer` returned by the previous call
+    consumed: usize,
+    max_size: usize,
+    limits: TlsRecordLimits,
+}
+
+impl Default for TlsStreamDefragmenter {
+    fn default() -> Self {
+        TlsStreamDefragmenter::new()
+    }
+}
+
+impl TlsStreamDefragmenter {
+    /// Create a new defragmenter, with a maximum buffer size of `DEFAULT_MAX_STREAM_BUFFER`
+    pub fn new() -> Self {
+        TlsStreamDefragmenter::with_max_size(DEFAULT_MAX_STREAM_BUFFER)
+    }
+
+    /// Create a new defragmenter, with a maximum buffer size of `max_size` bytes
+    ///
+    /// The maximum size applies to the incomplete record kept between calls, so it should be
+    /// larger than the largest record (`MAX_RECORD_LEN`, plus the header and the encryption
+    /// expansion). Pushed chunks can be larger.
+    pub fn with_max_size(max_size: usize) -> Self {
+        TlsStreamDefragmenter {
+            buffer: Vec::new(),
+            consumed: 0,
+            max_size,
+            limits: TlsRecordLimits::default(),
+        }
+    }
+
+    /// Set the record length limits (see `TlsRecordLimits`)
+    ///
+    /// A record larger than the limits is reported as `RecordTooLarge` as soon as its header
+    /// is received.
+    pub fn set_record_limits(&mut self, limits: TlsRecordLimits) {
+        self.limits = limits;
+    }
+
+    /// Test if an incomplete record is buffered
+    pub fn has_pending_data(&self) -> bool {
+        self.buffer.len() > self.consumed
+    }
+
+    /// Discard all buffered data
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.consumed = 0;
+    }
+
+    /// Add a chunk of the stream, and return all records completed by this data
+    ///
+    /// Records returned by the previous call are discarded. If the incomplete data kept after
+    /// the complete records would exceed the maximum buffer size, or if a record header is
+    /// invalid or too large, all buffered data is discarded (the records of the stream cannot
+    /// be found anymore).
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<TlsRawRecord>, TlsStreamError> {
+        self.buffer.drain(..self.consumed);
+        self.consumed = 0;
+        self.buffer.extend_from_slice(data);
+        // find the end of the last complete record
+        let mut end = 0;
+        loop {
+            match parse_tls_raw_record_with_limits(&self.buffer[end..], &self.limits) {
+                Ok((rem, _)) => end = self.buffer.len() - rem.len(),
+                Err(Err::Incomplete(_)) => break,
+                Err(Err::Error(e)) if e.code == ErrorKind::TooLarge => {
+                    self.reset();
+                    return Err(TlsStreamError::RecordTooLarge);
+                }
+                Err(_) => {
+                    self.reset();
+                    return Err(TlsStreamError::InvalidRecord);
+                }
+            }
+        }
+        if self.buffer.len() - end > self.max_size {
+            self.reset();
+            return Err(TlsStreamError::BufferFull);
+        }
+        self.consumed = end;
+        let records = TlsRecordIterator::with_limits(&self.buffer[..end], self.limits)
+            .map(|(hdr, data, _)| TlsRawRecord { hdr, data })
+            .collect();
+        Ok(records)
+    }
+}
+
+/// Error returned by the handshake reassembler
+#[derive(Debug, PartialEq)]
+pub enum HandshakeReassemblyError {
+    /// Buffered data would exceed the maximum size
+    BufferFull,
+    /// A complete message could not be parsed
+    InvalidMessage,
+}
+
+/// Handshake message reassembler
+///
+/// Payloads of successive handshake records are pushed in order, and complete handshake
+/// messages are returned as soon as they are available. Incomplete messages are kept in
+/// an internal buffer until the next call.
+///
+/// ```rust
+/// # use tls_parser::{parse_tls_raw_record, TlsHandshakeReassembler, TlsRecordType};
+/// # fn f(records: &[&[u8]]) {
+/// let mut reassembler = TlsHandshakeReassembler::new();
+/// for data in records {
+///     if let Ok((_, record)) = parse_tls_raw_record(data) {
+///         if record.hdr.record_type != TlsRecordType::Handshake {
+///             continue;
+///         }
+///         match reassembler.push(record.data) {
+///             Ok(messages) => {
+///                 for msg in messages {
+///                     // use msg
+///                 }
+///             }
+///             Err(e) => eprintln!("reassembly failed: {:?}", e),
+///         }
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TlsHandshakeReassembler {
+    buffer: Vec<u8>,
+    /// Number of bytes of `buffer` returned by the previous call
+    consumed: usize,
+    max_size: usize,
+    version: Option<TlsVersion>,
+}
+
+impl Default for TlsHandshakeReassembler {
+    fn default() -> Self {
+        TlsHandshakeReassembler::new()
+    }
+}
+
+impl TlsHandshakeReassembler {
+    /// Create a new reassembler, with a maximum buffer size of `DEFAULT_MAX_HANDSHAKE_BUFFER`
+    pub fn new() -> Self {
+        TlsHandshakeReassembler::with_max_size(DEFAULT_MAX_HANDSHAKE_BUFFER)
+    }
+
+    /// Create a new reassembler, with a maximum buffer size of `max_size` bytes
+    ///
+    /// Since a message is only returned when complete, this is also the maximum size of a
+    /// message (including the 4-bytes handshake header).
+    pub fn with_max_size(max_size: usize) -> Self {
+        TlsHandshakeReassembler {
+            buffer: Vec::new(),
+            consumed: 0,
+            max_size,
+            version: None,
+        }
+    }
+
+    /// Set the negotiated protocol version
+    ///
+    /// Some messages (for ex. Certificate and NewSessionTicket) have a different format in
+    /// TLS 1.3, see `parse_tls_message_handshake_with_version`.
+    pub fn set_version(&mut self, version: TlsVersion) {
+        self.version = Some(version);
+    }
+
+    /// Test if an incomplete message is buffered
+    pub fn has_pending_data(&self) -> bool {
+        self.buffer.len() > self.consumed
+    }
+
+    /// Discard all buffered data
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.consumed = 0;
+    }
+
+    /// Add the payload of a handshake record, and return all messages completed by this data
+    ///
+    /// Messages returned by the previous call are discarded. If the data would exceed the
+    /// maximum buffer size, all buffered data is discarded. If a complete message is invalid,
+    /// the complete messages are discarded, but an incomplete message is kept.
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<TlsMessage>, HandshakeReassemblyError> {
+        self.buffer.drain(..self.consumed);
+        self.consumed = 0;
+        if self.buffer.len() + data.len() > self.max_size {
+            self.reset();
+            return Err(HandshakeReassemblyError::BufferFull);
+        }
+        self.buffer.extend_from_slice(data);
+        // find the end of the last complete message
+        let mut end = 0;
+        while self.buffer.len() - end >= 4 {
+            let hdr = &self.buffer[end..end + 4];
+            let len = (hdr[1] as usize) << 16 | (hdr[2] as usize) << 8 | hdr[3] as usize;
+            if self.buffer.len() - end - 4 < len {
+                break;
+            }
+            end += 4 + len;
+        }
+        self.consumed = end;
+        let complete_data = &self.buffer[..end];
+        let res = match self.version {
+            Some(version) => many0(complete(|i| {
+                parse_tls_message_handshake_with_version(i, version)
+            }))(complete_data),
+            None => many0(complete(parse_tls_message_handshake))(complete_data),
+        };
+        match res {
+            Ok(([], messages)) => Ok(messages),
+            _ => Err(HandshakeReassemblyError::InvalidMessage),
+        }
+    }
+}
+
+/// Error returned by the DTLS handshake reassembler
+#[derive(Debug, PartialEq)]
+pub enum DTLSReassemblyError {
+    /// Buffered data would exceed the maximum size
+    BufferFull,
+    /// The fragment extends past the end of the message
+    InvalidFragment,
+    /// The fragment type or length differs from the previous fragments of the same message
+    InconsistentFragment,
+    /// The fragment overlaps previously received data, with different content
+    OverlapMismatch,
+    /// The reassembled message could not be parsed
+    InvalidMessage,
+}
+
+#[derive(Debug)]
+struct DTLSPendingMessage {
+    msg_type: TlsHandshakeType,
+    data: Vec<u8>,
+    /// Received ranges `[start, end)`, sorted and not overlapping
+    ranges: Vec<(u32, u32)>,
+}
+
+impl DTLSPendingMessage {
+    fn add_fragment(&mut self, offset: u32, fragment: &[u8]) -> Result<(), DTLSReassemblyError> {
+        let start = offset;
+        let end = offset + fragment.len() as u32;
+        // retransmitted (or re-fragmented) data must be identical
+        for &(s, e) in &self.ranges {
+            let (os, oe) = (s.max(start), e.min(end));
+            if os < oe
+                && self.data[os as usize..oe as usize]
+                    != fragment[(os - start) as usize..(oe - start) as usize]
+            {
+                return Err(DTLSReassemblyError::OverlapMismatch);
+            }
+        }
+        self.data[start as usize..end as usize].copy_from_slice(fragment);
+        let mut merged = Vec::with_capacity(self.ranges.len() + 1);
+        let (mut start, mut end) = (start, end);
+        for &(s, e) in &self.ranges {
+            if e < start || s > end {
+                merged.push((s, e));
+            } else {
+                start = start.min(s);
+                end = end.max(e);
+            }
+        }
+        merged.push((start, end));
+        merged.sort_unstable();
+        self.ranges = merged;
+        Ok(())
+    }
+
+    fn is_complete(&self) -> bool {
+        self.data.is_empty() || self.ranges == [(0, self.data.len() as u32)]
+    }
+
+    fn gaps(&self) -> Vec<(u32, u32)> {
+        let mut gaps = Vec::new();
+        let mut pos = 0;
+        for &(s, e) in &self.ranges {
+            if s > pos {
+                gaps.push((pos, s));
+            }
+            pos = e;
+        }
+        if (pos as usize) < self.data.len() {
+            gaps.push((pos, self.data.len() as u32));
+        }
+        gaps
+    }
+}
+
+/// DTLS handshake message reassembler
+///
+/// Fragments are identified by their `message_seq`, and can be pushed in any order. When all
+/// bytes of a message have been received, the message is parsed and returned. Duplicate
+/// fragments are accepted if their content is the same, and fragments of messages already
+/// reassembled (retransmissions) are ignored.
+///
+/// Use `gaps` to get the missing parts of an incomplete message.
+#[derive(Debug)]
+pub struct DTLSHandshakeReassembler {
+    pending: BTreeMap<u16, DTLSPendingMessage>,
+    completed: BTreeSet<u16>,
+    /// Body of the last reassembled message
+    last: Vec<u8>,
+    max_size: usize,
+}
+
+impl Default for DTLSHandshakeReassembler {
+    fn default() -> Self {
+        DTLSHandshakeReassembler::new()
+    }
+}
+
+impl DTLSHandshakeReassembler {
+    /// Create a new reassembler, with a maximum buffer size of `DEFAULT_MAX_HANDSHAKE_BUFFER`
+    pub fn new() -> Self {
+        DTLSHandshakeReassembler::with_max_size(DEFAULT_MAX_HANDSHAKE_BUFFER)
+    }
+
+    /// Create a new reassembler, with a maximum buffer size of `max_size` bytes
+    ///
+    /// The buffer size is the sum of the lengths of all incomplete messages.
+    pub fn with_max_size(max_size: usize) -> Self {
+        DTLSHandshakeReassembler {
+            pending: BTreeMap::new(),
+            completed: BTreeSet::new(),
+            last: Vec::new(),
+            max_size,
+        }
+    }
+
+    /// Get the sequence numbers of the incomplete messages
+    pub fn pending_messages(&self) -> Vec<u16> {
+        self.pending.keys().copied().collect()
+    }
+
+    /// Get the missing byte ranges `[start, end)` of an incomplete message
+    ///
+    /// Return `None` if no fragment was received for this message, or if it is complete.
+    pub fn gaps(&self, message_seq: u16) -> Option<Vec<(u32, u32)>> {
+        self.pending.get(&message_seq).map(|m| m.gaps())
+    }
+
+    /// Discard all buffered data
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.completed.clear();
+        self.last.clear();
+    }
+
+    /// Add a handshake message fragment, and return the message if it is now complete
+    ///
+    /// Messages which are not fragmented are ignored, and `Ok(None)` is returned.
+    /// On error, the fragments of this message are discarded.
+    pub fn push(
+        &mut self,
+        msg: &DTLSMessageHandshake,
+    ) -> Result<Option<DTLSMessageHandshake>, DTLSReassemblyError> {
+        let fragment = match msg.body {
+            DTLSMessageHandshakeBody::Fragment(f) => f,
+            _ => return Ok(None),
+        };
+        if self.completed.contains(&msg.message_seq) {
+            return Ok(None);
+        }
+        if msg.fragment_offset as usize + fragment.len() > msg.length as usize {
+            return Err(DTLSReassemblyError::InvalidFragment);
+        }
+        if !self.pending.contains_key(&msg.message_seq) {
+            let buffered: usize = self.pending.values().map(|m| m.data.len()).sum();
+            if buffered + msg.length as usize > self.max_size {
+                return Err(DTLSReassemblyError::BufferFull);
+            }
+            let pending = DTLSPendingMessage {
+                msg_type: msg.msg_type,
+                data: vec![0; msg.length as usize],
+                ranges: Vec::new(),
+            };
+            self.pending.insert(msg.message_seq, pending);
+        }
+        let pending = self
+            .pending
+            .get_mut(&msg.message_seq)
+            .expect("pending message");
+        let res = if pending.msg_type != msg.msg_type || pending.data.len() != msg.length as usize {
+            Err(DTLSReassemblyError::InconsistentFragment)
+        } else {
+            pending.add_fragment(msg.fragment_offset, fragment)
+        };
+        if let Err(e) = res {
+            self.pending.remove(&msg.message_seq);
+            return Err(e);
+        }
+        if !pending.is_complete() {
+            return Ok(None);
+        }
+        let pending = self
+            .pending
+            .remove(&msg.message_seq)
+            .expect("pending message");
+        self.completed.insert(msg.message_seq);
+        self.last = pending.data;
+        let (_, body) =
+            parse_dtls_handshake_msg_body(&self.last, pending.msg_type, self.last.len())
+                .or(Err(DTLSReassemblyError::InvalidMessage))?;
+        let msg = DTLSMessageHandshake {
+            msg_type: pending.msg_type,
+            length: msg.length,
+            message_seq: msg.message_seq,
+            fragment_offset: 0,
+            fragment_length: msg.length,
+            body,
+        };
+        Ok(Some(msg))
+    }
+}
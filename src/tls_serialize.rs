@@ -1,6 +1,6 @@
 use crate::tls::*;
 use crate::tls_ec::{ECPoint, NamedGroup};
-use crate::tls_extensions::{SNIType, TlsExtension, TlsExtensionType};
+use crate::tls_extensions::{MaxFragmentLength, SNIType, TlsExtension, TlsExtensionType};
 use cookie_factory::bytes::{be_u16, be_u24, be_u32, be_u8};
 use cookie_factory::combinator::slice;
 use cookie_factory::multi::{all, many_ref};
@@ -59,11 +59,14 @@ where
     )
 }
 
-fn gen_tls_ext_max_fragment_length<W>(l: u8) -> impl SerializeFn<W>
+fn gen_tls_ext_max_fragment_length<W>(l: MaxFragmentLength) -> impl SerializeFn<W>
 where
     W: Write,
 {
-    tagged_extension(u16::from(TlsExtensionType::MaxFragmentLength), be_u8(l))
+    tagged_extension(
+        u16::from(TlsExtensionType::MaxFragmentLength),
+        be_u8(u8::from(l)),
+    )
 }
 
 fn gen_tls_named_group<W>(g: NamedGroup) -> impl SerializeFn<W>
@@ -270,6 +273,19 @@ where
     ))
 }
 
+/// Serialize a synthetic message_hash message (TLS 1.3)
+///
+/// This message is used to reconstruct the handshake transcript after a HelloRetryRequest.
+pub fn gen_tls_message_hash<'a, W>(m: &'a [u8]) -> impl SerializeFn<W> + 'a
+where
+    W: Write + 'a,
+{
+    tuple((
+        be_u8(u8::from(TlsHandshakeType::MessageHash)),
+        length_be_u24(slice(m)),
+    ))
+}
+
 /// Serialize a TLS handshake message
 fn gen_tls_messagehandshake<'a, W>(m: &'a TlsMessageHandshake<'a>) -> impl SerializeFn<W> + 'a
 where
@@ -282,6 +298,7 @@ where
         TlsMessageHandshake::ServerHelloV13Draft18(ref m) => gen_tls_serverhellodraft18(m)(out),
         TlsMessageHandshake::ClientKeyExchange(ref m) => gen_tls_clientkeyexchange(m)(out),
         TlsMessageHandshake::Finished(ref m) => gen_tls_finished(m)(out),
+        TlsMessageHandshake::MessageHash(m) => gen_tls_message_hash(m)(out),
         _ => Err(GenError::NotYetImplemented),
     }
 }
@@ -473,6 +490,19 @@ mod tests {
         assert_eq!(&v[..], &res[..]);
     }
 
+    #[test]
+    fn serialize_message_hash() {
+        let hash = [0xaa; 32];
+        let m = TlsMessageHandshake::MessageHash(&hash);
+
+        let res = m.serialize().expect("Could not serialize messages");
+        assert_eq!(&res[..4], &[0xfe, 0x00, 0x00, 0x20]);
+        assert_eq!(&res[4..], &hash[..]);
+        let (_, res_reparse) =
+            parse_tls_message_handshake(&res).expect("Could not parse message_hash");
+        assert_eq!(res_reparse, TlsMessage::Handshake(m));
+    }
+
     #[test]
     fn serialize_tls_ext() {
         let ext = TlsExtension::SNI(vec![(SNIType::HostName, b"www.google.com")]);
@@ -7,7 +7,7 @@ use nom_derive::Nom;
 use rusticata_macros::newtype_enum;
 
 /// Hash algorithms, as defined in [RFC5246]
-#[derive(Debug, PartialEq, Eq, Nom)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Nom)]
 pub struct HashAlgorithm(pub u8);
 
 newtype_enum! {
@@ -24,7 +24,7 @@ impl display HashAlgorithm {
 }
 
 /// Signature algorithms, as defined in [RFC5246]
-#[derive(Debug, PartialEq, Eq, Nom)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Nom)]
 pub struct SignAlgorithm(pub u8);
 
 newtype_enum! {
@@ -38,14 +38,16 @@ impl display SignAlgorithm {
 }
 }
 
-#[derive(PartialEq, Nom)]
+#[derive(Clone, Copy, PartialEq, Nom)]
 pub struct SignatureAndHashAlgorithm {
     pub hash: HashAlgorithm,
     pub sign: SignAlgorithm,
 }
 
-/// Signature algorithms, as defined in [RFC8446] 4.2.3
-#[derive(Debug, PartialEq, Eq, Nom)]
+/// Signature algorithms, as defined in [RFC8446] 4.2.3 and the
+/// [IANA TLS SignatureScheme
+/// Registry](https://www.iana.org/assignments/tls-parameters/tls-parameters.xhtml#tls-signaturescheme)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Nom)]
 pub struct SignatureScheme(pub u16);
 
 newtype_enum! {
@@ -60,9 +62,29 @@ impl display SignatureScheme {
     ecdsa_secp384r1_sha384 = 0x0503,
     ecdsa_secp521r1_sha512 = 0x0603,
 
+    /* RSASSA-PKCS1-v1_5 algorithms for TLS 1.3 client authentication (draft-davidben-tls13-pkcs1) */
+    rsa_pkcs1_sha256_legacy = 0x0420,
+    rsa_pkcs1_sha384_legacy = 0x0520,
+    rsa_pkcs1_sha512_legacy = 0x0620,
+
+    /* Identity-based signatures */
+    eccsi_sha256 = 0x0704,
+    iso_ibs1 = 0x0705,
+    iso_ibs2 = 0x0706,
+    iso_chinese_ibs = 0x0707,
+
     /* ShangMi (SM) Cipher Suites */
     sm2sig_sm3 = 0x0708,
 
+    /* GOST R 34.10-2012 algorithms (RFC9367) */
+    gostr34102012_256a = 0x0709,
+    gostr34102012_256b = 0x070a,
+    gostr34102012_256c = 0x070b,
+    gostr34102012_256d = 0x070c,
+    gostr34102012_512a = 0x070d,
+    gostr34102012_512b = 0x070e,
+    gostr34102012_512c = 0x070f,
+
     /* RSASSA-PSS algorithms with public key OID rsaEncryption */
     rsa_pss_rsae_sha256 = 0x0804,
     rsa_pss_rsae_sha384 = 0x0805,
@@ -82,6 +104,11 @@ impl display SignatureScheme {
     ecdsa_brainpoolP384r1tls13_sha384 = 0x081b,
     ecdsa_brainpoolP512r1tls13_sha512 = 0x081c,
 
+    /* ML-DSA algorithms (draft-ietf-tls-mldsa) */
+    mldsa44 = 0x0904,
+    mldsa65 = 0x0905,
+    mldsa87 = 0x0906,
+
     /* Legacy algorithms */
     rsa_pkcs1_sha1 = 0x0201,
     ecdsa_sha1 = 0x0203,
@@ -93,6 +120,14 @@ impl SignatureScheme {
         self.0 >= 0xfe00 && self.0 < 0xff00
     }
 
+    /// Test if the scheme is a post-quantum signature algorithm
+    pub fn is_post_quantum(&self) -> bool {
+        matches!(
+            *self,
+            SignatureScheme::mldsa44 | SignatureScheme::mldsa65 | SignatureScheme::mldsa87
+        )
+    }
+
     /// Get Hash algorithm (for tls <= 1.2) for legacy extension format
     pub fn hash_alg(&self) -> u8 {
         ((self.0 >> 8) & 0xff) as u8
@@ -108,7 +143,7 @@ impl SignatureScheme {
 /// has no algorithm definition.
 /// This should be deprecated in favor if
 /// DigitallySigned structure from [RFC5246] section 4.7
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 pub struct DigitallySigned<'a> {
     pub alg: Option<SignatureAndHashAlgorithm>,
     // pub alg: Option<u16>, // SignatureScheme
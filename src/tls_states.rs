@@ -1,5 +1,7 @@
+use crate::dtls::*;
 use crate::tls::*;
-use crate::tls_alert::TlsAlertSeverity;
+use crate::tls_alert::{TlsAlertSeverity, TlsMessageAlert};
+use crate::tls_extensions::TlsExtension;
 
 /// Error types for the state machine
 pub enum StateChangeError {
@@ -13,8 +15,15 @@ pub enum TlsState {
     None,
     ClientHello,
     AskResumeSession,
+    /// DTLS server asked the client to send a new ClientHello with a cookie
+    HelloVerifyRequest,
+    /// TLS 1.3 server asked the client to send a new ClientHello
+    HelloRetryRequest,
     ResumeSession,
     ServerHello,
+    /// TLS 1.3 ServerHello (selecting TLS 1.3 in `supported_versions`): the next handshake
+    /// messages are encrypted
+    Tls13ServerHello,
     Certificate,
     CertificateSt,
     ServerKeyExchange,
@@ -35,6 +44,17 @@ pub enum TlsState {
     PskHelloDone,
     PskCKE,
 
+    // TLS 1.3 states, reached only if the encrypted handshake messages are decrypted
+    EncryptedExtensions,
+    Tls13CertRequest,
+    Tls13ServerCert,
+    Tls13ServerCertVerify,
+    /// TLS 1.3 server Finished: the client can end its early data, and send its Finished
+    ServerFinished,
+    EndOfEarlyData,
+    Tls13ClientCert,
+    Tls13ClientCertVerify,
+
     SessionEncrypted,
 
     Alert,
@@ -44,15 +64,30 @@ pub enum TlsState {
     Invalid,
 }
 
+fn is_tls13_server_hello(msg: &TlsServerHelloContents) -> bool {
+    matches!(msg.get_supported_version(), Some(v) if v.is_tls13())
+}
+
 #[rustfmt::skip]
 fn tls_state_transition_handshake(state: TlsState, msg: &TlsMessageHandshake, to_server:bool) -> Result<TlsState,StateChangeError> {
     match (state,msg,to_server) {
-        (TlsState::None,             &TlsMessageHandshake::ClientHello(ref msg), true) => {
+        (TlsState::None,             &TlsMessageHandshake::ClientHello(ref msg), true) |
+        (TlsState::HelloRetryRequest,&TlsMessageHandshake::ClientHello(ref msg), true) => {
             match msg.session_id {
                 Some(_) => Ok(TlsState::AskResumeSession),
                 _       => Ok(TlsState::ClientHello)
             }
         },
+        // TLS 1.3 HelloRetryRequest (sent as a ServerHello with a special random, or as a
+        // distinct message in drafts), the client must send a new ClientHello
+        (TlsState::ClientHello,      &TlsMessageHandshake::ServerHello(ref msg), false) |
+        (TlsState::AskResumeSession, &TlsMessageHandshake::ServerHello(ref msg), false)
+            if msg.is_hello_retry_request()                                              => Ok(TlsState::HelloRetryRequest),
+        (TlsState::ClientHello,      &TlsMessageHandshake::HelloRetryRequest(_), false) => Ok(TlsState::HelloRetryRequest),
+        // TLS 1.3 (the session ID is only used for middlebox compatibility)
+        (TlsState::ClientHello,      &TlsMessageHandshake::ServerHello(ref msg), false) |
+        (TlsState::AskResumeSession, &TlsMessageHandshake::ServerHello(ref msg), false)
+            if is_tls13_server_hello(msg)                                                => Ok(TlsState::Tls13ServerHello),
         // Server certificate
         (TlsState::ClientHello,      &TlsMessageHandshake::ServerHello(_), false)       => Ok(TlsState::ServerHello),
         (TlsState::ServerHello,      &TlsMessageHandshake::Certificate(_), false)       => Ok(TlsState::Certificate),
@@ -80,6 +115,36 @@ fn tls_state_transition_handshake(state: TlsState, msg: &TlsMessageHandshake, to
         (TlsState::AskResumeSession, &TlsMessageHandshake::ServerHello(_), false)       => Ok(TlsState::ResumeSession),
         // Resume session failed
         (TlsState::ResumeSession,    &TlsMessageHandshake::Certificate(_), false)       => Ok(TlsState::Certificate),
+        // TLS 1.3 (decrypted messages), the ServerHello is followed by EncryptedExtensions
+        (TlsState::Tls13ServerHello, &TlsMessageHandshake::EncryptedExtensions(_), false) => Ok(TlsState::EncryptedExtensions),
+        (TlsState::EncryptedExtensions, &TlsMessageHandshake::CertificateRequestV13(_), false) => Ok(TlsState::Tls13CertRequest),
+        (TlsState::EncryptedExtensions, &TlsMessageHandshake::CertificateV13(_), false) |
+        (TlsState::EncryptedExtensions, &TlsMessageHandshake::CompressedCertificate(_), false) |
+        (TlsState::Tls13CertRequest, &TlsMessageHandshake::CertificateV13(_), false) |
+        (TlsState::Tls13CertRequest, &TlsMessageHandshake::CompressedCertificate(_), false) => Ok(TlsState::Tls13ServerCert),
+        (TlsState::Tls13ServerCert,  &TlsMessageHandshake::CertificateVerify(_), false) => Ok(TlsState::Tls13ServerCertVerify),
+        // PSK handshakes have no certificate
+        (TlsState::EncryptedExtensions, &TlsMessageHandshake::Finished(_), false) |
+        (TlsState::Tls13ServerCertVerify, &TlsMessageHandshake::Finished(_), false) => Ok(TlsState::ServerFinished),
+        (TlsState::ServerFinished,   &TlsMessageHandshake::EndOfEarlyData, true)        => Ok(TlsState::EndOfEarlyData),
+        (TlsState::ServerFinished,   &TlsMessageHandshake::CertificateV13(_), true) |
+        (TlsState::ServerFinished,   &TlsMessageHandshake::CompressedCertificate(_), true) |
+        (TlsState::EndOfEarlyData,   &TlsMessageHandshake::CertificateV13(_), true) |
+        (TlsState::EndOfEarlyData,   &TlsMessageHandshake::CompressedCertificate(_), true) => Ok(TlsState::Tls13ClientCert),
+        (TlsState::Tls13ClientCert,  &TlsMessageHandshake::CertificateVerify(_), true)  => Ok(TlsState::Tls13ClientCertVerify),
+        // the client certificate can be empty (no CertificateVerify)
+        (TlsState::ServerFinished,   &TlsMessageHandshake::Finished(_), true) |
+        (TlsState::EndOfEarlyData,   &TlsMessageHandshake::Finished(_), true) |
+        (TlsState::Tls13ClientCert,  &TlsMessageHandshake::Finished(_), true) |
+        (TlsState::Tls13ClientCertVerify, &TlsMessageHandshake::Finished(_), true)      => Ok(TlsState::SessionEncrypted),
+        // TLS 1.3 post-handshake messages [RFC8446] 4.6
+        (TlsState::SessionEncrypted, &TlsMessageHandshake::NewSessionTicketV13(_), false) |
+        (TlsState::SessionEncrypted, &TlsMessageHandshake::KeyUpdate(_), _) |
+        (TlsState::SessionEncrypted, &TlsMessageHandshake::CertificateRequestV13(_), false) |
+        (TlsState::SessionEncrypted, &TlsMessageHandshake::CertificateV13(_), true) |
+        (TlsState::SessionEncrypted, &TlsMessageHandshake::CompressedCertificate(_), true) |
+        (TlsState::SessionEncrypted, &TlsMessageHandshake::CertificateVerify(_), true) |
+        (TlsState::SessionEncrypted, &TlsMessageHandshake::Finished(_), true)           => Ok(TlsState::SessionEncrypted),
         // TLS 1.3 Draft 18 1-RTT
         // Re-use the ClientChangeCipherSpec state to indicate the next message will be encrypted
         (TlsState::ClientHello,      &TlsMessageHandshake::ServerHelloV13Draft18(_), false)    => Ok(TlsState::ClientChangeCipherSpec),
@@ -123,6 +188,9 @@ pub fn tls_state_transition(state: TlsState, msg: &TlsMessage, to_server:bool) -
         (TlsState::ResumeSession,         &TlsMessage::ChangeCipherSpec, _) => Ok(TlsState::ClientChangeCipherSpec),
         // 0-rtt
         (TlsState::AskResumeSession,      &TlsMessage::ChangeCipherSpec, true) => Ok(TlsState::AskResumeSession),
+        // TLS 1.3 middlebox compatibility mode: dummy messages are ignored
+        // (see `is_tls13_compat_ccs`)
+        (s, &TlsMessage::ChangeCipherSpec, _) if is_tls13_compat_ccs(s, msg, to_server) => Ok(s),
         // non-fatal alerts
         (s,                               &TlsMessage::Alert(ref a), _) => {
             if a.severity == TlsAlertSeverity::Warning { Ok(s) } else { Ok(TlsState::Finished) }
@@ -130,3 +198,498 @@ pub fn tls_state_transition(state: TlsState, msg: &TlsMessage, to_server:bool) -
         (_,_,_) => Err(StateChangeError::InvalidTransition),
     }
 }
+
+/// Test if a message is a dummy ChangeCipherSpec, sent in TLS 1.3 middlebox compatibility mode
+///
+/// In TLS 1.3, a ChangeCipherSpec can be sent by the client before its second ClientHello or
+/// before its second flight, and by the server after its ServerHello or HelloRetryRequest
+/// ([RFC8446](https://tools.ietf.org/html/rfc8446) appendix D.4). These messages have no effect,
+/// and `tls_state_transition` accepts them without changing the state. Use this function to
+/// report them anyway (for ex. as a diagnostic).
+///
+/// Only the messages received once TLS 1.3 is selected (by a HelloRetryRequest, or by a
+/// ServerHello with a TLS 1.3 `supported_versions` extension) are accepted: a ChangeCipherSpec
+/// sent after a ClientHello cannot be distinguished from an invalid TLS 1.2 message.
+///
+/// `state` is the state before the message.
+pub fn is_tls13_compat_ccs(state: TlsState, msg: &TlsMessage, to_server: bool) -> bool {
+    if !matches!(msg, TlsMessage::ChangeCipherSpec) {
+        return false;
+    }
+    matches!(
+        (state, to_server),
+        (TlsState::ServerFinished, true)
+            | (TlsState::HelloRetryRequest, _)
+            | (TlsState::Tls13ServerHello, _)
+    )
+}
+
+// Messages with the same format as TLS are converted, to use the TLS transitions
+fn dtls_body_as_tls<'a>(body: &DTLSMessageHandshakeBody<'a>) -> Option<TlsMessageHandshake<'a>> {
+    let msg = match body {
+        DTLSMessageHandshakeBody::HelloRequest => TlsMessageHandshake::HelloRequest,
+        DTLSMessageHandshakeBody::ServerHello(c) => TlsMessageHandshake::ServerHello(c.clone()),
+        DTLSMessageHandshakeBody::NewSessionTicket(c) => {
+            TlsMessageHandshake::NewSessionTicket(c.clone())
+        }
+        DTLSMessageHandshakeBody::Certificate(c) => TlsMessageHandshake::Certificate(c.clone()),
+        DTLSMessageHandshakeBody::ServerKeyExchange(c) => {
+            TlsMessageHandshake::ServerKeyExchange(c.clone())
+        }
+        DTLSMessageHandshakeBody::CertificateRequest(c) => {
+            TlsMessageHandshake::CertificateRequest(c.clone())
+        }
+        DTLSMessageHandshakeBody::ServerDone(c) => TlsMessageHandshake::ServerDone(c),
+        DTLSMessageHandshakeBody::CertificateVerify(c) => TlsMessageHandshake::CertificateVerify(c),
+        DTLSMessageHandshakeBody::ClientKeyExchange(c) => {
+            TlsMessageHandshake::ClientKeyExchange(c.clone())
+        }
+        DTLSMessageHandshakeBody::Finished(c) => TlsMessageHandshake::Finished(c),
+        DTLSMessageHandshakeBody::CertificateStatus(c) => {
+            TlsMessageHandshake::CertificateStatus(c.clone())
+        }
+        _ => return None,
+    };
+    Some(msg)
+}
+
+#[rustfmt::skip]
+fn dtls_state_transition_handshake(state: TlsState, body: &DTLSMessageHandshakeBody, to_server:bool) -> Result<TlsState,StateChangeError> {
+    match (state,body,to_server) {
+        // Stateless cookie exchange [RFC6347] 4.2.1
+        (TlsState::ClientHello,       DTLSMessageHandshakeBody::HelloVerifyRequest(_), false) |
+        (TlsState::AskResumeSession,  DTLSMessageHandshakeBody::HelloVerifyRequest(_), false) => Ok(TlsState::HelloVerifyRequest),
+        // The new ClientHello must contain the cookie
+        (TlsState::HelloVerifyRequest,DTLSMessageHandshakeBody::ClientHello(msg), true) if msg.cookie.is_empty() => {
+            Err(StateChangeError::InvalidTransition)
+        },
+        // DTLS 1.3 uses a HelloRetryRequest with a cookie extension instead [RFC9147] 5.1
+        (TlsState::None,              DTLSMessageHandshakeBody::ClientHello(msg), true) |
+        (TlsState::HelloVerifyRequest,DTLSMessageHandshakeBody::ClientHello(msg), true) |
+        (TlsState::HelloRetryRequest, DTLSMessageHandshakeBody::ClientHello(msg), true) => {
+            match msg.session_id {
+                Some(_) => Ok(TlsState::AskResumeSession),
+                _       => Ok(TlsState::ClientHello)
+            }
+        },
+        (_, b, _) => match dtls_body_as_tls(b) {
+            Some(m) => tls_state_transition_handshake(state, &m, to_server),
+            None    => Err(StateChangeError::InvalidTransition),
+        },
+    }
+}
+
+/// Update the TLS state machine with a DTLS message, doing one transition
+///
+/// This is the same state machine as `tls_state_transition`, with the DTLS-specific
+/// transitions (HelloVerifyRequest and cookie exchange).
+///
+/// Handshake fragments do not change the state, they must be reassembled first (see
+/// `DTLSHandshakeReassembler`).
+#[rustfmt::skip]
+pub fn dtls_state_transition(state: TlsState, msg: &DTLSMessage, to_server:bool) -> Result<TlsState,StateChangeError> {
+    match (state,msg) {
+        (TlsState::Invalid,_) => Ok(TlsState::Invalid),
+        (TlsState::Finished,_) => Ok(TlsState::Invalid),
+        (_,DTLSMessage::Handshake(m)) if m.is_fragment() => Ok(state),
+        (_,DTLSMessage::Handshake(m)) => dtls_state_transition_handshake(state,&m.body,to_server),
+        (_,DTLSMessage::ChangeCipherSpec) => tls_state_transition(state,&TlsMessage::ChangeCipherSpec,to_server),
+        (_,DTLSMessage::Alert(a)) => tls_state_transition(state,&TlsMessage::Alert(a.clone()),to_server),
+        (_,_) => Err(StateChangeError::InvalidTransition),
+    }
+}
+
+/// Status of a message given to `DTLSStateMachine`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DTLSMessageStatus {
+    /// The message was expected, and the state was updated
+    Accepted,
+    /// The message was already received (retransmitted flight), it was ignored
+    Retransmission,
+    /// The message was received before the previous messages of the same peer (reordering),
+    /// it was ignored and can be given again later
+    OutOfOrder,
+}
+
+/// DTLS state machine, tolerant of retransmissions and reordering
+///
+/// DTLS runs over datagrams: a peer retransmits its whole flight if the answer is lost or
+/// delayed, and records can be reordered. Giving all messages to `dtls_state_transition`
+/// would fail on every retransmission.
+///
+/// This wrapper keeps, for each peer, the current epoch (incremented by ChangeCipherSpec) and
+/// the next handshake `message_seq`. Messages are ordered by `(epoch, message_seq)`:
+///
+/// - messages from a previous epoch, or with a `message_seq` already received, are
+///   retransmissions and are ignored
+/// - messages from a later epoch, or with a `message_seq` after the next expected one, are
+///   early and are ignored. The caller can keep them, and give them again once the previous
+///   messages are received (see `next_message_seq`), or wait for the retransmission.
+///
+/// Other messages are applied using `dtls_state_transition`. Handshake fragments must be
+/// reassembled first (see `DTLSHandshakeReassembler`).
+#[derive(Clone, Debug)]
+pub struct DTLSStateMachine {
+    state: TlsState,
+    /// Current epoch of the client and the server
+    epoch: [u16; 2],
+    /// Next handshake message_seq of the client and the server
+    next_message_seq: [u16; 2],
+}
+
+impl Default for DTLSStateMachine {
+    fn default() -> Self {
+        DTLSStateMachine::new()
+    }
+}
+
+impl DTLSStateMachine {
+    pub fn new() -> Self {
+        DTLSStateMachine {
+            state: TlsState::None,
+            epoch: [0; 2],
+            next_message_seq: [0; 2],
+        }
+    }
+
+    /// Get the current state
+    pub fn state(&self) -> TlsState {
+        self.state
+    }
+
+    /// Get the current epoch of a peer
+    pub fn epoch(&self, to_server: bool) -> u16 {
+        self.epoch[Self::peer(to_server)]
+    }
+
+    /// Get the `message_seq` of the next handshake message expected from a peer
+    pub fn next_message_seq(&self, to_server: bool) -> u16 {
+        self.next_message_seq[Self::peer(to_server)]
+    }
+
+    fn peer(to_server: bool) -> usize {
+        if to_server {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// Update the state machine with a message, received in a record of epoch `epoch`
+    ///
+    /// If the transition is invalid, the state becomes `Invalid` and an error is returned.
+    pub fn update(
+        &mut self,
+        epoch: u16,
+        msg: &DTLSMessage,
+        to_server: bool,
+    ) -> Result<DTLSMessageStatus, StateChangeError> {
+        let peer = Self::peer(to_server);
+        if epoch < self.epoch[peer] {
+            return Ok(DTLSMessageStatus::Retransmission);
+        }
+        if epoch > self.epoch[peer] {
+            return Ok(DTLSMessageStatus::OutOfOrder);
+        }
+        if let DTLSMessage::Handshake(m) = msg {
+            if m.message_seq < self.next_message_seq[peer] {
+                return Ok(DTLSMessageStatus::Retransmission);
+            }
+            if m.message_seq > self.next_message_seq[peer] {
+                return Ok(DTLSMessageStatus::OutOfOrder);
+            }
+        }
+        match dtls_state_transition(self.state, msg, to_server) {
+            Ok(state) => self.state = state,
+            Err(e) => {
+                self.state = TlsState::Invalid;
+                return Err(e);
+            }
+        }
+        match msg {
+            DTLSMessage::Handshake(m) if !m.is_fragment() => {
+                self.next_message_seq[peer] = m.message_seq.wrapping_add(1);
+            }
+            DTLSMessage::ChangeCipherSpec => {
+                self.epoch[peer] = epoch.wrapping_add(1);
+            }
+            _ => (),
+        }
+        Ok(DTLSMessageStatus::Accepted)
+    }
+}
+
+/// Tracker for TLS 1.3 post-handshake client authentication
+///
+/// After the handshake, a server can request a client certificate (if the client sent the
+/// `post_handshake_auth` extension) by sending a CertificateRequest with a non-empty
+/// `certificate_request_context`. The client answers with a Certificate message containing
+/// the same context, followed by CertificateVerify and Finished
+/// ([RFC8446](https://tools.ietf.org/html/rfc8446) section 4.6.2). Several requests can be
+/// pending at the same time.
+///
+/// These messages are encrypted: pass the decrypted messages, parsed using
+/// `parse_tls_message_handshake_with_version` (to get `CertificateRequestV13` and
+/// `CertificateV13` messages).
+#[derive(Clone, Debug, Default)]
+pub struct TlsPostHandshakeAuth {
+    pending: Vec<Vec<u8>>,
+}
+
+impl TlsPostHandshakeAuth {
+    pub fn new() -> Self {
+        TlsPostHandshakeAuth::default()
+    }
+
+    /// Get the contexts of the requests not yet answered by the client
+    pub fn pending_requests(&self) -> &[Vec<u8>] {
+        &self.pending
+    }
+
+    /// Update the tracker with a post-handshake message
+    ///
+    /// If the message is a client Certificate answering a request, return the context of
+    /// the request. A request with an empty or duplicate context, or a Certificate which
+    /// does not match a request, is an invalid transition. Other messages are ignored.
+    pub fn update<'a>(
+        &mut self,
+        msg: &TlsMessageHandshake<'a>,
+        to_server: bool,
+    ) -> Result<Option<&'a [u8]>, StateChangeError> {
+        match (msg, to_server) {
+            (TlsMessageHandshake::CertificateRequestV13(req), false) => {
+                let context = req.certificate_request_context;
+                if context.is_empty() || self.pending.iter().any(|c| c == context) {
+                    return Err(StateChangeError::InvalidTransition);
+                }
+                self.pending.push(context.to_vec());
+                Ok(None)
+            }
+            (TlsMessageHandshake::CertificateV13(cert), true) => {
+                let context = cert.certificate_request_context;
+                match self.pending.iter().position(|c| c == context) {
+                    Some(idx) => {
+                        self.pending.remove(idx);
+                        Ok(Some(context))
+                    }
+                    None => Err(StateChangeError::InvalidTransition),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Event reported by `TlsStateMachine`
+#[derive(Clone, Debug, PartialEq)]
+pub enum TlsStateEvent {
+    /// The client started a handshake (first ClientHello)
+    HandshakeStarted,
+    /// The server selected the protocol version and cipher suite (ServerHello)
+    ParametersNegotiated {
+        version: TlsVersion,
+        cipher: TlsCipherSuiteID,
+    },
+    /// The server sent a HelloRetryRequest (TLS 1.3)
+    HelloRetryRequest,
+    /// The server accepted the early data of the client (TLS 1.3 0-RTT)
+    EarlyDataAccepted,
+    /// The server rejected the early data of the client (TLS 1.3 0-RTT)
+    EarlyDataRejected,
+    /// The handshake is complete: both peers sent ChangeCipherSpec (TLS 1.2) or Finished
+    /// (TLS 1.3), the next messages are encrypted
+    HandshakeFinished,
+    /// The client started a new handshake in an established session
+    Renegotiation,
+    /// The server sent a NewSessionTicket after the handshake (TLS 1.3)
+    NewSessionTicket,
+    /// A peer updated its traffic keys, and possibly requested the other peer to do the same
+    /// (TLS 1.3)
+    KeyUpdate {
+        to_server: bool,
+        update_requested: bool,
+    },
+    /// The server requested a client certificate after the handshake (TLS 1.3)
+    PostHandshakeAuthRequested,
+    /// The client answered a post-handshake certificate request (TLS 1.3)
+    PostHandshakeAuthAnswered,
+    /// An alert was received (fatal alerts also end the session)
+    Alert(TlsMessageAlert),
+    /// The message is not valid in the current state (`state` is the state before the message)
+    ///
+    /// This event is only reported once: the state machine stays in the `Invalid` state.
+    ProtocolViolation { state: TlsState, to_server: bool },
+}
+
+/// Event-driven TLS state machine
+///
+/// This is a wrapper over `tls_state_transition`, keeping the current state and reporting
+/// the important transitions as events. Messages must be given in order, with their
+/// direction.
+///
+/// Some checks require the previous messages, and are done by the state machine: a second
+/// HelloRetryRequest, EndOfEarlyData if early data was not accepted, and post-handshake
+/// certificates not matching a request (see `TlsPostHandshakeAuth`) are protocol violations.
+///
+/// Encrypted messages can only be given if the caller decrypts them: for ex., a renegotiation
+/// is only detected if the new ClientHello is decrypted, and the TLS 1.3 handshake stays in
+/// the `Tls13ServerHello` state otherwise. TLS 1.3 messages must be parsed using
+/// `parse_tls_message_handshake_with_version`.
+#[derive(Clone, Debug)]
+pub struct TlsStateMachine {
+    state: TlsState,
+    hello_retry_request: bool,
+    early_data_offered: bool,
+    early_data_accepted: bool,
+    post_handshake_auth: TlsPostHandshakeAuth,
+}
+
+impl Default for TlsStateMachine {
+    fn default() -> Self {
+        TlsStateMachine::new()
+    }
+}
+
+impl TlsStateMachine {
+    pub fn new() -> Self {
+        TlsStateMachine {
+            state: TlsState::None,
+            hello_retry_request: false,
+            early_data_offered: false,
+            early_data_accepted: false,
+            post_handshake_auth: TlsPostHandshakeAuth::new(),
+        }
+    }
+
+    /// Get the current state
+    pub fn state(&self) -> TlsState {
+        self.state
+    }
+
+    /// Update the state machine with a message, and return the events caused by the message
+    pub fn update(&mut self, msg: &TlsMessage, to_server: bool) -> Vec<TlsStateEvent> {
+        let mut events = Vec::new();
+        let previous = self.state;
+        if previous == TlsState::Invalid {
+            return events;
+        }
+        let result = match (previous, msg, to_server) {
+            // a ClientHello in an established session starts a new handshake
+            (
+                TlsState::SessionEncrypted,
+                TlsMessage::Handshake(TlsMessageHandshake::ClientHello(_)),
+                true,
+            ) => {
+                events.push(TlsStateEvent::Renegotiation);
+                tls_state_transition(TlsState::None, msg, to_server)
+            }
+            _ => tls_state_transition(previous, msg, to_server),
+        };
+        let result = result.and_then(|state| {
+            match msg {
+                TlsMessage::Handshake(m) => {
+                    self.check_handshake(previous, m, to_server, &mut events)?
+                }
+                TlsMessage::Alert(alert) => events.push(TlsStateEvent::Alert(alert.clone())),
+                _ => (),
+            }
+            Ok(state)
+        });
+        self.state = match result {
+            Ok(state) => state,
+            Err(_) => {
+                events.push(TlsStateEvent::ProtocolViolation {
+                    state: previous,
+                    to_server,
+                });
+                TlsState::Invalid
+            }
+        };
+        if self.state == TlsState::SessionEncrypted && previous != TlsState::SessionEncrypted {
+            events.push(TlsStateEvent::HandshakeFinished);
+        }
+        events
+    }
+
+    /// Check a handshake message accepted by the transition table, using the previous messages
+    fn check_handshake(
+        &mut self,
+        previous: TlsState,
+        msg: &TlsMessageHandshake,
+        to_server: bool,
+        events: &mut Vec<TlsStateEvent>,
+    ) -> Result<(), StateChangeError> {
+        let is_hrr = match msg {
+            TlsMessageHandshake::ServerHello(sh) => sh.is_hello_retry_request(),
+            TlsMessageHandshake::HelloRetryRequest(_) => true,
+            _ => false,
+        };
+        match msg {
+            TlsMessageHandshake::ClientHello(ch) => {
+                if previous == TlsState::None || previous == TlsState::SessionEncrypted {
+                    if previous == TlsState::None {
+                        events.push(TlsStateEvent::HandshakeStarted);
+                    }
+                    self.hello_retry_request = false;
+                    self.early_data_accepted = false;
+                }
+                self.early_data_offered = ch.has_early_data();
+            }
+            _ if is_hrr => {
+                // a client receiving a second HelloRetryRequest must abort [RFC8446] 4.1.4
+                if self.hello_retry_request {
+                    return Err(StateChangeError::InvalidTransition);
+                }
+                self.hello_retry_request = true;
+                events.push(TlsStateEvent::HelloRetryRequest);
+                if self.early_data_offered {
+                    self.early_data_offered = false;
+                    events.push(TlsStateEvent::EarlyDataRejected);
+                }
+            }
+            TlsMessageHandshake::ServerHello(sh) => {
+                events.push(TlsStateEvent::ParametersNegotiated {
+                    version: sh.get_supported_version().unwrap_or(sh.version),
+                    cipher: sh.cipher,
+                });
+            }
+            TlsMessageHandshake::EncryptedExtensions(ee) if self.early_data_offered => {
+                let exts = ee
+                    .parse_extensions()
+                    .map(|(_, exts)| exts)
+                    .unwrap_or_default();
+                self.early_data_accepted = exts
+                    .iter()
+                    .any(|ext| matches!(ext, TlsExtension::EarlyData(_)));
+                events.push(if self.early_data_accepted {
+                    TlsStateEvent::EarlyDataAccepted
+                } else {
+                    TlsStateEvent::EarlyDataRejected
+                });
+            }
+            TlsMessageHandshake::EndOfEarlyData if !self.early_data_accepted => {
+                return Err(StateChangeError::InvalidTransition);
+            }
+            TlsMessageHandshake::NewSessionTicketV13(_) => {
+                events.push(TlsStateEvent::NewSessionTicket);
+            }
+            TlsMessageHandshake::KeyUpdate(request) => {
+                events.push(TlsStateEvent::KeyUpdate {
+                    to_server,
+                    update_requested: KeyUpdateRequest(*request) == KeyUpdateRequest::Requested,
+                });
+            }
+            TlsMessageHandshake::CertificateRequestV13(_)
+            | TlsMessageHandshake::CertificateV13(_)
+                if previous == TlsState::SessionEncrypted =>
+            {
+                match self.post_handshake_auth.update(msg, to_server)? {
+                    Some(_) => events.push(TlsStateEvent::PostHandshakeAuthAnswered),
+                    None => events.push(TlsStateEvent::PostHandshakeAuthRequested),
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+}
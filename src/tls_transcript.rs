@@ -0,0 +1,136 @@
+//! # Handshake transcript
+//!
+//! The transcript is the concatenation of the handshake messages (with their 4-byte headers),
+//! in the order they were sent by both peers. Its hash is used to compute the Finished
+//! messages, and (in TLS 1.3) the traffic secrets.
+//!
+//! `TlsTranscript` collects the handshake data, and replaces the first ClientHello by a
+//! synthetic `message_hash` message after a HelloRetryRequest
+//! ([RFC8446](https://tools.ietf.org/html/rfc8446) section 4.4.1).
+//!
+//! Only the TLS 1.2 and TLS 1.3 hash algorithms (SHA-256 and SHA-384) are supported. DTLS
+//! transcripts use a different message header, and are not supported.
+
+use sha2::{Digest, Sha256, Sha384};
+
+use crate::tls::{TlsHandshakeType, HRR_RANDOM};
+use crate::tls_ciphers::{TlsCipherMac, TlsCipherSuite};
+
+/// Hash algorithm of the handshake transcript
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsHashAlgorithm {
+    Sha256,
+    Sha384,
+}
+
+impl TlsHashAlgorithm {
+    /// Get the hash algorithm of a cipher suite
+    ///
+    /// This is the hash of the HKDF (TLS 1.3) or of the PRF (TLS 1.2): SHA-384 if the cipher
+    /// suite name ends with `SHA384`, SHA-256 otherwise.
+    pub fn from_ciphersuite(cipher: &TlsCipherSuite) -> Self {
+        if cipher.mac == TlsCipherMac::HmacSha384 || cipher.name.ends_with("SHA384") {
+            TlsHashAlgorithm::Sha384
+        } else {
+            TlsHashAlgorithm::Sha256
+        }
+    }
+
+    /// Get the length of the hash, in bytes
+    pub fn output_len(self) -> usize {
+        match self {
+            TlsHashAlgorithm::Sha256 => 32,
+            TlsHashAlgorithm::Sha384 => 48,
+        }
+    }
+
+    /// Compute the hash of `data`
+    pub fn hash(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            TlsHashAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+            TlsHashAlgorithm::Sha384 => Sha384::digest(data).to_vec(),
+        }
+    }
+}
+
+/// Collector of the handshake messages of a connection
+///
+/// Data is the content of the handshake records (plaintext, or decrypted for the encrypted
+/// TLS 1.3 handshake messages), and must be pushed in the order of the handshake, for both
+/// peers. Messages can be split over several calls to `push`.
+///
+/// The hash algorithm is usually only known after the ServerHello, so the data is kept, and
+/// hashed when `hash` is called.
+#[derive(Clone, Debug, Default)]
+pub struct TlsTranscript {
+    data: Vec<u8>,
+    /// Offset of the next message header in `data`
+    next_msg: usize,
+    /// Offset of the HelloRetryRequest in `data`
+    hrr_offset: Option<usize>,
+}
+
+impl TlsTranscript {
+    pub fn new() -> Self {
+        TlsTranscript::default()
+    }
+
+    /// Add handshake data to the transcript
+    pub fn push(&mut self, data: &[u8]) {
+        self.data.extend_from_slice(data);
+        while self.data.len() >= self.next_msg + 4 {
+            let hdr = &self.data[self.next_msg..self.next_msg + 4];
+            let msg_type = TlsHandshakeType(hdr[0]);
+            let len = (hdr[1] as usize) << 16 | (hdr[2] as usize) << 8 | hdr[3] as usize;
+            if msg_type == TlsHandshakeType::ServerHello && self.hrr_offset.is_none() {
+                // header, legacy_version and random
+                let random_start = self.next_msg + 4 + 2;
+                if self.data.len() < random_start + 32 {
+                    break;
+                }
+                if self.data[random_start..random_start + 32] == HRR_RANDOM {
+                    self.hrr_offset = Some(self.next_msg);
+                }
+            }
+            self.next_msg += 4 + len;
+        }
+    }
+
+    /// Test if the transcript is empty
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Test if the transcript contains a HelloRetryRequest
+    pub fn has_hello_retry_request(&self) -> bool {
+        self.hrr_offset.is_some()
+    }
+
+    /// Test if the last message of the transcript is complete
+    pub fn is_complete(&self) -> bool {
+        self.next_msg == self.data.len()
+    }
+
+    /// Get the data of the transcript, for the hash algorithm `alg`
+    ///
+    /// If a HelloRetryRequest was received, the messages before it (the first ClientHello)
+    /// are replaced by a `message_hash` message, containing their hash.
+    pub fn data(&self, alg: TlsHashAlgorithm) -> Vec<u8> {
+        match self.hrr_offset {
+            Some(offset) => {
+                let hash = alg.hash(&self.data[..offset]);
+                let mut data = vec![u8::from(TlsHandshakeType::MessageHash), 0, 0];
+                data.push(hash.len() as u8);
+                data.extend_from_slice(&hash);
+                data.extend_from_slice(&self.data[offset..]);
+                data
+            }
+            None => self.data.clone(),
+        }
+    }
+
+    /// Compute the hash of the transcript, using the hash algorithm `alg`
+    pub fn hash(&self, alg: TlsHashAlgorithm) -> Vec<u8> {
+        alg.hash(&self.data(alg))
+    }
+}
@@ -0,0 +1,84 @@
+//! Decoding of X.509 distinguished names and certificates, using
+//! [x509-parser](https://crates.io/crates/x509-parser)
+//!
+//! This module is only available with the `x509` feature.
+
+use sha2::{Digest, Sha256};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::der_parser::oid::Oid;
+use x509_parser::error::X509Error;
+use x509_parser::nom::Err;
+use x509_parser::traits::FromDer;
+use x509_parser::x509::X509Name;
+
+use crate::tls::{RawCertificate, TlsCertificateEntry};
+
+/// Decode a DER-encoded DistinguishedName
+///
+/// Distinguished names are found in the `certificate_authorities` extension
+/// (`TlsExtension::CertificateAuthorities`) and in the `unparsed_ca` field of the TLS 1.2
+/// CertificateRequest message.
+///
+/// The input must contain exactly one name.
+pub fn parse_distinguished_name(dn: &[u8]) -> Result<X509Name, X509Error> {
+    match X509Name::from_der(dn) {
+        Ok(([], name)) => Ok(name),
+        Ok(_) | Err(Err::Incomplete(_)) => Err(X509Error::InvalidX509Name),
+        Err(Err::Error(e)) | Err(Err::Failure(e)) => Err(e),
+    }
+}
+
+/// Hash a list of OIDs, for the JA4X fingerprint
+///
+/// The OIDs are encoded as hexadecimal (the DER content, without the tag and length), and
+/// separated by `,`. The result is the first 12 characters of the SHA-256 hash, or
+/// `000000000000` if the list is empty.
+fn ja4x_hash<'a, I: Iterator<Item = &'a Oid<'a>>>(oids: I) -> String {
+    let oids: Vec<String> = oids
+        .map(|oid| {
+            oid.as_bytes()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect()
+        })
+        .collect();
+    if oids.is_empty() {
+        return "000000000000".to_string();
+    }
+    let digest = Sha256::digest(oids.join(",").as_bytes());
+    digest[..6].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Compute the JA4X fingerprint of a DER-encoded X.509 certificate
+///
+/// The [JA4X](https://github.com/FoxIO-LLC/ja4) fingerprint identifies the application used
+/// to generate the certificate, from the order of the attributes and extensions (not from
+/// their values). It is formatted as `a_b_c`, where `a`, `b` and `c` are the hashes of the
+/// OIDs of the issuer attributes, the subject attributes, and the extensions.
+///
+/// The input must contain exactly one certificate.
+pub fn ja4x_fingerprint(der: &[u8]) -> Result<String, X509Error> {
+    let cert = match X509Certificate::from_der(der) {
+        Ok(([], cert)) => cert,
+        Ok(_) | Err(Err::Incomplete(_)) => return Err(X509Error::InvalidCertificate),
+        Err(Err::Error(e)) | Err(Err::Failure(e)) => return Err(e),
+    };
+    let issuer = ja4x_hash(cert.issuer().iter_attributes().map(|a| a.attr_type()));
+    let subject = ja4x_hash(cert.subject().iter_attributes().map(|a| a.attr_type()));
+    let extensions = ja4x_hash(cert.extensions().iter().map(|ext| &ext.oid));
+    Ok(format!("{}_{}_{}", issuer, subject, extensions))
+}
+
+impl<'a> RawCertificate<'a> {
+    /// Compute the JA4X fingerprint of this certificate (see `ja4x_fingerprint`)
+    pub fn ja4x_fingerprint(&self) -> Result<String, X509Error> {
+        ja4x_fingerprint(self.data)
+    }
+}
+
+impl<'a> TlsCertificateEntry<'a> {
+    /// Compute the JA4X fingerprint of this certificate (see `ja4x_fingerprint`)
+    pub fn ja4x_fingerprint(&self) -> Result<String, X509Error> {
+        ja4x_fingerprint(self.data)
+    }
+}
@@ -0,0 +1,297 @@
+//! # TLS 1.3 conformance checks
+//!
+//! `validate_client_hello` and `validate_server_hello` check the hello messages against the
+//! MUST-level requirements of [RFC8446](https://tools.ietf.org/html/rfc8446), and return the
+//! list of violations. Checks specific to TLS 1.3 are only done if TLS 1.3 is offered by the
+//! client (or selected by the server).
+//!
+//! `validate_extensions_in` checks that the extensions of any TLS 1.3 handshake message are
+//! allowed in this message (RFC8446 section 4.2).
+//!
+//! These functions report violations, they do not reject the messages: parsing is done
+//! independently, and invalid messages can still be inspected.
+
+use crate::tls::*;
+use crate::tls_diff::raw_extensions;
+use crate::tls_ec::NamedGroup;
+use crate::tls_extensions::*;
+
+/// Violation of a requirement of RFC8446
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsConformanceViolation {
+    /// Extensions could not be parsed (content checks are skipped)
+    InvalidExtensions,
+    /// `legacy_version` must be TLS 1.2 in TLS 1.3 hello messages, and at least SSL 3.0
+    /// otherwise (section 4.1.2)
+    InvalidLegacyVersion(TlsVersion),
+    /// A TLS 1.3 ClientHello must offer only the `null` compression method, and a TLS 1.3
+    /// ServerHello must select it (sections 4.1.2, 4.1.3)
+    InvalidLegacyCompression,
+    /// An extension block must not contain the same extension twice (section 4.2)
+    DuplicateExtension(TlsExtensionType),
+    /// `pre_shared_key` must be the last extension of the ClientHello (section 4.2.11)
+    PreSharedKeyNotLast,
+    /// A required extension is missing (sections 4.2.9 and 9.2)
+    MissingExtension(TlsExtensionType),
+    /// A key share must use a group offered by the client: in `supported_groups` for the
+    /// ClientHello and the HelloRetryRequest, and in `key_share` for the ServerHello
+    /// (section 4.2.8)
+    KeyShareGroupNotOffered(NamedGroup),
+    /// The client must not send two key shares for the same group (section 4.2.8)
+    DuplicateKeyShareGroup(NamedGroup),
+    /// A HelloRetryRequest must not select a group for which the client sent a key share
+    /// (section 4.2.8)
+    HelloRetryRequestGroupShared(NamedGroup),
+    /// The number of PSK binders must be equal to the number of identities (section 4.2.11)
+    PskBinderCountMismatch,
+    /// The client must not send `early_data` without `pre_shared_key` (section 4.2.10)
+    EarlyDataWithoutPsk,
+    /// The cipher suite selected by the server was not offered (section 4.1.3)
+    CipherNotOffered(TlsCipherSuiteID),
+    /// The server sent an extension not offered by the client (section 4.2)
+    UnsolicitedExtension(TlsExtensionType),
+    /// The server selected a PSK identity not offered by the client (section 4.2.11)
+    InvalidPskIdentity(u16),
+    /// A TLS 1.3 server must echo the session ID of the client (section 4.1.3)
+    SessionIdNotEchoed,
+    /// The extension is not allowed in this TLS 1.3 message (section 4.2)
+    ExtensionNotAllowed(TlsExtensionType, ExtensionContext),
+}
+
+fn check_duplicates(types: &[TlsExtensionType], violations: &mut Vec<TlsConformanceViolation>) {
+    for (idx, ext_type) in types.iter().enumerate() {
+        let violation = TlsConformanceViolation::DuplicateExtension(*ext_type);
+        if types[..idx].contains(ext_type) && !violations.contains(&violation) {
+            violations.push(violation);
+        }
+    }
+}
+
+fn supported_groups<'a>(exts: &'a [TlsExtension]) -> Option<&'a [NamedGroup]> {
+    exts.iter().find_map(|ext| match ext {
+        TlsExtension::EllipticCurves(groups) => Some(groups.as_slice()),
+        _ => None,
+    })
+}
+
+fn key_share_groups(exts: &[TlsExtension]) -> Vec<NamedGroup> {
+    exts.iter()
+        .find_map(|ext| match ext {
+            TlsExtension::KeyShare(entries) => Some(entries.iter().map(|e| e.group).collect()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+fn has_tls13_version(exts: &[TlsExtension]) -> bool {
+    exts.iter().any(|ext| match ext {
+        TlsExtension::SupportedVersions(v) => v.iter().any(|v| v.is_tls13()),
+        _ => false,
+    })
+}
+
+/// Check that the extensions of a TLS 1.3 message are allowed in this message
+///
+/// `ext` is the raw extension block of the message, and `ctx` the message type. Allowed
+/// extensions are given by `TlsExtensionType::tls13_contexts`, and unknown extensions are
+/// not reported.
+pub fn validate_extensions_in(ext: &[u8], ctx: ExtensionContext) -> Vec<TlsConformanceViolation> {
+    let mut violations = Vec::new();
+    for (ext_type, _) in raw_extensions(ext) {
+        let violation = TlsConformanceViolation::ExtensionNotAllowed(ext_type, ctx);
+        match ext_type.tls13_contexts() {
+            Some(contexts) if !contexts.contains(&ctx) && !violations.contains(&violation) => {
+                violations.push(violation);
+            }
+            _ => (),
+        }
+    }
+    violations
+}
+
+/// Check a ClientHello against the requirements of RFC8446
+pub fn validate_client_hello(
+    client_hello: &TlsClientHelloContents,
+) -> Vec<TlsConformanceViolation> {
+    let mut violations = Vec::new();
+    let ext = client_hello.ext.unwrap_or(&[]);
+    let types: Vec<_> = raw_extensions(ext).iter().map(|&(t, _)| t).collect();
+    check_duplicates(&types, &mut violations);
+    let has = |t| types.contains(&t);
+    if let Some(idx) = types
+        .iter()
+        .position(|&t| t == TlsExtensionType::PreSharedKey)
+    {
+        if idx + 1 != types.len() {
+            violations.push(TlsConformanceViolation::PreSharedKeyNotLast);
+        }
+    }
+    let exts = match parse_tls_extensions_in(ext, ExtensionContext::ClientHello) {
+        Ok((_, exts)) => exts,
+        Err(_) => {
+            violations.push(TlsConformanceViolation::InvalidExtensions);
+            Vec::new()
+        }
+    };
+    if !has_tls13_version(&exts) {
+        if client_hello.version.0 < TlsVersion::Ssl30.0 {
+            violations.push(TlsConformanceViolation::InvalidLegacyVersion(
+                client_hello.version,
+            ));
+        }
+        return violations;
+    }
+    if client_hello.version != TlsVersion::Tls12 {
+        violations.push(TlsConformanceViolation::InvalidLegacyVersion(
+            client_hello.version,
+        ));
+    }
+    if client_hello.comp != [TlsCompressionID::Null] {
+        violations.push(TlsConformanceViolation::InvalidLegacyCompression);
+    }
+    violations.extend(validate_extensions_in(ext, ExtensionContext::ClientHello));
+    // required extensions
+    let has_psk = has(TlsExtensionType::PreSharedKey);
+    let mut required = Vec::new();
+    if has_psk {
+        required.push(TlsExtensionType::PskExchangeModes);
+    } else {
+        required.push(TlsExtensionType::SignatureAlgorithms);
+        required.push(TlsExtensionType::SupportedGroups);
+    }
+    if has(TlsExtensionType::KeyShare) {
+        required.push(TlsExtensionType::SupportedGroups);
+    }
+    if has(TlsExtensionType::SupportedGroups) {
+        required.push(TlsExtensionType::KeyShare);
+    }
+    for t in required {
+        let violation = TlsConformanceViolation::MissingExtension(t);
+        if !has(t) && !violations.contains(&violation) {
+            violations.push(violation);
+        }
+    }
+    if has(TlsExtensionType::EarlyData) && !has_psk {
+        violations.push(TlsConformanceViolation::EarlyDataWithoutPsk);
+    }
+    // key shares
+    let groups = supported_groups(&exts).unwrap_or(&[]);
+    let shares = key_share_groups(&exts);
+    for (idx, group) in shares.iter().enumerate() {
+        if shares[..idx].contains(group) {
+            violations.push(TlsConformanceViolation::DuplicateKeyShareGroup(*group));
+        } else if !groups.contains(group) {
+            violations.push(TlsConformanceViolation::KeyShareGroupNotOffered(*group));
+        }
+    }
+    // pre-shared keys
+    for ext in &exts {
+        if let TlsExtension::PreSharedKey(offer) = ext {
+            if offer.identities.len() != offer.binders.len() {
+                violations.push(TlsConformanceViolation::PskBinderCountMismatch);
+            }
+        }
+    }
+    violations
+}
+
+/// Check a ServerHello (or HelloRetryRequest) against the requirements of RFC8446, and
+/// against the ClientHello it answers
+pub fn validate_server_hello(
+    server_hello: &TlsServerHelloContents,
+    client_hello: &TlsClientHelloContents,
+) -> Vec<TlsConformanceViolation> {
+    let mut violations = Vec::new();
+    let types: Vec<_> = raw_extensions(server_hello.ext.unwrap_or(&[]))
+        .iter()
+        .map(|&(t, _)| t)
+        .collect();
+    check_duplicates(&types, &mut violations);
+    if !client_hello.ciphers.contains(&server_hello.cipher) {
+        violations.push(TlsConformanceViolation::CipherNotOffered(
+            server_hello.cipher,
+        ));
+    }
+    let client_types: Vec<_> = raw_extensions(client_hello.ext.unwrap_or(&[]))
+        .iter()
+        .map(|&(t, _)| t)
+        .collect();
+    let is_hrr = server_hello.is_hello_retry_request();
+    for &t in &types {
+        // the cookie is only sent by the server in a HelloRetryRequest
+        let solicited = client_types.contains(&t) || (is_hrr && t == TlsExtensionType::Cookie);
+        let violation = TlsConformanceViolation::UnsolicitedExtension(t);
+        if !solicited && !violations.contains(&violation) {
+            violations.push(violation);
+        }
+    }
+    let exts = match server_hello.parse_extensions() {
+        Ok((_, exts)) => exts,
+        Err(_) => {
+            violations.push(TlsConformanceViolation::InvalidExtensions);
+            return violations;
+        }
+    };
+    if !has_tls13_version(&exts) {
+        if server_hello.version.0 < TlsVersion::Ssl30.0 {
+            violations.push(TlsConformanceViolation::InvalidLegacyVersion(
+                server_hello.version,
+            ));
+        }
+        return violations;
+    }
+    if server_hello.version != TlsVersion::Tls12 {
+        violations.push(TlsConformanceViolation::InvalidLegacyVersion(
+            server_hello.version,
+        ));
+    }
+    if server_hello.compression != TlsCompressionID::Null {
+        violations.push(TlsConformanceViolation::InvalidLegacyCompression);
+    }
+    if server_hello.session_id.unwrap_or(&[]) != client_hello.session_id.unwrap_or(&[]) {
+        violations.push(TlsConformanceViolation::SessionIdNotEchoed);
+    }
+    let ctx = if is_hrr {
+        ExtensionContext::HelloRetryRequest
+    } else {
+        ExtensionContext::ServerHello
+    };
+    violations.extend(validate_extensions_in(server_hello.ext.unwrap_or(&[]), ctx));
+    let client_exts = parse_tls_extensions_in(
+        client_hello.ext.unwrap_or(&[]),
+        ExtensionContext::ClientHello,
+    )
+    .map(|(_, exts)| exts)
+    .unwrap_or_default();
+    let client_groups = supported_groups(&client_exts).unwrap_or(&[]);
+    let client_shares = key_share_groups(&client_exts);
+    for ext in &exts {
+        match ext {
+            TlsExtension::KeyShareServer(entry) if !client_shares.contains(&entry.group) => {
+                violations.push(TlsConformanceViolation::KeyShareGroupNotOffered(
+                    entry.group,
+                ));
+            }
+            TlsExtension::KeyShareHelloRetryRequest(group) => {
+                if !client_groups.contains(group) {
+                    violations.push(TlsConformanceViolation::KeyShareGroupNotOffered(*group));
+                } else if client_shares.contains(group) {
+                    violations.push(TlsConformanceViolation::HelloRetryRequestGroupShared(
+                        *group,
+                    ));
+                }
+            }
+            TlsExtension::PreSharedKeySelected(idx) => {
+                let offered = client_exts.iter().find_map(|ext| match ext {
+                    TlsExtension::PreSharedKey(offer) => Some(offer.identities.len()),
+                    _ => None,
+                });
+                if *idx as usize >= offered.unwrap_or(0) {
+                    violations.push(TlsConformanceViolation::InvalidPskIdentity(*idx));
+                }
+            }
+            _ => (),
+        }
+    }
+    violations
+}
@@ -0,0 +1,472 @@
+#[macro_use]
+extern crate pretty_assertions;
+
+extern crate nom;
+extern crate tls_parser;
+
+mod dtls {
+    use tls_parser::*;
+
+    #[rustfmt::skip]
+static DTLS_CLIENT_HELLO: &[u8] = &[
+    0x16, 0xfe, 0xfd, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x42, 0x01,
+    0x00, 0x00, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x36, 0xfe, 0xfd, 0x01,
+    0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+    0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d,
+    0x1e, 0x1f, 0x20, 0x00, 0x04, 0xaa, 0xbb, 0xcc, 0xdd, 0x00, 0x02, 0xc0, 0x2b, 0x01,
+    0x00, 0x00, 0x06, 0x00, 0x36, 0x00, 0x02, 0x01, 0x42,
+];
+
+    #[test]
+    fn test_dtls_client_hello() {
+        let empty = &b""[..];
+        let bytes = DTLS_CLIENT_HELLO;
+        let expected = DTLSPlaintext {
+            header: DTLSRecordHeader {
+                content_type: TlsRecordType::Handshake,
+                version: TlsVersion::DTls12,
+                epoch: 0,
+                sequence_number: 1,
+                length: 0x42,
+            },
+            messages: vec![DTLSMessage::Handshake(DTLSMessageHandshake {
+                msg_type: TlsHandshakeType::ClientHello,
+                length: 0x36,
+                message_seq: 0,
+                fragment_offset: 0,
+                fragment_length: 0x36,
+                body: DTLSMessageHandshakeBody::ClientHello(DTLSClientHello {
+                    version: TlsVersion::DTls12,
+                    random: &bytes[27..59],
+                    session_id: None,
+                    cookie: &bytes[61..65],
+                    ciphers: vec![TlsCipherSuiteID(0xc02b)],
+                    comp: vec![TlsCompressionID(0)],
+                    ext: Some(&bytes[73..]),
+                }),
+            })],
+        };
+
+        let res = parse_dtls_plaintext_record(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+
+        let res = parse_tls_extensions(&bytes[73..]);
+        assert_eq!(res, Ok((empty, vec![TlsExtension::ConnectionId(&[0x42])])));
+    }
+
+    #[test]
+    fn test_dtls_hello_verify_request() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x16, 0xfe, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x13, 0x03,
+            0x00, 0x00, 0x07, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x07, 0xfe, 0xff, 0x04,
+            0x01, 0x02, 0x03, 0x04,
+        ];
+        let res = parse_dtls_plaintext_record(bytes);
+        let expected = DTLSMessageHandshakeBody::HelloVerifyRequest(DTLSHelloVerifyRequest {
+            server_version: TlsVersion::DTls10,
+            cookie: &bytes[28..],
+        });
+        match res {
+            Ok((rem, record)) => {
+                assert_eq!(rem, empty);
+                match record.messages[0] {
+                    DTLSMessage::Handshake(ref msg) => assert_eq!(msg.body, expected),
+                    _ => panic!("Wrong message type (expected handshake)"),
+                }
+            }
+            _ => panic!("Parsing HelloVerifyRequest failed"),
+        }
+    }
+
+    #[test]
+    fn test_dtls_handshake_fragment() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x0b, 0x00, 0x01, 0x00, 0x00, 0x02, 0x00, 0x00, 0x10, 0x00, 0x00, 0x04, 0x01, 0x02,
+            0x03, 0x04,
+        ];
+        let res = parse_dtls_message_handshake(bytes);
+        let expected = DTLSMessage::Handshake(DTLSMessageHandshake {
+            msg_type: TlsHandshakeType::Certificate,
+            length: 0x100,
+            message_seq: 2,
+            fragment_offset: 0x10,
+            fragment_length: 4,
+            body: DTLSMessageHandshakeBody::Fragment(&bytes[12..]),
+        });
+        assert!(expected.is_fragment());
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_dtls_handshake_reassembly() {
+        let record = DTLS_CLIENT_HELLO;
+        let body = &record[25..];
+        let fragment = |offset: usize, len: usize| DTLSMessageHandshake {
+            msg_type: TlsHandshakeType::ClientHello,
+            length: 0x36,
+            message_seq: 0,
+            fragment_offset: offset as u32,
+            fragment_length: len as u32,
+            body: DTLSMessageHandshakeBody::Fragment(&body[offset..offset + len]),
+        };
+        let (_, expected) = parse_dtls_plaintext_record(record).expect("invalid record");
+        let mut reassembler = DTLSHandshakeReassembler::new();
+        // out of order, with a gap
+        assert_eq!(reassembler.push(&fragment(40, 14)), Ok(None));
+        assert_eq!(reassembler.push(&fragment(0, 20)), Ok(None));
+        assert_eq!(reassembler.gaps(0), Some(vec![(20, 40)]));
+        assert_eq!(reassembler.pending_messages(), vec![0]);
+        // overlapping, with the same content
+        let res = reassembler
+            .push(&fragment(10, 35))
+            .expect("reassembly failed");
+        assert_eq!(
+            res.map(DTLSMessage::Handshake),
+            Some(expected.messages[0].clone())
+        );
+        assert_eq!(reassembler.gaps(0), None);
+        // retransmissions are ignored
+        assert_eq!(reassembler.push(&fragment(0, 20)), Ok(None));
+        assert!(reassembler.pending_messages().is_empty());
+    }
+
+    #[test]
+    fn test_dtls_handshake_reassembly_errors() {
+        let data = [0u8; 16];
+        let fragment = |offset: u32, data| DTLSMessageHandshake {
+            msg_type: TlsHandshakeType::Certificate,
+            length: 0x100,
+            message_seq: 2,
+            fragment_offset: offset,
+            fragment_length: 8,
+            body: DTLSMessageHandshakeBody::Fragment(data),
+        };
+        let mut reassembler = DTLSHandshakeReassembler::new();
+        assert_eq!(reassembler.push(&fragment(0x10, &data[..8])), Ok(None));
+        assert_eq!(
+            reassembler.push(&fragment(0x14, &[1; 8])),
+            Err(DTLSReassemblyError::OverlapMismatch)
+        );
+        assert!(reassembler.pending_messages().is_empty());
+        assert_eq!(
+            reassembler.push(&fragment(0xfc, &data[..8])),
+            Err(DTLSReassemblyError::InvalidFragment)
+        );
+        let mut reassembler = DTLSHandshakeReassembler::with_max_size(0x80);
+        assert_eq!(
+            reassembler.push(&fragment(0, &data[..8])),
+            Err(DTLSReassemblyError::BufferFull)
+        );
+    }
+
+    #[test]
+    fn test_dtls_cid_record() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x19, 0xfe, 0xfd, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0xca, 0xfe, 0x00,
+            0x03, 0x11, 0x22, 0x33,
+        ];
+        let expected = DTLSRawRecord {
+            header: DTLSRecordHeader {
+                content_type: TlsRecordType::Tls12Cid,
+                version: TlsVersion::DTls12,
+                epoch: 1,
+                sequence_number: 5,
+                length: 3,
+            },
+            connection_id: Some(&bytes[11..13]),
+            fragment: &bytes[15..],
+        };
+        let res = parse_dtls_raw_record_with_cid(bytes, 2);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_dtls13_unified_header() {
+        let empty = &b""[..];
+        // C=1, S=1, L=1, epoch 3
+        let bytes = &[
+            0x3f, 0xca, 0xfe, 0x01, 0x02, 0x00, 0x04, 0x11, 0x22, 0x33, 0x44,
+        ];
+        let expected = DTLS13Record::Ciphertext(DTLSCiphertext {
+            header: DTLSUnifiedHeader {
+                epoch_bits: 3,
+                connection_id: Some(&bytes[1..3]),
+                sequence_number: 0x0102,
+                sequence_number_16: true,
+                length: Some(4),
+            },
+            encrypted_record: &bytes[7..],
+        });
+        assert_eq!(parse_dtls13_record(bytes, 2), Ok((empty, expected)));
+        // minimal header: 8-bit sequence number, no length
+        let bytes = &[0x22, 0x07, 0x11, 0x22, 0x33];
+        let expected = DTLSCiphertext {
+            header: DTLSUnifiedHeader {
+                epoch_bits: 2,
+                connection_id: None,
+                sequence_number: 7,
+                sequence_number_16: false,
+                length: None,
+            },
+            encrypted_record: &bytes[2..],
+        };
+        assert_eq!(parse_dtls13_ciphertext(bytes, 0), Ok((empty, expected)));
+        // full header records are still accepted
+        let res = parse_dtls13_record(DTLS_CLIENT_HELLO, 0);
+        assert!(matches!(res, Ok((_, DTLS13Record::Plaintext(_)))));
+        assert!(parse_dtls13_unified_header(DTLS_CLIENT_HELLO, 0).is_err());
+    }
+
+    #[test]
+    fn test_dtls_state_cookie_exchange() {
+        let (_, record) = parse_dtls_plaintext_record(DTLS_CLIENT_HELLO).expect("invalid record");
+        let ch_cookie = record.messages[0].clone();
+        let mut ch = ch_cookie.clone();
+        if let DTLSMessage::Handshake(DTLSMessageHandshake {
+            body: DTLSMessageHandshakeBody::ClientHello(ref mut c),
+            ..
+        }) = ch
+        {
+            c.cookie = &[];
+        }
+        let hvr = DTLSMessage::Handshake(DTLSMessageHandshake {
+            msg_type: TlsHandshakeType::HelloVerifyRequest,
+            length: 7,
+            message_seq: 0,
+            fragment_offset: 0,
+            fragment_length: 7,
+            body: DTLSMessageHandshakeBody::HelloVerifyRequest(DTLSHelloVerifyRequest {
+                server_version: TlsVersion::DTls10,
+                cookie: &[1, 2, 3, 4],
+            }),
+        });
+        let state = TlsState::None;
+        let state = dtls_state_transition(state, &ch, true).ok();
+        assert_eq!(state, Some(TlsState::ClientHello));
+        let state = dtls_state_transition(TlsState::ClientHello, &hvr, false).ok();
+        assert_eq!(state, Some(TlsState::HelloVerifyRequest));
+        // the second ClientHello must have a cookie
+        assert!(dtls_state_transition(TlsState::HelloVerifyRequest, &ch, true).is_err());
+        let state = dtls_state_transition(TlsState::HelloVerifyRequest, &ch_cookie, true).ok();
+        assert_eq!(state, Some(TlsState::ClientHello));
+        // HelloVerifyRequest is only valid after a ClientHello
+        assert!(dtls_state_transition(TlsState::HelloVerifyRequest, &hvr, false).is_err());
+        // DTLS 1.3: HelloRetryRequest (with a cookie extension)
+        let hrr = DTLSMessage::Handshake(DTLSMessageHandshake {
+            msg_type: TlsHandshakeType::ServerHello,
+            length: 0,
+            message_seq: 0,
+            fragment_offset: 0,
+            fragment_length: 0,
+            body: DTLSMessageHandshakeBody::ServerHello(TlsServerHelloContents {
+                version: TlsVersion::DTls12,
+                rand_time: u32::from_be_bytes([
+                    HRR_RANDOM[0],
+                    HRR_RANDOM[1],
+                    HRR_RANDOM[2],
+                    HRR_RANDOM[3],
+                ]),
+                rand_data: &HRR_RANDOM[4..],
+                session_id: None,
+                cipher: TlsCipherSuiteID(0x1301),
+                compression: TlsCompressionID(0),
+                ext: None,
+            }),
+        });
+        let state = dtls_state_transition(TlsState::ClientHello, &hrr, false).ok();
+        assert_eq!(state, Some(TlsState::HelloRetryRequest));
+        let state = dtls_state_transition(TlsState::HelloRetryRequest, &ch, true).ok();
+        assert_eq!(state, Some(TlsState::ClientHello));
+    }
+
+    #[test]
+    fn test_dtls_state_machine_retransmission() {
+        let hs = |msg_type, message_seq, body| {
+            DTLSMessage::Handshake(DTLSMessageHandshake {
+                msg_type,
+                length: 0,
+                message_seq,
+                fragment_offset: 0,
+                fragment_length: 0,
+                body,
+            })
+        };
+        let (_, record) = parse_dtls_plaintext_record(DTLS_CLIENT_HELLO).expect("invalid record");
+        let mut ch_cookie = record.messages[0].clone();
+        let mut ch = ch_cookie.clone();
+        if let DTLSMessage::Handshake(DTLSMessageHandshake {
+            body: DTLSMessageHandshakeBody::ClientHello(ref mut c),
+            ..
+        }) = ch
+        {
+            c.cookie = &[];
+        }
+        if let DTLSMessage::Handshake(ref mut m) = ch_cookie {
+            m.message_seq = 1;
+        }
+        let hvr = hs(
+            TlsHandshakeType::HelloVerifyRequest,
+            0,
+            DTLSMessageHandshakeBody::HelloVerifyRequest(DTLSHelloVerifyRequest {
+                server_version: TlsVersion::DTls10,
+                cookie: &[0xaa, 0xbb, 0xcc, 0xdd],
+            }),
+        );
+        let sh = hs(
+            TlsHandshakeType::ServerHello,
+            1,
+            DTLSMessageHandshakeBody::ServerHello(TlsServerHelloContents::new(
+                0xfefd, 0, &[0; 28], None, 0xc02b, 0, None,
+            )),
+        );
+        let ske = hs(
+            TlsHandshakeType::ServerKeyExchange,
+            2,
+            DTLSMessageHandshakeBody::ServerKeyExchange(TlsServerKeyExchangeContents {
+                parameters: &[],
+            }),
+        );
+        let shd = hs(
+            TlsHandshakeType::ServerDone,
+            3,
+            DTLSMessageHandshakeBody::ServerDone(&[]),
+        );
+        let cke = hs(
+            TlsHandshakeType::ClientKeyExchange,
+            2,
+            DTLSMessageHandshakeBody::ClientKeyExchange(TlsClientKeyExchangeContents::Unknown(&[])),
+        );
+        let ccs = DTLSMessage::ChangeCipherSpec;
+        let mut sm = DTLSStateMachine::new();
+        let steps = vec![
+            (
+                0,
+                &ch,
+                true,
+                DTLSMessageStatus::Accepted,
+                TlsState::ClientHello,
+            ),
+            (
+                0,
+                &hvr,
+                false,
+                DTLSMessageStatus::Accepted,
+                TlsState::HelloVerifyRequest,
+            ),
+            // the first ClientHello is retransmitted
+            (
+                0,
+                &ch,
+                true,
+                DTLSMessageStatus::Retransmission,
+                TlsState::HelloVerifyRequest,
+            ),
+            (
+                0,
+                &ch_cookie,
+                true,
+                DTLSMessageStatus::Accepted,
+                TlsState::ClientHello,
+            ),
+            (
+                0,
+                &hvr,
+                false,
+                DTLSMessageStatus::Retransmission,
+                TlsState::ClientHello,
+            ),
+            (
+                0,
+                &sh,
+                false,
+                DTLSMessageStatus::Accepted,
+                TlsState::ServerHello,
+            ),
+            // ServerHelloDone received before ServerKeyExchange
+            (
+                0,
+                &shd,
+                false,
+                DTLSMessageStatus::OutOfOrder,
+                TlsState::ServerHello,
+            ),
+            (
+                0,
+                &ske,
+                false,
+                DTLSMessageStatus::Accepted,
+                TlsState::NoCertSKE,
+            ),
+            (
+                0,
+                &shd,
+                false,
+                DTLSMessageStatus::Accepted,
+                TlsState::NoCertHelloDone,
+            ),
+            (
+                0,
+                &cke,
+                true,
+                DTLSMessageStatus::Accepted,
+                TlsState::NoCertCKE,
+            ),
+            (
+                0,
+                &ccs,
+                true,
+                DTLSMessageStatus::Accepted,
+                TlsState::ClientChangeCipherSpec,
+            ),
+            // the client flight is retransmitted
+            (
+                0,
+                &cke,
+                true,
+                DTLSMessageStatus::Retransmission,
+                TlsState::ClientChangeCipherSpec,
+            ),
+            (
+                0,
+                &ccs,
+                true,
+                DTLSMessageStatus::Retransmission,
+                TlsState::ClientChangeCipherSpec,
+            ),
+            // a record of the next server epoch is received before ChangeCipherSpec
+            (
+                1,
+                &ccs,
+                false,
+                DTLSMessageStatus::OutOfOrder,
+                TlsState::ClientChangeCipherSpec,
+            ),
+            (
+                0,
+                &ccs,
+                false,
+                DTLSMessageStatus::Accepted,
+                TlsState::SessionEncrypted,
+            ),
+        ];
+        for (epoch, msg, to_server, status, state) in steps {
+            assert_eq!(sm.update(epoch, msg, to_server).ok(), Some(status));
+            assert_eq!(sm.state(), state);
+        }
+        assert_eq!(sm.epoch(true), 1);
+        assert_eq!(sm.epoch(false), 1);
+        assert_eq!(sm.next_message_seq(true), 3);
+        assert_eq!(sm.next_message_seq(false), 4);
+        // a new message which is not a retransmission is still checked
+        let shd = hs(
+            TlsHandshakeType::ServerDone,
+            4,
+            DTLSMessageHandshakeBody::ServerDone(&[]),
+        );
+        assert!(sm.update(1, &shd, false).is_err());
+        assert_eq!(sm.state(), TlsState::Invalid);
+    }
+} // mod dtls
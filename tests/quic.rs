@@ -0,0 +1,47 @@
+#[macro_use]
+extern crate pretty_assertions;
+
+extern crate nom;
+extern crate tls_parser;
+
+mod quic {
+    use nom::{Err, Needed};
+    use tls_parser::*;
+
+    static CLIENT_HELLO_RECORD: &[u8] = include_bytes!("../assets/client_hello_dhe.bin");
+
+    #[test]
+    fn test_quic_crypto_messages() {
+        let empty = &b""[..];
+        let ch = &CLIENT_HELLO_RECORD[5..];
+        let (_, expected) = parse_tls_message_handshake(ch).expect("invalid message");
+        let mut stream = ch.to_vec();
+        stream.extend_from_slice(&ch[..10]);
+        let res = parse_quic_crypto_messages(&stream);
+        assert_eq!(res, Ok((&ch[..10], vec![expected])));
+        // incomplete message only
+        let res = parse_quic_crypto_messages(&ch[..10]);
+        assert_eq!(res, Err(Err::Incomplete(Needed::new(ch.len() - 10))));
+        assert_eq!(parse_quic_crypto_messages(empty), Ok((empty, vec![])));
+    }
+
+    #[test]
+    fn test_quic_crypto_message_tls13_format() {
+        let empty = &b""[..];
+        // TLS 1.3 NewSessionTicket
+        let bytes = &[
+            0x04, 0x00, 0x00, 0x10, 0x00, 0x00, 0x1c, 0x20, 0x01, 0x02, 0x03, 0x04, 0x01, 0xaa,
+            0x00, 0x02, 0xbb, 0xcc, 0x00, 0x00,
+        ];
+        let expected = TlsMessage::Handshake(TlsMessageHandshake::NewSessionTicketV13(
+            TlsNewSessionTicketV13Content {
+                ticket_lifetime: 7200,
+                ticket_age_add: 0x0102_0304,
+                ticket_nonce: &bytes[13..14],
+                ticket: &bytes[16..18],
+                ext: empty,
+            },
+        ));
+        assert_eq!(parse_quic_crypto_message(bytes), Ok((empty, expected)));
+    }
+}
@@ -0,0 +1,151 @@
+#[macro_use]
+extern crate pretty_assertions;
+
+extern crate nom;
+extern crate tls_parser;
+
+mod ssl2 {
+    use nom::{Err, Needed};
+    use tls_parser::*;
+
+    #[rustfmt::skip]
+static SSL2_COMPAT_CLIENT_HELLO: &[u8] = &[
+    0x80, 0x2e, 0x01, 0x03, 0x01, 0x00, 0x15, 0x00, 0x00, 0x00, 0x10, 0x07,
+    0x00, 0xc0, 0x05, 0x00, 0x80, 0x03, 0x00, 0x80, 0x01, 0x00, 0x80, 0x06,
+    0x00, 0x40, 0x00, 0x00, 0x2f, 0x00, 0x00, 0xff, 0x01, 0x02, 0x03, 0x04,
+    0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10,
+];
+
+    #[test]
+    fn test_ssl2_compat_client_hello() {
+        let empty = &b""[..];
+        let bytes = SSL2_COMPAT_CLIENT_HELLO;
+        let expected = SSLv2Plaintext {
+            header: SSLv2RecordHeader {
+                length: 0x2e,
+                padding: 0,
+                is_escape: false,
+            },
+            message: SSLv2Message::ClientHello(SSLv2ClientHello {
+                version: TlsVersion::Tls10,
+                cipher_specs: vec![
+                    SSLv2CipherKind::DES_192_EDE3_CBC_WITH_MD5,
+                    SSLv2CipherKind::IDEA_128_CBC_WITH_MD5,
+                    SSLv2CipherKind::RC2_128_CBC_WITH_MD5,
+                    SSLv2CipherKind::RC4_128_WITH_MD5,
+                    SSLv2CipherKind::DES_64_CBC_WITH_MD5,
+                    SSLv2CipherKind(0x00_002f),
+                    SSLv2CipherKind(0x00_00ff),
+                ],
+                session_id: None,
+                challenge: &bytes[32..],
+            }),
+            padding: empty,
+        };
+        let res = parse_ssl2_plaintext_record(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+        if let Ok((
+            _,
+            SSLv2Plaintext {
+                message: SSLv2Message::ClientHello(hello),
+                ..
+            },
+        )) = res
+        {
+            assert_eq!(
+                hello.get_tls_ciphers(),
+                vec![TlsCipherSuiteID(0x2f), TlsCipherSuiteID(0xff)]
+            );
+        }
+    }
+
+    #[rustfmt::skip]
+static SSL2_SERVER_HELLO: &[u8] = &[
+    0x80, 0x29, 0x04, 0x00, 0x01, 0x00, 0x02, 0x00, 0x08, 0x00, 0x06, 0x00,
+    0x10, 0x30, 0x06, 0x02, 0x01, 0x00, 0x02, 0x01, 0x00, 0x01, 0x00, 0x80,
+    0x07, 0x00, 0xc0, 0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7, 0xa8,
+    0xa9, 0xaa, 0xab, 0xac, 0xad, 0xae, 0xaf,
+];
+
+    #[test]
+    fn test_ssl2_server_hello() {
+        let empty = &b""[..];
+        let bytes = SSL2_SERVER_HELLO;
+        let expected = SSLv2Plaintext {
+            header: SSLv2RecordHeader {
+                length: 0x29,
+                padding: 0,
+                is_escape: false,
+            },
+            message: SSLv2Message::ServerHello(SSLv2ServerHello {
+                session_id_hit: false,
+                certificate_type: SSLv2CertificateType::X509Certificate,
+                version: TlsVersion::Ssl20,
+                certificate: &bytes[13..21],
+                cipher_specs: vec![
+                    SSLv2CipherKind::RC4_128_WITH_MD5,
+                    SSLv2CipherKind::DES_192_EDE3_CBC_WITH_MD5,
+                ],
+                connection_id: &bytes[27..],
+            }),
+            padding: empty,
+        };
+        let res = parse_ssl2_plaintext_record(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_ssl2_drown_exposure() {
+        let (_, record) = parse_ssl2_plaintext_record(SSL2_SERVER_HELLO).expect("parsing failed");
+        let mut audit = TlsAudit::new();
+        audit.observe_sslv2_message(&record.message);
+        assert_eq!(audit.server_sslv2, Some(SSLv2Support::Enabled));
+        let export_hello = SSLv2ServerHello {
+            session_id_hit: false,
+            certificate_type: SSLv2CertificateType::X509Certificate,
+            version: TlsVersion::Ssl20,
+            certificate: &[],
+            cipher_specs: vec![SSLv2CipherKind::RC4_128_EXPORT40_WITH_MD5],
+            connection_id: &[],
+        };
+        audit.observe_sslv2_message(&SSLv2Message::ServerHello(export_hello));
+        audit.observe_sslv2_message(&record.message);
+        assert_eq!(audit.server_sslv2, Some(SSLv2Support::ExportCiphers));
+        // SSLv2 support of the server, for a TLS 1.2 session
+        let audit = TlsAudit {
+            version: Some(TlsVersion::Tls12),
+            cipher: Some(TlsCipherSuiteID(0xc02f)),
+            server_sslv2: audit.server_sslv2,
+            ..TlsAudit::default()
+        };
+        let findings = audit.findings();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, TlsAuditSeverity::Critical);
+        assert_eq!(
+            findings[0].issue,
+            TlsAuditIssue::SSLv2Enabled(SSLv2Support::ExportCiphers)
+        );
+    }
+
+    #[test]
+    fn test_ssl2_record_header() {
+        let empty = &b""[..];
+        let bytes = &[0x40, 0x10, 0x05];
+        let expected = SSLv2RecordHeader {
+            length: 0x10,
+            padding: 5,
+            is_escape: true,
+        };
+        assert_eq!(parse_ssl2_record_header(bytes), Ok((empty, expected)));
+        let res = parse_ssl2_plaintext_record(&SSL2_COMPAT_CLIENT_HELLO[..20]);
+        assert_eq!(res, Err(Err::Incomplete(Needed::new(28))));
+    }
+
+    #[test]
+    fn test_ssl2_client_hello_invalid_cipher_specs() {
+        let bytes = &[
+            0x00, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00, 0x10, 0x07, 0x00, 0xc0, 0x05,
+        ];
+        assert!(parse_ssl2_client_hello(bytes).is_err());
+    }
+}
@@ -145,4 +145,209 @@ static DHE_PARAMS: &[u8] = &[
         let res = pair!(bytes, parse_dh_params, parse_digitally_signed);
         assert_eq!(res, Ok((empty, (expected1, expected2))));
     }
+
+    #[test]
+    fn test_tls_server_key_exchange_params() {
+        let empty = &b""[..];
+        // TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256
+        let cipher = TlsCipherSuite::from_id(0xc02f).expect("unknown cipher");
+        let (rem, params) = parse_server_key_exchange_params(ECDHE_PARAMS, cipher, true)
+            .expect("could not parse ECDHE parameters");
+        assert_eq!(rem, empty);
+        match params {
+            ServerKeyExchangeParams::Ecdhe(ecdh, signature) => {
+                assert_eq!(
+                    ecdh.curve_params.params_content,
+                    ECParametersContent::NamedGroup(NamedGroup::Secp521r1)
+                );
+                assert_eq!(signature.data, &ECDHE_PARAMS[141..]);
+            }
+            _ => panic!("unexpected parameters {:?}", params),
+        }
+        // TLS_DHE_RSA_WITH_AES_128_CBC_SHA
+        let cipher = TlsCipherSuite::from_id(0x0033).expect("unknown cipher");
+        let (rem, params) = parse_server_key_exchange_params(DHE_PARAMS, cipher, true)
+            .expect("could not parse DHE parameters");
+        assert_eq!(rem, empty);
+        match params {
+            ServerKeyExchangeParams::Dhe(dh, signature) => {
+                assert_eq!(dh.dh_g, &DHE_PARAMS[260..516]);
+                assert_eq!(signature.data, &DHE_PARAMS[778..]);
+            }
+            _ => panic!("unexpected parameters {:?}", params),
+        }
+        // TLS_PSK_WITH_AES_128_CBC_SHA
+        let cipher = TlsCipherSuite::from_id(0x008c).expect("unknown cipher");
+        let bytes = &[0x00, 0x02, 0x68, 0x69];
+        let res = parse_server_key_exchange_params(bytes, cipher, true);
+        let expected = ServerKeyExchangeParams::Psk {
+            identity_hint: b"hi",
+        };
+        assert_eq!(res, Ok((empty, expected)));
+        // TLS_RSA_WITH_AES_128_CBC_SHA: no ServerKeyExchange
+        let cipher = TlsCipherSuite::from_id(0x002f).expect("unknown cipher");
+        assert!(parse_server_key_exchange_params(bytes, cipher, true).is_err());
+    }
+
+    #[rustfmt::skip]
+static MOD_SSL_512: &[u8] = &[
+    0xd4, 0xbc, 0xd5, 0x24, 0x06, 0xf6, 0x9b, 0x35, 0x99, 0x4b, 0x88, 0xde, 0x5d, 0xb8, 0x96, 0x82,
+    0xc8, 0x15, 0x7f, 0x62, 0xd8, 0xf3, 0x36, 0x33, 0xee, 0x57, 0x72, 0xf1, 0x1f, 0x05, 0xab, 0x22,
+    0xd6, 0xb5, 0x14, 0x5b, 0x9f, 0x24, 0x1e, 0x5a, 0xcc, 0x31, 0xff, 0x09, 0x0a, 0x4b, 0xc7, 0x11,
+    0x48, 0x97, 0x6f, 0x76, 0x79, 0x50, 0x94, 0xe7, 0x1e, 0x79, 0x03, 0x52, 0x9f, 0x5a, 0x82, 0x4b,
+];
+
+    #[test]
+    fn test_tls_dh_group_analysis() {
+        let (_, dh) = parse_dh_params(DHE_PARAMS).expect("could not parse DH parameters");
+        let analysis = dh.analyze_group();
+        assert_eq!(analysis.bits, 2048);
+        assert_eq!(analysis.known_group, None);
+        assert_eq!(analysis.risk, DhGroupRisk::Low);
+        // leading zeros are ignored
+        let mut prime = vec![0x00];
+        prime.extend_from_slice(MOD_SSL_512);
+        let dh = ServerDHParams {
+            dh_p: &prime,
+            dh_g: &[0x02],
+            dh_ys: &[0x03],
+        };
+        let analysis = dh.analyze_group();
+        assert_eq!(analysis.bits, 512);
+        assert_eq!(analysis.known_group, Some("mod_ssl 512-bit"));
+        assert_eq!(analysis.risk, DhGroupRisk::Export);
+        let prime = [0x7f; 96];
+        let dh = ServerDHParams {
+            dh_p: &prime,
+            dh_g: &[0x02],
+            dh_ys: &[0x03],
+        };
+        assert_eq!(dh.prime_bits(), 767);
+        assert_eq!(dh.analyze_group().risk, DhGroupRisk::Weak);
+        let prime = [0xff; 128];
+        let dh = ServerDHParams {
+            dh_p: &prime,
+            dh_g: &[0x02],
+            dh_ys: &[0x03],
+        };
+        assert_eq!(dh.analyze_group().risk, DhGroupRisk::Moderate);
+    }
+
+    #[test]
+    fn test_tls_audit() {
+        let server_hello = |version: u16, cipher: u16| {
+            let sh = TlsServerHelloContents::new(version, 0, &[0; 28], None, cipher, 0, None);
+            TlsMessage::Handshake(TlsMessageHandshake::ServerHello(sh))
+        };
+        let server_key_exchange = |parameters| {
+            let ske = TlsServerKeyExchangeContents { parameters };
+            TlsMessage::Handshake(TlsMessageHandshake::ServerKeyExchange(ske))
+        };
+        // TLS_DHE_RSA_WITH_AES_128_CBC_SHA, 2048-bit group
+        let mut audit = TlsAudit::new();
+        audit.observe_message(&server_hello(0x0303, 0x0033));
+        audit.observe_message(&server_key_exchange(DHE_PARAMS));
+        let dh = audit.dh_group.expect("no DH group");
+        assert_eq!((dh.bits, dh.known_group), (2048, None));
+        assert!(audit.findings().is_empty());
+        // TLS_DHE_RSA_EXPORT_WITH_DES40_CBC_SHA, TLS 1.0, 512-bit group
+        let mut ske = vec![0x00, 0x40];
+        ske.extend_from_slice(MOD_SSL_512);
+        ske.extend_from_slice(&[0x00, 0x01, 0x02, 0x00, 0x01, 0x03, 0x00, 0x00]);
+        let mut audit = TlsAudit::new();
+        audit.observe_message(&server_hello(0x0301, 0x0014));
+        audit.observe_message(&server_key_exchange(&ske));
+        let dh = audit.dh_group.expect("no DH group");
+        assert_eq!(dh.known_group, Some("mod_ssl 512-bit"));
+        assert_eq!(dh.risk, DhGroupRisk::Export);
+        let issues: Vec<_> = audit.findings().iter().map(|f| f.issue).collect();
+        assert_eq!(
+            issues,
+            vec![
+                TlsAuditIssue::ExportCipher(TlsCipherSuiteID(0x0014)),
+                TlsAuditIssue::WeakDhGroup(dh),
+                TlsAuditIssue::WeakEncryption(TlsCipherSuiteID(0x0014), TlsCipherEnc::Des),
+                TlsAuditIssue::ObsoleteVersion(TlsVersion::Tls10),
+            ]
+        );
+        // TLS_RSA_WITH_3DES_EDE_CBC_SHA
+        let audit = TlsAudit {
+            version: Some(TlsVersion::Tls12),
+            cipher: Some(TlsCipherSuiteID(0x000a)),
+            ..TlsAudit::default()
+        };
+        let findings = audit.findings();
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].severity, TlsAuditSeverity::Medium);
+        assert_eq!(
+            findings[1].issue,
+            TlsAuditIssue::NoForwardSecrecy(TlsCipherSuiteID(0x000a))
+        );
+    }
+
+    #[test]
+    fn test_tls_export_rsa() {
+        let ciphers = vec![TlsCipherSuiteID(0x0003), TlsCipherSuiteID(0x002f)];
+        let comp = vec![TlsCompressionID::Null];
+        let ch = TlsClientHelloContents::new(0x0301, 0, &[0; 28], None, ciphers, comp, None);
+        assert_eq!(ch.get_export_rsa_ciphers(), vec![TlsCipherSuiteID(0x0003)]);
+        // TLS_RSA_EXPORT_WITH_RC4_40_MD5, temporary 512-bit key
+        let mut ske = vec![0x00, 0x40];
+        ske.extend_from_slice(MOD_SSL_512);
+        ske.extend_from_slice(&[0x00, 0x03, 0x01, 0x00, 0x01, 0x00, 0x02, 0xaa, 0xbb]);
+        let cipher = TlsCipherSuite::from_id(0x0003).expect("unknown cipher");
+        assert!(cipher.is_export_rsa());
+        let (rem, params) = parse_server_key_exchange_params(&ske, cipher, false)
+            .expect("could not parse RSA parameters");
+        assert!(rem.is_empty());
+        match params {
+            ServerKeyExchangeParams::RsaExport(rsa, signature) => {
+                assert_eq!(rsa.modulus_bits(), 512);
+                assert_eq!(rsa.rsa_exponent, &[0x01, 0x00, 0x01]);
+                assert_eq!(signature.data, &[0xaa, 0xbb]);
+            }
+            _ => panic!("unexpected parameters {:?}", params),
+        }
+        let sh = TlsServerHelloContents::new(0x0301, 0, &[0; 28], None, 0x0003, 0, None);
+        let mut audit = TlsAudit::new();
+        audit.observe_message(&TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)));
+        audit.observe_message(&TlsMessage::Handshake(TlsMessageHandshake::ServerHello(sh)));
+        let ske = TlsServerKeyExchangeContents { parameters: &ske };
+        audit.observe_message(&TlsMessage::Handshake(
+            TlsMessageHandshake::ServerKeyExchange(ske),
+        ));
+        assert!(audit.client_export_rsa);
+        assert_eq!(audit.export_rsa_key_bits, Some(512));
+        let findings = audit.findings();
+        assert!(findings.contains(&TlsAuditIssue::ExportRsaOffered.into()));
+        assert!(findings.contains(&TlsAuditIssue::ExportRsaKey { bits: 512 }.into()));
+    }
+
+    #[test]
+    fn test_tls_client_key_exchange_params() {
+        let empty = &b""[..];
+        // TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256
+        let cipher = TlsCipherSuite::from_id(0xc02f).expect("unknown cipher");
+        let bytes = &[0x03, 0x04, 0xaa, 0xbb];
+        let expected = ClientKeyExchangeParams::Ecdh(ECPoint { point: &bytes[1..] });
+        let res = parse_client_key_exchange_params(bytes, cipher);
+        assert_eq!(res, Ok((empty, expected)));
+        // TLS_RSA_WITH_AES_128_CBC_SHA
+        let cipher = TlsCipherSuite::from_id(0x002f).expect("unknown cipher");
+        let bytes = &[0x00, 0x02, 0xaa, 0xbb];
+        let expected = ClientKeyExchangeParams::Rsa {
+            encrypted_pms: &bytes[2..],
+        };
+        let res = parse_client_key_exchange_params(bytes, cipher);
+        assert_eq!(res, Ok((empty, expected)));
+        // TLS_DHE_PSK_WITH_NULL_SHA
+        let cipher = TlsCipherSuite::from_id(0x002d).expect("unknown cipher");
+        let bytes = &[0x00, 0x02, 0x68, 0x69, 0x00, 0x01, 0xaa];
+        let expected = ClientKeyExchangeParams::DhePsk {
+            identity: b"hi",
+            dh_yc: &[0xaa],
+        };
+        let res = parse_client_key_exchange_params(bytes, cipher);
+        assert_eq!(res, Ok((empty, expected)));
+    }
 } // mod tls_dh
@@ -0,0 +1,51 @@
+#[macro_use]
+extern crate pretty_assertions;
+
+extern crate nom;
+extern crate tls_parser;
+
+mod tls_ech {
+    use tls_parser::*;
+
+    #[test]
+    fn test_ech_config_list() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x00, 0x3e, 0xfe, 0x0d, 0x00, 0x3a, 0x01, 0x00, 0x20, 0x00, 0x20, 0x20, 0x21, 0x22,
+            0x23, 0x24, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x2b, 0x2c, 0x2d, 0x2e, 0x2f, 0x30,
+            0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x3b, 0x3c, 0x3d, 0x3e,
+            0x3f, 0x00, 0x04, 0x00, 0x01, 0x00, 0x01, 0x00, 0x0b, 0x65, 0x78, 0x61, 0x6d, 0x70,
+            0x6c, 0x65, 0x2e, 0x63, 0x6f, 0x6d, 0x00, 0x00,
+        ];
+        let expected = vec![EchConfig::V0xfe0d(EchConfigContents {
+            key_config: HpkeKeyConfig {
+                config_id: 1,
+                kem_id: HpkeKemId::DhkemX25519HkdfSha256,
+                public_key: &bytes[11..43],
+                cipher_suites: vec![HpkeSymmetricCipherSuite {
+                    kdf_id: HpkeKdfId::HkdfSha256,
+                    aead_id: HpkeAeadId::Aes128Gcm,
+                }],
+            },
+            maximum_name_length: 0,
+            public_name: b"example.com",
+            extensions: Vec::new(),
+        })];
+
+        let res = parse_ech_config_list(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_ech_config_unknown_version() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x08, 0xfe, 0x0a, 0x00, 0x04, 0x01, 0x02, 0x03, 0x04];
+        let expected = vec![EchConfig::Unknown {
+            version: 0xfe0a,
+            data: &bytes[6..],
+        }];
+
+        let res = parse_ech_config_list(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+} // mod tls_ech
@@ -23,12 +23,26 @@ static CLIENT_EXTENSIONS1: &[u8] = &[
     fn test_tls_extensions() {
         let empty = &b""[..];
         let bytes = CLIENT_EXTENSIONS1;
-        let ec_point_formats = &[0, 1, 2];
-        let ext1 = &[0, 0, 0, 0];
+        let ec_point_formats = vec![
+            ECPointFormat::Uncompressed,
+            ECPointFormat::AnsiX962CompressedPrime,
+            ECPointFormat::AnsiX962CompressedChar2,
+        ];
+        let ocsp = OcspStatusRequest {
+            responder_id_list: vec![],
+            request_extensions: empty,
+        };
         let ecc: Vec<_> = vec![23, 25, 28, 27, 24, 26, 22, 14, 13, 11, 12, 9, 10]
             .iter()
             .map(|&x| NamedGroup(x))
             .collect();
+        let sig_algs: Vec<_> = vec![
+            0x0601, 0x0602, 0x0603, 0x0501, 0x0502, 0x0503, 0x0401, 0x0402, 0x0403, 0x0301, 0x0302,
+            0x0303, 0x0201, 0x0202, 0x0203,
+        ]
+        .iter()
+        .map(|&x| SignatureScheme(x))
+        .collect();
         let expected = Ok((
             empty,
             vec![
@@ -36,12 +50,9 @@ static CLIENT_EXTENSIONS1: &[u8] = &[
                 TlsExtension::EcPointFormats(ec_point_formats),
                 TlsExtension::EllipticCurves(ecc),
                 TlsExtension::SessionTicket(&empty),
-                TlsExtension::SignatureAlgorithms(vec![
-                    0x0601, 0x0602, 0x0603, 0x0501, 0x0502, 0x0503, 0x0401, 0x0402, 0x0403, 0x0301,
-                    0x0302, 0x0303, 0x0201, 0x0202, 0x0203,
-                ]),
-                TlsExtension::StatusRequest(Some((CertificateStatusType::OCSP, ext1))),
-                TlsExtension::Heartbeat(1),
+                TlsExtension::SignatureAlgorithms(sig_algs),
+                TlsExtension::StatusRequest(Some(CertificateStatusRequest::Ocsp(ocsp))),
+                TlsExtension::Heartbeat(HeartbeatMode::PeerAllowedToSend),
             ],
         ));
 
@@ -54,13 +65,25 @@ static CLIENT_EXTENSIONS1: &[u8] = &[
     fn test_tls_extension_max_fragment_length() {
         let empty = &b""[..];
         let bytes = &[0x00, 0x01, 0x00, 0x01, 0x04];
-        let expected = Ok((empty, TlsExtension::MaxFragmentLength(4)));
+        let expected = Ok((
+            empty,
+            TlsExtension::MaxFragmentLength(MaxFragmentLength::Length4096),
+        ));
 
         let res = parse_tls_extension(bytes);
 
         assert_eq!(res, expected);
     }
 
+    #[test]
+    fn test_tls_extension_max_fragment_length_illegal() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x01, 0x00, 0x01, 0x05];
+        let expected = TlsExtension::MaxFragmentLength(MaxFragmentLength::Other(5));
+        assert_eq!(parse_tls_extension(bytes), Ok((empty, expected)));
+        assert!(parse_tls_extension_strict(bytes, None).is_err());
+    }
+
     #[test]
     fn test_tls_extension_alpn() {
         let empty = &b""[..];
@@ -151,7 +174,10 @@ static CLIENT_EXTENSIONS1: &[u8] = &[
         let expected = Ok((
             empty,
             vec![
-                TlsExtension::KeyShare(&bytes[4..40]),
+                TlsExtension::KeyShareServer(KeyShareEntry {
+                    group: NamedGroup::EcdhX25519,
+                    kx: &bytes[8..40],
+                }),
                 TlsExtension::SupportedVersions(vec![TlsVersion(0x7f17)]),
             ],
         ));
@@ -160,6 +186,114 @@ static CLIENT_EXTENSIONS1: &[u8] = &[
         assert_eq!(res, expected);
     }
 
+    #[test]
+    fn test_tls_extension_keyshare_clienthello() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x00, 0x33, 0x00, 0x2b, 0x00, 0x29, 0x00, 0x1d, 0x00, 0x20, 0xa2, 0x4e, 0x84, 0xfa,
+            0x82, 0x63, 0xf8, 0xff, 0x20, 0x7a, 0x79, 0x82, 0xfd, 0x34, 0x12, 0xfc, 0xae, 0x8d,
+            0xd8, 0xe3, 0x1e, 0xf4, 0x5d, 0xe6, 0x61, 0x09, 0x3b, 0x7f, 0xa5, 0x81, 0x12, 0x63,
+            0x00, 0x17, 0x00, 0x01, 0x04,
+        ];
+        let expected = TlsExtension::KeyShare(vec![
+            KeyShareEntry {
+                group: NamedGroup::EcdhX25519,
+                kx: &bytes[10..42],
+            },
+            KeyShareEntry {
+                group: NamedGroup::Secp256r1,
+                kx: &bytes[46..],
+            },
+        ]);
+
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_extension_keyshare_hrr_selected_group() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x33, 0x00, 0x02, 0x00, 0x17];
+        let expected = TlsExtension::KeyShareHelloRetryRequest(NamedGroup::Secp256r1);
+
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_extension_pre_shared_key() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x00, 0x29, 0x00, 0x31, 0x00, 0x0c, 0x00, 0x06, 0x74, 0x69, 0x63, 0x6b, 0x65, 0x74,
+            0x01, 0x02, 0x03, 0x04, 0x00, 0x21, 0x20, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06,
+            0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f, 0x10, 0x11, 0x12, 0x13, 0x14,
+            0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f,
+        ];
+        let expected = TlsExtension::PreSharedKey(PreSharedKeyOffer {
+            identities: vec![PskIdentity {
+                identity: b"ticket",
+                obfuscated_ticket_age: 0x0102_0304,
+            }],
+            binders: vec![&bytes[21..]],
+        });
+
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+
+        let res = parse_tls_extension_pre_shared_key(bytes);
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_tls_extension_pre_shared_key_selected() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x29, 0x00, 0x02, 0x00, 0x01];
+        let expected = TlsExtension::PreSharedKeySelected(1);
+
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_extensions_in_context() {
+        let empty = &b""[..];
+        // supported_versions (single version) and key_share (selected group)
+        let bytes = &[
+            0x00, 0x2b, 0x00, 0x02, 0x03, 0x04, 0x00, 0x33, 0x00, 0x02, 0x00, 0x17,
+        ];
+        let expected = vec![
+            TlsExtension::SupportedVersions(vec![TlsVersion::Tls13]),
+            TlsExtension::KeyShareHelloRetryRequest(NamedGroup::Secp256r1),
+        ];
+        let res = parse_tls_extensions_in(bytes, ExtensionContext::HelloRetryRequest);
+        assert_eq!(res, Ok((empty, expected)));
+
+        // an empty key_share list has the same length as a selected group
+        let bytes = &[0x00, 0x33, 0x00, 0x02, 0x00, 0x00];
+        let res = parse_tls_extension_in(bytes, ExtensionContext::ClientHello);
+        assert_eq!(res, Ok((empty, TlsExtension::KeyShare(Vec::new()))));
+
+        // in a ClientHello, 2 bytes cannot hold a list of versions
+        let bytes = &[0x00, 0x2b, 0x00, 0x02, 0x00, 0x00];
+        let res = parse_tls_extension_in(bytes, ExtensionContext::ClientHello);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_tls_extension_early_data_in_context() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x2a, 0x00, 0x04, 0x00, 0x00, 0x40, 0x00];
+        let res = parse_tls_extension_in(bytes, ExtensionContext::NewSessionTicket);
+        assert_eq!(res, Ok((empty, TlsExtension::EarlyData(Some(0x4000)))));
+
+        let res = parse_tls_extension_in(bytes, ExtensionContext::EncryptedExtensions);
+        assert!(res.is_err());
+
+        let bytes = &[0x00, 0x2a, 0x00, 0x00];
+        let res = parse_tls_extension_in(bytes, ExtensionContext::NewSessionTicket);
+        assert!(res.is_err());
+    }
+
     #[test]
     fn test_tls_extension_signed_certificate_timestamp() {
         let empty = &b""[..];
@@ -171,6 +305,52 @@ static CLIENT_EXTENSIONS1: &[u8] = &[
         assert_eq!(res, expected);
     }
 
+    #[test]
+    fn test_tls_extension_signed_certificate_timestamp_list() {
+        let empty = &b""[..];
+        let mut bytes = vec![0x00, 0x12, 0x00, 0x37, 0x00, 0x35, 0x00, 0x33, 0x00];
+        bytes.extend_from_slice(&[0x11; 32]);
+        bytes.extend_from_slice(&[
+            0x00, 0x00, 0x01, 0x7a, 0xbc, 0xde, 0xf0, 0x12, 0x00, 0x00, 0x04, 0x03, 0x00, 0x04,
+            0xde, 0xad, 0xbe, 0xef,
+        ]);
+        let sct = SignedCertificateTimestamp {
+            version: 0,
+            id: &[0x11; 32],
+            timestamp: 0x0000_017a_bcde_f012,
+            extensions: empty,
+            signature: DigitallySigned {
+                alg: Some(SignatureAndHashAlgorithm {
+                    hash: HashAlgorithm::Sha256,
+                    sign: SignAlgorithm::Ecdsa,
+                }),
+                data: &[0xde, 0xad, 0xbe, 0xef],
+            },
+        };
+        let expected = TlsExtension::SignedCertificateTimestamp(Some(vec![sct]));
+        let res = parse_tls_extension(&bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_extension_signed_certificate_timestamp_list_malformed() {
+        // a valid entry, followed by an entry too short to contain an SCT
+        let mut list = vec![0x00, 0x33, 0x00];
+        list.extend_from_slice(&[0x11; 32]);
+        list.extend_from_slice(&[
+            0x00, 0x00, 0x01, 0x7a, 0xbc, 0xde, 0xf0, 0x12, 0x00, 0x00, 0x04, 0x03, 0x00, 0x04,
+            0xde, 0xad, 0xbe, 0xef,
+        ]);
+        list.extend_from_slice(&[0x00, 0x02, 0x00, 0x11]);
+        let mut bytes = (list.len() as u16).to_be_bytes().to_vec();
+        bytes.extend_from_slice(&list);
+        assert!(parse_signed_certificate_timestamp_list(&bytes).is_err());
+        let mut ext = vec![0x00, 0x12];
+        ext.extend_from_slice(&(bytes.len() as u16).to_be_bytes());
+        ext.extend_from_slice(&bytes);
+        assert!(parse_tls_extension(&ext).is_err());
+    }
+
     #[test]
     fn test_tls_extension_grease() {
         let empty = &b""[..];
@@ -182,6 +362,577 @@ static CLIENT_EXTENSIONS1: &[u8] = &[
         assert_eq!(res, Ok((empty, expected)));
     }
 
+    #[test]
+    fn test_tls_extension_quic_transport_parameters() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x00, 0x39, 0x00, 0x18, 0x01, 0x04, 0x80, 0x00, 0x75, 0x30, 0x04, 0x04, 0x80, 0x10,
+            0x00, 0x00, 0x0f, 0x08, 0x83, 0x94, 0xc8, 0xf0, 0x3e, 0x51, 0x57, 0x08, 0x0c, 0x00,
+        ];
+        let expected = TlsExtension::QuicTransportParameters(vec![
+            QuicTransportParameter {
+                id: QuicTransportParameterId::MaxIdleTimeout,
+                value: &bytes[6..10],
+            },
+            QuicTransportParameter {
+                id: QuicTransportParameterId::InitialMaxData,
+                value: &bytes[12..16],
+            },
+            QuicTransportParameter {
+                id: QuicTransportParameterId::InitialSourceConnectionId,
+                value: &bytes[18..26],
+            },
+            QuicTransportParameter {
+                id: QuicTransportParameterId::DisableActiveMigration,
+                value: empty,
+            },
+        ]);
+
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+
+        if let Ok((_, TlsExtension::QuicTransportParameters(v))) = res {
+            assert_eq!(v[0].integer_value(), Some(30_000));
+            assert_eq!(v[1].integer_value(), Some(0x10_0000));
+        }
+    }
+
+    #[test]
+    fn test_quic_varint() {
+        let empty = &b""[..];
+        assert_eq!(parse_quic_varint(&[0x25]), Ok((empty, 37)));
+        assert_eq!(parse_quic_varint(&[0x7b, 0xbd]), Ok((empty, 15_293)));
+        assert_eq!(
+            parse_quic_varint(&[0x9d, 0x7f, 0x3e, 0x7d]),
+            Ok((empty, 494_878_333))
+        );
+        assert_eq!(
+            parse_quic_varint(&[0xc2, 0x19, 0x7c, 0x5e, 0xff, 0x14, 0xe8, 0x8c]),
+            Ok((empty, 151_288_809_941_952_652))
+        );
+    }
+
+    #[test]
+    fn test_tls_extension_ech_outer() {
+        let empty = &b""[..];
+        let bytes = &[
+            0xfe, 0x0d, 0x00, 0x11, 0x00, 0x00, 0x01, 0x00, 0x01, 0x2a, 0x00, 0x04, 0xaa, 0xbb,
+            0xcc, 0xdd, 0x00, 0x03, 0x01, 0x02, 0x03,
+        ];
+        let expected = TlsExtension::EncryptedClientHello(EchClientHello::Outer {
+            cipher_suite: HpkeSymmetricCipherSuite {
+                kdf_id: HpkeKdfId::HkdfSha256,
+                aead_id: HpkeAeadId::Aes128Gcm,
+            },
+            config_id: 0x2a,
+            enc: &bytes[12..16],
+            payload: &bytes[18..],
+        });
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected.clone())));
+        let res = parse_tls_extension_in(bytes, ExtensionContext::ClientHello);
+        assert_eq!(res, Ok((empty, expected)));
+
+        let bytes = &[0xfe, 0x0d, 0x00, 0x01, 0x01];
+        let expected = TlsExtension::EncryptedClientHello(EchClientHello::Inner);
+        let res = parse_tls_extension_in(bytes, ExtensionContext::ClientHello);
+        assert_eq!(res, Ok((empty, expected)));
+
+        let bytes = &[0xfd, 0x00, 0x00, 0x05, 0x04, 0x00, 0x0a, 0x00, 0x0d];
+        let expected = TlsExtension::EchOuterExtensions(vec![
+            TlsExtensionType::SupportedGroups,
+            TlsExtensionType::SignatureAlgorithms,
+        ]);
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_extension_ech_server() {
+        let empty = &b""[..];
+        let bytes = &[
+            0xfe, 0x0d, 0x00, 0x08, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+        ];
+        let expected = TlsExtension::EchConfirmation(&bytes[4..]);
+        let res = parse_tls_extension_in(bytes, ExtensionContext::HelloRetryRequest);
+        assert_eq!(res, Ok((empty, expected)));
+
+        let bytes = &[0xfe, 0x0d, 0x00, 0x06, 0x00, 0x04, 0xfe, 0x0d, 0x00, 0x00];
+        let expected = TlsExtension::EchRetryConfigs(&bytes[4..]);
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected.clone())));
+        let res = parse_tls_extension_in(bytes, ExtensionContext::EncryptedExtensions);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_extension_delegated_credential() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x22, 0x00, 0x06, 0x00, 0x04, 0x04, 0x03, 0x08, 0x07];
+        let expected = TlsExtension::DelegatedCredentials(vec![
+            SignatureScheme::ecdsa_secp256r1_sha256,
+            SignatureScheme::ed25519,
+        ]);
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+
+        let bytes = &[
+            0x00, 0x22, 0x00, 0x13, 0x00, 0x09, 0x3a, 0x80, 0x08, 0x07, 0x00, 0x00, 0x04, 0x30,
+            0x2a, 0x30, 0x05, 0x04, 0x03, 0x00, 0x02, 0x01, 0x02,
+        ];
+        let expected = TlsExtension::DelegatedCredential(DelegatedCredential {
+            valid_time: 604_800,
+            expected_cert_verify_algorithm: SignatureScheme::ed25519,
+            asn1_subject_public_key_info: &bytes[13..17],
+            algorithm: SignatureScheme::ecdsa_secp256r1_sha256,
+            signature: &bytes[21..],
+        });
+        let res = parse_tls_extension_in(bytes, ExtensionContext::Certificate);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_extension_use_srtp() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x00, 0x0e, 0x00, 0x09, 0x00, 0x04, 0x00, 0x07, 0x00, 0x01, 0x02, 0xab, 0xcd,
+        ];
+        let expected = TlsExtension::UseSrtp {
+            profiles: vec![
+                SrtpProtectionProfile::SRTP_AEAD_AES_128_GCM,
+                SrtpProtectionProfile::SRTP_AES128_CM_HMAC_SHA1_80,
+            ],
+            mki: &[0xab, 0xcd],
+        };
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_extension_token_binding() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x18, 0x00, 0x05, 0x01, 0x00, 0x02, 0x02, 0x00];
+        let expected = TlsExtension::TokenBinding {
+            major: 1,
+            minor: 0,
+            key_parameters: vec![
+                TokenBindingKeyParameters::EcdsaP256,
+                TokenBindingKeyParameters::Rsa2048Pkcs15,
+            ],
+        };
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_extension_application_settings() {
+        let empty = &b""[..];
+        let bytes = &[0x44, 0x69, 0x00, 0x05, 0x00, 0x03, 0x02, 0x68, 0x32];
+        let expected = TlsExtension::ApplicationSettings(vec![b"h2"]);
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+        // same bytes, sent in EncryptedExtensions
+        let expected = TlsExtension::ApplicationSettingsData(&bytes[4..]);
+        let res = parse_tls_extension_in(bytes, ExtensionContext::EncryptedExtensions);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_extension_channel_id() {
+        let empty = &b""[..];
+        let bytes = &[0x75, 0x50, 0x00, 0x00];
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, TlsExtension::ChannelId(None))));
+        let mut bytes = vec![0x75, 0x50, 0x00, 0x80];
+        for b in 1..=4u8 {
+            bytes.extend_from_slice(&[b; 32]);
+        }
+        let expected = TlsExtension::ChannelId(Some(ChannelId {
+            x: &[1; 32],
+            y: &[2; 32],
+            r: &[3; 32],
+            s: &[4; 32],
+        }));
+        let res = parse_tls_extension(&bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_extension_status_request_ocsp() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x00, 0x05, 0x00, 0x0c, 0x01, 0x00, 0x05, 0x00, 0x03, 0xa1, 0x01, 0x00, 0x00, 0x02,
+            0x30, 0x00,
+        ];
+        let expected =
+            TlsExtension::StatusRequest(Some(CertificateStatusRequest::Ocsp(OcspStatusRequest {
+                responder_id_list: vec![&[0xa1, 0x01, 0x00]],
+                request_extensions: &[0x30, 0x00],
+            })));
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+
+        let bytes = &[0x00, 0x05, 0x00, 0x03, 0x07, 0xab, 0xcd];
+        let expected = TlsExtension::StatusRequest(Some(CertificateStatusRequest::Unknown(
+            CertificateStatusType(7),
+            &[0xab, 0xcd],
+        )));
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_extension_status_request_v2() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x00, 0x11, 0x00, 0x10, 0x00, 0x0e, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0x00, 0x04, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let ocsp = OcspStatusRequest {
+            responder_id_list: vec![],
+            request_extensions: empty,
+        };
+        let expected = TlsExtension::StatusRequestV2(vec![
+            CertificateStatusRequest::OcspMulti(ocsp.clone()),
+            CertificateStatusRequest::Ocsp(ocsp),
+        ]);
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    // CN=test
+    #[rustfmt::skip]
+    static DN_CN_TEST: &[u8] = &[
+0x30, 0x0f, 0x31, 0x0d, 0x30, 0x0b, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0c, 0x04, 0x74, 0x65, 0x73,
+0x74,
+    ];
+
+    #[test]
+    fn test_tls_extension_certificate_authorities() {
+        let empty = &b""[..];
+        let mut bytes = vec![0x00, 0x2f, 0x00, 0x15, 0x00, 0x13, 0x00, 0x11];
+        bytes.extend_from_slice(DN_CN_TEST);
+        let expected = TlsExtension::CertificateAuthorities(vec![DN_CN_TEST]);
+        let res = parse_tls_extension(&bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[cfg(feature = "x509")]
+    #[test]
+    fn test_parse_distinguished_name() {
+        let name = parse_distinguished_name(DN_CN_TEST).expect("could not parse DN");
+        assert_eq!(name.to_string(), "CN=test");
+        assert!(parse_distinguished_name(&DN_CN_TEST[..16]).is_err());
+    }
+
+    #[test]
+    fn test_tls_extension_client_certificate_url() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x02, 0x00, 0x00];
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, TlsExtension::ClientCertificateUrl)));
+    }
+
+    #[test]
+    fn test_tls_extension_trusted_ca_keys() {
+        let empty = &b""[..];
+        let mut bytes = vec![0x00, 0x03, 0x00, 0x1b, 0x00, 0x19, 0x00, 0x03];
+        bytes.extend_from_slice(&[0x5a; 20]);
+        bytes.extend_from_slice(&[0x02, 0x00, 0x00]);
+        let expected = TlsExtension::TrustedCaKeys(vec![
+            TrustedAuthority {
+                identifier_type: TrustedAuthorityIdentifierType::PreAgreed,
+                identifier: empty,
+            },
+            TrustedAuthority {
+                identifier_type: TrustedAuthorityIdentifierType::CertSha1Hash,
+                identifier: &[0x5a; 20],
+            },
+            TrustedAuthority {
+                identifier_type: TrustedAuthorityIdentifierType::X509Name,
+                identifier: empty,
+            },
+        ]);
+        let res = parse_tls_extension(&bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_extension_certificate_type() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x13, 0x00, 0x03, 0x02, 0x02, 0x00];
+        let expected = TlsExtension::ClientCertificateType(vec![
+            CertificateType::RawPublicKey,
+            CertificateType::X509,
+        ]);
+        let res = parse_tls_extension_in(bytes, ExtensionContext::ClientHello);
+        assert_eq!(res, Ok((empty, expected)));
+
+        let bytes = &[0x00, 0x14, 0x00, 0x01, 0x02];
+        let expected = TlsExtension::ServerCertificateTypeSelected(CertificateType::RawPublicKey);
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+        let res = parse_tls_extension_in(bytes, ExtensionContext::ClientHello);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_tls_extension_cached_info() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x19, 0x00, 0x06, 0x00, 0x04, 0x01, 0x02, 0xab, 0xcd];
+        let expected = TlsExtension::CachedInfo(vec![CachedObject {
+            cached_type: CachedInformationType::Cert,
+            hash_value: Some(&[0xab, 0xcd]),
+        }]);
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+
+        let bytes = &[0x00, 0x19, 0x00, 0x04, 0x00, 0x02, 0x01, 0x02];
+        let expected = TlsExtension::CachedInfo(vec![
+            CachedObject {
+                cached_type: CachedInformationType::Cert,
+                hash_value: None,
+            },
+            CachedObject {
+                cached_type: CachedInformationType::CertReq,
+                hash_value: None,
+            },
+        ]);
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_extension_srp() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x0c, 0x00, 0x06, 0x05, 0x61, 0x6c, 0x69, 0x63, 0x65];
+        let expected = TlsExtension::Srp(b"alice");
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+
+        let ch =
+            TlsClientHelloContents::new(0x0303, 0, &[0; 28], None, vec![], vec![], Some(bytes));
+        assert_eq!(ch.get_srp_identity(), Some("alice"));
+    }
+
+    #[test]
+    fn test_tls_extension_user_mapping() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x06, 0x00, 0x02, 0x01, 0x40];
+        let expected = TlsExtension::UserMapping(vec![UserMappingType::UpnDomainHint]);
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_extension_heartbeat() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x0f, 0x00, 0x01, 0x02];
+        let expected = TlsExtension::Heartbeat(HeartbeatMode::PeerNotAllowedToSend);
+        assert_eq!(parse_tls_extension(bytes), Ok((empty, expected.clone())));
+        assert_eq!(parse_tls_extension_heartbeat(bytes), Ok((empty, expected)));
+
+        let bytes = &[0x00, 0x0f, 0x00, 0x01, 0x03];
+        let expected = TlsExtension::Heartbeat(HeartbeatMode(3));
+        assert_eq!(parse_tls_extension(bytes), Ok((empty, expected)));
+        assert!(parse_tls_extension_strict(bytes, None).is_err());
+        assert!(parse_tls_extensions_strict(bytes, None).is_err());
+    }
+
+    #[test]
+    fn test_tls_extension_ec_point_formats() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x0b, 0x00, 0x02, 0x01, 0x00];
+        let expected = TlsExtension::EcPointFormats(vec![ECPointFormat::Uncompressed]);
+        assert_eq!(parse_tls_extension(bytes), Ok((empty, expected.clone())));
+        assert_eq!(
+            parse_tls_extension_ec_point_formats(bytes),
+            Ok((empty, expected))
+        );
+    }
+
+    #[test]
+    fn test_tls_extension_psk_key_exchange_modes() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x2d, 0x00, 0x03, 0x02, 0x01, 0x00];
+        let expected = TlsExtension::PskExchangeModes(vec![
+            PskKeyExchangeMode::PskDhe,
+            PskKeyExchangeMode::Psk,
+        ]);
+        assert_eq!(parse_tls_extension(bytes), Ok((empty, expected.clone())));
+        assert_eq!(
+            parse_tls_extension_strict(bytes, None),
+            Ok((empty, expected))
+        );
+
+        let bytes = &[0x00, 0x2d, 0x00, 0x02, 0x01, 0x02];
+        let expected = TlsExtension::PskExchangeModes(vec![PskKeyExchangeMode(2)]);
+        assert_eq!(parse_tls_extension(bytes), Ok((empty, expected)));
+        assert!(parse_tls_extension_strict(bytes, None).is_err());
+    }
+
+    #[test]
+    fn test_tls_extension_signature_algorithms_cert() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x32, 0x00, 0x06, 0x00, 0x04, 0x08, 0x04, 0x04, 0x03];
+        let expected = TlsExtension::SignatureAlgorithmsCert(vec![
+            SignatureScheme::rsa_pss_rsae_sha256,
+            SignatureScheme::ecdsa_secp256r1_sha256,
+        ]);
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_extension_signature_algorithms_cert_post_quantum() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x32, 0x00, 0x06, 0x00, 0x04, 0x09, 0x05, 0x08, 0x07];
+        let expected = TlsExtension::SignatureAlgorithmsCert(vec![
+            SignatureScheme::mldsa65,
+            SignatureScheme::ed25519,
+        ]);
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+        assert!(SignatureScheme::mldsa65.is_post_quantum());
+        assert!(!SignatureScheme::ed25519.is_post_quantum());
+        assert_eq!(SignatureScheme(0x0905).to_string(), "mldsa65");
+    }
+
+    #[test]
+    fn test_tls_extension_npn_server() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x33, 0x74, 0x00, 0x0a, 0x06, 0x73, 0x70, 0x64, 0x79, 0x2f, 0x33, 0x02, 0x68, 0x32,
+        ];
+        let expected = TlsExtension::NextProtocolNegotiationServer(vec![b"spdy/3", b"h2"]);
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+        let res = parse_tls_extension_in(bytes, ExtensionContext::ClientHello);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_tls_extension_cookie() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x2c, 0x00, 0x05, 0x00, 0x03, 0x01, 0x02, 0x03];
+        let expected = TlsExtension::Cookie(&[0x01, 0x02, 0x03]);
+        assert_eq!(parse_tls_extension(bytes), Ok((empty, expected)));
+        // empty cookie
+        let bytes = &[0x00, 0x2c, 0x00, 0x02, 0x00, 0x00];
+        assert!(parse_tls_extension(bytes).is_err());
+        // cookie length does not match extension length
+        let bytes = &[0x00, 0x2c, 0x00, 0x05, 0x00, 0x02, 0x01, 0x02, 0x03];
+        assert!(parse_tls_extension(bytes).is_err());
+    }
+
+    #[test]
+    fn test_sni_host_names() {
+        let v = vec![
+            (SNIType::HostName, &b"www.example.com"[..]),
+            (SNIType(1), &b"\xff"[..]),
+            (SNIType::HostName, &b"xn--bcher-kva.example"[..]),
+        ];
+        assert_eq!(
+            sni_host_names(&v),
+            Ok(vec!["www.example.com", "xn--bcher-kva.example"])
+        );
+        assert_eq!(
+            sni_host_name(SNIType(1), b"example.com"),
+            Err(SniError::UnsupportedNameType(SNIType(1)))
+        );
+        assert_eq!(
+            sni_host_name(SNIType::HostName, b""),
+            Err(SniError::EmptyHostName)
+        );
+        assert_eq!(
+            sni_host_name(SNIType::HostName, "bücher.example".as_bytes()),
+            Err(SniError::NotAscii)
+        );
+    }
+
+    #[cfg(feature = "idna")]
+    #[test]
+    fn test_sni_host_name_unicode() {
+        assert_eq!(
+            sni_host_name_unicode(SNIType::HostName, b"xn--bcher-kva.example"),
+            Ok("bücher.example".to_string())
+        );
+        assert_eq!(
+            sni_host_name_unicode(SNIType::HostName, b"xn--a.example"),
+            Err(SniError::InvalidIdna)
+        );
+    }
+
+    #[test]
+    fn test_tls_extension_elliptic_curves_post_quantum() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x00, 0x0a, 0x00, 0x08, 0x00, 0x06, 0x11, 0xec, 0x63, 0x99, 0x00, 0x1d,
+        ];
+        let expected = TlsExtension::EllipticCurves(vec![
+            NamedGroup::X25519MLKEM768,
+            NamedGroup::X25519Kyber768Draft00,
+            NamedGroup::EcdhX25519,
+        ]);
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+        assert!(NamedGroup::X25519MLKEM768.is_post_quantum());
+        assert!(!NamedGroup::EcdhX25519.is_post_quantum());
+        assert_eq!(NamedGroup(0x11ec).to_string(), "X25519MLKEM768");
+    }
+
+    #[test]
+    fn test_remove_grease() {
+        assert!(is_grease_u16(0x2a2a));
+        assert!(!is_grease_u16(0x0a1a));
+        assert!(TlsExtensionType(0xfafa).is_grease());
+        assert!(TlsCipherSuiteID(0x3a3a).is_grease());
+        assert!(!TlsVersion::Tls13.is_grease());
+        let exts = vec![
+            TlsExtension::Grease(0x3a3a, &[]),
+            TlsExtension::EllipticCurves(vec![NamedGroup(0x4a4a), NamedGroup::EcdhX25519]),
+            TlsExtension::SupportedVersions(vec![TlsVersion(0x5a5a), TlsVersion::Tls13]),
+            TlsExtension::KeyShare(vec![
+                KeyShareEntry {
+                    group: NamedGroup(0x4a4a),
+                    kx: &[0x00],
+                },
+                KeyShareEntry {
+                    group: NamedGroup::EcdhX25519,
+                    kx: &[0x01],
+                },
+            ]),
+            TlsExtension::Grease(0x6a6a, &[0x00]),
+        ];
+        let expected = vec![
+            TlsExtension::EllipticCurves(vec![NamedGroup::EcdhX25519]),
+            TlsExtension::SupportedVersions(vec![TlsVersion::Tls13]),
+            TlsExtension::KeyShare(vec![KeyShareEntry {
+                group: NamedGroup::EcdhX25519,
+                kx: &[0x01],
+            }]),
+        ];
+        assert_eq!(remove_grease(&exts), expected);
+    }
+
+    #[test]
+    fn test_tls_extension_supported_ekt_ciphers() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x27, 0x00, 0x03, 0x02, 0x01, 0x02];
+        let expected = TlsExtension::SupportedEktCiphers(vec![
+            EktCipherType::AesKeyWrap128,
+            EktCipherType::AesKeyWrap256,
+        ]);
+        let res = parse_tls_extension_in(bytes, ExtensionContext::ClientHello);
+        assert_eq!(res, Ok((empty, expected)));
+
+        let bytes = &[0x00, 0x27, 0x00, 0x01, 0x01];
+        let expected = TlsExtension::SupportedEktCiphersSelected(EktCipherType::AesKeyWrap128);
+        let res = parse_tls_extension_in(bytes, ExtensionContext::ServerHello);
+        assert_eq!(res, Ok((empty, expected.clone())));
+        assert_eq!(parse_tls_extension(bytes), Ok((empty, expected)));
+    }
+
     const ESNI: &[u8] = include_bytes!("../assets/esni.bin");
 
     #[test]
@@ -202,8 +953,28 @@ static CLIENT_EXTENSIONS1: &[u8] = &[
     fn test_tls_extension_record_size_limit() {
         let empty = &b""[..];
         let bytes = &[0x00, 0x1c, 0x00, 0x02, 0x40, 0x01];
-        let expected = TlsExtension::RecordSizeLimit(16385);
+        let expected = TlsExtension::RecordSizeLimit(RecordSizeLimit(16385));
         let res = parse_tls_extension(bytes);
         assert_eq!(res, Ok((empty, expected)));
     }
+
+    #[test]
+    fn test_tls_extension_record_size_limit_bounds() {
+        assert!(RecordSizeLimit(16385).is_valid(true));
+        assert!(!RecordSizeLimit(16385).is_valid(false));
+        assert!(!RecordSizeLimit(63).is_valid(true));
+        let bytes = &[0x00, 0x1c, 0x00, 0x02, 0x00, 0x3f];
+        assert!(parse_tls_extension(bytes).is_ok());
+        assert!(parse_tls_extension_strict(bytes, None).is_err());
+        // a client may advertise a value larger than the protocol maximum
+        let bytes = &[0x00, 0x1c, 0x00, 0x02, 0x80, 0x00];
+        assert!(parse_tls_extension_strict(bytes, None).is_ok());
+        assert!(parse_tls_extension_strict(bytes, Some(ExtensionContext::ClientHello)).is_ok());
+        let ctx = Some(ExtensionContext::EncryptedExtensions);
+        assert!(parse_tls_extension_strict(bytes, ctx).is_err());
+        let bytes = &[0x00, 0x1c, 0x00, 0x02, 0x40, 0x01];
+        assert!(parse_tls_extension_strict(bytes, ctx).is_ok());
+        let ctx = Some(ExtensionContext::ServerHello);
+        assert!(parse_tls_extension_strict(bytes, ctx).is_err());
+    }
 } // mod tls_extensions
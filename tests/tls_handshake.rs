@@ -5,6 +5,7 @@ extern crate nom;
 extern crate tls_parser;
 
 mod tls_handshake {
+    use nom::error::{make_error, ErrorKind};
     use nom::{Err, Needed};
     use tls_parser::*;
 
@@ -435,6 +436,1116 @@ static SERVER_REPLY1: &[u8] = &[
         assert_eq!(parse_tls_plaintext(&bytes), Ok((empty, expected)));
     }
 
+    #[test]
+    fn test_tls_record_multiple_alerts() {
+        let empty = &b""[..];
+        let bytes = &[0x15, 0x03, 0x03, 0x00, 0x04, 0x01, 0x70, 0x02, 0x28];
+        let expected = TlsPlaintext {
+            hdr: TlsRecordHeader {
+                record_type: TlsRecordType::Alert,
+                version: TlsVersion::Tls12,
+                len: 4,
+            },
+            msg: vec![
+                TlsMessage::Alert(TlsMessageAlert {
+                    severity: TlsAlertSeverity::Warning,
+                    code: TlsAlertDescription::UnrecognizedName,
+                }),
+                TlsMessage::Alert(TlsMessageAlert {
+                    severity: TlsAlertSeverity::Fatal,
+                    code: TlsAlertDescription::HandshakeFailure,
+                }),
+            ],
+        };
+        assert_eq!(parse_tls_plaintext(bytes), Ok((empty, expected)));
+        // truncated alert
+        let bytes = &[0x15, 0x03, 0x03, 0x00, 0x03, 0x01, 0x70, 0x02];
+        assert!(parse_tls_plaintext(bytes).is_err());
+    }
+
+    #[test]
+    fn test_tls_record_heartbeat() {
+        let empty = &b""[..];
+        let mut bytes = vec![0x18, 0x03, 0x02, 0x00, 0x17, 0x01, 0x00, 0x04];
+        bytes.extend_from_slice(&[0x61; 4]);
+        bytes.extend_from_slice(&[0x00; 16]);
+        let expected = TlsPlaintext {
+            hdr: TlsRecordHeader {
+                record_type: TlsRecordType::Heartbeat,
+                version: TlsVersion::Tls11,
+                len: 0x17,
+            },
+            msg: vec![TlsMessage::Heartbeat(TlsMessageHeartbeat {
+                heartbeat_type: TlsHeartbeatMessageType::HeartBeatRequest,
+                payload_len: 4,
+                payload: &bytes[8..12],
+                padding: &bytes[12..],
+            })],
+        };
+        assert_eq!(parse_tls_plaintext(&bytes), Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls_record_heartbleed() {
+        let empty = &b""[..];
+        let bytes = &[0x18, 0x03, 0x02, 0x00, 0x03, 0x01, 0x40, 0x00];
+        let res = parse_tls_plaintext(bytes);
+        let (rem, record) = res.expect("heartbeat record");
+        assert_eq!(rem, empty);
+        match &record.msg[..] {
+            [TlsMessage::Heartbeat(hb)] => {
+                assert_eq!(hb.payload_len, 0x4000);
+                assert_eq!(hb.payload, empty);
+                assert!(hb.is_payload_length_mismatch());
+                assert!(hb.is_heartbleed_probe());
+            }
+            _ => panic!("unexpected messages {:?}", record.msg),
+        }
+    }
+
+    #[test]
+    fn test_tls_record_iterator() {
+        let empty = &b""[..];
+        let mut iter = TlsRecordIterator::new(SERVER_REPLY1);
+        let records: Vec<_> = iter.by_ref().collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(iter.remaining(), empty);
+        let (hdr, data, range) = &records[1];
+        assert_eq!(hdr.record_type, TlsRecordType::Handshake);
+        assert_eq!(hdr.len, 3081);
+        assert_eq!(*range, 64..3150);
+        assert_eq!(*data, &SERVER_REPLY1[69..3150]);
+        // incomplete record
+        let mut iter = TlsRecordIterator::new(&SERVER_REPLY1[..100]);
+        assert_eq!(iter.next().map(|r| r.2), Some(0..64));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.offset(), 64);
+        assert_eq!(iter.remaining(), &SERVER_REPLY1[64..100]);
+    }
+
+    #[test]
+    fn test_tls_client_hello_truncated() {
+        let empty = &b""[..];
+        let ch = &include_bytes!("../assets/client_hello_dhe.bin")[5..];
+        // complete message
+        let (rem, res) = parse_tls_client_hello_truncated(ch).expect("parsing failed");
+        assert_eq!(rem, empty);
+        assert!(!res.is_truncated());
+        assert_eq!(res.hello.ciphers.len(), 28);
+        assert_eq!(res.extensions.len(), 4);
+        // truncated in the extensions
+        let (rem, res) = parse_tls_client_hello_truncated(&ch[..120]).expect("parsing failed");
+        assert_eq!(rem, empty);
+        assert!(res.is_truncated());
+        assert_eq!(res.missing_bytes, 29);
+        assert_eq!(res.hello.ciphers.len(), 28);
+        assert_eq!(res.hello.comp, vec![TlsCompressionID::Null]);
+        assert_eq!(res.hello.ext, Some(&ch[101..120]));
+        assert_eq!(res.extensions, vec![TlsExtension::SessionTicket(empty)]);
+        // truncated in the cipher suites
+        let (_, res) = parse_tls_client_hello_truncated(&ch[..60]).expect("parsing failed");
+        assert_eq!(res.hello.ciphers.len(), 9);
+        assert_eq!(res.hello.comp, vec![]);
+        assert_eq!(res.hello.ext, None);
+        // truncated before the end of the random
+        let res = parse_tls_client_hello_truncated(&ch[..20]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_tls_stream_defragmenter() {
+        let mut defrag = TlsStreamDefragmenter::new();
+        let records = defrag.push(&SERVER_REPLY1[..3]).expect("push failed");
+        assert!(records.is_empty());
+        assert!(defrag.has_pending_data());
+        let records = defrag.push(&SERVER_REPLY1[3..1000]).expect("push failed");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].hdr.record_type, TlsRecordType::Handshake);
+        assert_eq!(records[0].data, &SERVER_REPLY1[5..64]);
+        let records = defrag.push(&SERVER_REPLY1[1000..]).expect("push failed");
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].data, &SERVER_REPLY1[69..3150]);
+        assert!(!defrag.has_pending_data());
+        // invalid record length
+        let mut defrag = TlsStreamDefragmenter::new();
+        let res = defrag.push(&[0x17, 0x03, 0x03, 0xff, 0xff, 0x00]);
+        assert_eq!(res, Err(TlsStreamError::RecordTooLarge));
+        assert!(!defrag.has_pending_data());
+        // buffer limit
+        let mut defrag = TlsStreamDefragmenter::with_max_size(1024);
+        assert_eq!(defrag.push(&SERVER_REPLY1[64..1000]), Ok(vec![]));
+        assert_eq!(
+            defrag.push(&SERVER_REPLY1[1000..1100]),
+            Err(TlsStreamError::BufferFull)
+        );
+        // the limit only applies to the incomplete data, not to the pushed chunk
+        let mut stream = Vec::new();
+        for _ in 0..201 {
+            stream.extend_from_slice(&[0x17, 0x03, 0x03, 0x03, 0xe3]);
+            stream.extend_from_slice(&[0; 995]);
+        }
+        stream.extend_from_slice(&[0x17, 0x03]);
+        let mut defrag = TlsStreamDefragmenter::with_max_size(1024);
+        let records = defrag.push(&stream).expect("push failed");
+        assert_eq!(records.len(), 201);
+        assert!(defrag.has_pending_data());
+        let records = defrag.push(&[0x03, 0x00, 0x01, 0x00]).expect("push failed");
+        assert_eq!(records.len(), 1);
+    }
+
+    #[cfg(feature = "tcp")]
+    #[test]
+    fn test_tcp_stream_reassembly() {
+        let isn = 0xffff_fff0_u32;
+        let seq = |offset: usize| isn.wrapping_add(offset as u32);
+        let mut reassembler = TcpStreamReassembler::new(isn);
+        // out of order
+        let records = reassembler
+            .push(seq(1000), &SERVER_REPLY1[1000..2000])
+            .expect("push failed");
+        assert!(records.is_empty());
+        assert!(reassembler.has_pending_segments());
+        let records = reassembler
+            .push(seq(0), &SERVER_REPLY1[..100])
+            .expect("push failed");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].data, &SERVER_REPLY1[5..64]);
+        // overlapping retransmission, filling the gap
+        let records = reassembler
+            .push(seq(50), &SERVER_REPLY1[50..1500])
+            .expect("push failed");
+        assert!(records.is_empty());
+        assert!(!reassembler.has_pending_segments());
+        assert_eq!(reassembler.next_seq(), seq(2000));
+        let records = reassembler
+            .push(seq(2000), &SERVER_REPLY1[2000..])
+            .expect("push failed");
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].data, &SERVER_REPLY1[69..3150]);
+        // old retransmission
+        let records = reassembler
+            .push(seq(0), &SERVER_REPLY1[..100])
+            .expect("push failed");
+        assert!(records.is_empty());
+    }
+
+    #[cfg(feature = "tcp")]
+    #[test]
+    fn test_tcp_stream_reassembly_limit() {
+        let record = |len: usize| {
+            let mut v = vec![0x17, 0x03, 0x03];
+            v.extend_from_slice(&(len as u16 - 5).to_be_bytes());
+            v.resize(len, 0);
+            v
+        };
+        let mut reassembler = TcpStreamReassembler::new(0);
+        // the maximum out-of-order data, after a missing record
+        let mut offset = 1000;
+        while offset < 1000 + DEFAULT_MAX_STREAM_BUFFER {
+            let len = (1000 + DEFAULT_MAX_STREAM_BUFFER - offset).min(1000);
+            let records = reassembler
+                .push(offset as u32, &record(len))
+                .expect("push failed");
+            assert!(records.is_empty());
+            offset += len;
+        }
+        assert_eq!(
+            reassembler.push(offset as u32, &record(1000)),
+            Err(TlsStreamError::BufferFull)
+        );
+        // filling the gap returns all the records
+        let records = reassembler.push(0, &record(1000)).expect("push failed");
+        assert_eq!(records.len(), 133);
+        assert!(!reassembler.has_pending_segments());
+        assert_eq!(reassembler.next_seq(), offset as u32);
+    }
+
+    #[test]
+    fn test_tls_record_classifier() {
+        let mut classifier = TlsRecordClassifier::new();
+        for (hdr, data, _) in TlsRecordIterator::new(SERVER_REPLY1) {
+            let class = classifier.classify(&hdr, data, false);
+            assert_eq!(class, TlsRecordClass::PlaintextHandshake);
+        }
+        let mut records = TlsRecordIterator::new(&CLIENT_REPLY1[..81]);
+        let expected = [
+            TlsRecordClass::PlaintextHandshake,
+            TlsRecordClass::ChangeCipherSpec,
+        ];
+        for (class, (hdr, data, _)) in expected.iter().zip(&mut records) {
+            assert_eq!(classifier.classify(&hdr, data, true), *class);
+        }
+        let hdr = |record_type, len| TlsRecordHeader {
+            record_type,
+            version: TlsVersion::Tls12,
+            len,
+        };
+        let finished = hdr(TlsRecordType::Handshake, 40);
+        assert_eq!(
+            classifier.classify(&finished, &[], true),
+            TlsRecordClass::EncryptedHandshake
+        );
+        let data = hdr(TlsRecordType::ApplicationData, 100);
+        assert_eq!(
+            classifier.classify(&data, &[], true),
+            TlsRecordClass::ApplicationData
+        );
+        let alert = hdr(TlsRecordType::Alert, 26);
+        assert_eq!(
+            classifier.classify(&alert, &[], false),
+            TlsRecordClass::Alert
+        );
+        assert!(!classifier.is_tls13());
+    }
+
+    #[test]
+    fn test_tls_client_hello_scsv() {
+        let ciphers = vec![
+            TlsCipherSuiteID(0xc02f),
+            TlsCipherSuiteID(0x00ff),
+            TlsCipherSuiteID(0x5600),
+        ];
+        let random = [0; 28];
+        let ch = TlsClientHelloContents::new(0x0302, 0, &random, None, ciphers, vec![], None);
+        assert!(ch.has_fallback_scsv());
+        assert!(ch.has_renegotiation_info_scsv());
+        assert!(!ch.ciphers[0].is_scsv());
+        assert!(ch.ciphers[1].is_scsv());
+        let ch = TlsClientHelloContents::new(0x0303, 0, &random, None, vec![], vec![], None);
+        assert!(!ch.has_fallback_scsv());
+        assert!(!ch.has_renegotiation_info_scsv());
+    }
+
+    #[test]
+    fn test_tls_compression_methods() {
+        let random = [0; 28];
+        let comp = vec![TlsCompressionID::Deflate, TlsCompressionID::Null];
+        let ch = TlsClientHelloContents::new(0x0301, 0, &random, None, vec![], comp, None);
+        assert!(ch.has_compression());
+        let comp = vec![TlsCompressionID::Null];
+        let ch = TlsClientHelloContents::new(0x0301, 0, &random, None, vec![], comp, None);
+        assert!(!ch.has_compression());
+        let sh = TlsServerHelloContents::new(0x0301, 0, &random, None, 0x002f, 0x40, None);
+        assert_eq!(sh.compression, TlsCompressionID::LZS);
+        assert!(sh.compression.is_crime_risk());
+    }
+
+    #[test]
+    fn test_tls_session_id_echo() {
+        let random = [0; 28];
+        let sid = [0x11; 32];
+        let other_sid = [0x22; 32];
+        let supported_versions = &[0x00, 0x2b, 0x00, 0x02, 0x03, 0x04];
+        let ch = |sid| TlsClientHelloContents::new(0x0303, 0, &random, sid, vec![], vec![], None);
+        let sh = |sid, ext| TlsServerHelloContents::new(0x0303, 0, &random, sid, 0x1301, 0, ext);
+        let with_sid = ch(Some(&sid[..]));
+        let no_sid = ch(None);
+        // TLS 1.2
+        let res = sh(Some(&sid[..]), None).session_id_echo(&with_sid);
+        assert_eq!(res, TlsSessionIdEcho::Resumption);
+        let res = sh(Some(&other_sid[..]), None).session_id_echo(&with_sid);
+        assert_eq!(res, TlsSessionIdEcho::NewSession);
+        let res = sh(None, None).session_id_echo(&no_sid);
+        assert_eq!(res, TlsSessionIdEcho::NewSession);
+        // TLS 1.3
+        let tls13_sh = sh(Some(&sid[..]), Some(&supported_versions[..]));
+        assert_eq!(tls13_sh.get_supported_version(), Some(TlsVersion::Tls13));
+        let res = tls13_sh.session_id_echo(&with_sid);
+        assert_eq!(res, TlsSessionIdEcho::Tls13CompatibilityMode);
+        let res = tls13_sh.session_id_echo(&no_sid);
+        assert_eq!(res, TlsSessionIdEcho::Tls13Mismatch);
+        let res = sh(None, Some(&supported_versions[..])).session_id_echo(&no_sid);
+        assert_eq!(res, TlsSessionIdEcho::Tls13);
+    }
+
+    #[test]
+    fn test_tls_record_limits() {
+        // plaintext: 2^14 + 1
+        let bytes = &[0x16, 0x03, 0x03, 0x40, 0x01];
+        assert_eq!(
+            parse_tls_plaintext(bytes),
+            Err(Err::Error(make_error(&bytes[5..], ErrorKind::TooLarge)))
+        );
+        // ciphertext: 2^14 + 256 is accepted, 2^14 + 257 is not
+        let bytes = &[0x17, 0x03, 0x03, 0x41, 0x00];
+        assert_eq!(
+            parse_tls_encrypted(bytes),
+            Err(Err::Incomplete(Needed::new(0x4100)))
+        );
+        let bytes = &[0x17, 0x03, 0x03, 0x41, 0x01];
+        assert_eq!(
+            parse_tls_raw_record(bytes),
+            Err(Err::Error(make_error(&bytes[5..], ErrorKind::TooLarge)))
+        );
+        // configurable limits
+        let limits = TlsRecordLimits {
+            max_plaintext_len: 512,
+            max_ciphertext_len: 0xffff,
+        };
+        assert_eq!(
+            parse_tls_raw_record_with_limits(bytes, &limits),
+            Err(Err::Incomplete(Needed::new(0x4101)))
+        );
+        assert_eq!(
+            parse_tls_plaintext_with_limits(&SERVER_REPLY1[..64], &limits).map(|(rem, _)| rem),
+            Ok(&[][..])
+        );
+        assert_eq!(
+            parse_tls_plaintext_with_limits(&SERVER_REPLY1[64..], &limits).map(|(rem, _)| rem),
+            Err(Err::Error(make_error(
+                &SERVER_REPLY1[69..],
+                ErrorKind::TooLarge
+            )))
+        );
+        let mut defrag = TlsStreamDefragmenter::new();
+        defrag.set_record_limits(limits);
+        let res = defrag.push(&SERVER_REPLY1[..69]).expect("push failed");
+        assert_eq!(res.len(), 1);
+        assert!(defrag.has_pending_data());
+    }
+
+    #[cfg(feature = "fingerprint")]
+    #[test]
+    fn test_tls_ja3_fingerprint() {
+        let bytes = include_bytes!("../assets/client_hello_dhe.bin");
+        let (_, record) = parse_tls_plaintext(bytes).expect("parsing failed");
+        let ch = match &record.msg[0] {
+            TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)) => ch,
+            _ => panic!("Unexpected message"),
+        };
+        let ja3 = ja3_fingerprint(ch);
+        assert_eq!(
+            ja3.string,
+            "771,163-159-52394-49315-49311-162-158-49314-49310-107-106-196-195-103-64-190-189-\
+             57-56-136-135-51-50-154-153-69-68-255,35-13-22-23,,"
+        );
+        assert_eq!(ja3.hash, "7fa9a63f9bc008d8b70bddcc95d3aa42");
+        // GREASE cipher, extension and group
+        let mut bytes = vec![0x01, 0x00, 0x00, 0x4f, 0x03, 0x03];
+        bytes.extend_from_slice(&[0x01; 32]);
+        bytes.extend_from_slice(&[
+            0x00, 0x00, 0x06, 0x0a, 0x0a, 0x13, 0x01, 0xc0, 0x2f, 0x01, 0x00, 0x00, 0x20, 0x1a,
+            0x1a, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x06, 0x00, 0x04, 0x2a, 0x2a, 0x00, 0x1d, 0x00,
+            0x0b, 0x00, 0x02, 0x01, 0x00, 0x00, 0x2b, 0x00, 0x03, 0x02, 0x03, 0x04, 0xff, 0x01,
+            0x00, 0x01, 0x00,
+        ]);
+        let (_, msg) = parse_tls_message_handshake(&bytes).expect("parsing failed");
+        let ch = match &msg {
+            TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)) => ch,
+            _ => panic!("Unexpected message"),
+        };
+        let ja3 = ja3_fingerprint(ch);
+        assert_eq!(ja3.string, "771,4865-49199,10-11-43-65281,29,0");
+        assert_eq!(ja3.hash, "22aa39bb9c2dbd019e13620231792b99");
+    }
+
+    #[cfg(feature = "fingerprint")]
+    #[test]
+    fn test_tls_extension_order() {
+        // ClientHello with the extensions in the specified order
+        let client_hello = |exts: &[&[u8]]| {
+            let ext: Vec<u8> = exts.concat();
+            let len = 43 + ext.len();
+            let mut bytes = vec![0x01, 0x00, 0x00, len as u8, 0x03, 0x03];
+            bytes.extend_from_slice(&[0x01; 32]);
+            bytes.extend_from_slice(&[0x00, 0x00, 0x02, 0x13, 0x01, 0x01, 0x00, 0x00]);
+            bytes.push(ext.len() as u8);
+            bytes.extend_from_slice(&ext);
+            bytes
+        };
+        let grease1: &[u8] = &[0x1a, 0x1a, 0x00, 0x00];
+        let grease2: &[u8] = &[0x3a, 0x3a, 0x00, 0x00];
+        let groups: &[u8] = &[0x00, 0x0a, 0x00, 0x04, 0x00, 0x02, 0x00, 0x1d];
+        let versions: &[u8] = &[0x00, 0x2b, 0x00, 0x03, 0x02, 0x03, 0x04];
+        let reneg: &[u8] = &[0xff, 0x01, 0x00, 0x01, 0x00];
+        let hello1 = client_hello(&[grease1, versions, reneg, groups]);
+        let hello2 = client_hello(&[grease2, groups, versions, reneg]);
+        let hello3 = client_hello(&[grease1, groups, versions]);
+        let parse = |bytes| match parse_tls_message_handshake(bytes) {
+            Ok((_, TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)))) => ch,
+            _ => panic!("Unexpected message"),
+        };
+        let (ch1, ch2, ch3) = (parse(&hello1), parse(&hello2), parse(&hello3));
+        let order = extension_order(&ch1);
+        assert_eq!(
+            order.observed,
+            vec![
+                TlsExtensionType(0x1a1a),
+                TlsExtensionType::SupportedVersions,
+                TlsExtensionType::RenegotiationInfo,
+                TlsExtensionType::SupportedGroups,
+            ]
+        );
+        assert_eq!(
+            order.canonical,
+            vec![
+                TlsExtensionType::SupportedGroups,
+                TlsExtensionType::SupportedVersions,
+                TlsExtensionType::RenegotiationInfo,
+            ]
+        );
+        assert_ne!(ja3_string(&ch1), ja3_string(&ch2));
+        assert_eq!(ja3n_string(&ch1), "771,4865,10-43-65281,29,");
+        assert_eq!(ja3n_fingerprint(&ch1), ja3n_fingerprint(&ch2));
+        let mut detector = ExtensionPermutationDetector::new();
+        assert!(!detector.observe(&ch1));
+        assert!(!detector.observe(&ch1));
+        assert!(!detector.observe(&ch3));
+        assert!(detector.observe(&ch2));
+    }
+
+    #[test]
+    fn test_tls_compliance_profiles() {
+        let session = TlsSessionParameters {
+            version: Some(TlsVersion::Tls12),
+            cipher: Some(TlsCipherSuiteID(0xc02f)),
+            group: Some(NamedGroup::EcdhX25519),
+            signature_scheme: Some(SignatureScheme::rsa_pss_rsae_sha256),
+        };
+        assert!(TlsComplianceProfile::mozilla_intermediate()
+            .check(&session)
+            .is_compliant());
+        assert!(TlsComplianceProfile::mozilla_old()
+            .check(&session)
+            .is_compliant());
+        let modern = TlsComplianceProfile::mozilla_modern();
+        let res = modern.check(&session);
+        assert_eq!(res.profile, "Mozilla modern");
+        assert_eq!(
+            res.violations,
+            vec![
+                TlsComplianceViolation::Version(TlsVersion::Tls12),
+                TlsComplianceViolation::Cipher(TlsCipherSuiteID(0xc02f)),
+            ]
+        );
+        assert_eq!(
+            res.violations[1].to_string(),
+            "cipher suite TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256 not allowed"
+        );
+        let nist = TlsComplianceProfile::nist_sp800_52r2();
+        let res = nist.check(&session);
+        assert_eq!(
+            res.violations,
+            vec![TlsComplianceViolation::Group(NamedGroup::EcdhX25519)]
+        );
+        // custom profile, without restriction on groups and signature schemes
+        let profile = TlsComplianceProfile {
+            name: "custom".to_string(),
+            versions: vec![TlsVersion::Tls12],
+            ciphers: vec![TlsCipherSuiteID(0xc030)],
+            groups: Vec::new(),
+            signature_schemes: Vec::new(),
+        };
+        let res = profile.check(&session);
+        assert!(!res.is_compliant());
+        assert_eq!(
+            res.violations,
+            vec![TlsComplianceViolation::Cipher(TlsCipherSuiteID(0xc02f))]
+        );
+        // unknown parameters are not checked
+        let res = profile.check(&TlsSessionParameters::default());
+        assert!(res.is_compliant());
+    }
+
+    #[test]
+    fn test_tls_session() {
+        let mut session = TlsSession::new();
+        let flights: &[(&[u8], bool)] = &[
+            (CH, true),
+            (SERVER_REPLY1, false),
+            (CLIENT_REPLY1, true),
+            // warning alert (no_renegotiation), then ChangeCipherSpec
+            (&[0x15, 0x03, 0x03, 0x00, 0x02, 0x01, 0x64], false),
+            (&[0x14, 0x03, 0x03, 0x00, 0x01, 0x01], false),
+            // encrypted alert
+            (&[0x15, 0x03, 0x03, 0x00, 0x02, 0xaa, 0xbb], true),
+        ];
+        for &(mut data, to_server) in flights {
+            while !data.is_empty() {
+                let (rem, record) = parse_tls_raw_record(data).expect("could not parse record");
+                session.update(&record, to_server).expect("update failed");
+                data = rem;
+            }
+        }
+        let summary = session.summary();
+        assert_eq!(summary.versions_offered, vec![TlsVersion::Tls12]);
+        assert_eq!(summary.version, Some(TlsVersion::Tls12));
+        assert_eq!(summary.ciphers_offered.len(), 85);
+        assert_eq!(summary.cipher, Some(TlsCipherSuiteID(0xc02f)));
+        assert_eq!(summary.group, Some(NamedGroup::Secp256r1));
+        assert!(!summary.resumed);
+        assert_eq!(summary.server_certificates.len(), 3);
+        assert_eq!(
+            &summary.server_certificates[0][..4],
+            &[0x30, 0x82, 0x04, 0x80]
+        );
+        assert!(summary.client_certificates.is_empty());
+        assert_eq!(
+            summary.alerts,
+            vec![(
+                false,
+                TlsMessageAlert {
+                    severity: TlsAlertSeverity::Warning,
+                    code: TlsAlertDescription::NoRenegotiation,
+                }
+            )]
+        );
+        let kx = session.key_exchange();
+        assert_eq!(kx.client_random.as_deref(), Some(&CH[11..43]));
+        assert_eq!(kx.server_random.as_deref(), Some(&SERVER_REPLY1[11..43]));
+        assert_eq!(kx.group, Some(NamedGroup::Secp256r1));
+        assert!(kx.client_shares.is_empty());
+        // uncompressed points
+        let server_public = kx.server_public.as_ref().expect("no server public value");
+        assert_eq!((server_public.len(), server_public[0]), (65, 0x04));
+        let client_public = kx.client_public.as_ref().expect("no client public value");
+        assert_eq!((client_public.len(), client_public[0]), (65, 0x04));
+        // abbreviated handshake: ChangeCipherSpec right after the ServerHello
+        let mut session = TlsSession::new();
+        for data in &[&SERVER_REPLY1[..64], &[0x14, 0x03, 0x03, 0x00, 0x01, 0x01]] {
+            let (_, record) = parse_tls_raw_record(data).expect("could not parse record");
+            session.update(&record, false).expect("update failed");
+        }
+        assert!(session.summary().resumed);
+    }
+
+    #[test]
+    fn test_tls_state_machine() {
+        let messages = |mut data: &'static [u8]| {
+            let mut messages = Vec::new();
+            while let Ok((rem, record)) = parse_tls_plaintext(data) {
+                messages.extend(record.msg);
+                data = rem;
+            }
+            messages
+        };
+        let ccs = TlsMessage::ChangeCipherSpec;
+        let mut sm = TlsStateMachine::new();
+        let mut events = Vec::new();
+        let client_hello = messages(CH);
+        for msg in &client_hello {
+            events.extend(sm.update(msg, true));
+        }
+        for msg in &messages(SERVER_REPLY1) {
+            events.extend(sm.update(msg, false));
+        }
+        assert_eq!(sm.state(), TlsState::ServerHelloDone);
+        // client key exchange and ChangeCipherSpec (the Finished message is encrypted)
+        for msg in &messages(&CLIENT_REPLY1[..81]) {
+            events.extend(sm.update(msg, true));
+        }
+        events.extend(sm.update(&ccs, false));
+        assert_eq!(sm.state(), TlsState::SessionEncrypted);
+        assert_eq!(
+            events,
+            vec![
+                TlsStateEvent::HandshakeStarted,
+                TlsStateEvent::ParametersNegotiated {
+                    version: TlsVersion::Tls12,
+                    cipher: TlsCipherSuiteID(0xc02f),
+                },
+                TlsStateEvent::HandshakeFinished,
+            ]
+        );
+        // renegotiation (decrypted ClientHello), then fatal alert
+        assert_eq!(
+            sm.update(&client_hello[0], true),
+            vec![TlsStateEvent::Renegotiation]
+        );
+        assert_eq!(sm.state(), TlsState::ClientHello);
+        let alert = TlsMessageAlert {
+            severity: TlsAlertSeverity::Fatal,
+            code: TlsAlertDescription::HandshakeFailure,
+        };
+        let events = sm.update(&TlsMessage::Alert(alert.clone()), false);
+        assert_eq!(events, vec![TlsStateEvent::Alert(alert)]);
+        assert_eq!(sm.state(), TlsState::Finished);
+        // protocol violation, only reported once
+        let mut sm = TlsStateMachine::new();
+        assert_eq!(
+            sm.update(&ccs, false),
+            vec![TlsStateEvent::ProtocolViolation {
+                state: TlsState::None,
+                to_server: false
+            }]
+        );
+        assert_eq!(sm.state(), TlsState::Invalid);
+        assert!(sm.update(&client_hello[0], true).is_empty());
+    }
+
+    #[test]
+    fn test_tls_parse_with_diagnostics() {
+        let groups: &[u8] = &[0x00, 0x0a, 0x00, 0x04, 0x00, 0x02, 0x00, 0x1d];
+        // ALPN with a trailing byte, heartbeat with an unknown mode, incomplete extension
+        let alpn: &[u8] = &[0x00, 0x10, 0x00, 0x05, 0x00, 0x02, 0x01, 0x68, 0xff];
+        let heartbeat: &[u8] = &[0x00, 0x0f, 0x00, 0x01, 0x05];
+        let ext = [groups, groups, alpn, heartbeat, &[0x00, 0xff]].concat();
+        let mut hello = vec![0x03, 0x03];
+        hello.extend_from_slice(&[0x01; 32]);
+        hello.extend_from_slice(&[0x00, 0x00, 0x02, 0x13, 0x01, 0x02, 0x00, 0x01]);
+        hello.extend_from_slice(&[0x00, ext.len() as u8]);
+        hello.extend_from_slice(&ext);
+        hello.push(0x00);
+        let mut record = vec![0x16, 0x03, 0x01, 0x00, (hello.len() + 6) as u8];
+        record.extend_from_slice(&[0x01, 0x00, 0x00, hello.len() as u8]);
+        record.extend_from_slice(&hello);
+        record.extend_from_slice(&[0x16, 0x03]);
+        let (rem, (plaintext, diagnostics)) =
+            parse_tls_plaintext_with_diagnostics(&record).expect("could not parse record");
+        assert!(rem.is_empty());
+        assert_eq!(Ok((rem, plaintext)), parse_tls_plaintext(&record));
+        assert_eq!(
+            diagnostics,
+            vec![
+                TlsDiagnostic::HandshakeTrailingData(TlsHandshakeType::ClientHello, 1),
+                TlsDiagnostic::DeprecatedCompression,
+                TlsDiagnostic::ExtensionsTrailingData(TlsHandshakeType::ClientHello, 2),
+                TlsDiagnostic::DuplicateExtension(TlsExtensionType::SupportedGroups),
+                TlsDiagnostic::ExtensionTrailingData(
+                    TlsExtensionType::ApplicationLayerProtocolNegotiation,
+                    1
+                ),
+                TlsDiagnostic::IllegalExtensionValue(TlsExtensionType::Heartbeat),
+                TlsDiagnostic::RecordTrailingData(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tls_client_hello_diff() {
+        let client_hello = |ciphers: &[u8], exts: &[&[u8]]| {
+            let ext: Vec<u8> = exts.concat();
+            let len = 41 + ciphers.len() + ext.len();
+            let mut bytes = vec![0x01, 0x00, 0x00, len as u8, 0x03, 0x03];
+            bytes.extend_from_slice(&[0x01; 32]);
+            bytes.extend_from_slice(&[0x00, 0x00, ciphers.len() as u8]);
+            bytes.extend_from_slice(ciphers);
+            bytes.extend_from_slice(&[0x01, 0x00, 0x00, ext.len() as u8]);
+            bytes.extend_from_slice(&ext);
+            bytes
+        };
+        let groups1: &[u8] = &[0x00, 0x0a, 0x00, 0x04, 0x00, 0x02, 0x00, 0x1d];
+        let groups2: &[u8] = &[0x00, 0x0a, 0x00, 0x04, 0x00, 0x02, 0x00, 0x17];
+        let versions: &[u8] = &[0x00, 0x2b, 0x00, 0x03, 0x02, 0x03, 0x04];
+        let reneg: &[u8] = &[0xff, 0x01, 0x00, 0x01, 0x00];
+        let etm: &[u8] = &[0x00, 0x16, 0x00, 0x00];
+        let hello1 = client_hello(
+            &[0x13, 0x01, 0x13, 0x02, 0xc0, 0x2f],
+            &[groups1, versions, reneg],
+        );
+        let hello2 = client_hello(
+            &[0x13, 0x02, 0x13, 0x01, 0x13, 0x03],
+            &[groups2, etm, versions],
+        );
+        let parse = |bytes| match parse_tls_message_handshake(bytes) {
+            Ok((_, TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)))) => ch,
+            _ => panic!("Unexpected message"),
+        };
+        let (ch1, ch2) = (parse(&hello1), parse(&hello2));
+        assert!(ch1.diff(&ch1).is_empty());
+        let diff = ch1.diff(&ch2);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.version, None);
+        assert_eq!(diff.ciphers.added, vec![TlsCipherSuiteID(0x1303)]);
+        assert_eq!(diff.ciphers.removed, vec![TlsCipherSuiteID(0xc02f)]);
+        assert!(diff.ciphers.reordered);
+        assert!(diff.compression.is_empty());
+        assert_eq!(
+            diff.extensions.added,
+            vec![TlsExtensionType::EncryptThenMac]
+        );
+        assert_eq!(
+            diff.extensions.removed,
+            vec![TlsExtensionType::RenegotiationInfo]
+        );
+        assert!(!diff.extensions.reordered);
+        assert_eq!(
+            diff.changed_extensions,
+            vec![TlsExtensionChange {
+                ext_type: TlsExtensionType::SupportedGroups,
+                old: &groups1[4..],
+                new: &groups2[4..],
+            }]
+        );
+    }
+
+    #[cfg(feature = "fingerprint")]
+    #[test]
+    fn test_tls_ja4_fingerprint() {
+        let bytes = include_bytes!("../assets/client_hello_dhe.bin");
+        let (_, record) = parse_tls_plaintext(bytes).expect("parsing failed");
+        let ch = match &record.msg[0] {
+            TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)) => ch,
+            _ => panic!("Unexpected message"),
+        };
+        assert_eq!(
+            ja4_fingerprint(ch, 't'),
+            "t12i280400_7d45e5af174f_60e5f0f98664"
+        );
+        // server name, ALPN and supported_versions, no signature algorithms
+        let mut bytes = vec![0x01, 0x00, 0x00, 0x4d, 0x03, 0x03];
+        bytes.extend_from_slice(&[0x01; 32]);
+        bytes.extend_from_slice(&[
+            0x00, 0x00, 0x02, 0x13, 0x01, 0x01, 0x00, 0x00, 0x22, 0x00, 0x00, 0x00, 0x0e, 0x00,
+            0x0c, 0x00, 0x00, 0x09, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x68, 0x6f, 0x73, 0x74, 0x00,
+            0x10, 0x00, 0x05, 0x00, 0x03, 0x02, 0x68, 0x32, 0x00, 0x2b, 0x00, 0x03, 0x02, 0x03,
+            0x04,
+        ]);
+        let (_, msg) = parse_tls_message_handshake(&bytes).expect("parsing failed");
+        let ch = match &msg {
+            TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)) => ch,
+            _ => panic!("Unexpected message"),
+        };
+        assert_eq!(
+            ja4_fingerprint(ch, 't'),
+            "t13d0103h2_0f2cb44170f4_b9a491fefe05"
+        );
+    }
+
+    #[test]
+    fn test_tls_keylog() {
+        let data = "# SSL/TLS secrets log file\n\
+            CLIENT_RANDOM b29dd787ff21eb04c8a538399acfb7a3821f826c49bc8bb8a9030a2dce380bf4 \
+            0102030405060708090a0b0c0d0e0f10\n\
+            \n\
+            RSA 0001020304050607 0a0b\n\
+            UNKNOWN_LABEL not-hexadecimal\n\
+            CLIENT_HANDSHAKE_TRAFFIC_SECRET 1111111111111111111111111111111111111111111111111111111111111111 aabb\n\
+            SERVER_HANDSHAKE_TRAFFIC_SECRET 1111111111111111111111111111111111111111111111111111111111111111 CCDD\n";
+        let keylog = TlsKeyLog::parse(data).expect("invalid key log");
+        assert_eq!(keylog.len(), 2);
+        let secrets = keylog.get(&CH[11..43]).expect("session not found");
+        assert_eq!(
+            secrets.master_secret.as_deref(),
+            Some(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16][..])
+        );
+        assert_eq!(secrets.client_handshake_traffic_secret, None);
+        let secrets = keylog.get(&[0x11; 32]).expect("session not found");
+        assert_eq!(
+            secrets.client_handshake_traffic_secret.as_deref(),
+            Some(&[0xaa, 0xbb][..])
+        );
+        assert_eq!(
+            secrets.server_handshake_traffic_secret.as_deref(),
+            Some(&[0xcc, 0xdd][..])
+        );
+        assert!(keylog.get(&[0x22; 32]).is_none());
+        let res = TlsKeyLog::parse("CLIENT_RANDOM 0011\n");
+        assert!(matches!(res, Err(TlsKeyLogError::InvalidLine(1))));
+        let res = TlsKeyLog::parse("# comment\nCLIENT_RANDOM 001 0011\n");
+        assert!(matches!(res, Err(TlsKeyLogError::InvalidLine(2))));
+    }
+
+    #[cfg(feature = "fingerprint")]
+    #[test]
+    fn test_tls_fingerprint_db() {
+        let data = "\
+            # known clients\n\
+            ja3 7FA9A63F9BC008D8B70BDDCC95D3AA42 Old client\n\
+            ja3 22aa39bb9c2dbd019e13620231792b99 Other client\n\
+            \n\
+            ja4 t12i280400_7d45e5af174f_60e5f0f98664 Old client (JA4)\n\
+            rule version=0x0303,cipher=255,!ext=0 TLS 1.2 without SNI\n\
+            rule ext=0 Client with SNI\n";
+        let db = FingerprintDb::parse(data).expect("invalid database");
+        assert_eq!(db.len(), 5);
+        let bytes = include_bytes!("../assets/client_hello_dhe.bin");
+        let (_, record) = parse_tls_plaintext(bytes).expect("parsing failed");
+        let ch = match &record.msg[0] {
+            TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)) => ch,
+            _ => panic!("Unexpected message"),
+        };
+        let labels: Vec<_> = db.lookup(ch).iter().map(|m| (m.kind, m.label)).collect();
+        assert_eq!(
+            labels,
+            vec![
+                (FingerprintKind::JA3, "Old client"),
+                (FingerprintKind::JA4, "Old client (JA4)"),
+                (FingerprintKind::Rule, "TLS 1.2 without SNI"),
+            ]
+        );
+        assert!(FingerprintDb::new().lookup(ch).is_empty());
+        // GREASE cipher and extension, DTLS 1.2, 1.3 and 1.0 in supported_versions
+        let db = FingerprintDb::parse(
+            "rule version=0xfefc DTLS 1.3\n\
+            rule cipher=0x0a0a GREASE cipher\n\
+            rule ext=0x1a1a GREASE extension\n",
+        )
+        .expect("invalid database");
+        let mut bytes = vec![0x01, 0x00, 0x00, 0x3c, 0xfe, 0xfd];
+        bytes.extend_from_slice(&[0x01; 32]);
+        bytes.extend_from_slice(&[
+            0x00, 0x00, 0x04, 0x0a, 0x0a, 0x13, 0x01, 0x01, 0x00, 0x00, 0x0f, 0x1a, 0x1a, 0x00,
+            0x00, 0x00, 0x2b, 0x00, 0x07, 0x06, 0xfe, 0xfd, 0xfe, 0xfc, 0xfe, 0xff,
+        ]);
+        let (_, msg) = parse_tls_message_handshake(&bytes).expect("parsing failed");
+        let ch = match &msg {
+            TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)) => ch,
+            _ => panic!("Unexpected message"),
+        };
+        let labels: Vec<_> = db.lookup(ch).iter().map(|m| m.label).collect();
+        assert_eq!(labels, vec!["DTLS 1.3"]);
+        let res = FingerprintDb::parse("ja3 7fa9a63f9bc008d8b70bddcc95d3aa42\n");
+        assert!(matches!(res, Err(FingerprintDbError::InvalidLine(1))));
+        let res = FingerprintDb::parse("# comment\nrule cipher=0x1z Bad rule\n");
+        assert!(matches!(res, Err(FingerprintDbError::InvalidLine(2))));
+    }
+
+    #[cfg(feature = "fingerprint")]
+    #[test]
+    fn test_tls_ja3s_fingerprint() {
+        let (_, record) = parse_tls_plaintext(&SERVER_REPLY1[..64]).expect("parsing failed");
+        let sh = match &record.msg[0] {
+            TlsMessage::Handshake(TlsMessageHandshake::ServerHello(sh)) => sh,
+            _ => panic!("Unexpected message"),
+        };
+        let ja3s = ja3s_fingerprint(sh);
+        assert_eq!(ja3s.string, "771,49199,65281-35-11");
+        assert_eq!(ja3s.hash, "8ba84e601ebaf7869044acdccb816514");
+    }
+
+    #[cfg(all(feature = "fingerprint", feature = "serialize"))]
+    #[test]
+    fn test_tls_jarm_probes() {
+        let record = JARM_PROBES[0]
+            .client_hello_record("example.com", &[7; 32])
+            .expect("serialization failed");
+        let (rem, record) = parse_tls_plaintext(&record).expect("parsing failed");
+        assert!(rem.is_empty());
+        assert_eq!(record.hdr.version, TlsVersion::Tls12);
+        let ch = match &record.msg[0] {
+            TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)) => ch,
+            _ => panic!("Unexpected message"),
+        };
+        assert_eq!(ch.version, TlsVersion::Tls12);
+        assert_eq!(ch.ciphers.len(), 69);
+        assert_eq!(ch.ciphers[0], TlsCipherSuiteID(0x0016));
+        assert_eq!(ch.session_id.map(|s| s.len()), Some(32));
+        let ext_types: Vec<_> = extension_order(ch).observed.iter().map(|t| t.0).collect();
+        assert_eq!(
+            ext_types,
+            vec![
+                0x0000, 0x0017, 0x0001, 0xff01, 0x000a, 0x000b, 0x0023, 0x0010, 0x000d, 0x0033,
+                0x002d, 0x002b
+            ]
+        );
+        let (_, exts) =
+            parse_tls_extensions(ch.ext.unwrap_or(&[])).expect("parsing extensions failed");
+        assert!(exts.contains(&TlsExtension::SNI(vec![(
+            SNIType::HostName,
+            b"example.com"
+        )])));
+        // ALPN protocols and versions in reverse order
+        assert!(exts.iter().any(|ext| match ext {
+            TlsExtension::ALPN(v) => v.first() == Some(&&b"hq"[..]),
+            _ => false,
+        }));
+        assert!(exts.contains(&TlsExtension::SupportedVersions(vec![
+            TlsVersion::Tls12,
+            TlsVersion::Tls11,
+            TlsVersion::Tls10
+        ])));
+        // TLS 1.3 probe without the TLS 1.3 cipher suites
+        let record = JARM_PROBES[8]
+            .client_hello_record("example.com", &[7; 32])
+            .expect("serialization failed");
+        let (_, record) = parse_tls_plaintext(&record).expect("parsing failed");
+        assert_eq!(record.hdr.version, TlsVersion::Tls10);
+        match &record.msg[0] {
+            TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)) => {
+                assert_eq!(ch.version, TlsVersion::Tls12);
+                assert_eq!(ch.ciphers.len(), 64);
+            }
+            _ => panic!("Unexpected message"),
+        }
+        // GREASE probe
+        let record = JARM_PROBES[9]
+            .client_hello_record("example.com", &[7; 32])
+            .expect("serialization failed");
+        let (_, record) = parse_tls_plaintext(&record).expect("parsing failed");
+        match &record.msg[0] {
+            TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)) => {
+                assert!(ch.ciphers[0].is_grease());
+                assert!(extension_order(ch).observed[0].is_grease());
+            }
+            _ => panic!("Unexpected message"),
+        }
+    }
+
+    #[cfg(all(feature = "fingerprint", feature = "serialize"))]
+    #[test]
+    fn test_tls_jarm_fingerprint() {
+        let response = jarm_response_string(SERVER_REPLY1);
+        assert_eq!(response, "c02f|0303||ff01-0023-000b");
+        let alert = &[0x15, 0x03, 0x03, 0x00, 0x02, 0x02, 0x28];
+        assert_eq!(jarm_response_string(alert), "|||");
+        let mut responses = vec!["|||"; 10];
+        let jarm = jarm_fingerprint(&responses);
+        assert_eq!(jarm.hash, "0".repeat(62));
+        responses[0] = &response;
+        let jarm = jarm_fingerprint(&responses);
+        assert_eq!(
+            jarm.raw,
+            format!("{},|||,|||,|||,|||,|||,|||,|||,|||,|||", response)
+        );
+        assert_eq!(
+            jarm.hash,
+            "29d000000000000000000000000000addb9fb4a6e2840be1b20ffa01cc8539"
+        );
+    }
+
+    #[cfg(all(feature = "fingerprint", feature = "serialize"))]
+    #[test]
+    fn test_tls_client_hello_presets() {
+        let presets = &[
+            (
+                BrowserPreset::Chrome131,
+                "t13d1516h2_8daaf6152771_02713d6af862",
+            ),
+            (
+                BrowserPreset::Firefox133,
+                "t13d1716h2_5b57614c22b0_eeeea6562960",
+            ),
+            (
+                BrowserPreset::Safari18,
+                "t13d2014h2_a09f3c656075_14788d8d241b",
+            ),
+        ];
+        let mut seed = [0u8; 32];
+        seed[..].copy_from_slice(&CH[11..43]);
+        for &(preset, ja4) in presets {
+            let builder = TlsClientHelloBuilder::preset(preset, "example.com");
+            let record = builder.build(&seed).expect("serialization failed");
+            let (rem, plaintext) = parse_tls_plaintext(&record).expect("parsing failed");
+            assert!(rem.is_empty());
+            let ch = match &plaintext.msg[0] {
+                TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)) => ch,
+                _ => panic!("Unexpected message"),
+            };
+            assert_eq!(ch.rand_data, &seed[4..]);
+            assert_eq!(ch.ciphers[0].is_grease(), builder.grease);
+            assert_eq!(ja4_fingerprint(ch, 't'), ja4);
+            // the same seed gives the same record
+            assert_eq!(builder.build(&seed).expect("serialization failed"), record);
+        }
+        // the extensions of Chrome are permuted, using the seed
+        let builder = TlsClientHelloBuilder::preset(BrowserPreset::Chrome131, "example.com");
+        let orders: Vec<_> = (0..4u8)
+            .map(|i| {
+                let record = builder.build(&[i; 32]).expect("serialization failed");
+                let (_, plaintext) = parse_tls_plaintext(&record).expect("parsing failed");
+                match &plaintext.msg[0] {
+                    TlsMessage::Handshake(TlsMessageHandshake::ClientHello(ch)) => {
+                        extension_order(ch).observed
+                    }
+                    _ => panic!("Unexpected message"),
+                }
+            })
+            .collect();
+        assert!(orders.iter().any(|order| order != &orders[0]));
+    }
+
+    #[cfg(feature = "x509")]
+    #[test]
+    fn test_tls_ja4x_fingerprint() {
+        let (_, record) = parse_tls_plaintext(&SERVER_REPLY1[64..]).expect("parsing failed");
+        let chain = match &record.msg[0] {
+            TlsMessage::Handshake(TlsMessageHandshake::Certificate(c)) => &c.cert_chain,
+            _ => panic!("Unexpected message"),
+        };
+        let fingerprints: Vec<_> = chain
+            .iter()
+            .map(|cert| cert.ja4x_fingerprint().expect("invalid certificate"))
+            .collect();
+        assert_eq!(
+            fingerprints,
+            vec![
+                "a373a9f83c6b_2bab15409345_29378ef12ee8",
+                "a373a9f83c6b_a373a9f83c6b_d6ddd9f3da89",
+                "e7bc7ebc3d9e_a373a9f83c6b_97731fcbbbd7",
+            ]
+        );
+        assert!(ja4x_fingerprint(&chain[0].data[..100]).is_err());
+    }
+
+    #[test]
+    fn test_tls_record_version_warnings() {
+        let (_, record) = parse_tls_plaintext(&SERVER_REPLY1[..64]).expect("parsing failed");
+        assert_eq!(record.record_version_warnings(), vec![]);
+        let mut bytes = SERVER_REPLY1[..64].to_vec();
+        bytes[2] = 0x00;
+        let (_, record) = parse_tls_plaintext(&bytes).expect("parsing failed");
+        assert_eq!(
+            record.record_version_warnings(),
+            vec![TlsRecordVersionWarning::Ssl30Record]
+        );
+        assert_eq!(
+            check_record_version(TlsVersion::Tls12, Some(TlsVersion::Tls10)),
+            vec![TlsRecordVersionWarning::HigherThanHandshake]
+        );
+        assert_eq!(
+            check_record_version(TlsVersion(0x1234), None),
+            vec![TlsRecordVersionWarning::InvalidVersion(TlsVersion(0x1234))]
+        );
+        assert_eq!(
+            check_record_version(TlsVersion::Tls10, Some(TlsVersion::Tls12)),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_tls_server_hello_downgrade_sentinel() {
+        let mut random = [0x42; 28];
+        let sh = TlsServerHelloContents::new(0x0303, 0, &random, None, 0xc02f, 0, None);
+        assert_eq!(sh.get_downgrade_sentinel(), None);
+        random[20..].copy_from_slice(b"DOWNGRD\x01");
+        let sh = TlsServerHelloContents::new(0x0303, 0, &random, None, 0xc02f, 0, None);
+        assert_eq!(
+            sh.get_downgrade_sentinel(),
+            Some(TlsDowngradeSentinel::Tls12)
+        );
+        random[27] = 0;
+        let sh = TlsServerHelloContents::new(0x0302, 0, &random, None, 0xc02f, 0, None);
+        assert_eq!(
+            sh.get_downgrade_sentinel(),
+            Some(TlsDowngradeSentinel::Tls11)
+        );
+    }
+
+    #[test]
+    fn test_tls_handshake_reassembly() {
+        let empty = &b""[..];
+        // Certificate message, followed by the ServerKeyExchange and ServerHelloDone records
+        let cert_msg = &SERVER_REPLY1[69..3150];
+        let (_, expected) = parse_tls_message_handshake(cert_msg).expect("invalid message");
+        let mut reassembler = TlsHandshakeReassembler::new();
+        assert_eq!(reassembler.push(&cert_msg[..2]), Ok(vec![]));
+        assert_eq!(reassembler.push(&cert_msg[2..1000]), Ok(vec![]));
+        assert!(reassembler.has_pending_data());
+        assert_eq!(reassembler.push(&cert_msg[1000..]), Ok(vec![expected]));
+        assert!(!reassembler.has_pending_data());
+        // two complete messages in the same push
+        let (rem, skx) = parse_tls_raw_record(&SERVER_REPLY1[3150..]).expect("invalid record");
+        let (rem, done) = parse_tls_raw_record(rem).expect("invalid record");
+        assert_eq!(rem, empty);
+        let mut data = skx.data.to_vec();
+        data.extend_from_slice(done.data);
+        let messages = reassembler.push(&data).expect("reassembly failed");
+        assert_eq!(messages.len(), 2);
+        assert_eq!(
+            messages[1],
+            TlsMessage::Handshake(TlsMessageHandshake::ServerDone(empty))
+        );
+        // buffer limit
+        let mut reassembler = TlsHandshakeReassembler::with_max_size(1024);
+        assert_eq!(reassembler.push(&cert_msg[..1000]), Ok(vec![]));
+        assert_eq!(
+            reassembler.push(&cert_msg[1000..1100]),
+            Err(HandshakeReassemblyError::BufferFull)
+        );
+        assert!(!reassembler.has_pending_data());
+    }
+
     #[test]
     fn test_tls_record_serverkeyexchange() {
         let empty = &b""[..];
@@ -524,6 +1635,22 @@ static CLIENT_REPLY1: &[u8] = &[
         assert_eq!(parse_tls_plaintext(&bytes), Ok((empty, expected)));
     }
 
+    #[test]
+    fn test_tls_record_changecipherspec_invalid() {
+        let extra = &[0x14, 0x03, 0x03, 0x00, 0x03, 0x01, 0x41, 0x42];
+        let res = parse_tls_plaintext(extra);
+        assert_eq!(
+            res,
+            Err(Err::Error(make_error(&extra[5..], ErrorKind::LengthValue)))
+        );
+        let value = &[0x14, 0x03, 0x03, 0x00, 0x01, 0x02];
+        let res = parse_tls_plaintext(value);
+        assert_eq!(
+            res,
+            Err(Err::Error(make_error(&value[5..], ErrorKind::Verify)))
+        );
+    }
+
     #[test]
     fn test_tls_record_encryptedhandshake() {
         let empty = &b""[..];
@@ -814,4 +1941,127 @@ static SERVER_STATUS_RESPONSE: &[u8] = &[
         let res = parse_tls_record_with_header(&bytes, &hdr);
         assert_eq!(res, Ok((empty, expected)));
     }
+
+    #[cfg(feature = "x509")]
+    #[test]
+    fn test_tls_message_status_response_ocsp() {
+        let status = TlsCertificateStatusContents {
+            status_type: 1,
+            blob: &SERVER_STATUS_RESPONSE[8..],
+        };
+        let resp = status
+            .ocsp_response()
+            .expect("not an OCSP response")
+            .expect("could not decode OCSP response");
+        assert_eq!(resp.status, OcspResponseStatus::Successful);
+        let basic = resp.basic.expect("no basic response");
+        let data = &basic.tbs_response_data;
+        assert_eq!(
+            data.responder_id,
+            OcspResponderId::ByKey(&[
+                0x9b, 0xe1, 0x51, 0x6a, 0xe3, 0xbe, 0x1a, 0x97, 0x21, 0x2b, 0x67, 0xb1, 0x07, 0xd4,
+                0x54, 0x6c, 0x4f, 0xae, 0x53, 0x3c
+            ])
+        );
+        assert_eq!(data.produced_at.timestamp(), 1_476_315_771);
+        assert_eq!(data.responses.len(), 1);
+        let single = &data.responses[0];
+        assert_eq!(
+            single.cert_id.serial,
+            &[
+                0x7e, 0x91, 0xc2, 0x1d, 0xe5, 0x0b, 0x21, 0x74, 0x6d, 0x19, 0x78, 0xfa, 0x4b, 0xde,
+                0xbe, 0x48
+            ]
+        );
+        assert_eq!(single.cert_status, OcspCertStatus::Good);
+        assert_eq!(single.this_update.timestamp(), 1_476_315_771);
+        assert_eq!(
+            single.next_update.map(|t| t.timestamp()),
+            Some(1_476_920_571)
+        );
+        assert_eq!(basic.certs.len(), 1);
+        assert_eq!(
+            basic.certs[0].subject().to_string(),
+            "CN=Symantec Class 3 EV SSL CA - G3 OCSP Responder"
+        );
+        // other status types are not decoded
+        let status = TlsCertificateStatusContents {
+            status_type: 2,
+            blob: &SERVER_STATUS_RESPONSE[8..],
+        };
+        assert!(status.ocsp_response().is_none());
+    }
+
+    #[cfg(feature = "x509")]
+    #[test]
+    fn test_ocsp_response_revoked() {
+        const OCSP_REVOKED: &[u8] = include_bytes!("../assets/ocsp_response.der");
+        let resp = parse_ocsp_response(OCSP_REVOKED).expect("could not decode OCSP response");
+        let basic = resp.basic.expect("no basic response");
+        let single = &basic.tbs_response_data.responses[0];
+        assert_eq!(single.cert_id.serial, &[0x12, 0x34]);
+        match single.cert_status {
+            OcspCertStatus::Revoked {
+                revocation_time,
+                revocation_reason,
+            } => {
+                assert_eq!(revocation_time.timestamp(), 1_735_689_600);
+                assert_eq!(revocation_reason.map(|r| r.0), Some(1));
+            }
+            _ => panic!("unexpected status {:?}", single.cert_status),
+        }
+        assert!(basic.certs.is_empty());
+        assert!(parse_ocsp_response(&OCSP_REVOKED[..100]).is_err());
+    }
+
+    #[test]
+    fn test_tls_message_certificate_url() {
+        let empty = &b""[..];
+        let mut bytes = vec![0x15, 0x00, 0x00, 0x2e, 0x00, 0x00, 0x2b, 0x00, 0x14];
+        bytes.extend_from_slice(b"http://example.com/c");
+        bytes.push(0x01);
+        bytes.extend_from_slice(&[0xaa; 20]);
+        let expected = TlsMessage::Handshake(TlsMessageHandshake::CertificateURL(
+            TlsCertificateUrlContents {
+                chain_type: CertChainType::IndividualCerts,
+                url_and_hash_list: vec![TlsUrlAndHash {
+                    url: b"http://example.com/c",
+                    sha1_hash: &[0xaa; 20],
+                }],
+            },
+        ));
+        let res = parse_tls_message_handshake(&bytes);
+        assert_eq!(res, Ok((empty, expected)));
+        // padding must be 0x01
+        bytes[29] = 0x00;
+        assert!(parse_tls_message_handshake(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_tls_message_certificate_verify() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x0f, 0x00, 0x00, 0x08, 0x08, 0x04, 0x00, 0x04, 0x01, 0x02, 0x03, 0x04,
+        ];
+        let (rem, msg) = parse_tls_message_handshake(bytes).expect("could not parse message");
+        assert_eq!(rem, empty);
+        let raw = match msg {
+            TlsMessage::Handshake(TlsMessageHandshake::CertificateVerify(raw)) => raw,
+            _ => panic!("unexpected message {:?}", msg),
+        };
+        let expected = TlsCertificateVerifyContents {
+            algorithm: Some(SignatureScheme::rsa_pss_rsae_sha256),
+            signature: &[0x01, 0x02, 0x03, 0x04],
+        };
+        assert_eq!(parse_certificate_verify(raw, true), Ok((empty, expected)));
+        // before TLS 1.2, the signature algorithm is not sent
+        let expected = TlsCertificateVerifyContents {
+            algorithm: None,
+            signature: &[0x01, 0x02, 0x03, 0x04],
+        };
+        assert_eq!(
+            parse_certificate_verify(&raw[2..], false),
+            Ok((empty, expected))
+        );
+    }
 } // mod tls_handshake
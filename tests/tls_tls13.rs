@@ -52,6 +52,35 @@ static TV_SERVER_HELLO_1: &[u8] = &[
     0x25, 0x32, 0x3b, 0x79, 0xce, 0x20, 0x1c,
 ];
 
+    /// Records of a TLS 1.3 handshake with a HelloRetryRequest and middlebox compatibility
+    /// ChangeCipherSpec messages, up to the ServerHello (with the direction, `to_server`)
+    fn hello_retry_request_flow() -> Vec<(Vec<u8>, bool)> {
+        let mut hrr = vec![
+            0x16, 0x03, 0x03, 0x00, 0x41, 0x02, 0x00, 0x00, 0x3d, 0x03, 0x03,
+        ];
+        hrr.extend_from_slice(&HRR_RANDOM);
+        hrr.extend_from_slice(&[
+            0x00, 0x13, 0x01, 0x00, 0x00, 0x15, 0x00, 0x2b, 0x00, 0x02, 0x03, 0x04, 0x00, 0x33,
+            0x00, 0x02, 0x00, 0x17, 0x00, 0x2c, 0x00, 0x05, 0x00, 0x03, 0xaa, 0xbb, 0xcc,
+        ]);
+        let ccs = vec![0x14, 0x03, 0x03, 0x00, 0x01, 0x01];
+        let mut sh = vec![
+            0x16, 0x03, 0x03, 0x00, 0x32, 0x02, 0x00, 0x00, 0x2e, 0x03, 0x03,
+        ];
+        sh.extend_from_slice(&[0x22; 32]);
+        sh.extend_from_slice(&[
+            0x00, 0x13, 0x01, 0x00, 0x00, 0x06, 0x00, 0x2b, 0x00, 0x02, 0x03, 0x04,
+        ]);
+        vec![
+            (TV_CLIENT_HELLO_1.to_vec(), true),
+            (hrr, false),
+            (ccs.clone(), false),
+            (ccs, true),
+            (TV_CLIENT_HELLO_1.to_vec(), true),
+            (sh, false),
+        ]
+    }
+
     #[test]
     fn test_tls13_ch() {
         let empty = &b""[..];
@@ -119,4 +148,891 @@ static TV_SERVER_HELLO_1: &[u8] = &[
         let res_ext = parse_tls_extensions(ext_raw);
         assert_eq!(res_ext, Ok((empty, expected_ext)));
     }
+
+    #[test]
+    fn test_tls13_compressed_certificate() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x19, 0x00, 0x00, 0x1e, 0x00, 0x01, 0x00, 0x00, 0x0e, 0x00, 0x00, 0x16, 0x78, 0x9c,
+            0x63, 0x60, 0x60, 0xe0, 0x62, 0x60, 0x60, 0x4d, 0x4e, 0x2d, 0x2a, 0x51, 0x64, 0x60,
+            0x00, 0x00, 0x0a, 0x24, 0x01, 0xdf,
+        ];
+        let expected = TlsMessage::Handshake(TlsMessageHandshake::CompressedCertificate(
+            TlsCompressedCertificateContents {
+                algorithm: CertificateCompressionAlgorithm::Zlib,
+                uncompressed_length: 14,
+                compressed_certificate_message: &bytes[12..],
+            },
+        ));
+        let res = parse_tls_message_handshake(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_tls13_compressed_certificate_decompress() {
+        let bytes = &[
+            0x19, 0x00, 0x00, 0x1e, 0x00, 0x01, 0x00, 0x00, 0x0e, 0x00, 0x00, 0x16, 0x78, 0x9c,
+            0x63, 0x60, 0x60, 0xe0, 0x62, 0x60, 0x60, 0x4d, 0x4e, 0x2d, 0x2a, 0x51, 0x64, 0x60,
+            0x00, 0x00, 0x0a, 0x24, 0x01, 0xdf,
+        ];
+        let res = parse_tls_message_handshake(bytes);
+        let content = match res {
+            Ok((_, TlsMessage::Handshake(TlsMessageHandshake::CompressedCertificate(c)))) => c,
+            _ => panic!("Wrong message type (expected CompressedCertificate)"),
+        };
+        let data = content.decompress().expect("decompression failed");
+        assert_eq!(&data[7..12], b"cert!");
+
+        let mut bad_length = content.clone();
+        bad_length.uncompressed_length -= 1;
+        assert_eq!(
+            bad_length.decompress(),
+            Err(CertificateDecompressionError::LengthMismatch)
+        );
+    }
+
+    #[test]
+    fn test_tls13_compress_certificate_extension() {
+        let empty = &b""[..];
+        let bytes = &[0x00, 0x1b, 0x00, 0x05, 0x04, 0x00, 0x02, 0x00, 0x01];
+        let expected = TlsExtension::CompressCertificate(vec![
+            CertificateCompressionAlgorithm::Brotli,
+            CertificateCompressionAlgorithm::Zlib,
+        ]);
+        let res = parse_tls_extension(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls13_new_session_ticket_max_early_data_size() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x00, 0x00, 0x1c, 0x20, 0x01, 0x02, 0x03, 0x04, 0x01, 0x00, 0x00, 0x02, 0xaa, 0xbb,
+            0x00, 0x08, 0x00, 0x2a, 0x00, 0x04, 0x00, 0x00, 0x40, 0x00,
+        ];
+        let (rem, nst) = parse_tls13_new_session_ticket(bytes).expect("could not parse ticket");
+        assert_eq!(rem, empty);
+        assert_eq!(nst.ticket_lifetime, 7200);
+        assert_eq!(nst.ticket_age_add, 0x0102_0304);
+        assert_eq!(nst.ticket_nonce, &[0x00]);
+        assert_eq!(nst.ticket, &[0xaa, 0xbb]);
+        assert_eq!(nst.max_early_data_size(), Some(0x4000));
+
+        let bytes = &[
+            0x00, 0x00, 0x1c, 0x20, 0x01, 0x02, 0x03, 0x04, 0x00, 0x00, 0x01, 0xaa, 0x00, 0x00,
+        ];
+        let (_, nst) = parse_tls13_new_session_ticket(bytes).expect("could not parse ticket");
+        assert_eq!(nst.max_early_data_size(), None);
+    }
+
+    #[test]
+    fn test_tls13_certificate_entry_extensions() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x00, 0x00, 0x00, 0x12, 0x00, 0x00, 0x02, 0x30, 0x00, 0x00, 0x0b, 0x00, 0x05, 0x00,
+            0x07, 0x01, 0x00, 0x00, 0x03, 0xaa, 0xbb, 0xcc,
+        ];
+        let (rem, cert) = parse_tls13_certificate(bytes).expect("could not parse certificate");
+        assert_eq!(rem, empty);
+        assert_eq!(cert.certificate_request_context, empty);
+        assert_eq!(cert.cert_list.len(), 1);
+        let entry = &cert.cert_list[0];
+        assert_eq!(entry.data, &[0x30, 0x00]);
+        let expected = vec![TlsExtension::CertificateStatus(
+            TlsCertificateStatusContents {
+                status_type: 1,
+                blob: &[0xaa, 0xbb, 0xcc],
+            },
+        )];
+        assert_eq!(entry.parse_extensions(), Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls13_handshake_with_version() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x04, 0x00, 0x00, 0x0e, 0x00, 0x00, 0x1c, 0x20, 0x01, 0x02, 0x03, 0x04, 0x00, 0x00,
+            0x01, 0xaa, 0x00, 0x00,
+        ];
+        let expected = TlsMessage::Handshake(TlsMessageHandshake::NewSessionTicketV13(
+            TlsNewSessionTicketV13Content {
+                ticket_lifetime: 7200,
+                ticket_age_add: 0x0102_0304,
+                ticket_nonce: empty,
+                ticket: &[0xaa],
+                ext: empty,
+            },
+        ));
+        let res = parse_tls_message_handshake_with_version(bytes, TlsVersion::Tls13);
+        assert_eq!(res, Ok((empty, expected)));
+        // TLS 1.2 ticket format
+        let expected = TlsMessage::Handshake(TlsMessageHandshake::NewSessionTicket(
+            TlsNewSessionTicketContent {
+                ticket_lifetime_hint: 7200,
+                ticket: &bytes[8..],
+            },
+        ));
+        let res = parse_tls_message_handshake_with_version(bytes, TlsVersion::Tls12);
+        assert_eq!(res, Ok((empty, expected)));
+    }
+
+    #[test]
+    fn test_tls13_post_handshake_auth() {
+        let empty = &b""[..];
+        let request = &[
+            0x0d, 0x00, 0x00, 0x0d, 0x02, 0xab, 0xcd, 0x00, 0x08, 0x00, 0x0d, 0x00, 0x04, 0x00,
+            0x02, 0x04, 0x03,
+        ];
+        let (rem, request) = parse_tls_message_handshake_with_version(request, TlsVersion::Tls13)
+            .expect("parsing failed");
+        assert_eq!(rem, empty);
+        let request = match request {
+            TlsMessage::Handshake(msg @ TlsMessageHandshake::CertificateRequestV13(_)) => msg,
+            _ => panic!("unexpected message {:?}", request),
+        };
+        if let TlsMessageHandshake::CertificateRequestV13(ref req) = request {
+            assert_eq!(req.certificate_request_context, &[0xab, 0xcd]);
+            let (_, exts) = req.parse_extensions().expect("extensions parsing failed");
+            assert_eq!(exts.len(), 1);
+        }
+        let cert = &[0x0b, 0x00, 0x00, 0x06, 0x02, 0xab, 0xcd, 0x00, 0x00, 0x00];
+        let (_, cert) = parse_tls_message_handshake_with_version(cert, TlsVersion::Tls13)
+            .expect("parsing failed");
+        let cert = match cert {
+            TlsMessage::Handshake(msg) => msg,
+            _ => panic!("unexpected message {:?}", cert),
+        };
+        let mut auth = TlsPostHandshakeAuth::new();
+        assert!(auth.update(&request, false).is_ok());
+        // duplicate request
+        assert!(auth.update(&request, false).is_err());
+        assert_eq!(auth.pending_requests(), &[vec![0xab, 0xcd]]);
+        assert!(matches!(auth.update(&cert, true), Ok(Some(&[0xab, 0xcd]))));
+        assert!(auth.pending_requests().is_empty());
+        // no matching request
+        assert!(auth.update(&cert, true).is_err());
+    }
+
+    #[test]
+    fn test_tls13_end_of_early_data() {
+        let empty = &b""[..];
+        let bytes = &[0x05, 0x00, 0x00, 0x00];
+        let expected = TlsMessage::Handshake(TlsMessageHandshake::EndOfEarlyData);
+        let res = parse_tls_message_handshake(bytes);
+        assert_eq!(res, Ok((empty, expected)));
+        // the message has no content
+        let bytes = &[0x05, 0x00, 0x00, 0x01, 0x00];
+        assert!(parse_tls_message_handshake(bytes).is_err());
+    }
+
+    #[test]
+    fn test_tls13_encrypted_extensions() {
+        let empty = &b""[..];
+        let bytes = &[
+            0x08, 0x00, 0x00, 0x10, 0x00, 0x0e, 0x00, 0x10, 0x00, 0x05, 0x00, 0x03, 0x02, 0x68,
+            0x32, 0x00, 0x15, 0x00, 0x01, 0x00,
+        ];
+        let (rem, msg) = parse_tls_message_handshake(bytes).expect("could not parse message");
+        assert_eq!(rem, empty);
+        let ee = match msg {
+            TlsMessage::Handshake(TlsMessageHandshake::EncryptedExtensions(ee)) => ee,
+            _ => panic!("unexpected message {:?}", msg),
+        };
+        let expected = vec![
+            TlsExtension::ALPN(vec![b"h2"]),
+            TlsExtension::Padding(&[0x00]),
+        ];
+        let (_, exts) = ee.parse_extensions().expect("could not parse extensions");
+        assert_eq!(exts, expected);
+        // padding is only allowed in ClientHello
+        assert_eq!(
+            encrypted_extensions_violations(&exts),
+            vec![TlsExtensionType::Padding]
+        );
+        // GREASE and unknown extensions are allowed
+        let exts = vec![
+            TlsExtension::Grease(0x0a0a, &[]),
+            TlsExtension::Unknown(TlsExtensionType(0xfe00), &[]),
+        ];
+        assert!(encrypted_extensions_violations(&exts).is_empty());
+        let grease = &[0x0a, 0x0a, 0x00, 0x00];
+        let res = parse_tls_extension_strict(grease, Some(ExtensionContext::EncryptedExtensions));
+        assert!(res.is_ok());
+        let res = parse_tls_extensions_strict(ee.ext, Some(ExtensionContext::EncryptedExtensions));
+        assert!(res.is_err());
+        let res =
+            parse_tls_extensions_strict(&ee.ext[..9], Some(ExtensionContext::EncryptedExtensions));
+        assert_eq!(res, Ok((empty, vec![TlsExtension::ALPN(vec![b"h2"])])));
+    }
+
+    #[test]
+    fn test_tls13_hello_retry_request() {
+        let empty = &b""[..];
+        let mut bytes = vec![0x02, 0x00, 0x00, 0x3d, 0x03, 0x03];
+        bytes.extend_from_slice(&HRR_RANDOM);
+        bytes.extend_from_slice(&[
+            0x00, 0x13, 0x01, 0x00, 0x00, 0x15, 0x00, 0x2b, 0x00, 0x02, 0x03, 0x04, 0x00, 0x33,
+            0x00, 0x02, 0x00, 0x17, 0x00, 0x2c, 0x00, 0x05, 0x00, 0x03, 0xaa, 0xbb, 0xcc,
+        ]);
+        let (rem, msg) = parse_tls_message_handshake(&bytes).expect("could not parse message");
+        assert_eq!(rem, empty);
+        let sh = match msg {
+            TlsMessage::Handshake(TlsMessageHandshake::ServerHello(sh)) => sh,
+            _ => panic!("unexpected message {:?}", msg),
+        };
+        assert!(sh.is_hello_retry_request());
+        assert_eq!(sh.get_hrr_selected_group(), Some(NamedGroup::Secp256r1));
+        assert_eq!(sh.get_hrr_cookie(), Some(&[0xaa, 0xbb, 0xcc][..]));
+        // an ordinary ServerHello
+        bytes[6] = 0x00;
+        let (_, msg) = parse_tls_message_handshake(&bytes).expect("could not parse message");
+        if let TlsMessage::Handshake(TlsMessageHandshake::ServerHello(sh)) = msg {
+            assert!(!sh.is_hello_retry_request());
+            assert_eq!(sh.get_hrr_cookie(), None);
+        } else {
+            panic!("unexpected message");
+        }
+    }
+
+    #[test]
+    fn test_tls13_conformance() {
+        let key_share = |group: u8| {
+            let mut ext = vec![0x00, 0x33, 0x00, 0x26, 0x00, 0x24, 0x00, group, 0x00, 0x20];
+            ext.extend_from_slice(&[0x55; 32]);
+            ext
+        };
+        let versions: &[u8] = &[0x00, 0x2b, 0x00, 0x03, 0x02, 0x03, 0x04];
+        let groups: &[u8] = &[0x00, 0x0a, 0x00, 0x04, 0x00, 0x02, 0x00, 0x1d];
+        let sig_algs: &[u8] = &[0x00, 0x0d, 0x00, 0x04, 0x00, 0x02, 0x04, 0x03];
+        let ciphers = vec![TlsCipherSuiteID(0x1301)];
+        let comp = vec![TlsCompressionID::Null];
+        let sid = Some(&[0x01; 32][..]);
+        let ch_ext = [versions, groups, sig_algs, &key_share(0x1d)].concat();
+        let ch = TlsClientHelloContents::new(
+            0x0303,
+            0,
+            &[0; 28],
+            sid,
+            ciphers.clone(),
+            comp,
+            Some(&ch_ext),
+        );
+        assert!(validate_client_hello(&ch).is_empty());
+        // key share for a group not offered, PSK not last and without binders, duplicate
+        // supported_groups, early_data without psk_key_exchange_modes
+        let psk: &[u8] = &[
+            0x00, 0x29, 0x00, 0x0b, 0x00, 0x07, 0x00, 0x01, 0x61, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00,
+        ];
+        let early_data: &[u8] = &[0x00, 0x2a, 0x00, 0x00];
+        let bad_ext = [versions, groups, psk, groups, &key_share(0x17), early_data].concat();
+        let comp = vec![TlsCompressionID::Null, TlsCompressionID(1)];
+        let bad_ch =
+            TlsClientHelloContents::new(0x0301, 0, &[0; 28], None, ciphers, comp, Some(&bad_ext));
+        assert_eq!(
+            validate_client_hello(&bad_ch),
+            vec![
+                TlsConformanceViolation::DuplicateExtension(TlsExtensionType::SupportedGroups),
+                TlsConformanceViolation::PreSharedKeyNotLast,
+                TlsConformanceViolation::InvalidLegacyVersion(TlsVersion::Tls10),
+                TlsConformanceViolation::InvalidLegacyCompression,
+                TlsConformanceViolation::MissingExtension(TlsExtensionType::PskExchangeModes),
+                TlsConformanceViolation::KeyShareGroupNotOffered(NamedGroup::Secp256r1),
+                TlsConformanceViolation::PskBinderCountMismatch,
+            ]
+        );
+        // ServerHello
+        let sh_ext = [
+            &[0x00, 0x2b, 0x00, 0x02, 0x03, 0x04][..],
+            &[0x00, 0x33, 0x00, 0x24],
+            &key_share(0x1d)[6..],
+        ]
+        .concat();
+        let sh = TlsServerHelloContents::new(0x0303, 0, &[0; 28], sid, 0x1301, 0, Some(&sh_ext));
+        assert!(validate_server_hello(&sh, &ch).is_empty());
+        let bad_sh_ext = [
+            &sh_ext[..],
+            &[0x00, 0x17, 0x00, 0x00, 0x00, 0x29, 0x00, 0x02, 0x00, 0x00],
+        ]
+        .concat();
+        let bad_sh =
+            TlsServerHelloContents::new(0x0303, 0, &[0; 28], None, 0x1302, 0, Some(&bad_sh_ext));
+        assert_eq!(
+            validate_server_hello(&bad_sh, &ch),
+            vec![
+                TlsConformanceViolation::CipherNotOffered(TlsCipherSuiteID(0x1302)),
+                TlsConformanceViolation::UnsolicitedExtension(
+                    TlsExtensionType::ExtendedMasterSecret
+                ),
+                TlsConformanceViolation::UnsolicitedExtension(TlsExtensionType::PreSharedKey),
+                TlsConformanceViolation::SessionIdNotEchoed,
+                TlsConformanceViolation::ExtensionNotAllowed(
+                    TlsExtensionType::ExtendedMasterSecret,
+                    ExtensionContext::ServerHello
+                ),
+                TlsConformanceViolation::InvalidPskIdentity(0),
+            ]
+        );
+        // server_name in NewSessionTicket, key_share in EncryptedExtensions
+        let ext = [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x2a, 0x00, 0x04, 0x00, 0x00, 0x40, 0x00,
+        ];
+        assert_eq!(
+            validate_extensions_in(&ext, ExtensionContext::NewSessionTicket),
+            vec![TlsConformanceViolation::ExtensionNotAllowed(
+                TlsExtensionType::ServerName,
+                ExtensionContext::NewSessionTicket
+            )]
+        );
+        assert!(
+            validate_extensions_in(&ext[..4], ExtensionContext::EncryptedExtensions).is_empty()
+        );
+        let ext = [0x00, 0x33, 0x00, 0x00, 0xfa, 0xfa, 0x00, 0x00];
+        assert_eq!(
+            validate_extensions_in(&ext, ExtensionContext::EncryptedExtensions),
+            vec![TlsConformanceViolation::ExtensionNotAllowed(
+                TlsExtensionType::KeyShare,
+                ExtensionContext::EncryptedExtensions
+            )]
+        );
+    }
+
+    #[test]
+    fn test_tls13_middlebox_compat_ccs() {
+        let ccs = TlsMessage::ChangeCipherSpec;
+        // server: after ServerHello or HelloRetryRequest
+        assert!(is_tls13_compat_ccs(TlsState::Tls13ServerHello, &ccs, false));
+        let state = tls_state_transition(TlsState::Tls13ServerHello, &ccs, false).ok();
+        assert_eq!(state, Some(TlsState::Tls13ServerHello));
+        let state = tls_state_transition(TlsState::HelloRetryRequest, &ccs, false).ok();
+        assert_eq!(state, Some(TlsState::HelloRetryRequest));
+        // client: before its second ClientHello, or before its second flight
+        let state = tls_state_transition(TlsState::HelloRetryRequest, &ccs, true).ok();
+        assert_eq!(state, Some(TlsState::HelloRetryRequest));
+        let state = tls_state_transition(TlsState::Tls13ServerHello, &ccs, true).ok();
+        assert_eq!(state, Some(TlsState::Tls13ServerHello));
+        // the server cannot send a ChangeCipherSpec before its ServerHello
+        assert!(!is_tls13_compat_ccs(TlsState::ClientHello, &ccs, false));
+        assert!(tls_state_transition(TlsState::ClientHello, &ccs, false).is_err());
+        // TLS 1.2 ChangeCipherSpec
+        assert!(!is_tls13_compat_ccs(
+            TlsState::ClientKeyExchange,
+            &ccs,
+            true
+        ));
+        // TLS 1.3 is not selected yet
+        assert!(!is_tls13_compat_ccs(TlsState::ClientHello, &ccs, true));
+        assert!(tls_state_transition(TlsState::ClientHello, &ccs, true).is_err());
+        assert!(!is_tls13_compat_ccs(TlsState::ServerHello, &ccs, false));
+        assert!(tls_state_transition(TlsState::ServerHello, &ccs, false).is_err());
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn test_tls13_transcript_hello_retry_request() {
+        let empty_sha256 = &[
+            0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99, 0x6f,
+            0xb9, 0x24, 0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b,
+            0x78, 0x52, 0xb8, 0x55,
+        ];
+        let mut transcript = TlsTranscript::new();
+        assert_eq!(
+            &transcript.hash(TlsHashAlgorithm::Sha256)[..],
+            &empty_sha256[..]
+        );
+        let ch1 = &[0x01, 0x00, 0x00, 0x02, 0x03, 0x03];
+        let mut hrr = vec![0x02, 0x00, 0x00, 0x28, 0x03, 0x03];
+        hrr.extend_from_slice(&HRR_RANDOM);
+        hrr.extend_from_slice(&[0x00, 0x13, 0x02, 0x00, 0x00, 0x00]);
+        let ch2 = &[0x01, 0x00, 0x00, 0x03, 0x03, 0x03, 0x00];
+        transcript.push(ch1);
+        // the HelloRetryRequest is split
+        transcript.push(&hrr[..10]);
+        assert!(!transcript.has_hello_retry_request());
+        transcript.push(&hrr[10..]);
+        assert!(transcript.has_hello_retry_request());
+        transcript.push(&ch2[..5]);
+        assert!(!transcript.is_complete());
+        transcript.push(&ch2[5..]);
+        assert!(transcript.is_complete());
+        for &alg in &[TlsHashAlgorithm::Sha256, TlsHashAlgorithm::Sha384] {
+            let mut expected = vec![0xfe, 0x00, 0x00, alg.output_len() as u8];
+            expected.extend_from_slice(&alg.hash(ch1));
+            expected.extend_from_slice(&hrr);
+            expected.extend_from_slice(ch2);
+            assert_eq!(transcript.data(alg), expected);
+            assert_eq!(transcript.hash(alg), alg.hash(&expected));
+        }
+        let cipher = TlsCipherSuite::from_id(0x1302).expect("unknown cipher suite");
+        assert_eq!(
+            TlsHashAlgorithm::from_ciphersuite(cipher),
+            TlsHashAlgorithm::Sha384
+        );
+        let cipher = TlsCipherSuite::from_id(0xc02f).expect("unknown cipher suite");
+        assert_eq!(
+            TlsHashAlgorithm::from_ciphersuite(cipher),
+            TlsHashAlgorithm::Sha256
+        );
+    }
+
+    #[test]
+    fn test_tls13_negotiated_version() {
+        // supported_versions: TLS 1.3, TLS 1.2
+        let ch_ext = &[0x00, 0x2b, 0x00, 0x05, 0x04, 0x03, 0x04, 0x03, 0x03];
+        let ciphers = vec![TlsCipherSuiteID(0x1301)];
+        let ch =
+            TlsClientHelloContents::new(0x0303, 0, &[0; 28], None, ciphers, vec![], Some(ch_ext));
+        let sh =
+            |version, ext| TlsServerHelloContents::new(version, 0, &[0; 28], None, 0x1301, 0, ext);
+        let tls13 = &[0x00, 0x2b, 0x00, 0x02, 0x03, 0x04][..];
+        assert_eq!(
+            sh(0x0303, Some(tls13)).negotiated_version(&ch),
+            Ok(TlsVersion::Tls13)
+        );
+        // the legacy version is ignored if the extension is present
+        assert_eq!(
+            sh(0x0301, Some(tls13)).negotiated_version(&ch),
+            Ok(TlsVersion::Tls13)
+        );
+        assert_eq!(
+            sh(0x0303, None).negotiated_version(&ch),
+            Ok(TlsVersion::Tls12)
+        );
+        assert_eq!(
+            sh(0x0304, None).negotiated_version(&ch),
+            Err(TlsVersionNegotiationError::LegacyTls13(TlsVersion::Tls13))
+        );
+        let tls12 = &[0x00, 0x2b, 0x00, 0x02, 0x03, 0x03][..];
+        assert_eq!(
+            sh(0x0303, Some(tls12)).negotiated_version(&ch),
+            Err(TlsVersionNegotiationError::InvalidSupportedVersion(
+                TlsVersion::Tls12
+            ))
+        );
+        let draft = &[0x00, 0x2b, 0x00, 0x02, 0x7f, 0x17][..];
+        assert_eq!(
+            sh(0x0303, Some(draft)).negotiated_version(&ch),
+            Err(TlsVersionNegotiationError::NotOffered(
+                TlsVersion::Tls13Draft23
+            ))
+        );
+        // TLS 1.2 client
+        let ch12 = TlsClientHelloContents::new(0x0303, 0, &[0; 28], None, vec![], vec![], None);
+        assert_eq!(
+            sh(0x0303, Some(tls13)).negotiated_version(&ch12),
+            Err(TlsVersionNegotiationError::NotOffered(TlsVersion::Tls13))
+        );
+        assert_eq!(
+            sh(0x0302, None).negotiated_version(&ch12),
+            Ok(TlsVersion::Tls11)
+        );
+        let ch11 = TlsClientHelloContents::new(0x0302, 0, &[0; 28], None, vec![], vec![], None);
+        assert_eq!(
+            sh(0x0303, None).negotiated_version(&ch11),
+            Err(TlsVersionNegotiationError::NotOffered(TlsVersion::Tls12))
+        );
+        // DTLS versions decrease
+        let dtls10 = TlsClientHelloContents::new(0xfeff, 0, &[0; 28], None, vec![], vec![], None);
+        assert_eq!(
+            sh(0xfeff, None).negotiated_version(&dtls10),
+            Ok(TlsVersion::DTls10)
+        );
+        assert_eq!(
+            sh(0xfefd, None).negotiated_version(&dtls10),
+            Err(TlsVersionNegotiationError::NotOffered(TlsVersion::DTls12))
+        );
+    }
+
+    #[test]
+    fn test_tls13_state_machine() {
+        let hs = TlsMessage::Handshake;
+        // supported_versions (TLS 1.3) and early_data
+        let ch_ext = &[
+            0x00, 0x2b, 0x00, 0x03, 0x02, 0x03, 0x04, 0x00, 0x2a, 0x00, 0x00,
+        ];
+        let ch = |early_data| {
+            let ext = if early_data {
+                &ch_ext[..]
+            } else {
+                &ch_ext[..7]
+            };
+            let ciphers = vec![TlsCipherSuiteID(0x1301)];
+            let ch =
+                TlsClientHelloContents::new(0x0303, 0, &[0; 28], None, ciphers, vec![], Some(ext));
+            hs(TlsMessageHandshake::ClientHello(ch))
+        };
+        let sh_ext = &[0x00, 0x2b, 0x00, 0x02, 0x03, 0x04];
+        let sh = hs(TlsMessageHandshake::ServerHello(
+            TlsServerHelloContents::new(0x0303, 0, &[0; 28], None, 0x1301, 0, Some(sh_ext)),
+        ));
+        let hrr_time =
+            u32::from_be_bytes([HRR_RANDOM[0], HRR_RANDOM[1], HRR_RANDOM[2], HRR_RANDOM[3]]);
+        let hrr = hs(TlsMessageHandshake::ServerHello(
+            TlsServerHelloContents::new(
+                0x0303,
+                hrr_time,
+                &HRR_RANDOM[4..],
+                None,
+                0x1301,
+                0,
+                Some(sh_ext),
+            ),
+        ));
+        let ee = |ext| {
+            hs(TlsMessageHandshake::EncryptedExtensions(
+                TlsEncryptedExtensionsContents { ext },
+            ))
+        };
+        let finished = hs(TlsMessageHandshake::Finished(&[0; 32]));
+        let mut sm = TlsStateMachine::new();
+        assert_eq!(
+            sm.update(&ch(true), true),
+            vec![TlsStateEvent::HandshakeStarted]
+        );
+        assert_eq!(
+            sm.update(&hrr, false),
+            vec![
+                TlsStateEvent::HelloRetryRequest,
+                TlsStateEvent::EarlyDataRejected
+            ]
+        );
+        assert!(sm.update(&TlsMessage::ChangeCipherSpec, true).is_empty());
+        assert!(sm.update(&ch(false), true).is_empty());
+        let negotiated = TlsStateEvent::ParametersNegotiated {
+            version: TlsVersion::Tls13,
+            cipher: TlsCipherSuiteID(0x1301),
+        };
+        assert_eq!(sm.update(&sh, false), vec![negotiated.clone()]);
+        assert!(sm.update(&ee(&[]), false).is_empty());
+        assert!(sm.update(&finished, false).is_empty());
+        assert_eq!(sm.state(), TlsState::ServerFinished);
+        assert_eq!(
+            sm.update(&finished, true),
+            vec![TlsStateEvent::HandshakeFinished]
+        );
+        // post-handshake messages
+        let nst = &[
+            0x04, 0x00, 0x00, 0x0e, 0x00, 0x00, 0x0e, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x01, 0xaa, 0x00, 0x00,
+        ];
+        let (_, nst) = parse_tls_message_handshake_with_version(nst, TlsVersion::Tls13)
+            .expect("parsing failed");
+        assert_eq!(
+            sm.update(&nst, false),
+            vec![TlsStateEvent::NewSessionTicket]
+        );
+        assert_eq!(
+            sm.update(&hs(TlsMessageHandshake::KeyUpdate(1)), true),
+            vec![TlsStateEvent::KeyUpdate {
+                to_server: true,
+                update_requested: true
+            }]
+        );
+        let request = &[0x0d, 0x00, 0x00, 0x05, 0x02, 0xab, 0xcd, 0x00, 0x00];
+        let (_, request) = parse_tls_message_handshake_with_version(request, TlsVersion::Tls13)
+            .expect("parsing failed");
+        let cert = &[0x0b, 0x00, 0x00, 0x06, 0x02, 0xab, 0xcd, 0x00, 0x00, 0x00];
+        let (_, cert) = parse_tls_message_handshake_with_version(cert, TlsVersion::Tls13)
+            .expect("parsing failed");
+        assert_eq!(
+            sm.update(&request, false),
+            vec![TlsStateEvent::PostHandshakeAuthRequested]
+        );
+        assert_eq!(
+            sm.update(&cert, true),
+            vec![TlsStateEvent::PostHandshakeAuthAnswered]
+        );
+        assert!(sm.update(&finished, true).is_empty());
+        // certificate without a request
+        assert_eq!(
+            sm.update(&cert, true),
+            vec![TlsStateEvent::ProtocolViolation {
+                state: TlsState::SessionEncrypted,
+                to_server: true
+            }]
+        );
+        assert_eq!(sm.state(), TlsState::Invalid);
+
+        // a second HelloRetryRequest is a violation
+        let mut sm = TlsStateMachine::new();
+        sm.update(&ch(false), true);
+        sm.update(&hrr, false);
+        sm.update(&ch(false), true);
+        assert_eq!(
+            sm.update(&hrr, false),
+            vec![TlsStateEvent::ProtocolViolation {
+                state: TlsState::ClientHello,
+                to_server: false
+            }]
+        );
+
+        // early data accepted, then rejected
+        let end_of_early_data = hs(TlsMessageHandshake::EndOfEarlyData);
+        let mut sm = TlsStateMachine::new();
+        sm.update(&ch(true), true);
+        sm.update(&sh, false);
+        assert_eq!(
+            sm.update(&ee(&[0x00, 0x2a, 0x00, 0x00]), false),
+            vec![TlsStateEvent::EarlyDataAccepted]
+        );
+        sm.update(&finished, false);
+        assert!(sm.update(&end_of_early_data, true).is_empty());
+        assert_eq!(sm.state(), TlsState::EndOfEarlyData);
+        let mut sm = TlsStateMachine::new();
+        sm.update(&ch(true), true);
+        sm.update(&sh, false);
+        assert_eq!(
+            sm.update(&ee(&[]), false),
+            vec![TlsStateEvent::EarlyDataRejected]
+        );
+        sm.update(&finished, false);
+        assert_eq!(
+            sm.update(&end_of_early_data, true),
+            vec![TlsStateEvent::ProtocolViolation {
+                state: TlsState::ServerFinished,
+                to_server: true
+            }]
+        );
+    }
+
+    #[test]
+    fn test_tls13_record_classifier() {
+        let hdr = |record_type, len| TlsRecordHeader {
+            record_type,
+            version: TlsVersion::Tls12,
+            len,
+        };
+        let mut classifier = TlsRecordClassifier::new();
+        let (_, ch) = parse_tls_raw_record(TV_CLIENT_HELLO_1).expect("invalid record");
+        let (_, sh) = parse_tls_raw_record(TV_SERVER_HELLO_1).expect("invalid record");
+        let records = vec![
+            (ch.hdr, ch.data, true, TlsRecordClass::PlaintextHandshake),
+            (sh.hdr, sh.data, false, TlsRecordClass::PlaintextHandshake),
+            (
+                hdr(TlsRecordType::ChangeCipherSpec, 1),
+                &[1][..],
+                false,
+                TlsRecordClass::ChangeCipherSpec,
+            ),
+            (
+                hdr(TlsRecordType::ApplicationData, 2000),
+                &[][..],
+                false,
+                TlsRecordClass::EncryptedHandshake,
+            ),
+            (
+                hdr(TlsRecordType::ApplicationData, 300),
+                &[][..],
+                false,
+                TlsRecordClass::EncryptedHandshake,
+            ),
+            (
+                hdr(TlsRecordType::ChangeCipherSpec, 1),
+                &[1][..],
+                true,
+                TlsRecordClass::ChangeCipherSpec,
+            ),
+            (
+                hdr(TlsRecordType::ApplicationData, 53),
+                &[][..],
+                true,
+                TlsRecordClass::EncryptedHandshake,
+            ),
+            (
+                hdr(TlsRecordType::ApplicationData, 100),
+                &[][..],
+                true,
+                TlsRecordClass::ApplicationData,
+            ),
+            (
+                hdr(TlsRecordType::ApplicationData, 200),
+                &[][..],
+                false,
+                TlsRecordClass::ApplicationData,
+            ),
+            (
+                hdr(TlsRecordType::ApplicationData, 19),
+                &[][..],
+                true,
+                TlsRecordClass::ProbableEncryptedAlert,
+            ),
+        ];
+        for (i, (hdr, data, to_server, class)) in records.into_iter().enumerate() {
+            assert_eq!(
+                classifier.classify(&hdr, data, to_server),
+                class,
+                "record {}",
+                i
+            );
+        }
+        assert!(classifier.is_tls13());
+    }
+
+    #[test]
+    fn test_tls13_record_classifier_hello_retry_request() {
+        let mut classifier = TlsRecordClassifier::new();
+        for (record, to_server) in hello_retry_request_flow() {
+            let (_, record) = parse_tls_raw_record(&record).expect("invalid record");
+            let class = classifier.classify(&record.hdr, record.data, to_server);
+            if record.hdr.record_type == TlsRecordType::ChangeCipherSpec {
+                assert_eq!(class, TlsRecordClass::ChangeCipherSpec);
+            } else {
+                assert_eq!(class, TlsRecordClass::PlaintextHandshake);
+            }
+        }
+        assert!(classifier.is_tls13());
+        let hdr = TlsRecordHeader {
+            record_type: TlsRecordType::ApplicationData,
+            version: TlsVersion::Tls12,
+            len: 2000,
+        };
+        assert_eq!(
+            classifier.classify(&hdr, &[], false),
+            TlsRecordClass::EncryptedHandshake
+        );
+    }
+
+    #[test]
+    fn test_tls13_session_hello_retry_request() {
+        let mut session = TlsSession::new();
+        for (record, to_server) in hello_retry_request_flow() {
+            let (_, record) = parse_tls_raw_record(&record).expect("invalid record");
+            session.update(&record, to_server).expect("update failed");
+        }
+        let summary = session.summary();
+        assert!(summary.hello_retry_request);
+        assert_eq!(summary.version, Some(TlsVersion::Tls13));
+        assert_eq!(summary.cipher, Some(TlsCipherSuiteID(0x1301)));
+        assert!(!summary.resumed);
+        assert_eq!(session.key_exchange().server_random, Some(vec![0x22; 32]));
+    }
+
+    #[test]
+    fn test_tls13_handshake_status() {
+        let mut classifier = TlsRecordClassifier::new();
+        let (_, ch) = parse_tls_raw_record(TV_CLIENT_HELLO_1).expect("invalid record");
+        let (_, sh) = parse_tls_raw_record(TV_SERVER_HELLO_1).expect("invalid record");
+        classifier.classify(&ch.hdr, ch.data, true);
+        assert_eq!(classifier.handshake_status(), TlsHandshakeStatus::Plaintext);
+        classifier.classify(&sh.hdr, sh.data, false);
+        for &len in &[2000, 300] {
+            let hdr = TlsRecordHeader {
+                record_type: TlsRecordType::ApplicationData,
+                version: TlsVersion::Tls12,
+                len,
+            };
+            classifier.classify(&hdr, &[], false);
+        }
+        let expected = TlsHandshakeStatus::ContinuesEncrypted {
+            to_server: TlsEncryptedHandshakeStats::default(),
+            to_client: TlsEncryptedHandshakeStats {
+                records: 2,
+                bytes: 2300,
+            },
+        };
+        assert_eq!(classifier.handshake_status(), expected);
+    }
+
+    #[test]
+    fn test_tls13_record_classifier_early_data() {
+        let hdr = |record_type, len| TlsRecordHeader {
+            record_type,
+            version: TlsVersion::Tls12,
+            len,
+        };
+        // ClientHello with supported_versions and early_data
+        let mut ch = vec![
+            0x16, 0x03, 0x01, 0x00, 0x3a, 0x01, 0x00, 0x00, 0x36, 0x03, 0x03,
+        ];
+        ch.extend_from_slice(&[0x11; 32]);
+        ch.extend_from_slice(&[
+            0x00, 0x00, 0x02, 0x13, 0x01, 0x01, 0x00, 0x00, 0x0b, 0x00, 0x2b, 0x00, 0x03, 0x02,
+            0x03, 0x04, 0x00, 0x2a, 0x00, 0x00,
+        ]);
+        // ServerHello with supported_versions and pre_shared_key
+        let mut sh = vec![
+            0x16, 0x03, 0x03, 0x00, 0x38, 0x02, 0x00, 0x00, 0x34, 0x03, 0x03,
+        ];
+        sh.extend_from_slice(&[0x22; 32]);
+        sh.extend_from_slice(&[
+            0x00, 0x13, 0x01, 0x00, 0x00, 0x0c, 0x00, 0x2b, 0x00, 0x02, 0x03, 0x04, 0x00, 0x29,
+            0x00, 0x02, 0x00, 0x00,
+        ]);
+        let (_, ch_record) = parse_tls_raw_record(&ch).expect("invalid record");
+        let (_, sh_record) = parse_tls_raw_record(&sh).expect("invalid record");
+        let mut classifier = TlsRecordClassifier::new();
+        let records = vec![
+            (
+                ch_record.hdr,
+                ch_record.data,
+                true,
+                TlsRecordClass::PlaintextHandshake,
+            ),
+            (
+                hdr(TlsRecordType::ApplicationData, 100),
+                &[][..],
+                true,
+                TlsRecordClass::EarlyData,
+            ),
+            (
+                sh_record.hdr,
+                sh_record.data,
+                false,
+                TlsRecordClass::PlaintextHandshake,
+            ),
+            (
+                hdr(TlsRecordType::ApplicationData, 2000),
+                &[][..],
+                false,
+                TlsRecordClass::EncryptedHandshake,
+            ),
+            (
+                hdr(TlsRecordType::ApplicationData, 50),
+                &[][..],
+                true,
+                TlsRecordClass::EarlyData,
+            ),
+            // EndOfEarlyData
+            (
+                hdr(TlsRecordType::ApplicationData, 21),
+                &[][..],
+                true,
+                TlsRecordClass::EncryptedHandshake,
+            ),
+            (
+                hdr(TlsRecordType::ApplicationData, 53),
+                &[][..],
+                true,
+                TlsRecordClass::EncryptedHandshake,
+            ),
+            (
+                hdr(TlsRecordType::ApplicationData, 100),
+                &[][..],
+                true,
+                TlsRecordClass::ApplicationData,
+            ),
+        ];
+        for (hdr, data, to_server, class) in records {
+            assert_eq!(classifier.classify(&hdr, data, to_server), class);
+        }
+        assert!(!classifier.is_early_data());
+        // 0-RTT data rejected: the ServerHello has no pre_shared_key extension
+        sh[4] = 0x32;
+        sh[8] = 0x2e;
+        sh[48] = 0x06;
+        sh.truncate(sh.len() - 6);
+        let (_, sh_record) = parse_tls_raw_record(&sh).expect("invalid record");
+        let mut classifier = TlsRecordClassifier::new();
+        classifier.classify(&ch_record.hdr, ch_record.data, true);
+        assert!(classifier.is_early_data());
+        classifier.classify(&sh_record.hdr, sh_record.data, false);
+        assert!(classifier.is_tls13());
+        assert!(!classifier.is_early_data());
+        let record = hdr(TlsRecordType::ApplicationData, 53);
+        assert_eq!(
+            classifier.classify(&record, &[], true),
+            TlsRecordClass::EncryptedHandshake
+        );
+    }
 } // mod tls_13